@@ -0,0 +1,95 @@
+// --- ZIP archive creation (runs as a background job) ---
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+use zip::{CompressionMethod, ZipWriter, write::FileOptions};
+
+use crate::{fileops, jobs::JobContext};
+
+/// Files of this size and larger need the ZIP64 format.
+const LARGE_FILE_SIZE: u64 = u32::MAX as u64;
+
+/// Packs `sources` (files and folders) into a new ZIP archive at `archive_path`.
+/// `level` ranges from 0 (store only) to 9 (best compression).
+/// Runs as a background job, which reports progress and stops early when cancelled.
+/// The archive is written to a temporary file next to it and only replaces an existing
+/// file of the same name once it is complete. Returns the path of the archive, or an error message.
+pub fn create_zip_archive(sources: Vec<PathBuf>, archive_path: PathBuf, level: i32, job: &JobContext) -> Result<PathBuf, String> {
+    let Some(file_name) = archive_path.file_name() else {
+        return Err(format!("{} is not a file name", archive_path.display()));
+    };
+    let temp_path = archive_path.with_file_name(format!(".{}.part", file_name.to_string_lossy()));
+    // Walked and written in extended-length form, as paths inside the folders may be long
+    let sources: Vec<PathBuf> = sources.iter().map(|source| fileops::extended_root(source).into_owned()).collect();
+    let (archive, temp) = (fileops::extended_root(&archive_path), fileops::extended_root(&temp_path));
+    let result = write_archive(&sources, &archive, &temp, level, job).and_then(|()| fs::rename(&*temp, &*archive));
+    match result {
+        Ok(()) => Ok(archive_path),
+        Err(e) => {
+            let _ = fs::remove_file(&*temp); // Don't leave a broken archive behind
+            Err(e.to_string())
+        }
+    }
+}
+
+fn write_archive(
+    sources: &[PathBuf],
+    archive_path: &Path,
+    temp_path: &Path,
+    level: i32,
+    job: &JobContext,
+) -> io::Result<()> {
+    // Collect every file/folder first so progress can be reported as "x of y"
+    let mut items: Vec<(PathBuf, String)> = Vec::new();
+    for source in sources.iter().filter(|source| *source != archive_path) {
+        let base = source.parent().unwrap_or(source);
+        for entry in WalkDir::new(source) {
+            // An archive missing files that couldn't be read would look complete
+            let entry = entry?;
+            // Never pack the archive into itself
+            if entry.path() == archive_path || entry.path() == temp_path {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(base).unwrap_or(entry.path());
+            // ZIP entry names always use forward slashes
+            let name = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            items.push((entry.path().to_owned(), name));
+        }
+    }
+
+    let options = if level == 0 {
+        FileOptions::default().compression_method(CompressionMethod::Stored)
+    } else {
+        FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .compression_level(Some(level))
+    };
+
+    let mut writer = ZipWriter::new(File::create(temp_path)?);
+    let total = items.len();
+    for (done, (path, name)) in items.iter().enumerate() {
+        if job.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "compression cancelled"));
+        }
+        if path.is_dir() {
+            writer.add_directory(name.as_str(), options)?;
+        } else {
+            let mut file = File::open(path)?;
+            let large_file = file.metadata()?.len() >= LARGE_FILE_SIZE;
+            writer.start_file(name.as_str(), options.large_file(large_file))?;
+            io::copy(&mut file, &mut writer)?;
+        }
+        job.progress(done + 1, total);
+    }
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,443 @@
+// --- Checksums of files, to verify downloads and copies ---
+//
+// Checksum lists like `SHA256SUMS` or `file.iso.sha256` are read in the formats of
+// `sha256sum` ("<checksum>  <name>", "*" before the name in binary mode) and of BSD
+// `shasum --tag` ("SHA256 (<name>) = <checksum>").
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+};
+
+use eframe::egui;
+use md5::Md5;
+use rayon::prelude::*;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::repaint;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [Algorithm::Md5, Algorithm::Sha1, Algorithm::Sha256, Algorithm::Blake3];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha1 => "SHA-1",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Blake3 => "BLAKE3",
+        }
+    }
+
+    /// Algorithm named by the extension or name of a checksum list, e.g. "MD5SUMS" or "a.iso.sha1".
+    fn for_list(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+        [("md5", Algorithm::Md5), ("sha1", Algorithm::Sha1), ("sha256", Algorithm::Sha256), ("b3", Algorithm::Blake3)]
+            .into_iter()
+            .find(|(tag, _)| name.ends_with(&format!(".{}", tag)) || name.starts_with(&format!("{}sum", tag)))
+            .map(|(_, algorithm)| algorithm)
+    }
+
+    /// Algorithm of a BSD style line, e.g. "SHA256" or "SHA1".
+    fn for_tag(tag: &str) -> Option<Self> {
+        match tag.to_ascii_uppercase().as_str() {
+            "MD5" => Some(Algorithm::Md5),
+            "SHA1" => Some(Algorithm::Sha1),
+            "SHA256" => Some(Algorithm::Sha256),
+            "BLAKE3" => Some(Algorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    /// Guesses the algorithm from the number of hex digits, SHA-256 for 64 of them.
+    fn for_length(length: usize) -> Option<Self> {
+        match length {
+            32 => Some(Algorithm::Md5),
+            40 => Some(Algorithm::Sha1),
+            64 => Some(Algorithm::Sha256),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `path` looks like a checksum list that can be verified.
+pub fn is_checksum_list(path: &Path) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_ascii_uppercase()).unwrap_or_default();
+    Algorithm::for_list(path).is_some() || name.contains("SHASUMS") || name.contains("CHECKSUMS")
+}
+
+/// One running hash computation.
+enum Hasher {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Md5 => Hasher::Md5(Md5::new()),
+            Algorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(hasher) => hasher.update(data),
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    /// The checksum as lowercase hex digits.
+    fn finish(self) -> String {
+        let bytes = match self {
+            Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        };
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+/// Hashes `path` with every one of `algorithms` while reading it once. Adds the bytes read
+/// to `done`, and stops with an `Interrupted` error once `cancel` is set.
+pub fn hash_file(
+    path: &Path,
+    algorithms: &[Algorithm],
+    done: &AtomicU64,
+    cancel: &AtomicBool,
+) -> io::Result<Vec<String>> {
+    let mut hashers: Vec<Hasher> = algorithms.iter().map(|algorithm| Hasher::new(*algorithm)).collect();
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..read]);
+        }
+        done.fetch_add(read as u64, Ordering::Relaxed);
+    }
+    Ok(hashers.into_iter().map(Hasher::finish).collect())
+}
+
+/// All checksums of one file, computed on rayon's thread pool. Dropping it stops the computation.
+pub struct ChecksumTask {
+    total: u64,
+    done: Arc<AtomicU64>, // Bytes hashed so far
+    cancel: Arc<AtomicBool>,
+    receiver: mpsc::Receiver<Result<Vec<String>, String>>,
+}
+
+impl ChecksumTask {
+    pub fn start(path: &Path, total: u64, ctx: &egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let done = Arc::new(AtomicU64::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (path, thread_done, thread_cancel, ctx) = (path.to_owned(), done.clone(), cancel.clone(), ctx.clone());
+        rayon::spawn(move || {
+            let result = hash_file(&path, &Algorithm::ALL, &thread_done, &thread_cancel).map_err(|e| e.to_string());
+            if sender.send(result).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+        Self { total, done, cancel, receiver }
+    }
+
+    /// Share of the file hashed so far, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        self.done.load(Ordering::Relaxed) as f32 / self.total.max(1) as f32
+    }
+
+    /// The checksums in the order of `Algorithm::ALL`, once they're computed.
+    /// Keeps the UI repainting until then, so the progress moves.
+    pub fn poll(&self, ctx: &egui::Context) -> Option<Result<Vec<(Algorithm, String)>, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result.map(|checksums| Algorithm::ALL.into_iter().zip(checksums).collect())),
+            Err(_) => {
+                repaint::request_throttled_repaint(ctx);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for ChecksumTask {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Result of verifying one line of a checksum list.
+#[derive(Clone)]
+enum LineStatus {
+    Pending,
+    Ok,
+    Failed,
+    Missing,
+    Error(String),
+}
+
+/// One file referenced by a checksum list.
+struct ChecksumLine {
+    name: String,
+    algorithm: Option<Algorithm>, // None when it couldn't be guessed
+    expected: String,
+    status: LineStatus,
+}
+
+/// Reads the lines of the checksum list `list`. Comments, lines that aren't checksums and
+/// checksums too long or too short for the algorithm named by the list are skipped.
+fn parse_checksum_list(list: &Path, content: &str) -> Vec<ChecksumLine> {
+    let default_algorithm = Algorithm::for_list(list);
+    let mut lines = Vec::new();
+    for line in content.lines().map(str::trim_end).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        // BSD style: "SHA256 (name) = checksum"
+        if let Some((tag, rest)) = line.split_once(" (")
+            && !tag.contains(' ')
+            && let Some((name, checksum)) = rest.rsplit_once(") = ")
+        {
+            lines.push(ChecksumLine {
+                name: name.to_string(),
+                algorithm: Algorithm::for_tag(tag).or_else(|| Algorithm::for_length(checksum.len())),
+                expected: checksum.trim().to_ascii_lowercase(),
+                status: LineStatus::Pending,
+            });
+            continue;
+        }
+        // GNU style, where a leading "\" means backslashes and newlines in the name are escaped
+        let (escaped, line) = match line.strip_prefix('\\') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        let (checksum, name) = match line.split_once(' ') {
+            Some((checksum, name)) => {
+                let name = name.strip_prefix(' ').or_else(|| name.strip_prefix('*')).unwrap_or(name);
+                (checksum, name.to_string())
+            }
+            // Only a checksum, in a list named after the file: "file.iso.sha256"
+            None => (line, list.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()),
+        };
+        if checksum.is_empty() || !checksum.bytes().all(|byte| byte.is_ascii_hexdigit()) || name.is_empty() {
+            continue;
+        }
+        if let Some(algorithm) = default_algorithm
+            && Algorithm::for_length(checksum.len()) != Some(algorithm)
+        {
+            continue;
+        }
+        let name = if escaped { unescape_name(&name) } else { name };
+        lines.push(ChecksumLine {
+            name,
+            algorithm: default_algorithm.or_else(|| Algorithm::for_length(checksum.len())),
+            expected: checksum.to_ascii_lowercase(),
+            status: LineStatus::Pending,
+        });
+    }
+    lines
+}
+
+/// Undoes the escaping of `sha256sum` in names: `\\` is a backslash and `\n` a line break.
+fn unescape_name(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                chars.next();
+            }
+            (c, _) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Window verifying the files of one checksum list. The files are hashed in parallel
+/// on rayon's thread pool; closing the window stops the verification.
+pub struct ChecksumVerifier {
+    list: PathBuf,
+    lines: Vec<ChecksumLine>,
+    error: Option<String>, // The list couldn't be read
+    cancel: Arc<AtomicBool>,
+    receiver: mpsc::Receiver<(usize, LineStatus)>,
+}
+
+impl ChecksumVerifier {
+    /// Reads `list` and starts hashing the files it references, relative to its folder.
+    pub fn verify(list: &Path, ctx: &egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (lines, error) = match std::fs::read(list) {
+            Ok(content) => (parse_checksum_list(list, &String::from_utf8_lossy(&content)), None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+
+        let folder = list.parent().map(Path::to_path_buf).unwrap_or_default();
+        let jobs: Vec<(usize, PathBuf, Option<Algorithm>, String)> = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| (index, folder.join(&line.name), line.algorithm, line.expected.clone()))
+            .collect();
+        let (thread_cancel, ctx) = (cancel.clone(), ctx.clone());
+        rayon::spawn(move || {
+            jobs.into_par_iter().for_each(|(index, path, algorithm, expected)| {
+                if thread_cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let status = match algorithm {
+                    _ if !path.is_file() => LineStatus::Missing,
+                    None => LineStatus::Error("unknown checksum type".to_string()),
+                    Some(algorithm) => match hash_file(&path, &[algorithm], &AtomicU64::new(0), &thread_cancel) {
+                        Ok(checksums) if checksums[0] == expected => LineStatus::Ok,
+                        Ok(_) => LineStatus::Failed,
+                        Err(e) => LineStatus::Error(e.to_string()),
+                    },
+                };
+                if sender.send((index, status)).is_ok() {
+                    repaint::request_throttled_repaint(&ctx);
+                }
+            });
+            ctx.request_repaint(); // Show the last results without waiting for the throttle
+        });
+        Self { list: list.to_owned(), lines, error, cancel, receiver }
+    }
+
+    /// Renders the window. Returns `false` once it was closed.
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        for (index, status) in self.receiver.try_iter() {
+            self.lines[index].status = status;
+        }
+
+        let mut is_open = true;
+        let title = format!("Verify {}", self.list.file_name().unwrap_or_default().to_string_lossy());
+        egui::Window::new(title)
+            .id(egui::Id::new("checksum_verifier"))
+            .open(&mut is_open)
+            .default_size([600.0, 400.0])
+            .show(ctx, |ui| {
+                if let Some(e) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("Couldn't read the list: {}", e));
+                    return;
+                }
+                if self.lines.is_empty() {
+                    ui.label("The file contains no checksums.");
+                    return;
+                }
+
+                let count =
+                    |matches: fn(&LineStatus) -> bool| self.lines.iter().filter(|line| matches(&line.status)).count();
+                let pending = count(|status| matches!(status, LineStatus::Pending));
+                ui.horizontal(|ui| {
+                    if pending > 0 {
+                        ui.spinner();
+                    }
+                    ui.label(format!(
+                        "{} OK, {} failed, {} missing, {} errors",
+                        count(|status| matches!(status, LineStatus::Ok)),
+                        count(|status| matches!(status, LineStatus::Failed)),
+                        count(|status| matches!(status, LineStatus::Missing)),
+                        count(|status| matches!(status, LineStatus::Error(_))),
+                    ));
+                    if pending > 0 {
+                        ui.weak(format!("({} of {} left)", pending, self.lines.len()));
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                    egui::Grid::new("checksum_lines").num_columns(3).striped(true).show(ui, |ui| {
+                        for line in &self.lines {
+                            match &line.status {
+                                LineStatus::Pending => ui.weak("…"),
+                                LineStatus::Ok => ui.colored_label(egui::Color32::from_rgb(80, 190, 90), "OK"),
+                                LineStatus::Failed => ui.colored_label(ui.visuals().error_fg_color, "FAILED"),
+                                LineStatus::Missing => ui.colored_label(ui.visuals().warn_fg_color, "missing"),
+                                LineStatus::Error(e) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, "error").on_hover_text(e)
+                                }
+                            };
+                            ui.label(&line.name);
+                            ui.weak(line.algorithm.map_or("?", Algorithm::label));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        is_open
+    }
+}
+
+impl Drop for ChecksumVerifier {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gnu_and_bsd_lines_are_parsed() {
+        let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let content = [
+            "# comment".to_string(),
+            format!("{sha256}  plain.txt"),
+            format!("{} *binary.iso", sha256.to_ascii_uppercase()),
+            "d41d8cd98f00b204e9800998ecf8427e  md5.txt".to_string(),
+            format!("\\{sha256}  back\\\\new\\nline"),
+            "SHA1 (bsd name.txt) = da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string(),
+            "not a checksum".to_string(),
+        ]
+        .join("\n");
+        let lines = parse_checksum_list(Path::new("/downloads/SHA256SUMS"), &content);
+        let parsed: Vec<(&str, Option<Algorithm>, &str)> =
+            lines.iter().map(|line| (line.name.as_str(), line.algorithm, line.expected.as_str())).collect();
+        // The MD5 checksum can't be a SHA-256 one, as the list's name says
+        assert_eq!(parsed.len(), 4);
+        assert!(parsed[0] == ("plain.txt", Some(Algorithm::Sha256), sha256));
+        assert!(parsed[1] == ("binary.iso", Some(Algorithm::Sha256), sha256));
+        assert!(parsed[2] == ("back\\new\nline", Some(Algorithm::Sha256), sha256));
+        assert!(parsed[3].0 == "bsd name.txt" && parsed[3].1 == Some(Algorithm::Sha1));
+    }
+
+    #[test]
+    fn lists_named_after_a_file_hold_only_its_checksum() {
+        let lines = parse_checksum_list(Path::new("/downloads/image.iso.md5"), "D41D8CD98F00B204E9800998ECF8427E\n");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].name, "image.iso");
+        assert!(lines[0].algorithm == Some(Algorithm::Md5));
+    }
+}
@@ -0,0 +1,365 @@
+// --- Sync status of OneDrive, Dropbox and Google Drive folders ---
+//
+// Windows reports it through the placeholder files of the Cloud Files API, which
+// OneDrive and Dropbox use. macOS marks files that are only in the cloud as "dataless",
+// and on Linux the Dropbox daemon answers `dropbox filestatus`.
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+};
+
+use eframe::egui;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CloudProvider {
+    OneDrive,
+    Dropbox,
+    GoogleDrive,
+    ICloud,
+}
+
+impl CloudProvider {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::OneDrive => "OneDrive",
+            Self::Dropbox => "Dropbox",
+            Self::GoogleDrive => "Google Drive",
+            Self::ICloud => "iCloud Drive",
+        }
+    }
+}
+
+/// Sync status of an entry of a cloud folder.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CloudFileStatus {
+    Synced,
+    Syncing,
+    OnlineOnly, // Downloaded when opened
+    Pinned, // Always kept on this device
+}
+
+impl CloudFileStatus {
+    /// Short badge shown next to the entry name, with its color.
+    pub fn badge(self) -> (&'static str, egui::Color32) {
+        match self {
+            Self::Synced => ("✔", egui::Color32::from_rgb(80, 190, 90)),
+            Self::Syncing => ("🔄", egui::Color32::from_rgb(80, 170, 230)),
+            Self::OnlineOnly => ("☁", egui::Color32::from_rgb(80, 170, 230)),
+            Self::Pinned => ("📌", egui::Color32::from_rgb(80, 190, 90)),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Synced => "Available on this device",
+            Self::Syncing => "Syncing",
+            Self::OnlineOnly => "Online only",
+            Self::Pinned => "Always kept on this device",
+        }
+    }
+}
+
+/// Sync status of the entries of one directory inside a cloud folder.
+pub struct CloudStatus {
+    pub provider: CloudProvider,
+    pub entries: HashMap<OsString, CloudFileStatus>, // By entry name; entries without a status are left out
+}
+
+/// Reads the sync status of `dir`. `None` if it is not inside a cloud folder.
+pub fn read_status(dir: &Path) -> Option<CloudStatus> {
+    let provider = cloud_folders().into_iter().find(|(_, root)| dir.starts_with(root))?.0;
+    Some(CloudStatus { provider, entries: entry_statuses(dir) })
+}
+
+/// Folders of the cloud clients installed for the current user.
+fn cloud_folders() -> Vec<(CloudProvider, PathBuf)> {
+    let mut folders = Vec::new();
+    let Some(home) = dirs::home_dir() else {
+        return folders;
+    };
+    // Set by the OneDrive client, one per account
+    for variable in ["OneDrive", "OneDriveConsumer", "OneDriveCommercial"] {
+        if let Some(root) = std::env::var_os(variable) {
+            folders.push((CloudProvider::OneDrive, PathBuf::from(root)));
+        }
+    }
+    // Dropbox lists its folders in info.json, which lives in one of these places
+    let dropbox_info = [dirs::config_dir(), dirs::data_local_dir()]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join("Dropbox").join("info.json"))
+        .chain(Some(home.join(".dropbox").join("info.json")));
+    for info in dropbox_info {
+        if let Ok(info) = std::fs::read_to_string(info) {
+            folders.extend(json_paths(&info).into_iter().map(|root| (CloudProvider::Dropbox, root)));
+        }
+    }
+    folders.push((CloudProvider::Dropbox, home.join("Dropbox")));
+    folders.push((CloudProvider::OneDrive, home.join("OneDrive")));
+    folders.push((CloudProvider::GoogleDrive, home.join("Google Drive")));
+    // macOS keeps the folders of File Provider clients in ~/Library/CloudStorage
+    if let Ok(entries) = std::fs::read_dir(home.join("Library").join("CloudStorage")) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let provider = if name.starts_with("OneDrive") {
+                CloudProvider::OneDrive
+            } else if name.starts_with("Dropbox") {
+                CloudProvider::Dropbox
+            } else if name.starts_with("GoogleDrive") {
+                CloudProvider::GoogleDrive
+            } else {
+                continue;
+            };
+            folders.push((provider, entry.path()));
+        }
+    }
+    let icloud = home.join("Library").join("Mobile Documents").join("com~apple~CloudDocs");
+    folders.push((CloudProvider::ICloud, icloud));
+    #[cfg(windows)]
+    folders.extend(native::google_drive_volumes().into_iter().map(|root| (CloudProvider::GoogleDrive, root)));
+    folders
+}
+
+/// Folders of the accounts in Dropbox's info.json, e.g. `{"personal": {"path": "C:\\Users\\me\\Dropbox"}}`.
+fn json_paths(json: &str) -> Vec<PathBuf> {
+    let Ok(serde_json::Value::Object(accounts)) = serde_json::from_str(json) else {
+        return Vec::new();
+    };
+    accounts.values().filter_map(|account| account.get("path")?.as_str()).map(PathBuf::from).collect()
+}
+
+#[cfg(windows)]
+fn entry_statuses(dir: &Path) -> HashMap<OsString, CloudFileStatus> {
+    native::entry_statuses(dir)
+}
+
+/// Files whose contents are only in the cloud are "dataless" until they are opened.
+#[cfg(target_os = "macos")]
+fn entry_statuses(dir: &Path) -> HashMap<OsString, CloudFileStatus> {
+    use std::os::macos::fs::MetadataExt;
+
+    const SF_DATALESS: u32 = 0x4000_0000;
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return HashMap::new();
+    };
+    let mut statuses = HashMap::new();
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let status =
+            if metadata.st_flags() & SF_DATALESS != 0 { CloudFileStatus::OnlineOnly } else { CloudFileStatus::Synced };
+        statuses.insert(entry.file_name(), status);
+    }
+    statuses
+}
+
+/// Asks the Dropbox daemon, if it is running. Other clients don't report a status.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn entry_statuses(dir: &Path) -> HashMap<OsString, CloudFileStatus> {
+    let mut statuses = HashMap::new();
+    let Ok(output) = std::process::Command::new("dropbox").arg("filestatus").current_dir(dir).output() else {
+        return statuses;
+    };
+    // One "name: status" line per entry
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((name, status)) = line.rsplit_once(':') else {
+            continue;
+        };
+        let status = match status.trim() {
+            "up to date" => CloudFileStatus::Synced,
+            "syncing" => CloudFileStatus::Syncing,
+            _ => continue,
+        };
+        statuses.insert(OsString::from(name.trim()), status);
+    }
+    statuses
+}
+
+/// Whether the files of `dir` can be freed up or kept on the device from FileFox.
+pub fn can_change_pin(status: Option<&CloudStatus>) -> bool {
+    cfg!(windows) && status.is_some_and(|status| status.provider != CloudProvider::GoogleDrive)
+}
+
+/// "Always keep on this device" (`keep`), or "Free up space". The sync client downloads
+/// or dehydrates the file in the background, for folders everything inside.
+#[cfg(windows)]
+pub fn set_pinned(path: &Path, keep: bool) -> io::Result<()> {
+    native::set_pinned(path, keep)
+}
+
+#[cfg(not(windows))]
+pub fn set_pinned(_path: &Path, _keep: bool) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "the sync client decides which files stay on this device"))
+}
+
+#[cfg(windows)]
+#[allow(non_snake_case)]
+mod native {
+    use std::{
+        collections::HashMap,
+        ffi::OsString,
+        io, mem,
+        os::windows::ffi::{OsStrExt, OsStringExt},
+        path::{Path, PathBuf},
+        ptr,
+        sync::OnceLock,
+    };
+
+    use winapi::shared::minwindef::DWORD;
+    use winapi::um::fileapi::{
+        FindClose, FindFirstFileW, FindNextFileW, GetFileAttributesW, GetLogicalDrives, GetVolumeInformationW,
+        INVALID_FILE_ATTRIBUTES, SetFileAttributesW,
+    };
+    use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+    use winapi::um::libloaderapi::{GetProcAddress, LoadLibraryW};
+    use winapi::um::minwinbase::WIN32_FIND_DATAW;
+    use winapi::um::winnt::{FILE_ATTRIBUTE_OFFLINE, FILE_ATTRIBUTE_REPARSE_POINT};
+
+    use super::CloudFileStatus;
+
+    // Not part of winapi 0.3
+    const FILE_ATTRIBUTE_PINNED: DWORD = 0x0008_0000;
+    const FILE_ATTRIBUTE_UNPINNED: DWORD = 0x0010_0000;
+    const FILE_ATTRIBUTE_RECALL_ON_OPEN: DWORD = 0x0004_0000;
+    const FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS: DWORD = 0x0040_0000;
+    /// Set on files whose contents are downloaded when they are opened.
+    const ONLINE_ONLY_ATTRIBUTES: DWORD =
+        FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS | FILE_ATTRIBUTE_RECALL_ON_OPEN | FILE_ATTRIBUTE_OFFLINE;
+    const CF_PLACEHOLDER_STATE_PLACEHOLDER: DWORD = 0x1;
+    const CF_PLACEHOLDER_STATE_IN_SYNC: DWORD = 0x8;
+    const CF_PLACEHOLDER_STATE_INVALID: DWORD = 0xFFFF_FFFF;
+
+    type GetPlaceholderState = unsafe extern "system" fn(attributes: DWORD, reparse_tag: DWORD) -> DWORD;
+
+    fn wide(path: &Path) -> Vec<u16> {
+        path.as_os_str().encode_wide().chain(Some(0)).collect()
+    }
+
+    /// CfGetPlaceholderStateFromAttributeTag, loaded at runtime as cldapi.dll is missing before Windows 10.
+    fn placeholder_state() -> Option<GetPlaceholderState> {
+        static FUNCTION: OnceLock<Option<GetPlaceholderState>> = OnceLock::new();
+        *FUNCTION.get_or_init(|| unsafe {
+            let module = LoadLibraryW(wide(Path::new("cldapi.dll")).as_ptr());
+            if module.is_null() {
+                return None;
+            }
+            // The library stays loaded for the lifetime of the process
+            let address = GetProcAddress(module, b"CfGetPlaceholderStateFromAttributeTag\0".as_ptr() as *const i8);
+            (!address.is_null()).then(|| mem::transmute::<_, GetPlaceholderState>(address))
+        })
+    }
+
+    /// Statuses of the placeholders in `dir`, from one pass over the directory.
+    pub fn entry_statuses(dir: &Path) -> HashMap<OsString, CloudFileStatus> {
+        let mut statuses = HashMap::new();
+        let Some(placeholder_state) = placeholder_state() else {
+            return statuses;
+        };
+        unsafe {
+            let mut data: WIN32_FIND_DATAW = mem::zeroed();
+            let find = FindFirstFileW(wide(&dir.join("*")).as_ptr(), &mut data);
+            if find == INVALID_HANDLE_VALUE {
+                return statuses;
+            }
+            loop {
+                let name_len = data.cFileName.iter().position(|&c| c == 0).unwrap_or(data.cFileName.len());
+                let name = OsString::from_wide(&data.cFileName[..name_len]);
+                let attributes = data.dwFileAttributes;
+                // The reparse tag is only filled in for reparse points
+                let tag = if attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0 { data.dwReserved0 } else { 0 };
+                let state = placeholder_state(attributes, tag);
+                let status = if state == CF_PLACEHOLDER_STATE_INVALID || state & CF_PLACEHOLDER_STATE_PLACEHOLDER == 0 {
+                    None
+                } else if attributes & ONLINE_ONLY_ATTRIBUTES != 0 {
+                    Some(CloudFileStatus::OnlineOnly)
+                } else if state & CF_PLACEHOLDER_STATE_IN_SYNC == 0 {
+                    Some(CloudFileStatus::Syncing)
+                } else if attributes & FILE_ATTRIBUTE_PINNED != 0 {
+                    Some(CloudFileStatus::Pinned)
+                } else {
+                    Some(CloudFileStatus::Synced)
+                };
+                if let Some(status) = status
+                    && name != "."
+                    && name != ".."
+                {
+                    statuses.insert(name, status);
+                }
+                if FindNextFileW(find, &mut data) == 0 {
+                    break;
+                }
+            }
+            FindClose(find);
+        }
+        statuses
+    }
+
+    /// Pin states are file attributes, as set by `attrib +P` / `attrib +U`.
+    pub fn set_pinned(path: &Path, keep: bool) -> io::Result<()> {
+        let path = wide(path);
+        unsafe {
+            let attributes = GetFileAttributesW(path.as_ptr());
+            if attributes == INVALID_FILE_ATTRIBUTES {
+                return Err(io::Error::last_os_error());
+            }
+            let attributes = if keep {
+                (attributes & !FILE_ATTRIBUTE_UNPINNED) | FILE_ATTRIBUTE_PINNED
+            } else {
+                (attributes & !FILE_ATTRIBUTE_PINNED) | FILE_ATTRIBUTE_UNPINNED
+            };
+            if SetFileAttributesW(path.as_ptr(), attributes) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Google Drive for desktop shows up as a drive called "Google Drive".
+    pub fn google_drive_volumes() -> Vec<PathBuf> {
+        let mask = unsafe { GetLogicalDrives() };
+        let mut volumes = Vec::new();
+        for (index, letter) in (b'A'..=b'Z').enumerate() {
+            if mask & (1 << index) == 0 || letter < b'C' {
+                continue; // Floppy drives would be slow to ask
+            }
+            let root = PathBuf::from(format!("{}:\\", letter as char));
+            let mut name = [0u16; 64];
+            let has_name = unsafe {
+                GetVolumeInformationW(
+                    wide(&root).as_ptr(),
+                    name.as_mut_ptr(),
+                    name.len() as u32,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    0,
+                )
+            } != 0;
+            let name_len = name.iter().position(|&c| c == 0).unwrap_or(0);
+            if has_name && String::from_utf16_lossy(&name[..name_len]) == "Google Drive" {
+                volumes.push(root);
+            }
+        }
+        volumes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropbox_folders_are_read_from_info_json() {
+        let info = r#"{"personal": {"path": "C:\\Users\\me\\Dropbox \"old\"", "host": 1},
+            "business": {"path": "/home/me/Dropbox (Work)", "is_team": true}}"#;
+        let mut paths = json_paths(info);
+        paths.sort();
+        assert_eq!(paths, [PathBuf::from("/home/me/Dropbox (Work)"), PathBuf::from("C:\\Users\\me\\Dropbox \"old\"")]);
+        assert!(json_paths("not json").is_empty());
+    }
+}
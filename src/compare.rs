@@ -0,0 +1,317 @@
+// --- Folder comparison (entries only on one side, and files that differ) ---
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+};
+
+use eframe::egui;
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use crate::{fileops, repaint, view};
+
+/// Type and size of an entry found on one side.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct EntryInfo {
+    is_dir: bool,
+    size: u64,
+}
+
+/// One row of the comparison, with the path relative to both folders.
+struct Difference {
+    relative: PathBuf,
+    is_dir: bool,
+    left_size: Option<u64>,
+    right_size: Option<u64>,
+}
+
+/// Result of comparing two folder trees.
+#[derive(Default)]
+struct Comparison {
+    left: PathBuf,
+    right: PathBuf,
+    only_left: Vec<Difference>, // Folders only on one side are listed without their contents
+    only_right: Vec<Difference>,
+    different: Vec<Difference>,
+    identical: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    OnlyLeft,
+    OnlyRight,
+    Different,
+}
+
+/// Requested by the comparison window.
+pub enum CompareAction {
+    Close,
+    /// Copy these (source, destination) pairs, e.g. the files missing on the right.
+    Copy(Vec<(PathBuf, PathBuf)>),
+}
+
+/// Window comparing two folders. Scans run on rayon's thread pool;
+/// closing the window stops a scan that is still running.
+pub struct FolderComparison {
+    left: String,
+    right: String,
+    compare_contents: bool, // Also hash files of equal size (SHA-256)
+    tab: Tab,
+    comparison: Option<Result<Comparison, String>>,
+    scanned: Arc<AtomicU64>, // Entries scanned so far
+    cancel: Arc<AtomicBool>,
+    receiver: Option<mpsc::Receiver<Result<Comparison, String>>>,
+}
+
+impl FolderComparison {
+    /// Opens the window for `left` and `right`, comparing them right away when both are set.
+    pub fn new(left: &Path, right: Option<&Path>, ctx: &egui::Context) -> Self {
+        let mut comparison = Self {
+            left: left.display().to_string(),
+            right: right.map(|right| right.display().to_string()).unwrap_or_default(),
+            compare_contents: false,
+            tab: Tab::OnlyLeft,
+            comparison: None,
+            scanned: Arc::new(AtomicU64::new(0)),
+            cancel: Arc::new(AtomicBool::new(false)),
+            receiver: None,
+        };
+        if right.is_some() {
+            comparison.start(ctx);
+        }
+        comparison
+    }
+
+    /// Starts comparing the folders, stopping an earlier comparison that is still running.
+    fn start(&mut self, ctx: &egui::Context) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancel = Arc::new(AtomicBool::new(false));
+        self.scanned = Arc::new(AtomicU64::new(0));
+        let (sender, receiver) = mpsc::channel();
+        self.receiver = Some(receiver);
+        let (left, right) = (PathBuf::from(self.left.trim()), PathBuf::from(self.right.trim()));
+        let (compare_contents, scanned, cancel, ctx) =
+            (self.compare_contents, self.scanned.clone(), self.cancel.clone(), ctx.clone());
+        rayon::spawn(move || {
+            let result = compare(&left, &right, compare_contents, &scanned, &cancel);
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            if sender.send(result).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Renders the comparison window.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<CompareAction> {
+        if let Some(receiver) = &self.receiver
+            && let Ok(comparison) = receiver.try_recv()
+        {
+            self.comparison = Some(comparison);
+            self.receiver = None;
+        }
+
+        let mut action = None;
+        let mut is_open = true;
+        egui::Window::new("⇄ Compare folders")
+            .open(&mut is_open)
+            .default_size([700.0, 500.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                egui::Grid::new("compare_folders").num_columns(2).show(ui, |ui| {
+                    ui.label("Left:");
+                    ui.add(egui::TextEdit::singleline(&mut self.left).desired_width(500.0));
+                    ui.end_row();
+                    ui.label("Right:");
+                    ui.add(egui::TextEdit::singleline(&mut self.right).desired_width(500.0));
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.compare_contents, "Compare contents")
+                        .on_hover_text("Also hash files of the same size, which is slower");
+                    let ready = !self.left.trim().is_empty() && !self.right.trim().is_empty();
+                    let label = if self.comparison.is_some() { "🔄 Compare again" } else { "Compare" };
+                    if ui.add_enabled(ready, egui::Button::new(label)).clicked() {
+                        self.start(ctx);
+                    }
+                });
+                ui.separator();
+
+                if self.receiver.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("Comparing... {} items so far", self.scanned.load(Ordering::Relaxed)));
+                    });
+                    repaint::request_throttled_repaint(ctx); // Keep the counter moving
+                    return;
+                }
+                let comparison = match &self.comparison {
+                    None => return,
+                    Some(Err(e)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                        return;
+                    }
+                    Some(Ok(comparison)) => comparison,
+                };
+
+                ui.horizontal(|ui| {
+                    let tabs = [
+                        (Tab::OnlyLeft, "Only left", comparison.only_left.len()),
+                        (Tab::OnlyRight, "Only right", comparison.only_right.len()),
+                        (Tab::Different, "Different", comparison.different.len()),
+                    ];
+                    for (tab, label, count) in tabs {
+                        ui.selectable_value(&mut self.tab, tab, format!("{} ({})", label, count));
+                    }
+                    ui.weak(format!("{} identical", comparison.identical));
+                });
+
+                // Entries of the tab, and where copying them goes
+                let (left, right) = (&comparison.left, &comparison.right);
+                let (differences, from, to, copy_label) = match self.tab {
+                    Tab::OnlyLeft => (&comparison.only_left, left, right, "➡"),
+                    Tab::OnlyRight => (&comparison.only_right, right, left, "⬅"),
+                    Tab::Different => (&comparison.different, left, right, ""),
+                };
+                let pair = |difference: &Difference| (from.join(&difference.relative), to.join(&difference.relative));
+                let copy_all = match self.tab {
+                    Tab::OnlyLeft => Some("Copy all to the right ➡"),
+                    Tab::OnlyRight => Some("⬅ Copy all to the left"),
+                    Tab::Different => None,
+                };
+                if let Some(copy_all) = copy_all
+                    && ui.add_enabled(!differences.is_empty(), egui::Button::new(copy_all)).clicked()
+                {
+                    action = Some(CompareAction::Copy(differences.iter().map(pair).collect()));
+                }
+
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                    egui::Grid::new("compare_differences").num_columns(4).striped(true).show(ui, |ui| {
+                        for difference in differences {
+                            let icon = if difference.is_dir { "📁" } else { "📄" };
+                            ui.label(format!("{} {}", icon, difference.relative.display()));
+                            let size = |size: Option<u64>| size.map(view::format_size).unwrap_or_default();
+                            ui.label(size(difference.left_size));
+                            ui.label(size(difference.right_size));
+                            if self.tab != Tab::Different
+                                && ui.small_button(copy_label).on_hover_text("Copy to the other side").clicked()
+                            {
+                                action = Some(CompareAction::Copy(vec![pair(difference)]));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        if !is_open {
+            action = Some(CompareAction::Close);
+        }
+        action
+    }
+}
+
+impl Drop for FolderComparison {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed); // Stop a scan that is still running
+    }
+}
+
+/// Every entry below `root`, by path relative to it. Symlinks are not followed.
+fn scan(root: &Path, scanned: &AtomicU64, cancel: &AtomicBool) -> BTreeMap<PathBuf, EntryInfo> {
+    let mut entries = BTreeMap::new();
+    for entry in WalkDir::new(root).min_depth(1).into_iter().filter_map(|e| e.ok()) {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        scanned.fetch_add(1, Ordering::Relaxed);
+        let is_dir = entry.file_type().is_dir();
+        let size = if is_dir { 0 } else { entry.metadata().map_or(0, |m| m.len()) };
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_owned();
+        entries.insert(relative, EntryInfo { is_dir, size });
+    }
+    entries
+}
+
+/// Whether a folder containing `relative` is missing from `other`.
+fn inside_missing(relative: &Path, other: &BTreeMap<PathBuf, EntryInfo>) -> bool {
+    relative.ancestors().skip(1).any(|ancestor| !ancestor.as_os_str().is_empty() && !other.contains_key(ancestor))
+}
+
+fn compare(
+    left: &Path,
+    right: &Path,
+    compare_contents: bool,
+    scanned: &AtomicU64,
+    cancel: &AtomicBool,
+) -> Result<Comparison, String> {
+    for folder in [left, right] {
+        if !folder.is_dir() {
+            return Err(format!("{} is not a folder", folder.display()));
+        }
+    }
+    if left == right {
+        return Err("Both sides are the same folder".to_string());
+    }
+    let (left_entries, right_entries) = rayon::join(|| scan(left, scanned, cancel), || scan(right, scanned, cancel));
+
+    let mut comparison = Comparison { left: left.to_owned(), right: right.to_owned(), ..Default::default() };
+    let mut same_size = Vec::new(); // Files to hash with `compare_contents`
+    for (relative, info) in &left_entries {
+        // Contents of folders missing on the other side are covered by the folder
+        match right_entries.get(relative) {
+            None if !inside_missing(relative, &right_entries) => comparison.only_left.push(Difference {
+                relative: relative.clone(),
+                is_dir: info.is_dir,
+                left_size: (!info.is_dir).then_some(info.size),
+                right_size: None,
+            }),
+            None => {}
+            Some(other) if info.is_dir && other.is_dir => {}
+            Some(other) if info == other && compare_contents => same_size.push(relative.clone()),
+            Some(other) if info == other => comparison.identical += 1,
+            Some(other) => comparison.different.push(Difference {
+                relative: relative.clone(),
+                is_dir: info.is_dir,
+                left_size: (!info.is_dir).then_some(info.size),
+                right_size: (!other.is_dir).then_some(other.size),
+            }),
+        }
+    }
+    for (relative, info) in &right_entries {
+        if !left_entries.contains_key(relative) && !inside_missing(relative, &left_entries) {
+            comparison.only_right.push(Difference {
+                relative: relative.clone(),
+                is_dir: info.is_dir,
+                left_size: None,
+                right_size: (!info.is_dir).then_some(info.size),
+            });
+        }
+    }
+
+    let hashed: Vec<(PathBuf, bool)> = same_size
+        .into_par_iter()
+        .filter(|_| !cancel.load(Ordering::Relaxed))
+        .map(|relative| {
+            let hashes = (fileops::sha256_file(&left.join(&relative)), fileops::sha256_file(&right.join(&relative)));
+            let equal = matches!(hashes, (Ok(a), Ok(b)) if a == b);
+            (relative, equal)
+        })
+        .collect();
+    for (relative, equal) in hashed {
+        if equal {
+            comparison.identical += 1;
+        } else {
+            let size = left_entries.get(&relative).map(|info| info.size);
+            comparison.different.push(Difference { relative, is_dir: false, left_size: size, right_size: size });
+        }
+    }
+    comparison.different.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(comparison)
+}
@@ -0,0 +1,492 @@
+// --- Read-only browsing of ISO 9660 disk images (.iso, and .img files holding one) ---
+//
+// Long names come from the Joliet extension when the image has one, the plain ISO 9660
+// names (8.3, upper case, ";1" version suffix) are used otherwise.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use eframe::egui;
+
+use crate::{
+    fileops::{self, ConflictChoice},
+    jobs::JobContext,
+    view,
+};
+
+const SECTOR_SIZE: u64 = 2048;
+/// Volume descriptors start after the 32 KiB system area.
+const FIRST_DESCRIPTOR: u64 = 16;
+/// Directories larger than this are treated as corrupt instead of being read into memory.
+const MAX_DIRECTORY_SIZE: u32 = 64 * 1024 * 1024;
+/// Folders nested deeper than this are treated as corrupt when extracting.
+const MAX_DEPTH: usize = 64;
+/// Set in the flags of a record that is followed by another extent of the same file.
+const MULTI_EXTENT: u8 = 0x80;
+
+/// A file or folder inside an image.
+#[derive(Clone)]
+pub struct ImageEntry {
+    pub name: String,
+    pub is_dir: bool,
+    extents: Vec<(u32, u64)>, // Start sector and length of each part; files over 4 GiB have several
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl ImageEntry {
+    /// Start of the contents. Folders always have a single extent.
+    fn sector(&self) -> u32 {
+        self.extents.first().map_or(0, |(sector, _)| *sector)
+    }
+}
+
+/// An opened image. Every read opens the file again, so it can be shared with background jobs.
+pub struct DiskImage {
+    path: PathBuf,
+    root: ImageEntry,
+    joliet: bool, // Names are UTF-16 (Joliet) instead of ISO 9660 characters
+}
+
+/// Whether `path` is an ISO 9660 image: an .iso or .img file with the "CD001" signature.
+pub fn is_disk_image(path: &Path) -> bool {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if extension != "iso" && extension != "img" {
+        return false;
+    }
+    let mut signature = [0; 6];
+    File::open(path)
+        .and_then(|mut file| {
+            file.seek(SeekFrom::Start(FIRST_DESCRIPTOR * SECTOR_SIZE))?;
+            file.read_exact(&mut signature)
+        })
+        .is_ok_and(|()| &signature[1..6] == b"CD001")
+}
+
+impl DiskImage {
+    /// Reads the volume descriptors, preferring the Joliet one for its long names.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let mut descriptor = vec![0; SECTOR_SIZE as usize];
+        let mut primary = None;
+        for sector in FIRST_DESCRIPTOR..FIRST_DESCRIPTOR + 64 {
+            file.seek(SeekFrom::Start(sector * SECTOR_SIZE))?;
+            file.read_exact(&mut descriptor)?;
+            if &descriptor[1..6] != b"CD001" {
+                break;
+            }
+            let root = parse_record(&descriptor[156..190], false);
+            match descriptor[0] {
+                1 => primary = root,
+                // Supplementary descriptor with one of the Joliet escape sequences "%/@", "%/C", "%/E"
+                2 if descriptor[88..90] == *b"%/" && matches!(descriptor[90], b'@' | b'C' | b'E') => {
+                    if let Some(root) = root {
+                        return Ok(Self { path: path.to_owned(), root, joliet: true });
+                    }
+                }
+                255 => break, // Terminator
+                _ => {}
+            }
+        }
+        match primary {
+            Some(root) => Ok(Self { path: path.to_owned(), root, joliet: false }),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, "not an ISO 9660 image")),
+        }
+    }
+
+    /// Lists `folder`, or the root with `None`.
+    pub fn read_dir(&self, folder: Option<&ImageEntry>) -> io::Result<Vec<ImageEntry>> {
+        let folder = folder.unwrap_or(&self.root);
+        if folder.size > MAX_DIRECTORY_SIZE as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "directory too large"));
+        }
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(folder.sector() as u64 * SECTOR_SIZE))?;
+        let mut data = vec![0; folder.size as usize];
+        file.read_exact(&mut data)?;
+        Ok(parse_directory(&data, self.joliet))
+    }
+
+    /// Copies `entry` (with everything inside for folders) into the folder `destination`.
+    /// Files that exist already are replaced or skipped as `on_conflict` says. `visited` holds
+    /// the folders extracted so far, a folder listed a second time (e.g. a crafted image pointing
+    /// back at an ancestor) is an error. Returns the number of files written.
+    fn extract(
+        &self,
+        entry: &ImageEntry,
+        destination: &Path,
+        on_conflict: ConflictChoice,
+        job: &JobContext,
+        visited: &mut HashSet<u32>,
+        depth: usize,
+    ) -> io::Result<usize> {
+        if job.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "extraction cancelled"));
+        }
+        // Names come from the image and must not escape the destination
+        let target = fileops::safe_child_path(destination, &entry.name)?;
+        if entry.is_dir {
+            if depth >= MAX_DEPTH || !visited.insert(entry.sector()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("folder loop at {:?}", entry.name)));
+            }
+            fs::create_dir_all(&target)?;
+            let mut count = 0;
+            for child in self.read_dir(Some(entry))? {
+                count += self.extract(&child, &target, on_conflict, job, visited, depth + 1)?;
+            }
+            return Ok(count);
+        }
+        let written = fileops::write_new_file(&target, on_conflict, |output| self.copy_extents(entry, output, job))?;
+        Ok(written as usize)
+    }
+
+    /// Writes the contents of the file `entry` to `output`, one extent after the other.
+    fn copy_extents(&self, entry: &ImageEntry, output: &mut File, job: &JobContext) -> io::Result<()> {
+        let mut file = File::open(&self.path)?;
+        let mut buffer = vec![0; 1024 * 1024];
+        for &(sector, length) in &entry.extents {
+            file.seek(SeekFrom::Start(sector as u64 * SECTOR_SIZE))?;
+            let mut reader = (&mut file).take(length);
+            loop {
+                if job.is_cancelled() {
+                    return Err(io::Error::new(io::ErrorKind::Interrupted, "extraction cancelled"));
+                }
+                let read = reader.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                output.write_all(&buffer[..read])?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The entries of a directory's records, folders first. The extents of a file stored in several
+/// parts are joined into one entry.
+fn parse_directory(data: &[u8], joliet: bool) -> Vec<ImageEntry> {
+    let mut entries: Vec<ImageEntry> = Vec::new();
+    let mut continued = false; // The last record said another extent follows
+    let mut offset = 0;
+    while offset < data.len() {
+        let length = data[offset] as usize;
+        if length == 0 {
+            // Records don't cross sectors, the rest of this one is padding
+            offset = (offset / SECTOR_SIZE as usize + 1) * SECTOR_SIZE as usize;
+            continue;
+        }
+        let Some(record) = data.get(offset..offset + length) else {
+            break;
+        };
+        offset += length;
+        // The first two records are the folder itself and its parent
+        let Some(entry) = parse_record(record, joliet).filter(|entry| !entry.name.is_empty()) else {
+            continue;
+        };
+        let follows = continued && entries.last().is_some_and(|last| last.name == entry.name && !entry.is_dir);
+        continued = record[25] & MULTI_EXTENT != 0;
+        match entries.last_mut() {
+            Some(last) if follows => {
+                last.extents.extend(entry.extents);
+                last.size += entry.size;
+            }
+            _ => entries.push(entry),
+        }
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| view::natural_cmp(&a.name, &b.name)));
+    entries
+}
+
+/// Parses a directory record. The folder itself and its parent get an empty name.
+fn parse_record(record: &[u8], joliet: bool) -> Option<ImageEntry> {
+    if record.len() < 34 {
+        return None;
+    }
+    let name_length = record[32] as usize;
+    let raw_name = record.get(33..33 + name_length)?;
+    let is_dir = record[25] & 0x02 != 0;
+    let name = if raw_name == [0] || raw_name == [1] {
+        String::new()
+    } else if joliet {
+        let units: Vec<u16> = raw_name.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        String::from_utf8_lossy(raw_name).into_owned()
+    };
+    // Files end with a version number (";1"), and with a dot when they have no extension
+    let name = match name.rsplit_once(';') {
+        Some((name, _)) if !is_dir => name.trim_end_matches('.').to_string(),
+        _ => name,
+    };
+    let sector = u32::from_le_bytes(record[2..6].try_into().ok()?);
+    let size = u32::from_le_bytes(record[10..14].try_into().ok()?) as u64;
+    Some(ImageEntry { name, is_dir, extents: vec![(sector, size)], size, modified: parse_date(&record[18..25]) })
+}
+
+/// Parses the 7 byte date of a record: years since 1900, month, day, hour, minute, second
+/// and the offset from UTC in 15 minute steps.
+fn parse_date(date: &[u8]) -> Option<SystemTime> {
+    let local = chrono::NaiveDate::from_ymd_opt(1900 + date[0] as i32, date[1] as u32, date[2] as u32)?
+        .and_hms_opt(date[3] as u32, date[4] as u32, date[5] as u32)?;
+    let offset = chrono::FixedOffset::east_opt(date[6] as i8 as i32 * 15 * 60)?;
+    Some(local.and_local_timezone(offset).single()?.into())
+}
+
+/// Extraction ready to run as a background job.
+pub struct ExtractJob {
+    image: Arc<DiskImage>,
+    entries: Vec<ImageEntry>,
+    destination: PathBuf,
+    ancestors: HashSet<u32>, // Sectors of the listed folder and the ones above it
+    on_conflict: ConflictChoice,
+}
+
+impl ExtractJob {
+    /// Entries that already exist in the destination.
+    pub fn existing_targets(&self) -> Vec<PathBuf> {
+        self.entries
+            .iter()
+            .filter_map(|entry| fileops::safe_child_path(&self.destination, &entry.name).ok())
+            .filter(|target| fs::symlink_metadata(target).is_ok())
+            .collect()
+    }
+
+    /// Decides what happens to files that exist already; they are skipped unless this says otherwise.
+    pub fn set_on_conflict(&mut self, choice: ConflictChoice) {
+        self.on_conflict = choice;
+    }
+
+    pub fn run(self, job: &JobContext) {
+        let total = self.entries.len();
+        let mut visited = self.ancestors;
+        for (done, entry) in self.entries.iter().enumerate() {
+            if let Err(e) = self.image.extract(entry, &self.destination, self.on_conflict, job, &mut visited, 0) {
+                eprintln!("Error while extracting {:?} from {:?}: {}", entry.name, self.image.path, e);
+                if e.kind() == io::ErrorKind::Interrupted {
+                    return;
+                }
+            }
+            job.progress(done + 1, total);
+        }
+    }
+}
+
+pub enum ImageAction {
+    Close,
+    /// Extract these entries as a background job.
+    Extract(ExtractJob),
+}
+
+/// Window browsing the folders of one image. Click selects (Ctrl+click adds to the
+/// selection), double-clicking a folder opens it.
+pub struct ImageBrowser {
+    image: Arc<DiskImage>,
+    folders: Vec<ImageEntry>, // From the root down to the listed folder, empty at the root
+    listing: Result<Vec<ImageEntry>, String>,
+    selected: HashSet<String>, // Names in the listed folder
+}
+
+impl ImageBrowser {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let image = DiskImage::open(path)?;
+        let listing = image.read_dir(None).map_err(|e| e.to_string());
+        Ok(Self { image: Arc::new(image), folders: Vec::new(), listing, selected: HashSet::new() })
+    }
+
+    fn navigate(&mut self, folders: Vec<ImageEntry>) {
+        self.listing = self.image.read_dir(folders.last()).map_err(|e| e.to_string());
+        self.folders = folders;
+        self.selected.clear();
+    }
+
+    /// Renders the window. Extracted entries go to `destination`.
+    pub fn show(&mut self, ctx: &egui::Context, destination: &Path) -> Option<ImageAction> {
+        let mut action = None;
+        let mut is_open = true;
+        let title = format!("💿 {}", self.image.path.file_name().unwrap_or_default().to_string_lossy());
+        egui::Window::new(title)
+            .id(egui::Id::new("image_browser"))
+            .open(&mut is_open)
+            .default_size([600.0, 450.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                let go_up = ui
+                    .horizontal(|ui| {
+                        let up = ui.add_enabled(!self.folders.is_empty(), egui::Button::new("⬆ Up")).clicked();
+                        let names: Vec<&str> = self.folders.iter().map(|folder| folder.name.as_str()).collect();
+                        ui.label(format!("/{}", names.join("/")));
+                        up
+                    })
+                    .inner;
+                if go_up {
+                    self.navigate(self.folders[..self.folders.len() - 1].to_vec());
+                }
+
+                let entries = match &self.listing {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                        return;
+                    }
+                };
+                ui.horizontal(|ui| {
+                    let selection: Vec<ImageEntry> =
+                        entries.iter().filter(|entry| self.selected.contains(&entry.name)).cloned().collect();
+                    let hover = format!("Into {}", destination.display());
+                    let extract_selected = egui::Button::new(format!("Extract {} items", selection.len()));
+                    let extract_selected = ui.add_enabled(!selection.is_empty(), extract_selected).on_hover_text(&hover);
+                    let extract = if extract_selected.clicked() {
+                        Some(selection)
+                    } else if ui.button("Extract this folder").on_hover_text(&hover).clicked() {
+                        Some(entries.clone())
+                    } else {
+                        None
+                    };
+                    if let Some(entries) = extract {
+                        let (image, destination) = (self.image.clone(), destination.to_owned());
+                        let folders = std::iter::once(&self.image.root).chain(&self.folders);
+                        let ancestors = folders.map(ImageEntry::sector).collect();
+                        let on_conflict = ConflictChoice::Skip;
+                        let extraction = ExtractJob { image, entries, destination, ancestors, on_conflict };
+                        action = Some(ImageAction::Extract(extraction));
+                    }
+                });
+                ui.separator();
+
+                if entries.is_empty() {
+                    ui.weak("(empty folder)");
+                }
+                let mut navigate = None;
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                    egui::Grid::new("image_listing").num_columns(3).striped(true).show(ui, |ui| {
+                        for entry in entries {
+                            let icon = if entry.is_dir { "📁" } else { "📄" };
+                            let selected = self.selected.contains(&entry.name);
+                            let response = ui.selectable_label(selected, format!("{} {}", icon, entry.name));
+                            if response.double_clicked() && entry.is_dir {
+                                let mut folders = self.folders.clone();
+                                folders.push(entry.clone());
+                                navigate = Some(folders);
+                            } else if response.clicked() {
+                                if !ui.input(|i| i.modifiers.command) {
+                                    self.selected.clear();
+                                }
+                                if !self.selected.insert(entry.name.clone()) {
+                                    self.selected.remove(&entry.name);
+                                }
+                            }
+                            ui.label(if entry.is_dir { String::new() } else { view::format_size(entry.size) });
+                            ui.label(entry.modified.map(view::format_time).unwrap_or_default());
+                            ui.end_row();
+                        }
+                    });
+                });
+                if let Some(folders) = navigate {
+                    self.navigate(folders);
+                }
+            });
+        if !is_open {
+            action = Some(ImageAction::Close);
+        }
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory record as stored in an image.
+    fn record(name: &[u8], sector: u32, size: u32, flags: u8) -> Vec<u8> {
+        let mut record = vec![0; 33];
+        record[2..6].copy_from_slice(&sector.to_le_bytes());
+        record[6..10].copy_from_slice(&sector.to_be_bytes());
+        record[10..14].copy_from_slice(&size.to_le_bytes());
+        record[14..18].copy_from_slice(&size.to_be_bytes());
+        record[18..25].copy_from_slice(&[120, 6, 15, 12, 30, 0, 8]); // 2020-06-15 12:30 UTC+2
+        record[25] = flags;
+        record[32] = name.len() as u8;
+        record.extend_from_slice(name);
+        if record.len() % 2 == 1 {
+            record.push(0);
+        }
+        record[0] = record.len() as u8;
+        record
+    }
+
+    /// A directory with its "." and ".." records, then `records`.
+    fn directory(sector: u32, records: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = record(&[0], sector, SECTOR_SIZE as u32, 0x02);
+        data.extend(record(&[1], sector, SECTOR_SIZE as u32, 0x02));
+        for record in records {
+            data.extend(record);
+        }
+        data.resize(SECTOR_SIZE as usize, 0);
+        data
+    }
+
+    #[test]
+    fn parses_iso_names_and_dates() {
+        let entry = parse_record(&record(b"README.;1", 30, 512, 0), false).unwrap();
+        assert_eq!(entry.name, "README");
+        assert_eq!((entry.sector(), entry.size, entry.is_dir), (30, 512, false));
+        let expected = chrono::DateTime::parse_from_rfc3339("2020-06-15T12:30:00+02:00").unwrap();
+        assert_eq!(entry.modified, Some(expected.into()));
+
+        let folder = parse_record(&record(b"DOCS", 40, 2048, 0x02), false).unwrap();
+        assert_eq!((folder.name.as_str(), folder.is_dir), ("DOCS", true));
+
+        let joliet_name: Vec<u8> = "Long name.txt;1".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(parse_record(&record(&joliet_name, 50, 1, 0), true).unwrap().name, "Long name.txt");
+
+        assert!(parse_record(&[0; 20], false).is_none());
+        assert!(parse_record(&record(&[0], 20, 2048, 0x02), false).unwrap().name.is_empty());
+    }
+
+    #[test]
+    fn joins_the_extents_of_large_files() {
+        let data = directory(
+            20,
+            &[
+                record(b"BIG.BIN;1", 100, 4_294_965_248, MULTI_EXTENT),
+                record(b"BIG.BIN;1", 2_097_250, 1000, 0),
+                record(b"SMALL.TXT;1", 200, 10, 0),
+            ],
+        );
+        let entries = parse_directory(&data, false);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "BIG.BIN");
+        assert_eq!(entries[0].extents, [(100, 4_294_965_248), (2_097_250, 1000)]);
+        assert_eq!(entries[0].size, 4_294_966_248);
+        assert_eq!(entries[1].name, "SMALL.TXT");
+    }
+
+    #[test]
+    fn extracting_a_folder_loop_fails_instead_of_recursing_forever() {
+        let folder = std::env::temp_dir().join(format!("filefox-iso-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(folder.join("out")).unwrap();
+
+        // Sector 20 is the root, its folder LOOP points at sector 21, whose folder BACK points at the root
+        let mut data = vec![0; 20 * SECTOR_SIZE as usize];
+        data.extend(directory(20, &[record(b"LOOP", 21, SECTOR_SIZE as u32, 0x02)]));
+        data.extend(directory(21, &[record(b"BACK", 20, SECTOR_SIZE as u32, 0x02)]));
+        let path = folder.join("loop.iso");
+        fs::write(&path, data).unwrap();
+        let root = parse_record(&record(&[0], 20, SECTOR_SIZE as u32, 0x02), false).unwrap();
+        let image = DiskImage { path, root, joliet: false };
+
+        let entries = image.read_dir(None).unwrap();
+        let job = JobContext::detached();
+        let mut visited = HashSet::from([20]);
+        let result = image.extract(&entries[0], &folder.join("out"), ConflictChoice::Skip, &job, &mut visited, 0);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let _ = fs::remove_dir_all(&folder);
+    }
+}
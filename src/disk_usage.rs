@@ -0,0 +1,302 @@
+// --- Disk usage analyzer (treemap of a folder, scanned in the background) ---
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+};
+
+use eframe::egui;
+use rayon::prelude::*;
+
+use crate::{repaint, size_cache::SizeCache, view};
+
+/// Children are kept this many levels below the analyzed folder; deeper levels only count towards sizes.
+/// Sizes of folders down to this level are cached; at this level, unchanged folders are taken from the cache.
+const MAX_DEPTH: usize = 6;
+/// Per folder, only the largest children are kept, the rest is merged into one node.
+const MAX_CHILDREN: usize = 200;
+/// Segments smaller than this (in points) get no label.
+const MIN_LABEL_SIZE: egui::Vec2 = egui::vec2(60.0, 18.0);
+
+/// A file or folder with its total size.
+pub struct UsageNode {
+    pub name: String,
+    pub path: Option<PathBuf>, // `None` for the merged "smaller items" node
+    pub size: u64,
+    pub is_dir: bool,
+    pub children: Vec<UsageNode>, // Largest first
+}
+
+/// Requested by the analyzer window.
+pub enum UsageAction {
+    Close,
+    /// Show this folder in the listing.
+    Navigate(PathBuf),
+    /// Show this file in its folder.
+    Reveal(PathBuf),
+}
+
+/// Analyzer window for one folder.
+pub struct DiskUsage {
+    root: PathBuf,
+    tree: Option<UsageNode>,
+    focus: Vec<usize>, // Child indices from the root to the folder shown in the treemap
+    scanned: Arc<AtomicU64>, // Entries scanned so far
+    cancel: Arc<AtomicBool>,
+    receiver: Option<mpsc::Receiver<UsageNode>>,
+}
+
+impl DiskUsage {
+    /// Starts scanning `root` on rayon's thread pool.
+    pub fn analyze(root: &Path, cache: Arc<SizeCache>, ctx: &egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let scanned = Arc::new(AtomicU64::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (thread_root, thread_scanned, thread_cancel, ctx) = (root.to_owned(), scanned.clone(), cancel.clone(), ctx.clone());
+        rayon::spawn(move || {
+            let name = thread_root.display().to_string();
+            let tree = scan(&thread_root, name, 0, &thread_scanned, &thread_cancel, &cache);
+            if thread_cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            if sender.send(tree).is_ok() {
+                ctx.request_repaint();
+            }
+            if let Err(e) = cache.save_if_changed() {
+                eprintln!("Error while saving folder sizes: {}", e);
+            }
+        });
+        Self { root: root.to_owned(), tree: None, focus: Vec::new(), scanned, cancel, receiver: Some(receiver) }
+    }
+
+    /// Node currently shown in the treemap.
+    fn focused(&self) -> Option<&UsageNode> {
+        let mut node = self.tree.as_ref()?;
+        for &index in &self.focus {
+            node = node.children.get(index)?;
+        }
+        Some(node)
+    }
+
+    /// Renders the analyzer window.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<UsageAction> {
+        if let Some(receiver) = &self.receiver
+            && let Ok(tree) = receiver.try_recv()
+        {
+            self.tree = Some(tree);
+            self.receiver = None;
+        }
+
+        let mut action = None;
+        let mut is_open = true;
+        egui::Window::new("📊 Disk usage")
+            .open(&mut is_open)
+            .default_size([800.0, 600.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                let Some(node) = self.focused() else {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!(
+                            "Scanning {} ({} items so far)",
+                            self.root.display(),
+                            self.scanned.load(Ordering::Relaxed)
+                        ));
+                    });
+                    repaint::request_throttled_repaint(ctx); // Keep the counter moving
+                    return;
+                };
+
+                let mut focus_change = None;
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!self.focus.is_empty(), egui::Button::new("⬆ Up")).clicked() {
+                        focus_change = Some(None);
+                    }
+                    ui.label(format!("{} — {}", node.path.as_deref().unwrap_or(&self.root).display(), view::format_size(node.size)));
+                    if let Some(path) = &node.path
+                        && ui.button("Show in listing").clicked()
+                    {
+                        action = Some(UsageAction::Navigate(path.clone()));
+                    }
+                });
+                ui.weak("Click a folder to look inside, double-click a file to show it in the listing.");
+                ui.separator();
+
+                let (rect, _) = ui.allocate_exact_size(ui.available_size(), egui::Sense::hover());
+                let sizes: Vec<f64> = node.children.iter().map(|child| child.size as f64).collect();
+                for (index, (child, child_rect)) in node.children.iter().zip(squarify(&sizes, rect)).enumerate() {
+                    if child_rect.width() < 1.0 || child_rect.height() < 1.0 {
+                        continue;
+                    }
+                    let response = ui
+                        .interact(child_rect, ui.id().with(("usage_segment", index)), egui::Sense::click())
+                        .on_hover_text(format!("{}\n{}", child.name, view::format_size(child.size)));
+                    let fill = segment_color(child, index, response.hovered());
+                    ui.painter().rect(child_rect.shrink(1.0), 2.0, fill, egui::Stroke::NONE);
+                    if child_rect.width() >= MIN_LABEL_SIZE.x && child_rect.height() >= MIN_LABEL_SIZE.y {
+                        let label = format!("{}\n{}", child.name, view::format_size(child.size));
+                        ui.painter().with_clip_rect(child_rect.shrink(2.0)).text(
+                            child_rect.left_top() + egui::vec2(4.0, 3.0),
+                            egui::Align2::LEFT_TOP,
+                            label,
+                            egui::TextStyle::Small.resolve(ui.style()),
+                            egui::Color32::WHITE,
+                        );
+                    }
+                    if child.is_dir && !child.children.is_empty() && response.clicked() {
+                        focus_change = Some(Some(index));
+                    }
+                    if let Some(path) = &child.path
+                        && response.double_clicked()
+                    {
+                        action = Some(if child.is_dir {
+                            UsageAction::Navigate(path.clone())
+                        } else {
+                            UsageAction::Reveal(path.clone())
+                        });
+                    }
+                }
+
+                match focus_change {
+                    Some(Some(index)) => self.focus.push(index),
+                    Some(None) => {
+                        self.focus.pop();
+                    }
+                    None => {}
+                }
+            });
+        if !is_open {
+            self.cancel.store(true, Ordering::Relaxed);
+            action = Some(UsageAction::Close);
+        }
+        action
+    }
+}
+
+impl Drop for DiskUsage {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed); // Stop a scan that is still running
+    }
+}
+
+/// Scans `path` recursively. Symlinks are not followed; unreadable folders count as empty.
+fn scan(path: &Path, name: String, depth: usize, scanned: &AtomicU64, cancel: &AtomicBool, cache: &SizeCache) -> UsageNode {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    if depth >= MAX_DEPTH
+        && let Some(size) = modified.and_then(|modified| cache.get(path, modified))
+    {
+        return UsageNode { name, path: Some(path.to_owned()), size, is_dir: true, children: Vec::new() };
+    }
+    let entries: Vec<fs::DirEntry> = fs::read_dir(path).map(|entries| entries.flatten().collect()).unwrap_or_default();
+    let mut children: Vec<UsageNode> = entries
+        .par_iter()
+        .filter_map(|entry| {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            scanned.fetch_add(1, Ordering::Relaxed);
+            let file_type = entry.file_type().ok()?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if file_type.is_dir() {
+                Some(scan(&entry.path(), name, depth + 1, scanned, cancel, cache))
+            } else {
+                let size = if file_type.is_file() { entry.metadata().map(|m| m.len()).unwrap_or(0) } else { 0 };
+                Some(UsageNode { name, path: Some(entry.path()), size, is_dir: false, children: Vec::new() })
+            }
+        })
+        .collect();
+    let size = children.iter().map(|child| child.size).sum();
+    if depth <= MAX_DEPTH
+        && let Some(modified) = modified
+        && !cancel.load(Ordering::Relaxed)
+    {
+        cache.insert(path.to_owned(), modified, size);
+    }
+
+    if depth >= MAX_DEPTH {
+        children.clear(); // Only the size is needed this deep
+    } else {
+        children.retain(|child| child.size > 0);
+        children.sort_unstable_by_key(|child| std::cmp::Reverse(child.size));
+        if children.len() > MAX_CHILDREN {
+            let rest = children.split_off(MAX_CHILDREN - 1);
+            children.push(UsageNode {
+                name: format!("{} smaller items", rest.len()),
+                path: None,
+                size: rest.iter().map(|child| child.size).sum(),
+                is_dir: false,
+                children: Vec::new(),
+            });
+        }
+    }
+    UsageNode { name, path: Some(path.to_owned()), size, is_dir: true, children }
+}
+
+/// Folders get a hue per segment, files are gray.
+fn segment_color(node: &UsageNode, index: usize, hovered: bool) -> egui::Color32 {
+    let color = if node.is_dir {
+        let hue = (index as f32 * 0.13) % 1.0;
+        egui::Color32::from(egui::ecolor::Hsva::new(hue, 0.5, 0.6, 1.0))
+    } else {
+        egui::Color32::from_gray(110)
+    };
+    if hovered { color.linear_multiply(1.3) } else { color }
+}
+
+/// Squarified treemap layout: splits `rect` into one rectangle per size (sorted largest first),
+/// with areas proportional to the sizes and aspect ratios kept close to square.
+fn squarify(sizes: &[f64], rect: egui::Rect) -> Vec<egui::Rect> {
+    let total: f64 = sizes.iter().sum();
+    if total <= 0.0 || rect.area() <= 0.0 {
+        return vec![egui::Rect::NOTHING; sizes.len()];
+    }
+    let scale = rect.area() as f64 / total;
+    let areas: Vec<f64> = sizes.iter().map(|size| size * scale).collect();
+
+    // Worst aspect ratio of a row of `row` areas laid along a side of length `side`
+    let worst = |row: &[f64], side: f64| {
+        let sum: f64 = row.iter().sum();
+        let (max, min) = row.iter().fold((0.0f64, f64::MAX), |(max, min), &area| (max.max(area), min.min(area)));
+        (side * side * max / (sum * sum)).max(sum * sum / (side * side * min))
+    };
+
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut remaining = rect;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = remaining.width().min(remaining.height()) as f64;
+        let mut end = start + 1;
+        while end < areas.len() && worst(&areas[start..=end], side) <= worst(&areas[start..end], side) {
+            end += 1;
+        }
+
+        // Lay the row out along the shorter side and shrink the remaining space
+        let row_area: f64 = areas[start..end].iter().sum();
+        if remaining.width() >= remaining.height() {
+            let width = (row_area / remaining.height() as f64) as f32;
+            let mut y = remaining.top();
+            for &area in &areas[start..end] {
+                let height = (area / width as f64) as f32;
+                rects.push(egui::Rect::from_min_size(egui::pos2(remaining.left(), y), egui::vec2(width, height)));
+                y += height;
+            }
+            remaining.min.x += width;
+        } else {
+            let height = (row_area / remaining.width() as f64) as f32;
+            let mut x = remaining.left();
+            for &area in &areas[start..end] {
+                let width = (area / height as f64) as f32;
+                rects.push(egui::Rect::from_min_size(egui::pos2(x, remaining.top()), egui::vec2(width, height)));
+                x += width;
+            }
+            remaining.min.y += height;
+        }
+        start = end;
+    }
+    rects
+}
@@ -0,0 +1,96 @@
+// --- Native drag source (dragging entries out into other applications) ---
+
+use std::path::Path;
+
+/// Starts a native drag operation carrying `path`, so it can be dropped into
+/// other applications (mail clients, editors, Explorer, ...).
+/// Blocks until the drop finished or was cancelled.
+/// Returns `false` if the drag could not be started on this platform.
+#[cfg(windows)]
+pub fn start_file_drag(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+
+    use winapi::Interface;
+    use winapi::ctypes::c_void;
+    use winapi::shared::guiddef::GUID;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::windef::HWND;
+    use winapi::shared::winerror::SUCCEEDED;
+    use winapi::um::objidl::IDataObject;
+    use winapi::um::ole2::OleInitialize;
+    use winapi::um::oleidl::{DROPEFFECT_COPY, DROPEFFECT_LINK, IDropSource};
+    use winapi::um::shobjidl_core::{IShellItem, SHCreateItemFromParsingName};
+    use winapi::um::winnt::HRESULT;
+    use winapi::um::winuser::GetActiveWindow;
+
+    // BHID_DataObject: {B8C0BD9F-ED24-455C-83E6-D5390C4FE8C4}
+    const BHID_DATA_OBJECT: GUID = GUID {
+        Data1: 0xb8c0_bd9f,
+        Data2: 0xed24,
+        Data3: 0x455c,
+        Data4: [0x83, 0xe6, 0xd5, 0x39, 0x0c, 0x4f, 0xe8, 0xc4],
+    };
+
+    #[link(name = "shell32")]
+    unsafe extern "system" {
+        // Not exported by winapi; uses a default drop source when `pdsrc` is null.
+        fn SHDoDragDrop(
+            hwnd: HWND,
+            pdata: *mut IDataObject,
+            pdsrc: *mut IDropSource,
+            dweffect: DWORD,
+            pdweffect: *mut DWORD,
+        ) -> HRESULT;
+    }
+
+    // The shell parses display names and doesn't know extended-length paths
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+
+    unsafe {
+        // Safe to call repeatedly; winit already initializes OLE for drop support.
+        OleInitialize(ptr::null_mut());
+
+        let mut item: *mut c_void = ptr::null_mut();
+        if !SUCCEEDED(SHCreateItemFromParsingName(
+            wide_path.as_ptr(),
+            ptr::null_mut(),
+            &IShellItem::uuidof(),
+            &mut item,
+        )) {
+            return false;
+        }
+        let item = item as *mut IShellItem;
+
+        let mut data_object: *mut c_void = ptr::null_mut();
+        let bound = (*item).BindToHandler(
+            ptr::null_mut(),
+            &BHID_DATA_OBJECT,
+            &IDataObject::uuidof(),
+            &mut data_object,
+        );
+        (*item).Release();
+        if !SUCCEEDED(bound) {
+            return false;
+        }
+        let data_object = data_object as *mut IDataObject;
+
+        let mut effect: DWORD = 0;
+        let result = SHDoDragDrop(
+            GetActiveWindow(),
+            data_object,
+            ptr::null_mut(),
+            DROPEFFECT_COPY | DROPEFFECT_LINK,
+            &mut effect,
+        );
+        (*data_object).Release();
+        SUCCEEDED(result)
+    }
+}
+
+/// Dragging entries out of the window needs a native drag source,
+/// which is only implemented on Windows so far.
+#[cfg(not(windows))]
+pub fn start_file_drag(_path: &Path) -> bool {
+    false
+}
@@ -0,0 +1,287 @@
+// --- Drives and mounted volumes, for the "Drives" menu ---
+
+use std::path::PathBuf;
+
+use crate::fileops;
+
+/// File systems of network shares in /proc/mounts.
+#[cfg(all(unix, not(target_os = "macos")))]
+const NETWORK_FILESYSTEMS: [&str; 7] = ["nfs", "nfs4", "cifs", "smb3", "smbfs", "fuse.sshfs", "fuse.rclone"];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DriveKind {
+    Fixed,
+    Removable,
+    Network,
+    Optical,
+    Device, // Phones and cameras connected over MTP or PTP
+}
+
+impl DriveKind {
+    pub fn icon(self) -> &'static str {
+        match self {
+            Self::Fixed => "🖴",
+            Self::Removable => "💾",
+            Self::Network => "🖧",
+            Self::Optical => "💿",
+            Self::Device => "📱",
+        }
+    }
+}
+
+/// A drive letter (Windows) or mount point (elsewhere).
+pub struct Drive {
+    pub path: PathBuf,
+    pub label: String,
+    pub kind: DriveKind,
+    pub space: Option<(u64, u64)>, // (free, total) bytes
+}
+
+/// All drives with a drive letter.
+#[cfg(windows)]
+pub fn list_drives() -> Vec<Drive> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::{GetDriveTypeW, GetLogicalDrives, GetVolumeInformationW};
+    use winapi::um::winbase::{DRIVE_CDROM, DRIVE_FIXED, DRIVE_REMOTE, DRIVE_REMOVABLE};
+
+    let mask = unsafe { GetLogicalDrives() };
+    let mut drives = Vec::new();
+    for (index, letter) in (b'A'..=b'Z').enumerate() {
+        if mask & (1 << index) == 0 {
+            continue;
+        }
+        let path = PathBuf::from(format!("{}:\\", letter as char));
+        let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let kind = match unsafe { GetDriveTypeW(wide_path.as_ptr()) } {
+            DRIVE_FIXED => DriveKind::Fixed,
+            DRIVE_REMOVABLE => DriveKind::Removable,
+            DRIVE_REMOTE => DriveKind::Network,
+            DRIVE_CDROM => DriveKind::Optical,
+            _ => continue, // Unknown or no root directory
+        };
+        let mut name = [0u16; 261];
+        let has_name = unsafe {
+            GetVolumeInformationW(
+                wide_path.as_ptr(),
+                name.as_mut_ptr(),
+                name.len() as u32,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                0,
+            )
+        } != 0;
+        let name_len = if has_name { name.iter().position(|&c| c == 0).unwrap_or(name.len()) } else { 0 };
+        let name = String::from_utf16_lossy(&name[..name_len]);
+        let label = if name.is_empty() { format!("{}:", letter as char) } else { format!("{} ({}:)", name, letter as char) };
+        let space = fileops::disk_space(&path);
+        drives.push(Drive { path, label, kind, space });
+    }
+    drives
+}
+
+/// The root volume plus everything mounted in /Volumes.
+#[cfg(target_os = "macos")]
+pub fn list_drives() -> Vec<Drive> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut drives = vec![Drive {
+        path: PathBuf::from("/"),
+        label: "/".to_string(),
+        kind: DriveKind::Fixed,
+        space: fileops::disk_space(std::path::Path::new("/")),
+    }];
+    let Ok(volumes) = std::fs::read_dir("/Volumes") else {
+        return drives;
+    };
+    for entry in volumes.flatten() {
+        if entry.file_type().is_ok_and(|file_type| file_type.is_symlink()) {
+            continue; // The boot volume links back to "/"
+        }
+        let path = entry.path();
+        let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            continue;
+        };
+        let is_local = unsafe {
+            let mut stat: libc::statfs = std::mem::zeroed();
+            libc::statfs(c_path.as_ptr(), &mut stat) == 0 && stat.f_flags & libc::MNT_LOCAL as u32 != 0
+        };
+        drives.push(Drive {
+            label: entry.file_name().to_string_lossy().to_string(),
+            kind: if is_local { DriveKind::Removable } else { DriveKind::Network },
+            space: fileops::disk_space(&path),
+            path,
+        });
+    }
+    drives
+}
+
+/// File systems listed in /proc/mounts that are not pseudo file systems (proc, tmpfs, cgroups, snaps...),
+/// plus the phones and cameras GVfs mounted.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn list_drives() -> Vec<Drive> {
+    const DEVICE_FILESYSTEMS: [&str; 3] = ["fuse.jmtpfs", "fuse.simple-mtpfs", "fuse.go-mtpfs"];
+
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return Vec::new();
+    };
+    let mut drives: Vec<Drive> = Vec::new();
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(device), Some(mount_point), Some(fs_type)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let path = PathBuf::from(unescape_mount_field(mount_point));
+        let kind = if NETWORK_FILESYSTEMS.contains(&fs_type) {
+            DriveKind::Network
+        } else if DEVICE_FILESYSTEMS.contains(&fs_type) {
+            DriveKind::Device
+        } else if !device.starts_with("/dev/") || device.starts_with("/dev/loop") {
+            continue;
+        } else if fs_type == "iso9660" || fs_type == "udf" {
+            DriveKind::Optical
+        } else if is_removable_device(device) || path.starts_with("/media") || path.starts_with("/run/media") {
+            DriveKind::Removable // udisks mounts removable media below /media or /run/media
+        } else {
+            DriveKind::Fixed
+        };
+        if drives.iter().any(|drive| drive.path == path) {
+            continue; // Mounted more than once (e.g. bind mounts)
+        }
+        let label = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => "/".to_string(),
+        };
+        let space = fileops::disk_space(&path);
+        drives.push(Drive { path, label, kind, space });
+    }
+    drives.extend(gvfs_devices());
+    drives
+}
+
+/// Phones (MTP) and cameras (PTP) mounted by GVfs, which keeps them in folders like
+/// "mtp:host=SAMSUNG_SAMSUNG_Android_R58M12345" below /run/user/<uid>/gvfs.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn gvfs_devices() -> Vec<Drive> {
+    let runtime_dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => PathBuf::from(runtime_dir),
+        None => PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() })),
+    };
+    let Ok(mounts) = std::fs::read_dir(runtime_dir.join("gvfs")) else {
+        return Vec::new();
+    };
+    let mut devices = Vec::new();
+    for entry in mounts.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(host) = name.strip_prefix("mtp:host=").or_else(|| name.strip_prefix("gphoto2:host=")) else {
+            continue;
+        };
+        // The host is the vendor, model and serial number with "_" for spaces. Older GVfs
+        // versions use the USB port instead, e.g. "%5Busb%3A002%2C007%5D".
+        let label = match host.rsplit_once('_') {
+            _ if host.starts_with('%') => "Portable device",
+            Some((model, serial)) if serial.len() >= 8 && serial.chars().all(|c| c.is_ascii_alphanumeric()) => model,
+            _ => host,
+        };
+        let path = entry.path();
+        devices.push(Drive { label: label.replace('_', " "), kind: DriveKind::Device, space: None, path });
+    }
+    devices
+}
+
+/// Whether the kernel marks the disk holding `device` (e.g. /dev/sdb1) as removable.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn is_removable_device(device: &str) -> bool {
+    use std::path::Path;
+
+    let Some(name) = Path::new(device).file_name() else {
+        return false;
+    };
+    let block = Path::new("/sys/class/block").join(name);
+    // Partitions link into the folder of their disk
+    let disk = if block.join("partition").exists() {
+        block.canonicalize().ok().and_then(|path| path.parent().map(Path::to_owned))
+    } else {
+        Some(block)
+    };
+    disk.and_then(|disk| std::fs::read_to_string(disk.join("removable")).ok()).is_some_and(|value| value.trim() == "1")
+}
+
+/// Whether `path` is a UNC path or on a network drive. Doesn't access the path itself.
+#[cfg(windows)]
+pub fn is_network_path(path: &std::path::Path) -> bool {
+    use std::path::{Component, Prefix};
+    use winapi::um::fileapi::GetDriveTypeW;
+    use winapi::um::winbase::DRIVE_REMOTE;
+
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return false;
+    };
+    match prefix.kind() {
+        Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+            let root: Vec<u16> = format!("{}:\\", letter as char).encode_utf16().chain(Some(0)).collect();
+            unsafe { GetDriveTypeW(root.as_ptr()) == DRIVE_REMOTE }
+        }
+        _ => true, // \\server\share, \\?\UNC\... and device paths
+    }
+}
+
+/// Whether `path` is on a volume that is not local (SMB, NFS, AFP...).
+#[cfg(target_os = "macos")]
+pub fn is_network_path(path: &std::path::Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe {
+        let mut stat: libc::statfs = std::mem::zeroed();
+        libc::statfs(c_path.as_ptr(), &mut stat) == 0 && stat.f_flags & libc::MNT_LOCAL as u32 == 0
+    }
+}
+
+/// Whether `path` is below a network mount, or below GVfs' mounts of SMB, SFTP and WebDAV shares.
+/// Only reads /proc/mounts, so a hanging share can't block the check.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn is_network_path(path: &std::path::Path) -> bool {
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return false;
+    };
+    // The innermost mount holding the path decides
+    let mut network = false;
+    let mut longest = 0;
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace().skip(1);
+        let (Some(mount_point), Some(fs_type)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let mount_point = PathBuf::from(unescape_mount_field(mount_point));
+        let depth = mount_point.components().count();
+        if path.starts_with(&mount_point) && depth >= longest {
+            longest = depth;
+            network = NETWORK_FILESYSTEMS.contains(&fs_type) || fs_type == "fuse.gvfsd-fuse";
+        }
+    }
+    network
+}
+
+/// Decodes the octal escapes /proc/mounts uses for spaces, tabs and backslashes in paths.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn unescape_mount_field(field: &str) -> String {
+    let mut bytes = Vec::with_capacity(field.len());
+    let raw = field.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'\\'
+            && let Some(code) = raw.get(i + 1..i + 4).and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok())
+        {
+            bytes.push(code);
+            i += 4;
+        } else {
+            bytes.push(raw[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
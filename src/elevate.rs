@@ -0,0 +1,174 @@
+// --- Elevated file operations ("Retry as administrator") ---
+
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::PathBuf,
+    sync::atomic::AtomicBool,
+};
+
+use crate::fileops;
+
+/// Command line flag that makes FileFox perform a single operation and exit
+/// instead of opening a window. Used by the elevated helper process.
+pub const ELEVATED_OPERATION_FLAG: &str = "--elevated-op";
+
+/// A single file operation that can be re-run in an elevated helper process.
+#[derive(Clone, Debug)]
+pub enum ElevatedOperation {
+    Rename { from: PathBuf, to: PathBuf },
+    Delete(PathBuf),
+    Move { from: PathBuf, to: PathBuf },
+    Copy { from: PathBuf, to: PathBuf },
+}
+
+impl ElevatedOperation {
+    /// Human readable description for the retry prompt.
+    pub fn description(&self) -> String {
+        match self {
+            Self::Rename { from, to } => format!("Rename {} to {}", from.display(), to.display()),
+            Self::Delete(path) => format!("Delete {}", path.display()),
+            Self::Move { from, to } => format!("Move {} to {}", from.display(), to.display()),
+            Self::Copy { from, to } => format!("Copy {} to {}", from.display(), to.display()),
+        }
+    }
+
+    /// Arguments passed to the helper process (after `ELEVATED_OPERATION_FLAG`).
+    #[cfg_attr(not(windows), allow(dead_code))]
+    fn to_args(&self) -> Vec<OsString> {
+        match self {
+            Self::Rename { from, to } => vec!["rename".into(), from.into(), to.into()],
+            Self::Delete(path) => vec!["delete".into(), path.into()],
+            Self::Move { from, to } => vec!["move".into(), from.into(), to.into()],
+            Self::Copy { from, to } => vec!["copy".into(), from.into(), to.into()],
+        }
+    }
+
+    /// Parses the arguments following `ELEVATED_OPERATION_FLAG`.
+    pub fn from_args(args: &[OsString]) -> Option<Self> {
+        let kind = args.first()?.to_str()?;
+        let path = |i: usize| args.get(i).map(PathBuf::from);
+        match kind {
+            "rename" => Some(Self::Rename { from: path(1)?, to: path(2)? }),
+            "delete" => Some(Self::Delete(path(1)?)),
+            "move" => Some(Self::Move { from: path(1)?, to: path(2)? }),
+            "copy" => Some(Self::Copy { from: path(1)?, to: path(2)? }),
+            _ => None,
+        }
+    }
+
+    /// Performs the operation in the current process.
+    pub fn perform(&self) -> io::Result<()> {
+        match self {
+            Self::Rename { from, to } => fileops::rename_path(from, to),
+            Self::Delete(path) if path.is_dir() => fs::remove_dir_all(fileops::extended_path(path)),
+            Self::Delete(path) => fs::remove_file(fileops::extended_path(path)),
+            Self::Move { from, to } => fileops::move_path(from, to, &AtomicBool::new(false)),
+            Self::Copy { from, to } => fileops::copy_recursively(from, to, &AtomicBool::new(false)),
+        }
+    }
+}
+
+/// Returns `true` if `error` is worth retrying with administrator rights.
+#[cfg(windows)]
+pub fn is_access_denied(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::PermissionDenied
+}
+
+/// Without UAC there is nothing to retry, so the offer is never made.
+#[cfg(not(windows))]
+pub fn is_access_denied(_error: &io::Error) -> bool {
+    false
+}
+
+/// Relaunches FileFox with administrator rights (showing the UAC prompt) to
+/// perform just `operation`, and waits for the helper to finish.
+#[cfg(windows)]
+pub fn run_elevated(operation: &ElevatedOperation) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::mem;
+
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::GetExitCodeProcess;
+    use winapi::um::shellapi::{SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW, ShellExecuteExW};
+    use winapi::um::synchapi::WaitForSingleObject;
+    use winapi::um::winbase::INFINITE;
+    use winapi::um::winuser::SW_HIDE;
+
+    let to_wide = |s: &std::ffi::OsStr| s.encode_wide().chain(Some(0)).collect::<Vec<u16>>();
+
+    let exe = std::env::current_exe()?;
+    let mut parameters = OsString::from(ELEVATED_OPERATION_FLAG);
+    for arg in operation.to_args() {
+        parameters.push(" ");
+        parameters.push(quote_argument(&arg));
+    }
+
+    let verb = to_wide("runas".as_ref());
+    let file = to_wide(exe.as_os_str());
+    let parameters = to_wide(&parameters);
+
+    unsafe {
+        let mut info: SHELLEXECUTEINFOW = mem::zeroed();
+        info.cbSize = mem::size_of::<SHELLEXECUTEINFOW>() as u32;
+        info.fMask = SEE_MASK_NOCLOSEPROCESS | SEE_MASK_NOASYNC;
+        info.lpVerb = verb.as_ptr();
+        info.lpFile = file.as_ptr();
+        info.lpParameters = parameters.as_ptr();
+        info.nShow = SW_HIDE;
+
+        // Fails (e.g. with ERROR_CANCELLED) when the user declines the UAC prompt
+        if ShellExecuteExW(&mut info) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if info.hProcess.is_null() {
+            return Err(io::Error::other("elevated helper did not start"));
+        }
+
+        WaitForSingleObject(info.hProcess, INFINITE);
+        let mut exit_code = 1;
+        GetExitCodeProcess(info.hProcess, &mut exit_code);
+        CloseHandle(info.hProcess);
+
+        if exit_code == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("elevated helper failed with exit code {}", exit_code)))
+        }
+    }
+}
+
+/// Elevation via UAC only exists on Windows.
+#[cfg(not(windows))]
+pub fn run_elevated(_operation: &ElevatedOperation) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "running as administrator is only supported on Windows"))
+}
+
+/// Quotes a single argument following the Windows command line parsing rules.
+#[cfg(windows)]
+fn quote_argument(arg: &std::ffi::OsStr) -> OsString {
+    let arg = arg.to_string_lossy();
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote must be doubled, and the quote escaped
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    // Backslashes before the closing quote must be doubled as well
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted.into()
+}
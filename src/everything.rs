@@ -0,0 +1,125 @@
+// --- Name searches answered by Everything (voidtools) ---
+//
+// Everything keeps a live index of every NTFS volume and answers name queries
+// machine-wide in milliseconds. Its SDK DLL (Everything64.dll / Everything32.dll)
+// is loaded at runtime, so FileFox runs fine without it: when the DLL is missing
+// or the Everything service isn't running, callers fall back to the built-in walker.
+
+use std::path::{Path, PathBuf};
+
+/// Returns `true` if the Everything SDK DLL could be loaded.
+/// (Whether the Everything service answers is only known when querying.)
+#[cfg(windows)]
+pub fn is_available() -> bool {
+    sdk::api().is_some()
+}
+
+#[cfg(not(windows))]
+pub fn is_available() -> bool {
+    false
+}
+
+/// Asks Everything for all files and folders below `root` whose name contains `query`.
+/// Returns `None` if Everything is not available, so the caller can fall back.
+#[cfg(windows)]
+pub fn search(query: &str, root: &Path) -> Option<Vec<PathBuf>> {
+    // A term containing a backslash is matched against the full path by Everything
+    let mut root_term = root.display().to_string();
+    if !root_term.ends_with('\\') {
+        root_term.push('\\');
+    }
+    let search = format!("\"{}\" \"{}\"", root_term, query.replace('"', ""));
+    sdk::query(&search).map(|paths| paths.into_iter().filter(|path| path.starts_with(root) && path != root).collect())
+}
+
+#[cfg(not(windows))]
+pub fn search(_query: &str, _root: &Path) -> Option<Vec<PathBuf>> {
+    None
+}
+
+#[cfg(windows)]
+mod sdk {
+    use std::{
+        ffi::{OsStr, OsString},
+        os::windows::ffi::{OsStrExt, OsStringExt},
+        path::PathBuf,
+        sync::{Mutex, OnceLock},
+    };
+
+    use winapi::shared::minwindef::{BOOL, DWORD, TRUE};
+    use winapi::um::libloaderapi::{GetProcAddress, LoadLibraryW};
+
+    type SetSearchW = unsafe extern "system" fn(*const u16);
+    type QueryW = unsafe extern "system" fn(BOOL) -> BOOL;
+    type GetNumResults = unsafe extern "system" fn() -> DWORD;
+    type GetResultFullPathNameW = unsafe extern "system" fn(DWORD, *mut u16, DWORD) -> DWORD;
+
+    /// The SDK functions FileFox uses.
+    pub struct Api {
+        set_search: SetSearchW,
+        query: QueryW,
+        get_num_results: GetNumResults,
+        get_result_full_path_name: GetResultFullPathNameW,
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    const DLL_NAME: &str = "Everything64.dll";
+    #[cfg(not(target_pointer_width = "64"))]
+    const DLL_NAME: &str = "Everything32.dll";
+
+    /// The loaded SDK, or `None` if the DLL isn't installed next to FileFox or on PATH.
+    pub fn api() -> Option<&'static Api> {
+        static API: OnceLock<Option<Api>> = OnceLock::new();
+        API.get_or_init(load).as_ref()
+    }
+
+    fn load() -> Option<Api> {
+        let name: Vec<u16> = OsStr::new(DLL_NAME).encode_wide().chain(Some(0)).collect();
+        unsafe {
+            let module = LoadLibraryW(name.as_ptr());
+            if module.is_null() {
+                return None;
+            }
+            // The library stays loaded for the lifetime of the process
+            let function = |name: &[u8]| {
+                let address = GetProcAddress(module, name.as_ptr() as *const i8);
+                (!address.is_null()).then_some(address)
+            };
+            Some(Api {
+                set_search: std::mem::transmute::<_, SetSearchW>(function(b"Everything_SetSearchW\0")?),
+                query: std::mem::transmute::<_, QueryW>(function(b"Everything_QueryW\0")?),
+                get_num_results: std::mem::transmute::<_, GetNumResults>(function(b"Everything_GetNumResults\0")?),
+                get_result_full_path_name: std::mem::transmute::<_, GetResultFullPathNameW>(function(
+                    b"Everything_GetResultFullPathNameW\0",
+                )?),
+            })
+        }
+    }
+
+    /// Runs `search` (Everything search syntax) and returns the full paths of all results.
+    /// Returns `None` if the SDK is missing or the Everything service doesn't answer.
+    pub fn query(search: &str) -> Option<Vec<PathBuf>> {
+        // The SDK keeps the query and its results in global state, so queries must not overlap
+        static QUERY_LOCK: Mutex<()> = Mutex::new(());
+
+        let api = api()?;
+        let _guard = QUERY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let search: Vec<u16> = OsStr::new(search).encode_wide().chain(Some(0)).collect();
+        unsafe {
+            (api.set_search)(search.as_ptr());
+            if (api.query)(TRUE) == 0 {
+                return None; // Typically "Everything is not running"
+            }
+            let count = (api.get_num_results)();
+            let mut buffer = vec![0u16; 32 * 1024]; // Long path limit
+            let mut paths = Vec::with_capacity(count as usize);
+            for index in 0..count {
+                let len = (api.get_result_full_path_name)(index, buffer.as_mut_ptr(), buffer.len() as DWORD);
+                if len > 0 {
+                    paths.push(PathBuf::from(OsString::from_wide(&buffer[..len as usize])));
+                }
+            }
+            Some(paths)
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// --- Exporting search results to a file ---
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+
+/// File layout of an export.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One path per line (tab separated columns with metadata).
+    Text,
+    /// Comma separated values with a header row.
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// Writes `paths` to `destination`. With `with_metadata`, the size in bytes and the
+/// modification time are added as extra columns (empty if they can't be read).
+pub fn export_results(
+    paths: impl IntoIterator<Item = PathBuf>,
+    destination: &Path,
+    format: ExportFormat,
+    with_metadata: bool,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(destination)?);
+    let separator = match format {
+        ExportFormat::Text => "\t",
+        ExportFormat::Csv => ",",
+    };
+
+    if format == ExportFormat::Csv {
+        let header: &[&str] = if with_metadata { &["path", "size", "modified"] } else { &["path"] };
+        writeln!(writer, "{}", header.join(separator))?;
+    }
+
+    for path in paths {
+        let mut columns = vec![path.display().to_string()];
+        if with_metadata {
+            let metadata = fs::metadata(&path).ok();
+            let size = metadata.as_ref().filter(|m| m.is_file()).map(|m| m.len().to_string());
+            let modified = metadata.and_then(|m| m.modified().ok()).map(|time| {
+                DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string()
+            });
+            columns.push(size.unwrap_or_default());
+            columns.push(modified.unwrap_or_default());
+        }
+        if format == ExportFormat::Csv {
+            columns = columns.iter().map(|column| csv_field(column)).collect();
+        }
+        writeln!(writer, "{}", columns.join(separator))?;
+    }
+    writer.flush()
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
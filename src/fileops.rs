@@ -0,0 +1,853 @@
+// --- File operations (move, copy, merge) ---
+
+use std::{
+    borrow::Cow,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+};
+
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::{elevate, jobs::JobContext};
+
+/// Whether pasting moves or copies the clipboard entries.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardMode {
+    Cut,
+    Copy,
+}
+
+/// Paths this long (in UTF-16 units) exceed the classic Win32 limit.
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+/// Whether `path` is longer than the classic Windows limit of 260 characters.
+/// Such paths only work in extended-length form, and many other programs can't open them.
+#[cfg(windows)]
+pub fn is_long_path(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    path.as_os_str().encode_wide().count() >= MAX_PATH
+}
+
+/// Whether `path` is longer than the classic Windows limit (never outside Windows).
+#[cfg(not(windows))]
+pub fn is_long_path(_path: &Path) -> bool {
+    false
+}
+
+/// `path` in extended-length form (`\\?\C:\...` or `\\?\UNC\server\share\...`) if it is
+/// a long path, so Win32 calls accept it. Other paths are returned as-is. Only for the
+/// program's own file system calls: other programs and shell commands get the plain path.
+#[cfg(windows)]
+pub fn extended_path(path: &Path) -> Cow<'_, Path> {
+    if !is_long_path(path) {
+        return Cow::Borrowed(path);
+    }
+    to_extended(path).map_or(Cow::Borrowed(path), Cow::Owned)
+}
+
+/// `path` unchanged; extended-length paths only exist on Windows.
+#[cfg(not(windows))]
+pub fn extended_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// `path` in extended-length form whatever its length, as the root of a folder walk:
+/// paths deeper in the tree may be long even if the root isn't. The walked paths carry
+/// the prefix, too; `plain_path` removes it before they are shown.
+#[cfg(windows)]
+pub fn extended_root(path: &Path) -> Cow<'_, Path> {
+    to_extended(path).map_or(Cow::Borrowed(path), Cow::Owned)
+}
+
+/// `path` unchanged; extended-length paths only exist on Windows.
+#[cfg(not(windows))]
+pub fn extended_root(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+// Helper function
+#[cfg(windows)]
+fn to_extended(path: &Path) -> Option<PathBuf> {
+    let text = path.to_str()?;
+    if !path.is_absolute() || text.starts_with(r"\\?\") || text.starts_with(r"\\.\") {
+        return None; // Relative, already extended or a device path
+    }
+    // The prefix turns off normalization: "." and ".." would be taken as names,
+    // and only backslashes separate components. `push` joins with backslashes.
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(normalized.components().next_back(), Some(Component::Normal(_))) {
+                    normalized.pop();
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    let text = normalized.to_str()?;
+    Some(PathBuf::from(match text.strip_prefix(r"\\") {
+        Some(unc) => format!(r"\\?\UNC\{}", unc),
+        None => format!(r"\\?\{}", text),
+    }))
+}
+
+/// `path` without the extended-length prefix, the form shown to people and handed to other programs.
+#[cfg(windows)]
+pub fn plain_path(path: PathBuf) -> PathBuf {
+    let Some(text) = path.to_str() else {
+        return path;
+    };
+    if let Some(unc) = text.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{}", unc))
+    } else if let Some(local) = text.strip_prefix(r"\\?\")
+        && local.as_bytes().get(1) == Some(&b':')
+    {
+        PathBuf::from(local)
+    } else {
+        path // Not extended, or a volume path (\\?\Volume{...}) without a plain form
+    }
+}
+
+/// `path` unchanged; extended-length paths only exist on Windows.
+#[cfg(not(windows))]
+pub fn plain_path(path: PathBuf) -> PathBuf {
+    path
+}
+
+/// `dir` joined with `name`, a file name from an untrusted source like a disk image or a device.
+/// Fails unless `name` is a single plain component: separators, "." and "..", drive prefixes
+/// ("C:x") and, on Windows, alternate data streams ("name:stream") could write elsewhere.
+pub fn safe_child_path(dir: &Path, name: &str) -> io::Result<PathBuf> {
+    let mut components = Path::new(name).components();
+    let is_plain = matches!((components.next(), components.next()), (Some(Component::Normal(_)), None));
+    if !is_plain || name.contains(['/', '\\']) || (cfg!(windows) && name.contains(':')) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid name {:?}", name)));
+    }
+    Ok(dir.join(name))
+}
+
+/// Creates the file `target` and fills it through `write`, following `on_conflict` if the file
+/// exists already. A replaced file is only swapped out once the new contents are complete,
+/// and a failed write leaves nothing behind. Returns `false` if the file was skipped.
+pub fn write_new_file(
+    target: &Path,
+    on_conflict: ConflictChoice,
+    write: impl FnOnce(&mut File) -> io::Result<()>,
+) -> io::Result<bool> {
+    let exists = fs::symlink_metadata(target).is_ok();
+    if exists && on_conflict == ConflictChoice::Skip {
+        return Ok(false);
+    }
+    let temp_path = match target.file_name() {
+        Some(name) if exists => target.with_file_name(format!(".{}.part", name.to_string_lossy())),
+        _ => target.to_owned(),
+    };
+    let result = File::create_new(&temp_path).and_then(|mut file| write(&mut file));
+    let result = result.and_then(|()| if exists { fs::rename(&temp_path, target) } else { Ok(()) });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path); // Don't leave a partial file behind
+        return Err(e);
+    }
+    Ok(true)
+}
+
+/// Replaces the contents of the file `path` with `content`. They are written to a temporary file
+/// next to it first, so a crash or a full disk leaves the old contents rather than a truncated file.
+pub fn replace_file(path: &Path, content: &str) -> io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".part");
+    let temp_path = path.with_file_name(temp_name);
+    let result = fs::write(&temp_path, content).and_then(|()| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path); // Don't leave a partial file behind
+    }
+    result
+}
+
+/// Moves `source` to `destination` (full target path, not the parent folder).
+/// Falls back to copy + delete only when the rename fails because the destination is on
+/// another drive; other errors (missing source, no permission...) are returned as they are.
+/// Setting `cancel` stops the copy, which is removed again while the source stays complete.
+pub fn move_path(source: &Path, destination: &Path, cancel: &AtomicBool) -> io::Result<()> {
+    let (source, destination) = (&*extended_path(source), &*extended_path(destination));
+    match fs::rename(source, destination) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+        result => return result,
+    }
+    if fs::symlink_metadata(destination).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, "the destination exists already"));
+    }
+    if let Err(e) = copy_recursively(source, destination, cancel) {
+        let _ = if destination.is_dir() { fs::remove_dir_all(destination) } else { fs::remove_file(destination) };
+        return Err(e);
+    }
+    if source.is_dir() {
+        fs::remove_dir_all(source)
+    } else {
+        fs::remove_file(source)
+    }
+}
+
+/// Renames `source` to `destination`. A change of case only (e.g. readme.md to README.md) goes
+/// through a temporary name, as case-insensitive file systems may treat it as renaming onto itself.
+pub fn rename_path(source: &Path, destination: &Path) -> io::Result<()> {
+    let (source, destination) = (&*extended_path(source), &*extended_path(destination));
+    let (Some(source_name), Some(destination_name)) = (source.file_name(), destination.file_name()) else {
+        return fs::rename(source, destination);
+    };
+    let is_case_only = source.parent() == destination.parent()
+        && source_name != destination_name
+        && source_name.to_string_lossy().to_lowercase() == destination_name.to_string_lossy().to_lowercase();
+    if !is_case_only {
+        return fs::rename(source, destination);
+    }
+
+    let mut temporary_name = source_name.to_owned();
+    temporary_name.push(format!(".filefox-rename-{}", std::process::id()));
+    let temporary = source.with_file_name(temporary_name);
+    fs::rename(source, &temporary)?;
+    fs::rename(&temporary, destination).inspect_err(|_| {
+        let _ = fs::rename(&temporary, source); // Put it back under its old name
+    })
+}
+
+/// Extensions of macOS bundles, folders that Finder shows and opens as a single item.
+#[cfg(target_os = "macos")]
+const PACKAGE_EXTENSIONS: [&str; 16] = [
+    "app", "appex", "bundle", "framework", "kext", "plugin", "prefpane", "qlgenerator", "mdimporter", "saver", "xpc",
+    "pkg", "mpkg", "rtfd", "photoslibrary", "xcarchive",
+];
+
+/// Whether the folder `path` is a bundle (application, framework, plug-in...) that is opened
+/// as a whole instead of browsed into. Always `false` outside macOS.
+#[cfg(target_os = "macos")]
+pub fn is_package(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| PACKAGE_EXTENSIONS.iter().any(|package| extension.eq_ignore_ascii_case(package)))
+}
+
+/// Whether the folder `path` is a macOS bundle. Always `false` outside macOS.
+#[cfg(not(target_os = "macos"))]
+pub fn is_package(_path: &Path) -> bool {
+    false
+}
+
+/// Copies a file or a whole folder tree from `source` to `destination`, until `cancel` is set.
+pub fn copy_recursively(source: &Path, destination: &Path, cancel: &AtomicBool) -> io::Result<()> {
+    if cancel.load(Ordering::Relaxed) {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+    }
+    let (source, destination) = (&*extended_path(source), &*extended_path(destination));
+    if source.is_dir() {
+        fs::create_dir_all(destination)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursively(&entry.path(), &destination.join(entry.file_name()), cancel)?;
+        }
+    } else {
+        fs::copy(source, destination)?;
+    }
+    Ok(())
+}
+
+/// Returns whether `path` is read-only.
+pub fn is_readonly(path: &Path) -> bool {
+    fs::metadata(path).map(|m| m.permissions().readonly()).unwrap_or(false)
+}
+
+/// Sets or clears the read-only flag of `path`.
+/// On Unix only the owner's write permission is touched.
+pub fn set_readonly(path: &Path, readonly: bool) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = permissions.mode();
+        permissions.set_mode(if readonly { mode & !0o222 } else { mode | 0o200 });
+    }
+    #[cfg(not(unix))]
+    permissions.set_readonly(readonly);
+    fs::set_permissions(path, permissions)
+}
+
+/// Returns whether `path` has the hidden attribute (Windows only).
+#[cfg(windows)]
+pub fn is_hidden(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use winapi::um::winnt::FILE_ATTRIBUTE_HIDDEN;
+    fs::metadata(path).map(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0).unwrap_or(false)
+}
+
+/// Returns whether a listed entry is hidden: it has the hidden attribute on Windows,
+/// its name starts with a dot elsewhere. Doesn't read any metadata the listing didn't.
+#[cfg(windows)]
+pub fn is_hidden_entry(entry: &fs::DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use winapi::um::winnt::FILE_ATTRIBUTE_HIDDEN;
+    entry.metadata().is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+#[cfg(not(windows))]
+pub fn is_hidden_entry(entry: &fs::DirEntry) -> bool {
+    entry.file_name().as_encoded_bytes().starts_with(b".")
+}
+
+/// Sets or clears the hidden attribute of `path` (Windows only).
+#[cfg(windows)]
+pub fn set_hidden(path: &Path, hidden: bool) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::fs::MetadataExt;
+    use winapi::um::fileapi::SetFileAttributesW;
+    use winapi::um::winnt::{FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_NORMAL};
+
+    let attributes = fs::metadata(extended_path(path))?.file_attributes();
+    let mut attributes = if hidden { attributes | FILE_ATTRIBUTE_HIDDEN } else { attributes & !FILE_ATTRIBUTE_HIDDEN };
+    if attributes == 0 {
+        attributes = FILE_ATTRIBUTE_NORMAL; // Zero is not a valid attribute set
+    }
+    let wide_path: Vec<u16> = extended_path(path).as_os_str().encode_wide().chain(Some(0)).collect();
+    if unsafe { SetFileAttributesW(wide_path.as_ptr(), attributes) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Returns (free, total) bytes of the volume containing `path`.
+/// "Free" is the space available to the current user.
+#[cfg(windows)]
+pub fn disk_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::GetDiskFreeSpaceExW;
+    use winapi::shared::ntdef::ULARGE_INTEGER;
+
+    let wide_path: Vec<u16> = extended_path(path).as_os_str().encode_wide().chain(Some(0)).collect();
+    unsafe {
+        let mut free: ULARGE_INTEGER = std::mem::zeroed();
+        let mut total: ULARGE_INTEGER = std::mem::zeroed();
+        if GetDiskFreeSpaceExW(wide_path.as_ptr(), &mut free, &mut total, std::ptr::null_mut()) == 0 {
+            return None;
+        }
+        Some((*free.QuadPart(), *total.QuadPart()))
+    }
+}
+
+/// Returns (free, total) bytes of the volume containing `path`.
+/// "Free" is the space available to the current user.
+#[cfg(unix)]
+pub fn disk_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        let block_size = stat.f_frsize as u64;
+        Some((stat.f_bavail as u64 * block_size, stat.f_blocks as u64 * block_size))
+    }
+}
+
+/// Attribute letters of a file for the details view, e.g. "RHA" (read-only, hidden, archive).
+#[cfg(windows)]
+pub fn file_attributes(metadata: &fs::Metadata) -> String {
+    use std::os::windows::fs::MetadataExt;
+    use winapi::um::winnt::{
+        FILE_ATTRIBUTE_ARCHIVE, FILE_ATTRIBUTE_COMPRESSED, FILE_ATTRIBUTE_ENCRYPTED, FILE_ATTRIBUTE_HIDDEN,
+        FILE_ATTRIBUTE_READONLY, FILE_ATTRIBUTE_SYSTEM,
+    };
+
+    let attributes = metadata.file_attributes();
+    [
+        (FILE_ATTRIBUTE_READONLY, 'R'),
+        (FILE_ATTRIBUTE_HIDDEN, 'H'),
+        (FILE_ATTRIBUTE_SYSTEM, 'S'),
+        (FILE_ATTRIBUTE_ARCHIVE, 'A'),
+        (FILE_ATTRIBUTE_COMPRESSED, 'C'),
+        (FILE_ATTRIBUTE_ENCRYPTED, 'E'),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| attributes & flag != 0)
+    .map(|(_, letter)| letter)
+    .collect()
+}
+
+/// Permission bits of a file for the details view, e.g. "rwxr-xr-x".
+#[cfg(unix)]
+pub fn file_attributes(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode();
+    (0..9)
+        .map(|bit| if mode & (0o400 >> bit) != 0 { ['r', 'w', 'x'][bit % 3] } else { '-' })
+        .collect()
+}
+
+/// Name of the account owning `path`, as "DOMAIN\user".
+#[cfg(windows)]
+pub fn file_owner(path: &Path) -> Option<String> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::{
+        accctrl::SE_FILE_OBJECT,
+        aclapi::GetNamedSecurityInfoW,
+        winbase::{LocalFree, LookupAccountSidW},
+        winnt::{OWNER_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR, PSID, SID_NAME_USE},
+    };
+
+    let wide_path: Vec<u16> = extended_path(path).as_os_str().encode_wide().chain(Some(0)).collect();
+    unsafe {
+        let mut owner: PSID = std::ptr::null_mut();
+        let mut descriptor: PSECURITY_DESCRIPTOR = std::ptr::null_mut();
+        let status = GetNamedSecurityInfoW(
+            wide_path.as_ptr(),
+            SE_FILE_OBJECT,
+            OWNER_SECURITY_INFORMATION,
+            &mut owner,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut descriptor,
+        );
+        if status != 0 {
+            return None;
+        }
+
+        let mut name = [0u16; 256];
+        let mut domain = [0u16; 256];
+        let (mut name_len, mut domain_len) = (name.len() as u32, domain.len() as u32);
+        let mut sid_type: SID_NAME_USE = 0;
+        let found = LookupAccountSidW(
+            std::ptr::null(),
+            owner,
+            name.as_mut_ptr(),
+            &mut name_len,
+            domain.as_mut_ptr(),
+            &mut domain_len,
+            &mut sid_type,
+        ) != 0;
+        LocalFree(descriptor); // `owner` points into the descriptor
+        if !found {
+            return None;
+        }
+
+        let name = String::from_utf16_lossy(&name[..name_len as usize]);
+        let domain = String::from_utf16_lossy(&domain[..domain_len as usize]);
+        Some(if domain.is_empty() { name } else { format!("{}\\{}", domain, name) })
+    }
+}
+
+/// Names of the user and group owning `path`, as "user:group" (numeric ids for unnamed ones).
+#[cfg(unix)]
+pub fn file_owner(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path).ok()?;
+    let (uid, gid) = (metadata.uid(), metadata.gid());
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    unsafe {
+        let mut passwd: libc::passwd = std::mem::zeroed();
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        let user = if libc::getpwuid_r(uid, &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) != 0 || result.is_null() {
+            uid.to_string()
+        } else {
+            std::ffi::CStr::from_ptr(passwd.pw_name).to_string_lossy().into_owned()
+        };
+        let mut group: libc::group = std::mem::zeroed();
+        let mut result: *mut libc::group = std::ptr::null_mut();
+        let group = if libc::getgrgid_r(gid, &mut group, buffer.as_mut_ptr(), buffer.len(), &mut result) != 0 || result.is_null() {
+            gid.to_string()
+        } else {
+            std::ffi::CStr::from_ptr(group.gr_name).to_string_lossy().into_owned()
+        };
+        Some(format!("{}:{}", user, group))
+    }
+}
+
+/// Permission bits of `path`, e.g. 0o755 (including setuid, setgid and sticky bits).
+#[cfg(unix)]
+pub fn file_mode(path: &Path) -> io::Result<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::metadata(path)?.permissions().mode() & 0o7777)
+}
+
+/// Sets the permission bits of `path`, like `chmod`.
+#[cfg(unix)]
+pub fn set_file_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+/// Returns `destination`, or a variant like "name - Copy.txt" / "name - Copy (2).txt"
+/// if something with that name already exists.
+pub fn unique_destination(destination: &Path) -> PathBuf {
+    if !destination.exists() {
+        return destination.to_owned();
+    }
+    let stem = destination.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = destination.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 { " - Copy".to_string() } else { format!(" - Copy ({})", n) };
+            destination.with_file_name(format!("{}{}{}", stem, suffix, extension))
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| destination.to_owned())
+}
+
+/// Computes the SHA-256 hash of a file's content.
+pub fn sha256_file(path: &Path) -> io::Result<[u8; 32]> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Outcome of a copy operation.
+#[derive(Default)]
+pub struct CopyReport {
+    pub errors: Vec<String>,
+    /// Copied files whose hash differs from the source (only with verification enabled).
+    pub mismatches: Vec<PathBuf>,
+    /// (source, destination) pairs that failed with access denied.
+    pub access_denied: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Copies every (source, destination) pair, including whole folder trees.
+/// With `verify`, each copied file is hashed (SHA-256) and compared to its source.
+/// Runs as a background job, which reports progress and stops early when cancelled.
+pub fn copy_entries(pairs: Vec<(PathBuf, PathBuf)>, verify: bool, job: &JobContext) -> CopyReport {
+    // Collect all files/folders up front so progress can be reported as "x of y"
+    let mut items: Vec<(PathBuf, PathBuf, usize)> = Vec::new(); // (source, destination, index of pair)
+    for (index, (source, destination)) in pairs.iter().enumerate() {
+        // Walked and copied in extended-length form, as paths inside folders may be long
+        let (source, destination) = (extended_root(source), extended_root(destination));
+        for entry in WalkDir::new(&*source).into_iter().filter_map(|e| e.ok()) {
+            let target = match entry.path().strip_prefix(&*source) {
+                Ok(relative) if !relative.as_os_str().is_empty() => destination.join(relative),
+                _ => destination.to_path_buf(),
+            };
+            items.push((entry.path().to_owned(), target, index));
+        }
+    }
+
+    let mut report = CopyReport::default();
+    let total = items.len();
+    for (done, (source, destination, index)) in items.iter().enumerate() {
+        if job.is_cancelled() {
+            let source = plain_path(source.clone());
+            report.errors.push(format!("{}: cancelled after {} of {} files", source.display(), done, total));
+            break;
+        }
+        let result = if source.is_dir() {
+            fs::create_dir_all(destination)
+        } else {
+            fs::copy(source, destination).map(|_| ())
+        };
+
+        match result {
+            Ok(()) if verify && source.is_file() => {
+                let matches = match (sha256_file(source), sha256_file(destination)) {
+                    (Ok(a), Ok(b)) => a == b,
+                    _ => false,
+                };
+                if !matches {
+                    report.mismatches.push(plain_path(destination.clone()));
+                }
+            }
+            Ok(()) => {}
+            Err(e) => {
+                report.errors.push(format!("{}: {}", plain_path(source.clone()).display(), e));
+                let pair = pairs[*index].clone();
+                if e.kind() == io::ErrorKind::PermissionDenied && !report.access_denied.contains(&pair) {
+                    report.access_denied.push(pair);
+                }
+            }
+        }
+
+        job.progress(done + 1, total);
+    }
+    report
+}
+
+/// Outcome of a move or merge.
+#[derive(Default)]
+pub struct MoveReport {
+    pub errors: Vec<String>,
+    /// (source, destination) pairs that failed with access denied.
+    pub access_denied: Vec<(PathBuf, PathBuf)>,
+}
+
+/// How to resolve a merge conflict and whether to resolve later conflicts the same way,
+/// or `None` to stop the merge.
+pub type ConflictAnswer = mpsc::Sender<Option<(ConflictChoice, bool)>>;
+
+/// Sent by move and merge jobs to the UI.
+pub enum MoveEvent {
+    /// A merge found a file that exists in the target already. It waits for the answer.
+    Conflict(PathBuf, ConflictAnswer),
+    Finished(MoveReport),
+}
+
+/// Moves every (source, destination) pair, whose destinations don't exist yet.
+/// Runs as a background job, which reports progress and stops early when cancelled.
+pub fn move_entries(pairs: Vec<(PathBuf, PathBuf)>, job: &JobContext) -> MoveReport {
+    let mut report = MoveReport::default();
+    let total = pairs.len();
+    for (done, (source, destination)) in pairs.into_iter().enumerate() {
+        if job.is_cancelled() {
+            report.errors.push(format!("{}: cancelled after {} of {} entries", source.display(), done, total));
+            break;
+        }
+        if let Err(e) = move_path(&source, &destination, job.cancel_flag()) {
+            report.errors.push(format!("{}: {}", source.display(), e));
+            if elevate::is_access_denied(&e) {
+                report.access_denied.push((source, destination));
+            }
+        }
+        job.progress(done + 1, total);
+    }
+    report
+}
+
+/// Outcome of deleting entries permanently.
+#[derive(Default)]
+pub struct DeleteReport {
+    pub deleted: Vec<PathBuf>,
+    pub errors: Vec<String>,
+    /// Entries that failed with access denied.
+    pub access_denied: Vec<PathBuf>,
+}
+
+/// Deletes every path in `paths`, files and whole folder trees.
+/// Runs as a background job, which reports progress and stops early when cancelled.
+pub fn delete_entries(paths: Vec<PathBuf>, job: &JobContext) -> DeleteReport {
+    let mut report = DeleteReport::default();
+    let total = paths.len();
+    for (done, path) in paths.into_iter().enumerate() {
+        if job.is_cancelled() {
+            report.errors.push(format!("{}: cancelled after {} of {} entries", path.display(), done, total));
+            break;
+        }
+        let extended = extended_path(&path);
+        let result = if extended.is_dir() { fs::remove_dir_all(&extended) } else { fs::remove_file(&extended) };
+        match result {
+            Ok(()) => report.deleted.push(path),
+            Err(e) => {
+                report.errors.push(format!("{}: {}", path.display(), e));
+                if elevate::is_access_denied(&e) {
+                    report.access_denied.push(path);
+                }
+            }
+        }
+        job.progress(done + 1, total);
+    }
+    report
+}
+
+/// How a file that already exists in the target should be treated (during a merge, extraction...).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ConflictChoice {
+    Replace,
+    Skip,
+}
+
+/// Moves the contents of a folder into an existing folder of the same name.
+/// Runs as a background job and asks the UI about every file that already exists in the target.
+pub struct MergeMove {
+    pending: Vec<(PathBuf, PathBuf)>, // (source, destination) pairs still to move
+    merged_dirs: Vec<PathBuf>, // Source folders to remove once their contents are moved
+    choice_for_all: Option<ConflictChoice>,
+    report: MoveReport,
+}
+
+impl MergeMove {
+    /// With `choice_for_all`, conflicts are resolved that way without asking.
+    pub fn new(source: PathBuf, destination: PathBuf, choice_for_all: Option<ConflictChoice>) -> Self {
+        Self {
+            pending: vec![(source, destination)],
+            merged_dirs: Vec::new(),
+            choice_for_all,
+            report: MoveReport::default(),
+        }
+    }
+
+    /// Moves everything, sending conflicts to `events` and waiting for the answers.
+    /// Stops early when the job is cancelled or the UI stops the merge.
+    pub fn run(mut self, events: &mpsc::Sender<MoveEvent>, job: &JobContext) -> MoveReport {
+        let mut done = 0;
+        while let Some((source, destination)) = self.pending.pop() {
+            if job.is_cancelled() {
+                self.report.errors.push(format!("{}: cancelled", source.display()));
+                return self.report; // Already moved entries stay where they are
+            }
+
+            if !destination.exists() {
+                if let Err(e) = move_path(&source, &destination, job.cancel_flag()) {
+                    self.report.errors.push(format!("{}: {}", source.display(), e));
+                }
+            } else if source.is_dir() && destination.is_dir() {
+                // Both are folders: merge their contents instead of replacing
+                match fs::read_dir(&source) {
+                    Ok(children) => {
+                        for child in children.flatten() {
+                            self.pending.push((child.path(), destination.join(child.file_name())));
+                        }
+                        self.merged_dirs.push(source);
+                    }
+                    Err(e) => self.report.errors.push(format!("{}: {}", source.display(), e)),
+                }
+            } else {
+                let choice = match self.choice_for_all {
+                    Some(choice) => choice,
+                    None => match ask(events, &destination, job) {
+                        Some((choice, apply_to_all)) => {
+                            if apply_to_all {
+                                self.choice_for_all = Some(choice);
+                            }
+                            choice
+                        }
+                        None => return self.report, // Already moved entries stay where they are
+                    },
+                };
+                self.apply(choice, source, destination, job.cancel_flag());
+            }
+            done += 1;
+            job.progress(done, done + self.pending.len());
+        }
+        self.remove_merged_dirs();
+        self.report
+    }
+
+    fn apply(&mut self, choice: ConflictChoice, source: PathBuf, destination: PathBuf, cancel: &AtomicBool) {
+        if choice == ConflictChoice::Skip {
+            return;
+        }
+        let removed = if destination.is_dir() {
+            fs::remove_dir_all(&destination)
+        } else {
+            fs::remove_file(&destination)
+        };
+        if let Err(e) = removed.and_then(|_| move_path(&source, &destination, cancel)) {
+            self.report.errors.push(format!("{}: {}", source.display(), e));
+        }
+    }
+
+    /// Deletes the source folders that were merged, innermost first.
+    /// Folders still containing skipped entries are left in place.
+    fn remove_merged_dirs(&mut self) {
+        while let Some(dir) = self.merged_dirs.pop() {
+            let _ = fs::remove_dir(&dir); // Fails (intentionally) if not empty
+        }
+    }
+}
+
+/// Asks the UI how to resolve the conflict at `destination` and waits for the answer.
+/// `None` when the merge should stop, also if the UI went away.
+fn ask(events: &mpsc::Sender<MoveEvent>, destination: &Path, job: &JobContext) -> Option<(ConflictChoice, bool)> {
+    let (sender, answer) = mpsc::channel();
+    events.send(MoveEvent::Conflict(destination.to_owned(), sender)).ok()?;
+    job.request_repaint();
+    answer.recv().ok().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_child_path_accepts_only_plain_names() {
+        let dir = Path::new("extracted");
+        assert_eq!(safe_child_path(dir, "notes.txt").unwrap(), dir.join("notes.txt"));
+        assert_eq!(safe_child_path(dir, "..hidden").unwrap(), dir.join("..hidden"));
+        for name in ["", ".", "..", "a/b", "a\\b", "/etc/passwd", "../outside"] {
+            assert!(safe_child_path(dir, name).is_err(), "{:?} was accepted", name);
+        }
+        #[cfg(windows)]
+        for name in ["C:x", "C:\\x", "name:stream", "\\\\server\\share"] {
+            assert!(safe_child_path(dir, name).is_err(), "{:?} was accepted", name);
+        }
+    }
+
+    #[test]
+    fn short_paths_are_left_alone() {
+        let path = Path::new(if cfg!(windows) { r"C:\Users\me\..\file.txt" } else { "/home/me/../file.txt" });
+        assert_eq!(&*extended_path(path), path);
+        assert_eq!(plain_path(path.to_owned()), path);
+        assert_eq!(&*extended_path(Path::new("relative/file.txt")), Path::new("relative/file.txt"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn extended_paths_are_normalized_and_convert_back() {
+        let name = "x".repeat(250);
+        let path = PathBuf::from(format!(r"C:\data\.\skipped\..\{}/file.txt", name));
+        let extended = PathBuf::from(format!(r"\\?\C:\data\{}\file.txt", name));
+        assert_eq!(&*extended_path(&path), extended);
+        assert_eq!(plain_path(extended), PathBuf::from(format!(r"C:\data\{}\file.txt", name)));
+
+        let unc = PathBuf::from(format!(r"\\server\share\{}", name));
+        let extended_unc = PathBuf::from(format!(r"\\?\UNC\server\share\{}", name));
+        assert_eq!(&*extended_path(&unc), extended_unc);
+        assert_eq!(plain_path(extended_unc), unc);
+
+        assert_eq!(&*extended_root(Path::new(r"C:\short\..\root")), Path::new(r"\\?\C:\root"));
+        assert_eq!(&*extended_path(&extended_path(&path)), &*extended_path(&path)); // Already extended
+    }
+
+    #[test]
+    fn write_new_file_skips_or_replaces_existing_files() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("filefox-write-new-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("file.txt");
+        fs::write(&target, "old").unwrap();
+
+        assert!(!write_new_file(&target, ConflictChoice::Skip, |file| file.write_all(b"new")).unwrap());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+
+        // A failed write keeps the old file and leaves no temporary file behind
+        let failed = write_new_file(&target, ConflictChoice::Replace, |_| Err(io::Error::other("disk full")));
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        assert!(write_new_file(&target, ConflictChoice::Replace, |file| file.write_all(b"new")).unwrap());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_moves_on_the_same_drive_do_not_copy() {
+        let dir = std::env::temp_dir().join(format!("filefox-move-{}", std::process::id()));
+        fs::create_dir_all(dir.join("folder")).unwrap();
+        fs::write(dir.join("folder").join("file.txt"), "content").unwrap();
+        fs::create_dir_all(dir.join("target").join("folder")).unwrap();
+        fs::write(dir.join("target").join("folder").join("other.txt"), "other").unwrap();
+
+        // Renaming onto a folder that isn't empty fails, and must not merge the two by copying
+        let cancel = AtomicBool::new(false);
+        assert!(move_path(&dir.join("folder"), &dir.join("target").join("folder"), &cancel).is_err());
+        assert!(dir.join("folder").join("file.txt").exists());
+        assert!(!dir.join("target").join("folder").join("file.txt").exists());
+
+        let missing = move_path(&dir.join("missing"), &dir.join("moved"), &cancel);
+        assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,120 @@
+// --- File type detection by content (magic bytes) ---
+
+use std::{fs::File, io::Read, path::Path};
+
+use crate::fileops;
+
+/// Broad kind of a file, detected from its first bytes instead of its extension.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContentType {
+    Image,
+    Video,
+    Document,
+    Archive,
+}
+
+impl ContentType {
+    pub const ALL: [Self; 4] = [Self::Image, Self::Video, Self::Document, Self::Archive];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Image => "Images",
+            Self::Video => "Videos",
+            Self::Document => "Documents",
+            Self::Archive => "Archives",
+        }
+    }
+}
+
+/// Enough bytes for every signature below (tar's "ustar" sits at offset 257).
+const SNIFF_LEN: usize = 512;
+
+/// Reads the start of `path` and detects its content type.
+/// Returns `None` for unreadable files and unknown formats.
+pub fn detect_content_type(path: &Path) -> Option<ContentType> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    File::open(fileops::extended_path(path)).ok()?.take(SNIFF_LEN as u64).read_to_end(&mut head).ok()?;
+    sniff(&head)
+}
+
+/// Detects the content type from the first bytes of a file.
+pub fn sniff(head: &[u8]) -> Option<ContentType> {
+    let at = |offset: usize, signature: &[u8]| head.get(offset..offset + signature.len()) == Some(signature);
+
+    // ISO base media files (MP4, MOV, HEIC, AVIF) share the "ftyp" box, the brand decides
+    if at(4, b"ftyp") {
+        let brand = head.get(8..12)?;
+        return match brand {
+            b"heic" | b"heix" | b"mif1" | b"msf1" | b"avif" => Some(ContentType::Image),
+            _ => Some(ContentType::Video),
+        };
+    }
+    // RIFF container: WebP image or AVI video
+    if at(0, b"RIFF") {
+        return match head.get(8..12)? {
+            b"WEBP" => Some(ContentType::Image),
+            b"AVI " => Some(ContentType::Video),
+            _ => None,
+        };
+    }
+    // ZIP container: office documents / e-books, or a plain archive
+    if at(0, b"PK\x03\x04") {
+        return Some(if is_zipped_document(head) { ContentType::Document } else { ContentType::Archive });
+    }
+
+    const SIGNATURES: &[(usize, &[u8], ContentType)] = &[
+        // Images
+        (0, b"\x89PNG\r\n\x1a\n", ContentType::Image),
+        (0, b"\xFF\xD8\xFF", ContentType::Image),
+        (0, b"GIF87a", ContentType::Image),
+        (0, b"GIF89a", ContentType::Image),
+        (0, b"BM", ContentType::Image),
+        (0, b"II*\x00", ContentType::Image),
+        (0, b"MM\x00*", ContentType::Image),
+        (0, b"\x00\x00\x01\x00", ContentType::Image), // ICO
+        // Videos
+        (0, b"\x1A\x45\xDF\xA3", ContentType::Video), // Matroska / WebM
+        (0, b"FLV\x01", ContentType::Video),
+        (0, b"\x00\x00\x01\xBA", ContentType::Video), // MPEG program stream
+        (0, b"\x00\x00\x01\xB3", ContentType::Video), // MPEG video
+        (0, b"\x30\x26\xB2\x75\x8E\x66\xCF\x11", ContentType::Video), // ASF / WMV
+        // Documents
+        (0, b"%PDF-", ContentType::Document),
+        (0, b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1", ContentType::Document), // Legacy Office (doc, xls, ppt)
+        (0, b"{\\rtf", ContentType::Document),
+        // Archives
+        (0, b"Rar!\x1A\x07", ContentType::Archive),
+        (0, b"7z\xBC\xAF\x27\x1C", ContentType::Archive),
+        (0, b"\x1F\x8B", ContentType::Archive), // gzip
+        (0, b"BZh", ContentType::Archive),
+        (0, b"\xFD7zXZ\x00", ContentType::Archive),
+        (0, b"\x28\xB5\x2F\xFD", ContentType::Archive), // zstd
+        (0, b"MSCF", ContentType::Archive), // Cabinet
+        (257, b"ustar", ContentType::Archive), // tar
+    ];
+    SIGNATURES
+        .iter()
+        .find(|(offset, signature, _)| at(*offset, signature))
+        .map(|(_, _, content_type)| *content_type)
+}
+
+/// Office Open XML, OpenDocument and EPUB files are ZIP archives whose first entry
+/// gives them away ("[Content_Types].xml", or a "mimetype" file naming the format).
+fn is_zipped_document(head: &[u8]) -> bool {
+    // Local file header: name length at offset 26, name at 30, extra field after it
+    let Some(name_len) = head.get(26..28).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize) else {
+        return false;
+    };
+    let extra_len = head.get(28..30).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize).unwrap_or(0);
+    let Some(name) = head.get(30..30 + name_len) else {
+        return false;
+    };
+    if name == b"[Content_Types].xml" || name.starts_with(b"word/") || name.starts_with(b"xl/") || name.starts_with(b"ppt/") {
+        return true;
+    }
+    if name == b"mimetype" {
+        let content = head.get(30 + name_len + extra_len..).unwrap_or_default();
+        return content.starts_with(b"application/vnd.oasis.opendocument") || content.starts_with(b"application/epub+zip");
+    }
+    false
+}
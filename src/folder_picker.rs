@@ -0,0 +1,91 @@
+// --- Folder chooser tree (used by "Move to..." / "Copy to...") ---
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use eframe::egui;
+
+/// A lazily loaded tree of folders to pick a destination from.
+pub struct FolderPicker {
+    pub selected: PathBuf,
+    start: PathBuf,
+    roots: Vec<PathBuf>,
+    children: HashMap<PathBuf, Vec<PathBuf>>, // Subfolders of every expanded folder
+}
+
+impl FolderPicker {
+    /// Creates a picker with `start` selected and its ancestors expanded.
+    pub fn new(start: &Path) -> Self {
+        Self {
+            selected: start.to_owned(),
+            start: start.to_owned(),
+            roots: filesystem_roots(),
+            children: HashMap::new(),
+        }
+    }
+
+    /// Renders the tree. Clicking a folder selects it.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        for root in self.roots.clone() {
+            self.show_folder(ui, &root);
+        }
+    }
+
+    fn show_folder(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => path.display().to_string(), // Drive / filesystem root
+        };
+        let id = ui.make_persistent_id(path);
+        let default_open = self.start.starts_with(path) && self.start != path;
+
+        egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, default_open)
+            .show_header(ui, |ui| {
+                if ui.selectable_label(self.selected == path, format!("📁 {}", name)).clicked() {
+                    self.selected = path.to_owned();
+                }
+            })
+            .body(|ui| {
+                let subfolders = self
+                    .children
+                    .entry(path.to_owned())
+                    .or_insert_with(|| read_subfolders(path))
+                    .clone();
+                if subfolders.is_empty() {
+                    ui.weak("(no subfolders)");
+                }
+                for subfolder in &subfolders {
+                    self.show_folder(ui, subfolder);
+                }
+            });
+    }
+}
+
+/// Lists the subfolders of `path`, sorted by name.
+fn read_subfolders(path: &Path) -> Vec<PathBuf> {
+    let mut subfolders: Vec<PathBuf> = std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    subfolders.sort_by(|a, b| crate::view::natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    subfolders
+}
+
+/// Top level entries of the tree: all drives on Windows, `/` elsewhere.
+pub fn filesystem_roots() -> Vec<PathBuf> {
+    if cfg!(windows) {
+        (b'A'..=b'Z')
+            .map(|letter| PathBuf::from(format!("{}:\\", letter as char)))
+            .filter(|drive| drive.exists())
+            .collect()
+    } else {
+        vec![PathBuf::from("/")]
+    }
+}
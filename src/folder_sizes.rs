@@ -0,0 +1,117 @@
+// --- Recursive folder sizes, calculated on demand in the background ---
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
+
+use eframe::egui;
+use rayon::prelude::*;
+
+use crate::size_cache::SizeCache;
+
+/// Folder size calculations of the current directory.
+/// Each folder is walked on rayon's thread pool; finished sizes are collected by `poll`.
+pub struct FolderSizes {
+    pending: HashSet<PathBuf>,
+    cancel: Arc<AtomicBool>, // Shared by the running calculations, replaced by `cancel`
+    cache: Arc<SizeCache>, // Sizes of earlier runs, also filled by the disk usage analyzer
+    sender: mpsc::Sender<(PathBuf, Option<u64>)>,
+    receiver: mpsc::Receiver<(PathBuf, Option<u64>)>,
+}
+
+impl FolderSizes {
+    pub fn new(cache: Arc<SizeCache>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { pending: HashSet::new(), cancel: Arc::new(AtomicBool::new(false)), cache, sender, receiver }
+    }
+
+    /// Starts calculating the size of the folder `path`, unless that is already running.
+    /// With `use_cache`, sizes of unchanged folders are taken from the size cache.
+    pub fn calculate(&mut self, path: PathBuf, use_cache: bool) {
+        if !self.pending.insert(path.clone()) {
+            return;
+        }
+        let (sender, cancel, cache) = (self.sender.clone(), self.cancel.clone(), self.cache.clone());
+        rayon::spawn(move || {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            let size = folder_size(&path, &cancel, use_cache.then_some(&*cache));
+            if cancel.load(Ordering::Relaxed) {
+                return;
+            }
+            if let (Some(size), Some(modified)) = (size, modified) {
+                cache.insert(path.clone(), modified, size);
+            }
+            let _ = sender.send((path, size));
+        });
+    }
+
+    pub fn is_pending(&self, path: &Path) -> bool {
+        self.pending.contains(path)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the sizes finished since the last frame (`None` if the folder couldn't be read).
+    /// Keeps repainting while calculations are running, so results show up without input.
+    pub fn poll(&mut self, ctx: &egui::Context) -> Vec<(PathBuf, Option<u64>)> {
+        let finished: Vec<_> = self.receiver.try_iter().collect();
+        for (path, _) in &finished {
+            self.pending.remove(path);
+        }
+        if !self.pending.is_empty() {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        } else if !finished.is_empty() {
+            let cache = self.cache.clone();
+            rayon::spawn(move || {
+                if let Err(e) = cache.save_if_changed() {
+                    eprintln!("Error while saving folder sizes: {}", e);
+                }
+            });
+        }
+        finished
+    }
+
+    /// Stops all running calculations, e.g. when leaving the directory.
+    pub fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancel = Arc::new(AtomicBool::new(false));
+        self.pending.clear();
+    }
+}
+
+/// Total size of the files below `path`. Symlinks are not followed.
+/// Unreadable subfolders count as empty; `None` only if `path` itself can't be read.
+/// Sizes of unchanged (sub)folders are taken from `cache` if given.
+pub fn folder_size(path: &Path, cancel: &AtomicBool, cache: Option<&SizeCache>) -> Option<u64> {
+    if let Some(cache) = cache
+        && let Ok(modified) = fs::metadata(path).and_then(|m| m.modified())
+        && let Some(size) = cache.get(path, modified)
+    {
+        return Some(size);
+    }
+    let entries: Vec<fs::DirEntry> = fs::read_dir(path).ok()?.flatten().collect();
+    let size = entries
+        .par_iter()
+        .map(|entry| {
+            if cancel.load(Ordering::Relaxed) {
+                return 0;
+            }
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => folder_size(&entry.path(), cancel, cache).unwrap_or(0),
+                Ok(file_type) if file_type.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+                _ => 0, // Symlinks and special files
+            }
+        })
+        .sum();
+    Some(size)
+}
@@ -0,0 +1,81 @@
+// --- View mode, sort order and grouping remembered per folder ---
+//
+// Like Explorer's folder views: a folder shows up the way it was last arranged, e.g. photo
+// folders as a grid and source folders as details. Folders never arranged keep the view
+// of the folder shown before them.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::view::{GroupBy, SortOrder, ViewMode};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct FolderView {
+    pub view_mode: ViewMode,
+    pub sort_order: SortOrder,
+    pub group_by: GroupBy,
+}
+
+/// Views of all arranged folders, by absolute path.
+#[derive(Default)]
+pub struct FolderViews {
+    views: HashMap<PathBuf, FolderView>,
+}
+
+impl FolderViews {
+    /// Location of the database on disk.
+    fn database_file() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("FileFox").join("folder_views.toml"))
+    }
+
+    /// Loads the views written by `save`. A missing file yields no views.
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = Self::database_file() else {
+            return Ok(Self::default());
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let views: BTreeMap<String, FolderView> =
+            toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self { views: views.into_iter().map(|(path, view)| (PathBuf::from(path), view)).collect() })
+    }
+
+    /// Writes the views to disk, one table per folder. Paths that are not valid UTF-8 are left out.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::database_file() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no data directory available"));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let views: BTreeMap<&str, &FolderView> =
+            self.views.iter().filter_map(|(path, view)| Some((path.to_str()?, view))).collect();
+        let content = toml::to_string(&views).map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    pub fn get(&self, dir: &Path) -> Option<FolderView> {
+        self.views.get(dir).copied()
+    }
+
+    pub fn set(&mut self, dir: &Path, view: FolderView) {
+        self.views.insert(dir.to_owned(), view);
+    }
+
+    /// Forgets the views of all folders, which then keep the view of the folder shown before.
+    pub fn clear(&mut self) {
+        self.views.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+}
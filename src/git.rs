@@ -0,0 +1,122 @@
+// --- Git status of the current directory (shells out to `git`) ---
+
+use std::{collections::HashMap, ffi::OsString, path::Path, process::Command};
+
+use eframe::egui;
+
+/// Status of an entry in the working tree, in increasing priority.
+/// A folder shows the highest status of anything inside it.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitFileStatus {
+    Ignored,
+    Untracked,
+    Added,
+    Modified,
+    Conflicted,
+}
+
+impl GitFileStatus {
+    /// Short badge shown next to the entry name, with its color.
+    pub fn badge(self) -> (&'static str, egui::Color32) {
+        match self {
+            Self::Ignored => ("I", egui::Color32::from_gray(120)),
+            Self::Untracked => ("U", egui::Color32::from_rgb(80, 170, 230)),
+            Self::Added => ("A", egui::Color32::from_rgb(80, 190, 90)),
+            Self::Modified => ("M", egui::Color32::from_rgb(230, 160, 50)),
+            Self::Conflicted => ("C", egui::Color32::from_rgb(230, 70, 70)),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ignored => "Ignored",
+            Self::Untracked => "Untracked",
+            Self::Added => "Added",
+            Self::Modified => "Modified",
+            Self::Conflicted => "Conflicted",
+        }
+    }
+}
+
+/// Git status of the entries of one directory inside a repository.
+pub struct GitStatus {
+    pub branch: Option<String>, // `None` on a detached HEAD
+    pub entries: HashMap<OsString, GitFileStatus>, // By entry name; clean entries are left out
+}
+
+/// Reads the git status of `dir`. `None` if it is not inside a repository
+/// or git is not installed.
+pub fn read_status(dir: &Path) -> Option<GitStatus> {
+    // Location of `dir` inside the repository, like "src/ui/" (empty at the root)
+    let prefix = run_git(dir, &["rev-parse", "--show-prefix"])?;
+    let prefix = prefix.trim_end_matches(['\r', '\n']);
+
+    // -z: NUL separated, no quoting. Paths are relative to the repository root.
+    let output = run_git(dir, &["status", "--porcelain=v1", "-z", "--branch", "--ignored=matching", "--", "."])?;
+    let mut status = GitStatus { branch: None, entries: HashMap::new() };
+    let mut fields = output.split('\0');
+    while let Some(field) = fields.next() {
+        if let Some(branch) = field.strip_prefix("## ") {
+            status.branch = parse_branch(branch);
+            continue;
+        }
+        if field.len() < 4 {
+            continue;
+        }
+        let (code, path) = field.split_at(3);
+        let code = code.trim_end();
+        if code.starts_with('R') || code.starts_with('C') {
+            fields.next(); // Renames and copies are followed by the original path
+        }
+        // First component below `dir` is the listed entry
+        let Some(relative) = path.strip_prefix(prefix) else {
+            continue;
+        };
+        let Some(name) = relative.split('/').next().filter(|name| !name.is_empty()) else {
+            continue;
+        };
+        let file_status = parse_code(code);
+        let entry = status.entries.entry(OsString::from(name)).or_insert(file_status);
+        *entry = (*entry).max(file_status);
+    }
+    Some(status)
+}
+
+/// Branch name from the "## main...origin/main [ahead 1]" header line.
+fn parse_branch(header: &str) -> Option<String> {
+    if let Some(branch) = header.strip_prefix("No commits yet on ") {
+        return Some(branch.to_string());
+    }
+    if header.starts_with("HEAD (no branch)") {
+        return None;
+    }
+    let end = header.find("...").or_else(|| header.find(' ')).unwrap_or(header.len());
+    Some(header[..end].to_string())
+}
+
+/// Maps the two-letter "XY" code of the porcelain format.
+fn parse_code(code: &str) -> GitFileStatus {
+    match code {
+        "!!" => GitFileStatus::Ignored,
+        "??" => GitFileStatus::Untracked,
+        "DD" | "AU" | "UD" | "UA" | "DU" | "AA" | "UU" => GitFileStatus::Conflicted,
+        _ if code.starts_with('A') => GitFileStatus::Added,
+        _ => GitFileStatus::Modified,
+    }
+}
+
+/// Runs git in `dir` and returns its output, or `None` if it failed.
+fn run_git(dir: &Path, args: &[&str]) -> Option<String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir).args(args);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW); // No console window flashing up
+    }
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,228 @@
+// --- Built-in image viewer window ---
+
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use eframe::egui;
+use image::RgbaImage;
+
+/// Larger images are scaled down to this size before being uploaded as a texture.
+const MAX_TEXTURE_SIZE: u32 = 4096;
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 16.0;
+const ZOOM_FACTOR: f32 = 1.25;
+
+/// Viewer window for the images of one folder.
+pub struct ImageViewer {
+    images: Vec<PathBuf>, // All images of the folder, in listing order
+    current: usize,
+    image: Option<RgbaImage>, // Decoded and rotated, kept to rotate again
+    texture: Option<egui::TextureHandle>,
+    error: Option<String>,
+    zoom: Option<f32>, // `None` fits the image into the window
+    fit_zoom: f32, // Zoom that fit the image into the window last frame, the base for zooming in / out
+    receiver: Option<mpsc::Receiver<Result<RgbaImage, String>>>,
+}
+
+impl ImageViewer {
+    /// Opens `path`. `images` are the images of its folder to step through; `path` must be one of them.
+    pub fn open(path: &Path, images: Vec<PathBuf>, ctx: &egui::Context) -> Self {
+        let current = images.iter().position(|image| image == path).unwrap_or(0);
+        let mut viewer = Self {
+            images,
+            current,
+            image: None,
+            texture: None,
+            error: None,
+            zoom: None,
+            fit_zoom: 1.0,
+            receiver: None,
+        };
+        viewer.load(ctx);
+        viewer
+    }
+
+    fn path(&self) -> Option<&Path> {
+        self.images.get(self.current).map(PathBuf::as_path)
+    }
+
+    /// Starts decoding the current image on a background thread.
+    fn load(&mut self, ctx: &egui::Context) {
+        self.image = None;
+        self.texture = None;
+        self.error = None;
+        self.zoom = None;
+        let Some(path) = self.path().map(Path::to_owned) else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let image = image::open(&path).map_err(|e| e.to_string()).map(|image| {
+                if image.width() > MAX_TEXTURE_SIZE || image.height() > MAX_TEXTURE_SIZE {
+                    image.thumbnail(MAX_TEXTURE_SIZE, MAX_TEXTURE_SIZE).into_rgba8()
+                } else {
+                    image.into_rgba8()
+                }
+            });
+            if sender.send(image).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+        self.receiver = Some(receiver);
+    }
+
+    /// Moves `step` images forward (or back), wrapping around at the ends.
+    fn step(&mut self, step: isize, ctx: &egui::Context) {
+        if self.images.is_empty() {
+            return;
+        }
+        self.current = (self.current as isize + step).rem_euclid(self.images.len() as isize) as usize;
+        self.load(ctx);
+    }
+
+    /// Rotates by a quarter turn, clockwise or counter-clockwise.
+    fn rotate(&mut self, clockwise: bool, ctx: &egui::Context) {
+        if let Some(image) = &self.image {
+            let rotated = if clockwise {
+                image::imageops::rotate90(image)
+            } else {
+                image::imageops::rotate270(image)
+            };
+            self.set_image(rotated, ctx);
+        }
+    }
+
+    fn set_image(&mut self, image: RgbaImage, ctx: &egui::Context) {
+        let size = [image.width() as usize, image.height() as usize];
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+        let name = format!("image_viewer:{}", self.path().map(|p| p.display().to_string()).unwrap_or_default());
+        self.texture = Some(ctx.load_texture(name, color_image, egui::TextureOptions::LINEAR));
+        self.image = Some(image);
+    }
+
+    fn zoom_by(&mut self, factor: f32) {
+        let zoom = self.zoom.unwrap_or(self.fit_zoom) * factor;
+        self.zoom = Some(zoom.clamp(MIN_ZOOM, MAX_ZOOM));
+    }
+
+    /// Renders the viewer window. Returns `false` once it was closed.
+    /// Keys: ←/→ previous/next, +/- zoom, 0 fit to window, R rotate, Esc close.
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        if let Some(receiver) = &self.receiver
+            && let Ok(loaded) = receiver.try_recv()
+        {
+            self.receiver = None;
+            match loaded {
+                Ok(image) => self.set_image(image, ctx),
+                Err(e) => self.error = Some(e),
+            }
+        }
+
+        let title = self
+            .path()
+            .and_then(|p| p.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut is_open = true;
+        egui::Window::new(format!("🖼 {}", title))
+            .id(egui::Id::new("image_viewer"))
+            .open(&mut is_open)
+            .default_size([800.0, 600.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("◀").on_hover_text("Previous (←)").clicked() {
+                        self.step(-1, ctx);
+                    }
+                    ui.label(format!("{} / {}", self.current + 1, self.images.len()));
+                    if ui.button("▶").on_hover_text("Next (→)").clicked() {
+                        self.step(1, ctx);
+                    }
+                    ui.separator();
+                    if ui.selectable_label(self.zoom.is_none(), "Fit").on_hover_text("0").clicked() {
+                        self.zoom = None;
+                    }
+                    if ui.selectable_label(self.zoom == Some(1.0), "100%").clicked() {
+                        self.zoom = Some(1.0);
+                    }
+                    if ui.button("➖").on_hover_text("Zoom out (-)").clicked() {
+                        self.zoom_by(1.0 / ZOOM_FACTOR);
+                    }
+                    if ui.button("➕").on_hover_text("Zoom in (+)").clicked() {
+                        self.zoom_by(ZOOM_FACTOR);
+                    }
+                    ui.separator();
+                    if ui.button("⟲").on_hover_text("Rotate left (Shift+R)").clicked() {
+                        self.rotate(false, ctx);
+                    }
+                    if ui.button("⟳").on_hover_text("Rotate right (R)").clicked() {
+                        self.rotate(true, ctx);
+                    }
+                    if let Some(texture) = &self.texture {
+                        ui.weak(format!("{} × {}", texture.size()[0], texture.size()[1]));
+                    }
+                });
+                ui.separator();
+
+                match (&self.texture, &self.error) {
+                    (_, Some(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("Could not open the image: {}", error));
+                    }
+                    (None, None) => {
+                        ui.spinner();
+                    }
+                    (Some(texture), None) => {
+                        let size = texture.size_vec2();
+                        let available = ui.available_size();
+                        self.fit_zoom = (available.x / size.x).min(available.y / size.y).min(1.0);
+                        let zoom = self.zoom.unwrap_or(self.fit_zoom);
+                        egui::ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                            ui.centered_and_justified(|ui| {
+                                ui.image(texture.id(), size * zoom);
+                            });
+                        });
+                    }
+                }
+            });
+
+        // Keyboard shortcuts, unless a text field has focus
+        if is_open && ctx.memory(|m| m.focus().is_none()) {
+            let (previous, next, zoom_in, zoom_out, fit, rotate, close) = ctx.input(|i| {
+                (
+                    i.key_pressed(egui::Key::ArrowLeft),
+                    i.key_pressed(egui::Key::ArrowRight),
+                    i.key_pressed(egui::Key::PlusEquals),
+                    i.key_pressed(egui::Key::Minus),
+                    i.key_pressed(egui::Key::Num0),
+                    i.key_pressed(egui::Key::R).then_some(!i.modifiers.shift),
+                    i.key_pressed(egui::Key::Escape),
+                )
+            });
+            if previous {
+                self.step(-1, ctx);
+            }
+            if next {
+                self.step(1, ctx);
+            }
+            if zoom_in {
+                self.zoom_by(ZOOM_FACTOR);
+            }
+            if zoom_out {
+                self.zoom_by(1.0 / ZOOM_FACTOR);
+            }
+            if fit {
+                self.zoom = None;
+            }
+            if let Some(clockwise) = rotate {
+                self.rotate(clockwise, ctx);
+            }
+            if close {
+                is_open = false;
+            }
+        }
+        is_open
+    }
+}
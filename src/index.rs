@@ -0,0 +1,195 @@
+// --- Persistent file name index for instant searches ---
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::fileops;
+
+/// File format version, bumped whenever the layout changes.
+const INDEX_VERSION: u32 = 2;
+
+/// How old the index may get before it is rebuilt automatically.
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// All paths below one indexed root folder.
+#[derive(Serialize, Deserialize)]
+pub struct IndexedRoot {
+    pub root: PathBuf,
+    pub relative_paths: Vec<PathBuf>, // Relative to `root` to keep the index compact
+}
+
+/// Names of every file and folder below a set of chosen root folders.
+/// Answers name searches without walking the disk.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileIndex {
+    version: u32,
+    pub built: u64, // Seconds since the Unix epoch, 0 if never built
+    pub roots: Vec<IndexedRoot>,
+}
+
+impl FileIndex {
+    /// Location of the index on disk.
+    pub fn index_file() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("FileFox").join("index.toml"))
+    }
+
+    /// Crawls `roots` and builds a new index. Returns `None` if cancelled.
+    pub fn build(roots: &[PathBuf], cancel: &AtomicBool) -> Option<Self> {
+        let built = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |age| age.as_secs());
+        let mut index = Self { version: INDEX_VERSION, built, roots: Vec::new() };
+        for root in roots {
+            let mut relative_paths = Vec::new();
+            // Walked in extended-length form, as folders deep down may have long paths
+            let walk_root = fileops::extended_root(root);
+            for entry in WalkDir::new(&*walk_root).min_depth(1).into_iter().filter_map(|e| e.ok()) {
+                if cancel.load(Ordering::Relaxed) {
+                    return None;
+                }
+                if let Ok(relative) = entry.path().strip_prefix(&*walk_root) {
+                    relative_paths.push(relative.to_owned());
+                }
+            }
+            index.roots.push(IndexedRoot { root: root.clone(), relative_paths });
+        }
+        Some(index)
+    }
+
+    /// Loads the index written by `save`. A missing file yields an empty index.
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = Self::index_file() else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads an index from its TOML form. An older layout yields an empty index.
+    fn parse(content: &str) -> io::Result<Self> {
+        let index: Self = toml::from_str(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if index.version != INDEX_VERSION {
+            return Ok(Self::default()); // Unknown format, needs to be rebuilt
+        }
+        Ok(index)
+    }
+
+    /// Writes the index to disk, replacing the old one only once it is complete.
+    /// Paths that are not valid UTF-8 are left out and will only be found by the live search.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::index_file() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no data directory available"));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fileops::replace_file(&path, &self.to_toml()?)
+    }
+
+    /// The TOML form written by `save`.
+    fn to_toml(&self) -> io::Result<String> {
+        let roots = self
+            .roots
+            .iter()
+            .filter(|root| root.root.to_str().is_some())
+            .map(|root| IndexedRoot {
+                root: root.root.clone(),
+                relative_paths: root.relative_paths.iter().filter(|path| path.to_str().is_some()).cloned().collect(),
+            })
+            .collect();
+        let index = Self { version: INDEX_VERSION, built: self.built, roots };
+        toml::to_string(&index).map_err(io::Error::other)
+    }
+
+    /// Time left until the index should be rebuilt, zero if it is due.
+    pub fn refresh_due_in(&self) -> Duration {
+        let built = SystemTime::UNIX_EPOCH + Duration::from_secs(self.built);
+        let age = SystemTime::now().duration_since(built).unwrap_or_default();
+        REFRESH_INTERVAL.saturating_sub(age)
+    }
+
+    /// Returns `true` if `dir` lies inside one of the indexed roots.
+    pub fn covers(&self, dir: &Path) -> bool {
+        self.roots.iter().any(|root| dir.starts_with(&root.root))
+    }
+
+    /// Total number of indexed paths.
+    pub fn len(&self) -> usize {
+        self.roots.iter().map(|root| root.relative_paths.len()).sum()
+    }
+
+    /// All indexed paths below `dir` (absolute).
+    pub fn paths_under<'a>(&'a self, dir: &'a Path) -> impl Iterator<Item = PathBuf> + 'a {
+        self.roots.iter().flat_map(move |root| {
+            let prefix = dir.strip_prefix(&root.root).ok();
+            root.relative_paths
+                .iter()
+                .filter(move |relative| prefix.is_some_and(|prefix| relative.starts_with(prefix) && *relative != prefix))
+                .map(move |relative| root.root.join(relative))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_index() -> FileIndex {
+        FileIndex {
+            version: INDEX_VERSION,
+            built: 1_700_000_000,
+            roots: vec![IndexedRoot {
+                root: PathBuf::from("/data"),
+                relative_paths: vec![
+                    PathBuf::from("R notes.txt"),
+                    PathBuf::from("line\nbreak"),
+                    PathBuf::from("photos/holiday.jpg"),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn round_trips_names_that_look_like_markers() {
+        let index = sample_index();
+        let loaded = FileIndex::parse(&index.to_toml().unwrap()).unwrap();
+        assert_eq!(loaded.built, index.built);
+        assert_eq!(loaded.roots.len(), 1);
+        assert_eq!(loaded.roots[0].root, PathBuf::from("/data"));
+        assert_eq!(loaded.roots[0].relative_paths, index.roots[0].relative_paths);
+    }
+
+    #[test]
+    fn other_versions_are_rebuilt() {
+        let mut index = sample_index();
+        index.version = INDEX_VERSION + 1;
+        let content = toml::to_string(&index).unwrap();
+        assert!(FileIndex::parse(&content).unwrap().roots.is_empty());
+        assert!(FileIndex::parse("not an index").is_err());
+    }
+
+    #[test]
+    fn paths_under_lists_only_descendants() {
+        let index = sample_index();
+        let found: Vec<_> = index.paths_under(Path::new("/data/photos")).collect();
+        assert_eq!(found, vec![PathBuf::from("/data/photos/holiday.jpg")]);
+        assert_eq!(index.paths_under(Path::new("/elsewhere")).count(), 0);
+    }
+
+    #[test]
+    fn old_indexes_are_due_for_a_refresh() {
+        let mut index = sample_index();
+        assert_eq!(index.refresh_due_in(), Duration::ZERO);
+        index.built = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        assert!(index.refresh_due_in() > Duration::ZERO);
+    }
+}
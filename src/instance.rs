@@ -0,0 +1,207 @@
+// --- One running FileFox for filefox:// links ---
+//
+// Every instance listens on a local TCP port and writes it to the cache directory, so
+// the one started last receives links. FileFox started for a link hands its folder to
+// that instance, which navigates there and comes to the front, instead of opening a
+// second window.
+//
+// Links can come from any web page, so they only open local folders: listing a network
+// share would send the user's credentials to whichever server the link names. The port
+// file also holds a random token that requests have to repeat, so other users' processes
+// (which can't read the file) can't send folders.
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use eframe::egui;
+
+use crate::{drives, webdav};
+
+pub const URI_SCHEME: &str = "filefox";
+
+/// Starts every request, telling the listening instance apart from whatever else took over a stale port.
+const GREETING: &str = "FileFox open ";
+
+fn port_file() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("FileFox").join("instance_port.txt"))
+}
+
+/// 128 random bits from the operating system's random generator, as hex.
+fn new_token() -> io::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| io::Error::other(e.to_string()))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Whether `request_token` is `token`. Looks at every byte instead of stopping at the first
+/// difference, so the time an answer takes doesn't tell how much of a guess was right.
+fn tokens_match(request_token: &str, token: &str) -> bool {
+    request_token.len() == token.len()
+        && request_token.bytes().zip(token.bytes()).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+/// Whether a link may open `path`: an absolute path on a local drive, not a UNC path
+/// (\\server\share), a network drive or a network mount.
+fn is_local_folder(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    if !path.is_absolute() || text.starts_with(r"\\") || text.starts_with("//") {
+        return false;
+    }
+    !drives::is_network_path(path)
+}
+
+/// The folder of a link like "filefox://open?path=C:\Reports". The path may be percent-encoded.
+/// `None` for other links and for folders that are not local.
+pub fn parse_uri(uri: &str) -> Option<PathBuf> {
+    let (scheme, rest) = uri.split_once(':')?;
+    if !scheme.eq_ignore_ascii_case(URI_SCHEME) {
+        return None;
+    }
+    // Browsers may add a slash after the host, e.g. "filefox://open/?path=..."
+    let (action, query) = rest.trim_start_matches('/').split_once('?')?;
+    if !action.trim_end_matches('/').eq_ignore_ascii_case("open") {
+        return None;
+    }
+    let path = query.split('&').find_map(|parameter| parameter.strip_prefix("path="))?;
+    let path = PathBuf::from(webdav::percent_decode(path));
+    if !is_local_folder(&path) {
+        eprintln!("Ignoring the {}:// link to {:?}, only local folders can be opened", URI_SCHEME, path);
+        return None;
+    }
+    Some(path)
+}
+
+/// Hands `folder` to the running instance. False if there is none, then this process shows it itself.
+pub fn forward(folder: &Path) -> bool {
+    // "<port> <token>"
+    let Some(content) = port_file().and_then(|path| fs::read_to_string(path).ok()) else {
+        return false;
+    };
+    let Some((port, token)) = content.trim().split_once(' ') else {
+        return false;
+    };
+    let Ok(port) = port.parse::<u16>() else {
+        return false;
+    };
+    let send = || -> io::Result<bool> {
+        let mut stream = TcpStream::connect_timeout(&(Ipv4Addr::LOCALHOST, port).into(), Duration::from_secs(1))?;
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        writeln!(stream, "{}{} {}", GREETING, token, folder.display())?;
+        let mut answer = String::new();
+        BufReader::new(stream).read_line(&mut answer)?;
+        Ok(answer.trim() == "ok")
+    };
+    send().unwrap_or(false)
+}
+
+/// Starts accepting folders from processes started for links. They arrive on the returned receiver.
+pub fn listen(ctx: &egui::Context) -> Option<mpsc::Receiver<PathBuf>> {
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Error while listening for filefox:// links: {}", e);
+            return None;
+        }
+    };
+    let port = listener.local_addr().ok()?.port();
+    let written = new_token().and_then(|token| {
+        let path = port_file().ok_or_else(|| io::Error::other("no cache directory available"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+        // The mode above only applies to new files, a file left by an older run keeps its own
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        write!(file, "{} {}", port, token)?;
+        Ok(token)
+    });
+    let token = match written {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("Error while saving the port for filefox:// links: {}", e);
+            return None;
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+            let mut request = String::new();
+            if BufReader::new(&stream).read_line(&mut request).is_err() {
+                continue;
+            }
+            let Some(request) = request.trim_end_matches(['\r', '\n']).strip_prefix(GREETING) else {
+                continue;
+            };
+            let Some((request_token, folder)) = request.split_once(' ') else {
+                continue;
+            };
+            let folder = PathBuf::from(folder);
+            if !tokens_match(request_token, &token) || !is_local_folder(&folder) {
+                continue; // The sender gets no answer and opens its own window
+            }
+            let _ = (&stream).write_all(b"ok\n");
+            if sender.send(folder).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        }
+    });
+    Some(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_open_links() {
+        #[cfg(windows)]
+        let (link, folder) = ("filefox://open?path=C%3A%5CReports%20Q1", r"C:\Reports Q1");
+        #[cfg(not(windows))]
+        let (link, folder) = ("filefox://open?path=%2Fhome%2Fme%2FReports%20Q1", "/home/me/Reports Q1");
+        assert_eq!(parse_uri(link), Some(PathBuf::from(folder)));
+        let link = link.replace("filefox://open?", "FileFox://open/?x=1&");
+        assert_eq!(parse_uri(&link), Some(PathBuf::from(folder)));
+    }
+
+    #[test]
+    fn rejects_other_links() {
+        assert_eq!(parse_uri("https://open?path=/tmp"), None);
+        assert_eq!(parse_uri("filefox://delete?path=/tmp"), None);
+        assert_eq!(parse_uri("filefox://open?folder=/tmp"), None);
+        assert_eq!(parse_uri("filefox://open"), None);
+    }
+
+    #[test]
+    fn tokens_are_random_and_compared_in_full() {
+        let (token, other) = (new_token().unwrap(), new_token().unwrap());
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, other);
+        assert!(tokens_match(&token, &token.clone()));
+        assert!(!tokens_match(&token, &other));
+        assert!(!tokens_match(&token, &token[..31]));
+    }
+
+    #[test]
+    fn rejects_unc_and_relative_paths() {
+        let paths = [r"\\attacker\share", "%5C%5Cattacker%5Cshare", "//attacker/share", r"\\?\UNC\attacker\share", "Reports"];
+        for path in paths {
+            assert_eq!(parse_uri(&format!("filefox://open?path={}", path)), None, "{} was accepted", path);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+// --- Background jobs: searches, copies, moves, compression and indexing, each on its own thread ---
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use eframe::egui;
+
+use crate::repaint;
+
+pub type JobId = u64;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    Search,
+    Copy,
+    Move,
+    Delete,
+    Compress,
+    Index,
+    Transfer,
+    Sync,
+    Extract,
+}
+
+impl JobKind {
+    /// Shown in the status bar while jobs of this kind are running.
+    pub fn label(self) -> &'static str {
+        match self {
+            JobKind::Search => "Searching",
+            JobKind::Copy => "Copying",
+            JobKind::Move => "Moving",
+            JobKind::Delete => "Deleting",
+            JobKind::Compress => "Compressing",
+            JobKind::Index => "Indexing",
+            JobKind::Transfer => "Transferring",
+            JobKind::Sync => "Synchronizing",
+            JobKind::Extract => "Extracting",
+        }
+    }
+}
+
+/// Sent by running jobs to the UI, over one channel for all jobs.
+enum JobEvent {
+    /// `done` of `total` steps are finished.
+    Progress { id: JobId, done: usize, total: usize },
+    Finished(JobId),
+}
+
+/// Handed to a running job: tells it when to stop and reports its progress.
+pub struct JobContext {
+    id: JobId,
+    cancel: Arc<AtomicBool>,
+    sender: mpsc::Sender<JobEvent>,
+    ctx: egui::Context,
+}
+
+impl JobContext {
+    /// Set once the job was cancelled; long-running loops should stop then.
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn progress(&self, done: usize, total: usize) {
+        if self.sender.send(JobEvent::Progress { id: self.id, done, total }).is_ok() {
+            repaint::request_throttled_repaint(&self.ctx);
+        }
+    }
+
+    /// Repaints the UI right away, after sending it something it has to answer.
+    pub fn request_repaint(&self) {
+        self.ctx.request_repaint();
+    }
+}
+
+#[cfg(test)]
+impl JobContext {
+    /// A context for calling job functions directly in tests. Never cancelled, progress goes nowhere.
+    pub fn detached() -> Self {
+        let (sender, _) = mpsc::channel();
+        Self { id: 0, cancel: Arc::default(), sender, ctx: egui::Context::default() }
+    }
+}
+
+impl Drop for JobContext {
+    // Also reports jobs that ended with a panic as finished
+    fn drop(&mut self) {
+        if self.sender.send(JobEvent::Finished(self.id)).is_ok() {
+            self.ctx.request_repaint();
+        }
+    }
+}
+
+struct RunningJob {
+    kind: JobKind,
+    cancel: Arc<AtomicBool>,
+    progress: Option<(usize, usize)>,
+}
+
+/// Running background jobs. Each gets a thread of its own: jobs mostly wait on disks and
+/// networks, and on rayon's pool they would hold up the thumbnail, folder size and metadata
+/// workers (and the parallel part of searches) for as long as they run.
+pub struct Jobs {
+    next_id: JobId,
+    running: BTreeMap<JobId, RunningJob>,
+    sender: mpsc::Sender<JobEvent>,
+    receiver: mpsc::Receiver<JobEvent>,
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { next_id: 0, running: BTreeMap::new(), sender, receiver }
+    }
+}
+
+impl Jobs {
+    /// Starts `work` on a new thread.
+    pub fn spawn(&mut self, kind: JobKind, ctx: &egui::Context, work: impl FnOnce(&JobContext) + Send + 'static) -> JobId {
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.running.insert(id, RunningJob { kind, cancel: cancel.clone(), progress: None });
+        let job = JobContext { id, cancel, sender: self.sender.clone(), ctx: ctx.clone() };
+        let spawned = thread::Builder::new().name(format!("FileFox {}", kind.label())).spawn(move || work(&job));
+        if let Err(e) = spawned {
+            eprintln!("Error while starting a background job: {}", e); // Dropping the job reports it as finished
+        }
+        id
+    }
+
+    /// Asks every running job of `kind` to stop. They count as running until they did.
+    pub fn cancel_kind(&mut self, kind: JobKind) {
+        for job in self.running.values().filter(|job| job.kind == kind) {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Applies the progress and finished jobs reported since the last frame.
+    pub fn poll(&mut self) {
+        for event in self.receiver.try_iter() {
+            match event {
+                JobEvent::Progress { id, done, total } => {
+                    if let Some(job) = self.running.get_mut(&id) {
+                        job.progress = Some((done, total));
+                    }
+                }
+                JobEvent::Finished(id) => {
+                    self.running.remove(&id);
+                }
+            }
+        }
+    }
+
+    pub fn is_running(&self, kind: JobKind) -> bool {
+        self.running.values().any(|job| job.kind == kind)
+    }
+
+    /// Summed progress of the running jobs of `kind`, if they report any.
+    pub fn progress(&self, kind: JobKind) -> Option<(usize, usize)> {
+        self.running
+            .values()
+            .filter(|job| job.kind == kind)
+            .filter_map(|job| job.progress)
+            .reduce(|(done, total), (job_done, job_total)| (done + job_done, total + job_total))
+    }
+
+    /// One status bar line per kind of running job, e.g. "Copying 12/40".
+    pub fn activities(&self) -> Vec<String> {
+        let mut activities = Vec::new();
+        let kinds = [
+            JobKind::Search,
+            JobKind::Copy,
+            JobKind::Move,
+            JobKind::Delete,
+            JobKind::Compress,
+            JobKind::Index,
+            JobKind::Transfer,
+            JobKind::Sync,
+            JobKind::Extract,
+        ];
+        for kind in kinds {
+            if !self.is_running(kind) {
+                continue;
+            }
+            match self.progress(kind) {
+                Some((done, total)) => activities.push(format!("{} {}/{}", kind.label(), done, total)),
+                None => activities.push(kind.label().to_string()),
+            }
+        }
+        activities
+    }
+}
@@ -0,0 +1,270 @@
+// --- Keyboard shortcuts ---
+//
+// Every action with a shortcut is looked up in the keymap instead of checking keys
+// directly, so all of them can be changed in the settings. Only the bindings that
+// differ from the defaults are saved, e.g. `new_folder = "Ctrl+Shift+N"`; an empty
+// value removes the shortcut.
+
+use std::collections::{BTreeMap, HashMap};
+
+use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Serialize};
+
+/// Something the user can do with a keyboard shortcut.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    NavigateUp,
+    Refresh,
+    NewFolder,
+    Rename,
+    MoveToTrash,
+    DeletePermanently,
+    SelectAll,
+    Copy,
+    Cut,
+    Paste,
+    FocusFilter,
+    Search,
+    TogglePreview,
+    ToggleHidden,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+}
+
+impl Action {
+    pub const ALL: [Self; 17] = [
+        Self::NavigateUp,
+        Self::Refresh,
+        Self::NewFolder,
+        Self::Rename,
+        Self::MoveToTrash,
+        Self::DeletePermanently,
+        Self::SelectAll,
+        Self::Copy,
+        Self::Cut,
+        Self::Paste,
+        Self::FocusFilter,
+        Self::Search,
+        Self::TogglePreview,
+        Self::ToggleHidden,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::ResetZoom,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::NavigateUp => "Go to parent folder",
+            Self::Refresh => "Refresh",
+            Self::NewFolder => "New folder",
+            Self::Rename => "Rename",
+            Self::MoveToTrash => "Move to trash",
+            Self::DeletePermanently => "Delete permanently",
+            Self::SelectAll => "Select all",
+            Self::Copy => "Copy",
+            Self::Cut => "Cut",
+            Self::Paste => "Paste",
+            Self::FocusFilter => "Filter this folder",
+            Self::Search => "Search",
+            Self::TogglePreview => "Show / hide preview",
+            Self::ToggleHidden => "Show / hide hidden files",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::ResetZoom => "Reset zoom",
+        }
+    }
+
+    /// Key used in the settings file.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::NavigateUp => "navigate_up",
+            Self::Refresh => "refresh",
+            Self::NewFolder => "new_folder",
+            Self::Rename => "rename",
+            Self::MoveToTrash => "move_to_trash",
+            Self::DeletePermanently => "delete_permanently",
+            Self::SelectAll => "select_all",
+            Self::Copy => "copy",
+            Self::Cut => "cut",
+            Self::Paste => "paste",
+            Self::FocusFilter => "focus_filter",
+            Self::Search => "search",
+            Self::TogglePreview => "toggle_preview",
+            Self::ToggleHidden => "toggle_hidden",
+            Self::ZoomIn => "zoom_in",
+            Self::ZoomOut => "zoom_out",
+            Self::ResetZoom => "reset_zoom",
+        }
+    }
+
+    fn default_shortcut(self) -> Option<KeyboardShortcut> {
+        let (modifiers, key) = match self {
+            Self::NavigateUp => (Modifiers::ALT, Key::ArrowUp),
+            Self::Refresh => (Modifiers::NONE, Key::F5),
+            Self::NewFolder => (Modifiers::COMMAND | Modifiers::SHIFT, Key::N),
+            Self::Rename => (Modifiers::NONE, Key::F2),
+            Self::MoveToTrash => (Modifiers::NONE, Key::Delete),
+            Self::DeletePermanently => (Modifiers::SHIFT, Key::Delete),
+            Self::SelectAll => (Modifiers::COMMAND, Key::A),
+            Self::Copy => (Modifiers::COMMAND, Key::C),
+            Self::Cut => (Modifiers::COMMAND, Key::X),
+            Self::Paste => (Modifiers::COMMAND, Key::V),
+            Self::FocusFilter => (Modifiers::COMMAND, Key::F),
+            Self::Search => (Modifiers::COMMAND | Modifiers::SHIFT, Key::F),
+            Self::TogglePreview => (Modifiers::ALT, Key::P),
+            Self::ToggleHidden => (Modifiers::COMMAND, Key::H),
+            Self::ZoomIn => (Modifiers::COMMAND, Key::PlusEquals),
+            Self::ZoomOut => (Modifiers::COMMAND, Key::Minus),
+            Self::ResetZoom => (Modifiers::COMMAND, Key::Num0),
+        };
+        Some(KeyboardShortcut::new(modifiers, key))
+    }
+
+    /// Whether the shortcut also works while a text field has the keyboard focus.
+    /// The others (Delete, Ctrl+C, ...) belong to the text field then.
+    fn works_while_typing(self) -> bool {
+        matches!(self, Self::FocusFilter | Self::TogglePreview | Self::ZoomIn | Self::ZoomOut | Self::ResetZoom)
+    }
+}
+
+/// Keys that can be bound, all of egui's except Escape, which cancels.
+const KEYS: [Key; 72] = [
+    Key::ArrowDown, Key::ArrowLeft, Key::ArrowRight, Key::ArrowUp, Key::Tab, Key::Backspace, Key::Enter,
+    Key::Space, Key::Insert, Key::Delete, Key::Home, Key::End, Key::PageUp, Key::PageDown, Key::Minus,
+    Key::PlusEquals, Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7,
+    Key::Num8, Key::Num9, Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J,
+    Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W,
+    Key::X, Key::Y, Key::Z, Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9,
+    Key::F10, Key::F11, Key::F12, Key::F13, Key::F14, Key::F15, Key::F16, Key::F17,
+    Key::F18, Key::F19, Key::F20,
+];
+
+/// Text of a shortcut in the settings file, e.g. "Ctrl+Shift+N". Ctrl means Cmd on macOS.
+fn shortcut_to_string(shortcut: &KeyboardShortcut) -> String {
+    shortcut.format(&egui::ModifierNames::NAMES, false)
+}
+
+fn parse_shortcut(text: &str) -> Option<KeyboardShortcut> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    let key_name = parts.pop()?;
+    let key = KEYS.into_iter().find(|key| key.name().eq_ignore_ascii_case(key_name))?;
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "cmd" => modifiers.command = true,
+            "alt" => modifiers.alt = true,
+            "shift" => modifiers.shift = true,
+            _ => return None,
+        }
+    }
+    Some(KeyboardShortcut::new(modifiers, key))
+}
+
+/// Shortcut of every action.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<String, String>", into = "BTreeMap<String, String>")]
+pub struct Keymap {
+    shortcuts: HashMap<Action, Option<KeyboardShortcut>>, // Actions missing here have their default
+}
+
+impl From<BTreeMap<String, String>> for Keymap {
+    /// Unknown actions and shortcuts that can't be parsed keep the default.
+    fn from(saved: BTreeMap<String, String>) -> Self {
+        let mut keymap = Self::default();
+        for action in Action::ALL {
+            match saved.get(action.as_str()).map(String::as_str) {
+                Some("") => keymap.set(action, None),
+                Some(text) => {
+                    if let Some(shortcut) = parse_shortcut(text) {
+                        keymap.set(action, Some(shortcut));
+                    }
+                }
+                None => {}
+            }
+        }
+        keymap
+    }
+}
+
+impl From<Keymap> for BTreeMap<String, String> {
+    fn from(keymap: Keymap) -> Self {
+        let text = |shortcut: &Option<KeyboardShortcut>| shortcut.as_ref().map(shortcut_to_string).unwrap_or_default();
+        keymap.shortcuts.iter().map(|(action, shortcut)| (action.as_str().to_string(), text(shortcut))).collect()
+    }
+}
+
+impl Keymap {
+    pub fn shortcut(&self, action: Action) -> Option<KeyboardShortcut> {
+        self.shortcuts.get(&action).copied().unwrap_or_else(|| action.default_shortcut())
+    }
+
+    /// Binds `action` to `shortcut`, or removes its shortcut.
+    pub fn set(&mut self, action: Action, shortcut: Option<KeyboardShortcut>) {
+        if shortcut == action.default_shortcut() {
+            self.shortcuts.remove(&action);
+        } else {
+            self.shortcuts.insert(action, shortcut);
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.shortcuts.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.shortcuts.clear();
+    }
+
+    /// Other actions with the same shortcut as `action`. Only the first of them would run.
+    pub fn conflicts(&self, action: Action) -> Vec<Action> {
+        let Some(shortcut) = self.shortcut(action) else {
+            return Vec::new();
+        };
+        Action::ALL.into_iter().filter(|other| *other != action && self.shortcut(*other) == Some(shortcut)).collect()
+    }
+
+    /// Shortcut of `action` as shown to the user, e.g. for hover texts. Empty if it has none.
+    pub fn describe(&self, ctx: &egui::Context, action: Action) -> String {
+        self.shortcut(action).map(|shortcut| ctx.format_shortcut(&shortcut)).unwrap_or_default()
+    }
+
+    /// Actions whose shortcut was pressed this frame. Their key presses are consumed.
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Action> {
+        let typing = ctx.wants_keyboard_input();
+        ctx.input_mut(|i| {
+            Action::ALL
+                .into_iter()
+                .filter(|action| !typing || action.works_while_typing())
+                .filter(|action| self.shortcut(*action).is_some_and(|shortcut| i.consume_shortcut(&shortcut)))
+                .collect()
+        })
+    }
+}
+
+/// The shortcut typed this frame while a new one is recorded in the settings. Escape cancels,
+/// which gives `Some(None)`.
+pub fn record_shortcut(ctx: &egui::Context) -> Option<Option<KeyboardShortcut>> {
+    ctx.input_mut(|i| {
+        let mut recorded = None;
+        i.events.retain(|event| match event {
+            egui::Event::Key { key: Key::Escape, pressed: true, .. } if recorded.is_none() => {
+                recorded = Some(None);
+                false
+            }
+            egui::Event::Key { key, pressed: true, modifiers, .. } if recorded.is_none() && KEYS.contains(key) => {
+                // Ctrl is stored as "command", which is Cmd on macOS
+                let modifiers = Modifiers {
+                    alt: modifiers.alt,
+                    shift: modifiers.shift,
+                    command: modifiers.command,
+                    ..Modifiers::NONE
+                };
+                recorded = Some(Some(KeyboardShortcut::new(modifiers, *key)));
+                false
+            }
+            _ => true,
+        });
+        recorded
+    })
+}
@@ -0,0 +1,57 @@
+// --- Listings of recently visited directories, reused while a directory is unchanged ---
+
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::view::EntryDetails;
+
+/// At most this many listings are kept, the least recently left one is dropped first.
+const MAX_CACHED_LISTINGS: usize = 16;
+
+struct CachedListing {
+    dir: PathBuf,
+    entries: Vec<OsString>,
+    details: HashMap<OsString, EntryDetails>,
+    modified: SystemTime, // Of the directory when it was read
+}
+
+/// Makes going back to a directory instant, even on slow network drives: the listing is
+/// reused if the directory's modification time, which changes whenever an entry is added,
+/// removed or renamed, is still the same.
+#[derive(Default)]
+pub struct ListingCache {
+    listings: VecDeque<CachedListing>, // Most recently left last
+}
+
+impl ListingCache {
+    /// Remembers the listing of `dir` when leaving it. `modified` is the directory's
+    /// modification time when the listing was read.
+    pub fn store(&mut self, dir: PathBuf, entries: Vec<OsString>, details: HashMap<OsString, EntryDetails>, modified: SystemTime) {
+        self.listings.retain(|listing| listing.dir != dir);
+        if self.listings.len() >= MAX_CACHED_LISTINGS {
+            self.listings.pop_front();
+        }
+        self.listings.push_back(CachedListing { dir, entries, details, modified });
+    }
+
+    /// Modification time of `dir` when its cached listing was read, to compare with the current one.
+    pub fn modified(&self, dir: &Path) -> Option<SystemTime> {
+        self.listings.iter().find(|listing| listing.dir == dir).map(|listing| listing.modified)
+    }
+
+    /// Takes the listing of `dir` out of the cache, if the directory's current modification time
+    /// `modified` is still the same.
+    pub fn take(
+        &mut self,
+        dir: &Path,
+        modified: SystemTime,
+    ) -> Option<(Vec<OsString>, HashMap<OsString, EntryDetails>)> {
+        let index = self.listings.iter().position(|listing| listing.dir == dir)?;
+        let listing = self.listings.remove(index)?;
+        (modified == listing.modified).then_some((listing.entries, listing.details))
+    }
+}
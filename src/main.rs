@@ -1,466 +1,4597 @@
 #![windows_subsystem = "windows"] // Prevents terminal window from opening
 use eframe::egui;
+use eframe::egui::text::{LayoutJob, TextFormat};
 use image::ImageFormat;
 
 use std::{
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    ops::Range,
+    path::{Path, PathBuf},
     process::Command,
-    sync::mpsc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
     thread,
+    time::{Duration, Instant, SystemTime},
 };
 
 // Imports for parallel search
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
+mod archive;
+mod checksum;
+mod cloud;
+mod compare;
+mod disk_image;
+mod disk_usage;
+mod drag;
+mod drives;
+mod elevate;
+mod everything;
+mod export;
+mod fileops;
+mod filetype;
+mod folder_picker;
+mod folder_sizes;
+mod folder_views;
+mod git;
+mod image_viewer;
+mod index;
+mod instance;
+mod jobs;
+mod keymap;
+mod listing_cache;
+mod media;
+mod metadata;
+mod mtp;
+mod open_with;
+mod pdf;
+mod photo;
+mod places;
+mod preview;
+mod properties;
+mod recycle;
+mod remote;
+mod repaint;
+mod result_spill;
+mod s3;
+mod search;
+mod session;
+mod settings;
+mod shell_integration;
+mod shell_menu;
+mod size_cache;
+mod sync;
+mod tags;
+mod text_viewer;
+mod thumbnails;
+mod tray;
+mod view;
+mod watcher;
+mod webdav;
+
+use elevate::ElevatedOperation;
+use export::ExportFormat;
+use checksum::ChecksumVerifier;
+use compare::{CompareAction, FolderComparison};
+use sync::{FolderSync, SyncAction};
+use disk_image::{ExtractJob, ImageAction, ImageBrowser};
+use disk_usage::{DiskUsage, UsageAction};
+use drives::{Drive, DriveKind};
+use fileops::{ClipboardMode, ConflictAnswer, ConflictChoice, CopyReport, DeleteReport, MergeMove, MoveEvent, MoveReport};
+use filetype::ContentType;
+use folder_picker::FolderPicker;
+use folder_sizes::FolderSizes;
+use git::GitStatus;
+use cloud::CloudStatus;
+use mtp::{Device, DeviceAction, DeviceBrowser, DeviceCopyJob};
+use image_viewer::ImageViewer;
+use index::FileIndex;
+use jobs::{JobContext, JobKind, Jobs};
+use listing_cache::ListingCache;
+use metadata::MetadataLoader;
+use open_with::{ChooserAction, Handler, ProgramChooser};
+use preview::Preview;
+use properties::Properties;
+use remote::{RemoteAction, RemoteBrowser};
+use result_spill::{MAX_RESULTS_IN_MEMORY, ResultSpill};
+use search::{
+    EntryKind, LineHit, ResultBatcher, ResultMetadata, ResultSortKey, SearchMatcher, SearchMessage, SearchMode,
+    SearchOptions, SearchProgress, SearchStats,
+};
+use settings::{Settings, StartupFolder, Theme};
+use shell_integration::Integration;
+use keymap::Action;
+use folder_views::{FolderView, FolderViews};
+use places::Places;
+use session::Session;
+use tray::Tray;
+use size_cache::SizeCache;
+use tags::{ColorLabel, TagDatabase};
+use text_viewer::TextViewer;
+use thumbnails::ThumbnailCache;
+use view::{EntryDetails, GroupBy, RowDensity, SortColumn, SortOrder, ViewMode};
+use watcher::{DirectoryChanges, DirectoryWatcher};
+
+/// Search results are rendered in pages of this size.
+const RESULTS_PAGE_SIZE: usize = 5_000;
+
+/// Typing in the filter box of folders with more entries than this re-filters
+/// only after a short pause, instead of on every keystroke.
+const FILTER_DEBOUNCE_THRESHOLD: usize = 5_000;
+const FILTER_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// The directory reader sends its entries in batches of at most this size...
+const LISTING_BATCH_SIZE: usize = 1_000;
+/// ...or whatever it has read after this long, so slow drives show entries early.
+const LISTING_BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Entries of the current directory other programs changed, read in the background.
+pub struct ExternalChanges {
+    dir: PathBuf,
+    entries: Vec<(OsString, Option<EntryDetails>)>, // `None` for removed entries
+    dir_modified: Option<SystemTime>,
+}
+
+/// Messages from the thread reading the current directory. The channel closes once it is done.
+pub enum ListingMessage {
+    /// Sent first: the directory's modification time and its volume's free space and trash. No batches
+    /// follow if the directory is unchanged since its cached listing.
+    Info { modified: Option<SystemTime>, disk_space: Option<(u64, u64)>, trash_available: bool },
+    /// Entries read since the last batch, by name.
+    Batch(Vec<(OsString, EntryDetails)>),
+    /// The directory could not be read.
+    Error(std::io::Error),
+}
+
+/// Work that would replace existing files, held back until the user allows it.
+pub enum ReplaceOperation {
+    Zip { sources: Vec<PathBuf>, archive_path: PathBuf, level: i32 },
+    Extract(ExtractJob),
+    DeviceCopy(DeviceCopyJob),
+    Rename { from: PathBuf, to: PathBuf },
+    Export { destination: PathBuf },
+}
+
 // --- App Structure and Initialization ---
 
 pub struct MyExplorerApp {
     pub current_dir: PathBuf,
-    pub entries: Vec<String>,
-    pub filtered_entries: Option<Vec<String>>,
+    pub entries: Vec<OsString>, // Names as stored on disk, converted lossily only for display
+    pub filtered_entries: Option<Vec<OsString>>,
+    pub directory_filter: String, // Narrows `entries` of the current directory as you type
+    pub directory_filter_edited: Option<Instant>, // Last keystroke not applied yet (large folders)
+    pub lowercase_names: HashMap<OsString, String>, // Lowercase entry names, cached for filtering
+    pub label_filter: Option<ColorLabel>, // Only show entries with this label
+    pub tag_filter: Option<String>, // Only show entries with this tag
+    pub tags: TagDatabase, // Color labels and tags, persisted in the data directory
+    pub new_tag_input: String, // Tag typed in the context menu
+    pub entry_details: HashMap<OsString, EntryDetails>, // Keyed by entry name
+    pub listing_receiver: Option<mpsc::Receiver<ListingMessage>>, // While the current directory is read
+    pub listing_modified: Option<SystemTime>, // Of the current directory when its listing was read
+    pub listing_cache: ListingCache, // Listings of recently left directories
+    pub jobs: Jobs, // Searches, copies, compression and indexing running in the background
+    pub metadata_loader: MetadataLoader, // Reads size, dates and attributes of the entries shown
+    pub watcher: DirectoryWatcher, // Updates the listing when other programs change the current directory
+    pub external_changes_receiver: Option<mpsc::Receiver<ExternalChanges>>, // While changed entries are read
+    pub disk_space: Option<(u64, u64)>, // (free, total) bytes of the current volume
+    pub drives: Vec<Drive>, // Shown in the "Drives" menu, listed again whenever it is opened
+    pub devices: Vec<Device>, // Phones and cameras only reachable through WPD (Windows), also in the "Drives" menu
+    pub drives_receiver: Option<mpsc::Receiver<(Vec<Drive>, Vec<Device>)>>, // While the drives are listed
+    pub trash_available: bool, // Entries of the current volume can be moved to the trash
+    pub git_status: Option<GitStatus>, // If the current directory is inside a git repository
+    pub git_receiver: Option<mpsc::Receiver<Option<GitStatus>>>, // While git status is running
+    pub cloud_status: Option<CloudStatus>, // If the current directory is inside a cloud folder (OneDrive, Dropbox, ...)
+    pub cloud_receiver: Option<mpsc::Receiver<Option<CloudStatus>>>, // While the sync status is read
+    pub view_mode: ViewMode,
+    pub sort_order: SortOrder,
+    pub group_by: GroupBy,
+    pub folder_views: FolderViews, // View mode, sort order and grouping chosen per folder
+    pub collapsed_groups: HashSet<&'static str>, // Titles of the groups folded away
+    pub dragged_column: Option<SortColumn>, // Details header being dragged to a new position
+    pub grid_cell_size: f32, // Icon cell size of the grid view
+    pub thumbnails: ThumbnailCache, // Image thumbnails of the current directory (grid view)
+    pub folder_sizes: FolderSizes, // Running folder size calculations of the current directory
+    pub size_cache: Arc<SizeCache>, // Folder sizes of earlier runs, shared with the disk usage analyzer
+    pub show_preview: bool, // Right-hand preview pane, toggled with Alt+P by default
+    pub preview: Option<Preview>, // Preview of the single selected entry
+    pub image_viewer: Option<ImageViewer>, // Open while viewing an image of the current directory
+    pub text_viewer: Option<TextViewer>, // Open while viewing a source or config file
+    pub image_browser: Option<ImageBrowser>, // Open while browsing the contents of an ISO image
+    pub device_browser: Option<DeviceBrowser>, // Open while browsing a phone or camera (Windows)
+    pub disk_usage: Option<DiskUsage>, // Open while analyzing the disk usage of a folder
+    pub folder_comparison: Option<FolderComparison>, // Open while comparing two folders
+    pub folder_sync: Option<FolderSync>, // Open while synchronizing two folders
+    pub properties: Option<Properties>, // Open while showing the properties of an entry
+    pub checksum_verifier: Option<ChecksumVerifier>, // Open while verifying a checksum list
+    pub remote_browser: Option<RemoteBrowser>, // Open while browsing S3 or WebDAV storage
+    pub open_with_handlers: Option<(PathBuf, Vec<Handler>)>, // Programs listed in the "Open with" menu of a file
+    pub open_with_remember: bool, // "Always use for .ext files" in the "Open with" menu
+    pub program_chooser: Option<ProgramChooser>, // Open while choosing a program to open a file with
     pub recursive_search_results: Option<Vec<PathBuf>>,
-    pub rename_mode: Option<String>,
+    pub search_spill: Option<ResultSpill>, // Results beyond `MAX_RESULTS_IN_MEMORY`, read back page by page
+    pub search_line_hits: HashMap<PathBuf, Vec<LineHit>>, // Matching lines of a content search
+    pub search_result_metadata: HashMap<PathBuf, ResultMetadata>, // Sizes and dates of the results, for sorting
+    pub results_filter: String, // Narrows the displayed search results without searching again
+    pub filtered_results: Option<Vec<usize>>, // Indices of the results matching `results_filter`, None without one
+    pub results_sort: Option<ResultSortKey>, // None = order found (best match first for fuzzy searches)
+    pub results_sort_descending: bool,
+    pub results_display_limit: usize, // Number of results rendered, grows by `RESULTS_PAGE_SIZE` on "load more"
+    pub show_export_popup: bool,
+    pub export_path: String,
+    pub export_format: ExportFormat,
+    pub export_with_metadata: bool, // Add size and modified columns
+    pub export_receiver: Option<mpsc::Receiver<Result<PathBuf, String>>>, // While an export is written
+    pub export_status: Option<String>, // Outcome of the last export, shown in the popup
+    pub rename_mode: Option<OsString>,
     pub rename_input: String,
     pub show_search_popup: bool,
     pub search_query: String,
-    pub search_sender: Option<mpsc::Sender<Vec<PathBuf>>>,
-    pub search_receiver: Option<mpsc::Receiver<Vec<PathBuf>>>,
+    pub search_mode: SearchMode,
+    pub search_respect_ignore_files: bool,
+    pub search_exclusions: String, // Comma separated name globs skipped by the search
+    pub search_max_depth: Option<usize>, // None = unlimited
+    pub search_entry_kind: EntryKind,
+    pub search_content_type: Option<ContentType>, // Detected from magic bytes, not the extension
+    pub search_extra_roots: Vec<PathBuf>, // Searched in addition to the current folder
+    pub search_all_drives: bool,
+    pub search_root_picker: Option<FolderPicker>, // Open while adding a folder to search in
+    pub search_roots_in_use: Vec<PathBuf>, // Roots of the running / last search, to show where results come from
+    pub search_matcher: Option<Arc<SearchMatcher>>, // Matcher of the running / last search, highlights results
+    pub search_receiver: Option<mpsc::Receiver<SearchMessage>>,
+    pub search_stats: SearchStats, // Live counters of the running / last search
     pub is_searching: bool,
-    pub app_icon: Option<egui::ColorImage>, // For in-app display
+    pub file_index: Arc<FileIndex>, // Persistent name index, shared with search threads
+    pub use_index: bool, // Answer name searches from the index where it covers the folder
+    pub use_everything: bool, // Delegate plain name searches to Everything when it is running
+    pub show_index_window: bool,
+    pub index_roots: Vec<PathBuf>, // Folders the next index rebuild will crawl
+    pub index_receiver: Option<mpsc::Receiver<FileIndex>>, // While loading or rebuilding the index
+    pub next_index_refresh: Option<Instant>, // When the index is rebuilt automatically
+    pub app_icon_receiver: Option<mpsc::Receiver<egui::ColorImage>>, // Decoded in the background at startup
+    pub app_icon_texture: Option<egui::TextureHandle>, // Uploaded once it is decoded
+    pub startup_pending: bool, // Until the first frame is shown, see `finish_startup`
+    pub settings: Settings, // Persisted in the config directory
+    pub show_settings_window: bool,
+    pub link_receiver: Option<mpsc::Receiver<PathBuf>>, // Folders of filefox:// links opened while running
+    pub session: Session, // Window geometry of this run, saved with the folder and view on exit
+    pub recording_shortcut: Option<Action>, // Waiting for the new shortcut of this action in the settings
+    pub places: Places, // Bookmarked and recently visited folders, recently opened files
+    pub show_recent_files: bool,
+    pub tray: Option<Tray>, // While FileFox is in the system tray
+    pub selected_entries: HashSet<OsString>, // Entry names
+    pub scroll_to_entry: Option<OsString>, // Scrolled into view on the next frame it is rendered
+    pub show_zip_popup: bool,
+    pub zip_sources: Vec<OsString>,
+    pub zip_archive_name: String,
+    pub zip_compression_level: i32,
+    pub zip_receiver: Option<mpsc::Receiver<Result<PathBuf, String>>>,
+    pub clipboard_entries: Vec<PathBuf>, // Entries waiting for "Paste"
+    pub clipboard_mode: ClipboardMode,
+    pub verify_copies: bool, // Hash every copied file and compare it to the source
+    pub copy_receiver: Option<mpsc::Receiver<CopyReport>>,
+    pub copy_mismatches: Vec<PathBuf>, // Copies that failed verification
+    pub move_receiver: Option<mpsc::Receiver<MoveEvent>>, // Conflicts and the report of the running move or merge
+    pub delete_receivers: Vec<mpsc::Receiver<DeleteReport>>, // One per running deletion
+    pub pending_open: Vec<PathBuf>, // Files waiting for "open all" confirmation
+    pub pending_delete: Vec<OsString>, // Entries waiting for "delete permanently" confirmation
+    pub pending_trash: Vec<OsString>, // Entries waiting for "move to trash" confirmation
+    pub folder_picker: Option<FolderPicker>, // Open while choosing a "Move to..." / "Copy to..." target
+    pub folder_picker_entries: Vec<PathBuf>,
+    pub folder_picker_mode: ClipboardMode,
+    pub pending_merges: Vec<(PathBuf, PathBuf)>, // Moves onto existing folders or files, awaiting confirmation
+    pub merge_conflict: Option<(PathBuf, ConflictAnswer)>, // The merge waits for the answer
+    pub merge_apply_to_all: bool,
+    pub pending_replace: Option<(Vec<PathBuf>, ReplaceOperation)>, // Existing targets, awaiting "Replace"
+    pub elevation_offer: Option<ElevatedOperation>, // Failed with access denied, can be retried as admin
+    pub elevation_receiver: Option<mpsc::Receiver<Result<(), String>>>,
 }
 
 impl Default for MyExplorerApp {
     fn default() -> Self {
         let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from(""));
-        let mut app = Self {
+        let settings = Settings::load();
+        let size_cache = Arc::new(SizeCache::default()); // Filled in the background, see `finish_startup`
+        Self {
             current_dir,
             entries: Vec::new(),
             filtered_entries: None,
+            directory_filter: String::new(),
+            directory_filter_edited: None,
+            lowercase_names: HashMap::new(),
+            label_filter: None,
+            tag_filter: None,
+            tags: TagDatabase::load().unwrap_or_else(|e| {
+                eprintln!("Error while loading tags: {}", e);
+                TagDatabase::not_loaded()
+            }),
+            new_tag_input: String::new(),
+            entry_details: HashMap::new(),
+            disk_space: None,
+            drives: Vec::new(),
+            devices: Vec::new(),
+            drives_receiver: None,
+            trash_available: false,
+            git_status: None,
+            git_receiver: None,
+            cloud_status: None,
+            cloud_receiver: None,
+            listing_receiver: None,
+            listing_modified: None,
+            listing_cache: ListingCache::default(),
+            jobs: Jobs::default(),
+            metadata_loader: MetadataLoader::default(),
+            watcher: DirectoryWatcher::default(),
+            external_changes_receiver: None,
+            view_mode: ViewMode::Details,
+            sort_order: settings.sort_order,
+            group_by: GroupBy::None,
+            folder_views: FolderViews::load().unwrap_or_else(|e| {
+                eprintln!("Error while loading folder views: {}", e);
+                FolderViews::default()
+            }),
+            collapsed_groups: HashSet::new(),
+            dragged_column: None,
+            grid_cell_size: view::DEFAULT_GRID_CELL_SIZE,
+            thumbnails: ThumbnailCache::default(),
+            folder_sizes: FolderSizes::new(size_cache.clone()),
+            size_cache,
+            show_preview: false,
+            preview: None,
+            image_viewer: None,
+            text_viewer: None,
+            image_browser: None,
+            device_browser: None,
+            disk_usage: None,
+            folder_comparison: None,
+            folder_sync: None,
+            properties: None,
+            checksum_verifier: None,
+            remote_browser: None,
+            open_with_handlers: None,
+            open_with_remember: false,
+            program_chooser: None,
             recursive_search_results: None,
+            search_spill: None,
+            search_line_hits: HashMap::new(),
+            search_result_metadata: HashMap::new(),
+            results_filter: String::new(),
+            filtered_results: None,
+            results_sort: None,
+            results_sort_descending: false,
+            results_display_limit: RESULTS_PAGE_SIZE,
+            show_export_popup: false,
+            export_path: String::new(),
+            export_format: ExportFormat::Csv,
+            export_with_metadata: false,
+            export_receiver: None,
+            export_status: None,
             rename_mode: None,
             rename_input: String::new(),
             show_search_popup: false,
             search_query: String::new(),
-            search_sender: None,
+            search_mode: SearchMode::Substring,
+            search_respect_ignore_files: false,
+            search_exclusions: search::DEFAULT_EXCLUSIONS.to_string(),
+            search_max_depth: None,
+            search_entry_kind: EntryKind::All,
+            search_content_type: None,
+            search_extra_roots: Vec::new(),
+            search_all_drives: false,
+            search_root_picker: None,
+            search_roots_in_use: Vec::new(),
+            search_matcher: None,
             search_receiver: None,
+            search_stats: SearchStats::default(),
             is_searching: false,
-            app_icon: load_egui_image_from_bytes(include_bytes!("./icon.png")),
-        };
-
-        app.read_current_directory_entries();
-        app
+            file_index: Arc::new(FileIndex::default()),
+            use_index: true,
+            use_everything: false,
+            show_index_window: false,
+            show_recent_files: false,
+            index_roots: Vec::new(),
+            index_receiver: None,
+            next_index_refresh: None,
+            app_icon_receiver: None,
+            app_icon_texture: None,
+            startup_pending: true,
+            settings,
+            show_settings_window: false,
+            link_receiver: None,
+            session: Session::default(),
+            recording_shortcut: None,
+            places: Places::load().unwrap_or_else(|e| {
+                eprintln!("Error while loading bookmarks: {}", e);
+                Places::default()
+            }),
+            tray: None,
+            selected_entries: HashSet::new(),
+            scroll_to_entry: None,
+            show_zip_popup: false,
+            zip_sources: Vec::new(),
+            zip_archive_name: String::new(),
+            zip_compression_level: 6,
+            zip_receiver: None,
+            clipboard_entries: Vec::new(),
+            clipboard_mode: ClipboardMode::Cut,
+            verify_copies: false,
+            copy_receiver: None,
+            copy_mismatches: Vec::new(),
+            move_receiver: None,
+            delete_receivers: Vec::new(),
+            pending_open: Vec::new(),
+            pending_delete: Vec::new(),
+            pending_trash: Vec::new(),
+            folder_picker: None,
+            folder_picker_entries: Vec::new(),
+            folder_picker_mode: ClipboardMode::Cut,
+            pending_merges: Vec::new(),
+            merge_conflict: None,
+            merge_apply_to_all: false,
+            pending_replace: None,
+            elevation_offer: None,
+            elevation_receiver: None,
+        }
     }
 }
 
 // --- App Logic Methods ---
 
 impl MyExplorerApp {
+    /// Work left out of `default` so the window appears right away: the first frame shows
+    /// an empty skeleton, which fills in as the directory, icon and caches are read.
+    fn finish_startup(&mut self, ctx: &egui::Context) {
+        self.startup_pending = false;
+
+        let (sender, receiver) = mpsc::channel();
+        self.app_icon_receiver = Some(receiver);
+        let repaint_ctx = ctx.clone();
+        thread::spawn(move || {
+            if let Some(icon) = load_egui_image_from_bytes(include_bytes!("./icon.png"))
+                && sender.send(icon).is_ok()
+            {
+                repaint_ctx.request_repaint();
+            }
+        });
+
+        let size_cache = self.size_cache.clone();
+        thread::spawn(move || {
+            if let Err(e) = size_cache.load() {
+                eprintln!("Error while loading folder sizes: {}", e);
+            }
+        });
+
+        if self.settings.tray {
+            self.start_tray(ctx);
+        }
+        self.apply_folder_view();
+        self.read_current_directory_entries();
+        self.load_file_index();
+        ctx.request_repaint();
+    }
+
+    /// Adds the tray icon, which keeps FileFox running when its window is closed.
+    fn start_tray(&mut self, ctx: &egui::Context) {
+        self.tray = Tray::start(ctx);
+        if let Some(tray) = &self.tray {
+            tray.set_places(&self.places);
+        }
+    }
+
+    /// Saves the bookmarks, recent folders and files, and updates the tray menu listing them.
+    fn places_changed(&mut self) {
+        if let Err(e) = self.places.save() {
+            eprintln!("Error while saving bookmarks: {}", e);
+        }
+        if let Some(tray) = &self.tray {
+            tray.set_places(&self.places);
+        }
+    }
+
+    /// Saves the folder, window and view of this run for the next start.
+    fn save_session(&mut self) {
+        self.session.current_dir = Some(self.current_dir.clone());
+        self.session.view_mode = Some(self.view_mode);
+        if let Err(e) = self.session.save() {
+            eprintln!("Error while saving the session: {}", e);
+        }
+    }
+
     /// Reads the entries of the current directory and updates `self.entries`.
     /// Also resets all search results.
     fn read_current_directory_entries(&mut self) {
         self.entries.clear();
-        match std::fs::read_dir(&self.current_dir) {
-            Ok(entries) => {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        let name = entry.file_name().to_string_lossy().to_string();
-                        if entry.path().is_dir() {
-                            self.entries.push(format!("{}/", name)); // Mark folder with slash
-                        } else {
-                            self.entries.push(name);
-                        }
+        self.entry_details.clear();
+        self.folder_sizes.cancel(); // Sizes are recalculated for the new listing
+        self.metadata_loader.clear();
+        self.lowercase_names.clear();
+        self.listing_modified = None;
+        self.disk_space = None;
+        self.trash_available = false;
+        self.watcher.watch(&self.current_dir);
+        self.external_changes_receiver = None; // Changes read for the old listing
+
+        // Read on a background thread, large folders and network drives take a while
+        let (sender, receiver) = mpsc::channel();
+        let dir = self.current_dir.clone();
+        let cached_modified = self.listing_cache.modified(&dir);
+        thread::spawn(move || {
+            let modified = std::fs::metadata(fileops::extended_path(&dir)).and_then(|m| m.modified()).ok();
+            let disk_space = fileops::disk_space(&dir);
+            let trash_available = recycle::is_available(&dir);
+            if sender.send(ListingMessage::Info { modified, disk_space, trash_available }).is_err() {
+                return; // Another directory is listed by now
+            }
+            // Unchanged since the last visit: the cached listing is used
+            if modified.is_some() && modified == cached_modified {
+                return;
+            }
+            let entries = match std::fs::read_dir(fileops::extended_path(&dir)) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    let _ = sender.send(ListingMessage::Error(e));
+                    return;
+                }
+            };
+            let mut batch = Vec::new();
+            let mut last_sent = Instant::now();
+            for entry in entries.flatten() {
+                // Only names and types, metadata is read once the entry is shown
+                let name = entry.file_name();
+                let file_type = entry.file_type().ok();
+                let is_symlink = file_type.is_some_and(|t| t.is_symlink());
+                let is_dir = if is_symlink { entry.path().is_dir() } else { file_type.is_some_and(|t| t.is_dir()) };
+                let mut details = EntryDetails::basic(&entry.path(), is_dir, is_symlink);
+                details.hidden = fileops::is_hidden_entry(&entry);
+                batch.push((name, details));
+                if batch.len() >= LISTING_BATCH_SIZE || last_sent.elapsed() >= LISTING_BATCH_INTERVAL {
+                    if sender.send(ListingMessage::Batch(std::mem::take(&mut batch))).is_err() {
+                        return; // Another directory is listed by now
                     }
+                    last_sent = Instant::now();
                 }
-                self.entries.sort_unstable(); // Sort for better display
             }
-            Err(e) => {
-                eprintln!("Error while loading directory {:?}: {}", self.current_dir, e);
-                // Optionally: show error in UI
+            let _ = sender.send(ListingMessage::Batch(batch));
+        });
+        self.listing_receiver = Some(receiver); // Drops the receiver of a listing still being read
+        // Small folders are read by then, which avoids flashing an empty listing
+        self.poll_directory_listing(Duration::from_millis(30));
+        self.reset_for_new_listing();
+    }
+
+    /// Updates everything else that depends on the current directory after reading it.
+    fn reset_for_new_listing(&mut self) {
+        self.apply_directory_filter(); // Keep the typed filter applied to the new listing
+        self.load_git_status();
+        self.load_cloud_status();
+        self.clear_search_results(); // Reset recursive search results
+        self.cancel_search(); // Stop searching if directory changes
+        self.selected_entries.clear(); // Selection belongs to the previous listing
+    }
+
+    /// Adds the entries read since the last call, waiting up to `wait` for more.
+    /// Once the whole directory is read, loads the optional details and sorts the listing.
+    /// Returns whether the directory is still being read.
+    fn poll_directory_listing(&mut self, wait: Duration) -> bool {
+        let Some(receiver) = self.listing_receiver.take() else {
+            return false;
+        };
+        let deadline = Instant::now() + wait;
+        let mut received = false;
+        let finished = loop {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(ListingMessage::Info { modified, disk_space, trash_available }) => {
+                    self.listing_modified = modified;
+                    self.disk_space = disk_space;
+                    self.trash_available = trash_available;
+                    if let Some(modified) = modified
+                        && let Some((entries, details)) = self.listing_cache.take(&self.current_dir, modified)
+                    {
+                        self.entries = entries;
+                        self.entry_details = details;
+                        received = true;
+                    }
+                }
+                Ok(ListingMessage::Batch(batch)) => {
+                    let mut new_entries = Vec::with_capacity(batch.len());
+                    for (name, details) in batch {
+                        new_entries.push(name.clone());
+                        self.entry_details.insert(name, details);
+                    }
+                    self.sort_order.merge_entries(&mut self.entries, new_entries, &self.entry_details);
+                    received = true;
+                }
+                Ok(ListingMessage::Error(e)) => {
+                    eprintln!("Error while loading directory {:?}: {}", self.current_dir, e);
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => break false,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break true,
+            }
+        };
+
+        if finished {
+            self.listing_complete();
+        } else {
+            self.listing_receiver = Some(receiver);
+            if received {
+                self.apply_directory_filter();
             }
         }
-        self.filtered_entries = None; // Reset filtering for current directory
-        self.recursive_search_results = None; // Reset recursive search results
-        self.is_searching = false; // Stop searching if directory changes
-        self.search_sender = None; // Close channels
-        self.search_receiver = None; // Close channels
+        !finished
     }
 
-    /// Navigates into a subfolder.
-    fn navigate_to(&mut self, entry_name: &str) {
-        let mut new_path = self.current_dir.clone();
-        new_path.push(entry_name);
-        if new_path.is_dir() {
-            self.current_dir = new_path;
-            self.read_current_directory_entries(); // Reload entries and reset search
+    /// Runs once all entries of the current directory are listed: loads their remaining details and sorts them.
+    fn listing_complete(&mut self) {
+        // Fill in the metadata of entries not shown yet (or refresh it, for a cached listing)
+        let paths = self.entry_details.keys().map(|name| self.current_dir.join(name)).collect();
+        self.metadata_loader.prefetch(paths);
+        self.load_optional_details();
+        if self.settings.auto_folder_sizes {
+            self.calculate_all_folder_sizes();
         }
+        self.resort_entries();
     }
 
-    /// Navigates to the parent directory.
-    fn navigate_up(&mut self) {
-        if self.current_dir.parent().is_some() {
-            self.current_dir.pop();
-            self.read_current_directory_entries(); // Reload entries and reset search
+    /// Returns the entries an action on `entry_name` applies to:
+    /// the whole selection if the entry is part of it, otherwise just the entry itself.
+    fn action_targets(&self, entry_name: &OsStr) -> Vec<OsString> {
+        if self.selected_entries.contains(entry_name) {
+            let mut targets: Vec<OsString> = self.selected_entries.iter().cloned().collect();
+            targets.sort_unstable();
+            targets
+        } else {
+            vec![entry_name.to_owned()]
         }
     }
 
-    /// Renames an entry.
-    fn rename_entry(&mut self, old_name: &str, new_name: &str) {
-        let mut old_path = self.current_dir.clone();
-        old_path.push(old_name);
-        let mut new_path = self.current_dir.clone();
-        new_path.push(new_name);
-
-        if let Err(e) = std::fs::rename(&old_path, &new_path) {
-            eprintln!("Error while renaming {:?} to {:?}: {}", old_path, new_path, e);
-            // Optionally: show error in UI
-        } else {
-            self.read_current_directory_entries(); // Update entries after renaming and reset search
+    /// Opens `path` with the program chosen for its extension in "Open with",
+    /// or with its default application if there is none.
+    fn open_externally(&self, path: &Path) {
+        let command = open_with::extension_key(path).and_then(|extension| self.settings.open_with.get(&extension));
+        match command {
+            Some(command) => {
+                if let Err(e) = open_with::launch(command, path) {
+                    eprintln!("Error while opening {:?} with {}: {}", path, command, e);
+                }
+            }
+            None => open_with_default_app(path),
         }
     }
 
-    /// Deletes an entry (file or folder).
-    fn delete_entry(&mut self, entry_name: &str) {
-        let mut path_to_delete = self.current_dir.clone();
-        path_to_delete.push(entry_name);
+    /// Opens `path` like `open_externally` and adds it to the recent files.
+    fn open_file(&mut self, path: &Path) {
+        self.remember_opened_file(path);
+        self.open_externally(path);
+    }
+
+    /// Adds `path` to the recent files, listed in "Places" > "Recent files...".
+    fn remember_opened_file(&mut self, path: &Path) {
+        self.places.open_file(path);
+        self.places_changed();
+    }
 
-        let result = if path_to_delete.is_dir() {
-            std::fs::remove_dir_all(&path_to_delete)
-        } else {
-            std::fs::remove_file(&path_to_delete)
-        };
+    /// Opens `path` with the program `command`. With `remember`, the program
+    /// is also used from now on for all files with the same extension.
+    fn open_with_program(&mut self, path: &Path, command: String, remember: bool) {
+        if let Err(e) = open_with::launch(&command, path) {
+            eprintln!("Error while opening {:?} with {}: {}", path, command, e);
+            return;
+        }
+        self.remember_opened_file(path);
+        if remember && let Some(extension) = open_with::extension_key(path) {
+            self.settings.open_with.insert(extension, command);
+            if let Err(e) = self.settings.save() {
+                eprintln!("Error while saving settings: {}", e);
+            }
+        }
+    }
 
-        if let Err(e) = result {
-            eprintln!("Error while deleting {:?}: {}", path_to_delete, e);
-            // Optionally: show error in UI
+    /// Opens every file in `paths` with its default application. Asks for confirmation
+    /// first when there are more than the threshold in the settings.
+    fn open_files(&mut self, paths: Vec<PathBuf>) {
+        if self.settings.confirm_open_many && paths.len() > self.settings.open_confirm_threshold {
+            self.pending_open = paths;
         } else {
-            self.read_current_directory_entries(); // Update entries after deletion and reset search
+            for path in &paths {
+                self.open_file(path);
+            }
         }
     }
 
-    /// Recursively searches from `start_path` for entries containing `query_lower`.
-    /// Uses `rayon` for parallelization.
-    fn find_entries_recursively(
-        start_path: &PathBuf,
-        query_lower: &str,
-    ) -> Vec<PathBuf> {
-        WalkDir::new(start_path)
-            .into_iter()
-            .filter_map(|e| e.ok()) // Skip entries with errors
-            .par_bridge() // Parallelize iteration using rayon
-            .filter_map(|entry| {
-                let path = entry.path();
-                let file_name = path.file_name()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("");
-                // Check if current entry (file or folder name) contains the search term (case-insensitive)
-                if file_name.to_lowercase().contains(query_lower) {
-                    Some(path.to_owned())
-                } else {
-                    None
+    /// Contents of the "Open with" submenu of the file `path`: the programs registered
+    /// for its type, and a dialog to pick any other program.
+    fn show_open_with_menu(&mut self, ui: &mut egui::Ui, path: &Path) {
+        if self.open_with_handlers.as_ref().is_none_or(|(listed, _)| listed != path) {
+            self.open_with_handlers = Some((path.to_owned(), open_with::handlers_for(path)));
+        }
+        let extension = open_with::extension_key(path);
+        if let Some(extension) = &extension {
+            ui.checkbox(&mut self.open_with_remember, format!("Always use for .{} files", extension));
+            ui.separator();
+        }
+
+        let mut chosen = None;
+        if let Some((_, handlers)) = &self.open_with_handlers {
+            for handler in handlers {
+                if ui.button(&handler.name).clicked() {
+                    chosen = Some(handler.command.clone());
                 }
-            })
-            .collect() // Collect all results into a Vec
+            }
+        }
+        if ui.button("Choose another program...").clicked() {
+            self.program_chooser = Some(ProgramChooser::new(path, self.open_with_remember));
+            ui.close_menu();
+        }
+        if let Some(command) = chosen {
+            self.open_with_program(path, command, self.open_with_remember);
+            ui.close_menu();
+        }
+
+        // The program remembered for this extension replaces the default application
+        if let Some(extension) = extension
+            && let Some(command) = self.settings.open_with.get(&extension)
+        {
+            ui.separator();
+            let button = ui.button(format!("Use the default application for .{} files", extension));
+            if button.on_hover_text(format!("Instead of {}", command)).clicked() {
+                self.settings.open_with.remove(&extension);
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Error while saving settings: {}", e);
+                }
+                ui.close_menu();
+            }
+        }
     }
 
-    /// Executes the recursive search based on `self.search_query`
-    /// and saves the results in `self.recursive_search_results`.
-    /// This function starts a new thread for searching, with rayon parallelization inside.
-    fn execute_search(&mut self, ctx: egui::Context) {
-        let query_lower = self.search_query.to_lowercase();
-        if query_lower.is_empty() {
-            self.recursive_search_results = None;
-            self.is_searching = false; // Reset search status
+    /// Reads the entries other programs added, removed or changed (`paths`) in the background.
+    /// `merge_external_changes` applies them once they are read.
+    fn apply_external_changes(&mut self, paths: HashSet<PathBuf>, ctx: &egui::Context) {
+        let dir = self.current_dir.clone();
+        let names: Vec<OsString> = paths
+            .iter()
+            .filter(|path| path.parent() == Some(dir.as_path())) // Others are left over from the previous directory
+            .filter_map(|path| path.file_name().map(OsStr::to_owned))
+            .collect();
+        if names.is_empty() {
             return;
         }
-
-        // Create new channel for this search operation
+        let load_owner = self.settings.columns.contains(&SortColumn::Owner);
+        let load_date_taken = self.settings.columns.contains(&SortColumn::Taken);
         let (sender, receiver) = mpsc::channel();
-        self.search_sender = Some(sender.clone());
-        self.search_receiver = Some(receiver);
-        self.is_searching = true;
-        self.recursive_search_results = None; // Immediately clear old results
-
-        let current_dir_for_thread = self.current_dir.clone();
-        let search_query_for_thread = query_lower.clone(); // Clone for thread
-
-        // Start a new thread for the search
-        // Rayon handles parallelization *within* this thread
+        let ctx = ctx.clone();
         thread::spawn(move || {
-            let found_paths = Self::find_entries_recursively(&current_dir_for_thread, &search_query_for_thread);
-            if sender.send(found_paths).is_ok() {
-                ctx.request_repaint(); // Request repaint in main thread when results sent
+            let entries = names
+                .into_iter()
+                .map(|name| {
+                    let path = dir.join(&name);
+                    let details = std::fs::symlink_metadata(fileops::extended_path(&path)).is_ok().then(|| {
+                        let mut details = EntryDetails::read(&path);
+                        read_optional_details(&path, &mut details, load_owner, load_date_taken);
+                        details
+                    });
+                    (name, details)
+                })
+                .collect();
+            // The directory's modification time changed with these entries
+            let dir_modified = std::fs::metadata(&dir).and_then(|m| m.modified()).ok();
+            if sender.send(ExternalChanges { dir, entries, dir_modified }).is_ok() {
+                ctx.request_repaint();
             }
-            // Sender is automatically dropped when thread ends
         });
+        self.external_changes_receiver = Some(receiver);
     }
-}
-
-// Helper function to load PNG bytes into egui::ColorImage (for in-app display)
-fn load_egui_image_from_bytes(bytes: &'static [u8]) -> Option<egui::ColorImage> {
-    let image = image::load_from_memory_with_format(bytes, ImageFormat::Png).ok()?;
-    let size = [image.width() as _, image.height() as _];
-    let image_buffer = image.into_rgba8();
-    let pixels = image_buffer.as_flat_samples();
-    Some(egui::ColorImage::from_rgba_unmultiplied(size, pixels.as_slice()))
-}
-
-// --- Egui/Eframe Implementation ---
-
-impl eframe::App for MyExplorerApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Flags for delayed state changes
-        let mut should_navigate_to_path: Option<PathBuf> = None;
-        let mut should_clear_recursive_results_after_interaction = false;
-        let mut should_clear_rename_mode = false;
-        let mut should_close_search_popup = false;
 
-        // Check for search results from background thread
-        if let Some(receiver) = &self.search_receiver {
-            match receiver.try_recv() {
-                Ok(results) => {
-                    self.recursive_search_results = Some(results);
-                    self.is_searching = false; // Search finished
-                    self.search_sender = None; // Close channels
-                    self.search_receiver = None; // Close channels
-                }
-                Err(mpsc::TryRecvError::Empty) => {
-                    // No results yet, search still running
+    /// Updates the listing with the entries read by `apply_external_changes`.
+    /// Keeps the selection and search results, unlike reading the whole directory again.
+    fn merge_external_changes(&mut self, changes: ExternalChanges) {
+        if changes.dir != self.current_dir {
+            return;
+        }
+        let changed: HashSet<&OsString> = changes.entries.iter().map(|(name, _)| name).collect();
+        self.entries.retain(|entry| !changed.contains(entry));
+        let mut added = Vec::new();
+        for (name, details) in changes.entries {
+            self.thumbnails.remove(&self.current_dir.join(&name)); // The content may have changed
+            match details {
+                Some(details) => {
+                    self.entry_details.insert(name.clone(), details);
+                    added.push(name);
                 }
-                Err(mpsc::TryRecvError::Disconnected) => {
-                    // Sender dropped, search ended or failed
-                    self.is_searching = false;
-                    self.search_sender = None;
-                    self.search_receiver = None;
+                None => {
+                    self.entry_details.remove(&name);
+                    self.selected_entries.remove(&name);
                 }
             }
         }
+        self.sort_order.merge_entries(&mut self.entries, added, &self.entry_details);
+        // The listing is up to date again
+        self.listing_modified = changes.dir_modified;
+        self.apply_directory_filter();
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                if let Some(icon) = &self.app_icon {
-                    let texture_id = ctx.tex_manager().write().alloc(
-                        "app_logo".to_owned(),
-                        egui::ImageData::Color(icon.clone()),
-                        egui::TextureOptions::default(),
-                    );
-                    ui.image(texture_id, egui::vec2(24.0, 24.0)); // Adjust size
-                }
-                ui.heading("FileFox");
-            });
+    /// Looks up the owners and EXIF dates of the listed entries while their columns are shown.
+    /// Skipped otherwise, as this is slow for large folders.
+    fn load_optional_details(&mut self) {
+        let load_owner = self.settings.columns.contains(&SortColumn::Owner);
+        let load_date_taken = self.settings.columns.contains(&SortColumn::Taken);
+        if !load_owner && !load_date_taken {
+            return;
+        }
+        for (name, details) in &mut self.entry_details {
+            read_optional_details(&self.current_dir.join(name), details, load_owner, load_date_taken);
+        }
+    }
 
-            // --- Navigation bar ---
-            ui.horizontal(|ui| {
-                if ui.button("⬆️ Up").clicked() {
-                    self.navigate_up();
-                }
-                ui.label(format!("Current Path: {}", self.current_dir.display()));
-            });
+    /// Runs git status for the current directory in the background.
+    /// The old status stays visible until the new one arrives.
+    fn load_git_status(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        let dir = self.current_dir.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(git::read_status(&dir));
+        });
+        self.git_receiver = Some(receiver); // Drops the receiver of a status still running
+    }
 
-            ui.separator();
+    /// Reads the sync status of a cloud folder in the background, like `load_git_status`.
+    fn load_cloud_status(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        let dir = self.current_dir.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(cloud::read_status(&dir));
+        });
+        self.cloud_receiver = Some(receiver);
+    }
 
-            // Loading indicator when searching
-            if self.is_searching {
-                ui.horizontal(|ui| {
-                    ui.spinner();
-                    ui.label(format!("Searching for: '{}'...", self.search_query));
-                });
-                ui.separator();
+    /// Starts calculating the size of every listed folder. Unchanged folders are taken from the size cache.
+    fn calculate_all_folder_sizes(&mut self) {
+        for (name, details) in &self.entry_details {
+            if (details.is_dir() || details.is_package()) && details.size.is_none() {
+                self.folder_sizes.calculate(self.current_dir.join(name), true);
             }
+        }
+    }
 
-            // --- Display file entries / search results ---
-            let display_mode_is_recursive_search = self.recursive_search_results.is_some();
+    /// Saves the details view columns after they were shown, hidden or moved.
+    fn columns_changed(&mut self) {
+        // Sorting by a column that is no longer shown would be confusing
+        if self.sort_order.column != SortColumn::Name && !self.settings.columns.contains(&self.sort_order.column) {
+            self.sort_order.column = SortColumn::Name;
+            self.sort_order.descending = false;
+            self.settings.sort_order = self.sort_order;
+        }
+        if let Err(e) = self.settings.save() {
+            eprintln!("Error while saving settings: {}", e);
+        }
+        self.load_optional_details();
+        self.resort_entries();
+    }
 
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                if display_mode_is_recursive_search {
-                    // Show recursive search results
-                    let results_cloned = self.recursive_search_results.clone().unwrap_or_default();
+    /// Applies the sort order the user picked, and keeps it for the next start.
+    fn sort_order_changed(&mut self) {
+        self.settings.sort_order = self.sort_order;
+        if let Err(e) = self.settings.save() {
+            eprintln!("Error while saving settings: {}", e);
+        }
+        self.folder_view_changed();
+        self.resort_entries();
+    }
 
-                    if results_cloned.is_empty() {
-                        ui.label(format!("No results found for: '{}'", self.search_query));
-                    } else {
-                        ui.heading(format!("Results for: '{}'", self.search_query));
-                        ui.add_space(10.0);
+    /// Switches to the view chosen for the current directory, if it has one.
+    fn apply_folder_view(&mut self) {
+        if !self.settings.remember_folder_views {
+            return;
+        }
+        if let Some(view) = self.folder_views.get(&self.current_dir) {
+            self.view_mode = view.view_mode;
+            self.sort_order = view.sort_order;
+            self.group_by = view.group_by;
+        }
+    }
 
-                        for path in &results_cloned {
-                            let path_str = path.display().to_string();
-                            let response = ui.button(&path_str);
+    /// Remembers the view of the current directory after the view mode, sort order or grouping changed.
+    fn folder_view_changed(&mut self) {
+        if !self.settings.remember_folder_views {
+            return;
+        }
+        let view = FolderView { view_mode: self.view_mode, sort_order: self.sort_order, group_by: self.group_by };
+        self.folder_views.set(&self.current_dir, view);
+        if let Err(e) = self.folder_views.save() {
+            eprintln!("Error while saving folder views: {}", e);
+        }
+    }
 
-                            // Double click: navigate or open
-                            if response.double_clicked() {
-                                if path.is_dir() {
-                                    should_navigate_to_path = Some(path.clone());
-                                    should_clear_recursive_results_after_interaction = true;
-                                } else {
-                                    let _ = Command::new("cmd")
-                                        .args(&["/C", "start", "", &path_str])
-                                        .spawn();
-                                }
-                            }
-                            // Right-click context menu for search results
-                            response.context_menu(|ui| {
-                                if ui.button("Open").clicked() {
-                                    if path.is_dir() {
-                                        should_navigate_to_path = Some(path.clone());
-                                        should_clear_recursive_results_after_interaction = true;
-                                    } else {
-                                        let _ = Command::new("cmd")
-                                            .args(&["/C", "start", "", &path_str])
-                                            .spawn();
-                                    }
-                                    ui.close_menu();
-                                }
-                                if ui.button("Show in explorer").clicked() {
-                                    let _ = Command::new("explorer")
-                                        .args(&["/select,", &path_str])
-                                        .spawn();
-                                    ui.close_menu();
-                                }
-                            });
-                        }
+    /// Opens a file of the current directory: images and source / config files
+    /// in the built-in viewers, everything else with its default application.
+    fn open_entry_file(&mut self, entry_name: &OsStr, ctx: &egui::Context) {
+        let path = self.current_dir.join(entry_name);
+        self.remember_opened_file(&path);
+        if text_viewer::can_view(&path) {
+            self.text_viewer = Some(TextViewer::open(&path, ctx));
+            return;
+        }
+        if disk_image::is_disk_image(&path) {
+            match ImageBrowser::open(&path) {
+                Ok(browser) => self.image_browser = Some(browser),
+                Err(e) => eprintln!("Error while opening the image {:?}: {}", path, e),
+            }
+            return;
+        }
+        if !thumbnails::is_image(&path) {
+            self.open_externally(&path);
+            return;
+        }
+        // Step through the images in the order they are listed
+        let images = self
+            .filtered_entries
+            .as_ref()
+            .unwrap_or(&self.entries)
+            .iter()
+            .filter(|entry| !self.entry_details.get(*entry).is_some_and(EntryDetails::is_dir))
+            .map(|entry| self.current_dir.join(entry))
+            .filter(|path| thumbnails::is_image(path))
+            .collect();
+        self.image_viewer = Some(ImageViewer::open(&path, images, ctx));
+    }
+
+    /// Sorts the listing again after the sort order changed.
+    fn resort_entries(&mut self) {
+        self.sort_order.sort_entries(&mut self.entries, &self.entry_details);
+        self.apply_directory_filter();
+    }
+
+    /// Recomputes `filtered_entries` from `directory_filter` (case-insensitive substring),
+    /// the label / tag filters and whether hidden entries are shown.
+    fn apply_directory_filter(&mut self) {
+        self.directory_filter_edited = None;
+        if self.directory_filter.is_empty()
+            && self.label_filter.is_none()
+            && self.tag_filter.is_none()
+            && self.settings.show_hidden
+        {
+            self.filtered_entries = None;
+            return;
+        }
+        let filter_lower = self.directory_filter.to_lowercase();
+        for entry in &self.entries {
+            if !self.lowercase_names.contains_key(entry) {
+                self.lowercase_names.insert(entry.clone(), entry.to_string_lossy().to_lowercase());
+            }
+        }
+        self.filtered_entries = Some(
+            self.entries
+                .iter()
+                .filter(|entry| self.lowercase_names.get(*entry).is_some_and(|lower| lower.contains(&filter_lower)))
+                .filter(|entry| self.settings.show_hidden || !self.entry_details.get(*entry).is_some_and(|d| d.hidden))
+                .filter(|entry| {
+                    if self.label_filter.is_none() && self.tag_filter.is_none() {
+                        return true;
                     }
-                } else {
-                    // Normal view of entries in current directory
-                    let entries_to_display_cloned: Vec<String> = if let Some(filtered) = &self.filtered_entries {
-                        filtered.clone()
-                    } else {
-                        self.entries.clone()
+                    let Some(tags) = self.tags.get(&self.current_dir.join(entry)) else {
+                        return false;
                     };
+                    self.label_filter.is_none_or(|label| tags.label == Some(label))
+                        && self.tag_filter.as_ref().is_none_or(|tag| tags.tags.contains(tag))
+                })
+                .cloned()
+                .collect(),
+        );
+    }
 
-                    for entry in &entries_to_display_cloned {
-                        let is_dir = entry.ends_with('/');
-                        let entry_name = if is_dir {
-                            entry.trim_end_matches('/').to_string()
-                        } else {
-                            entry.clone()
-                        };
+    /// Called after typing in the filter box. Large folders are filtered once typing pauses.
+    fn directory_filter_changed(&mut self) {
+        if self.entries.len() > FILTER_DEBOUNCE_THRESHOLD {
+            self.directory_filter_edited = Some(Instant::now());
+        } else {
+            self.apply_directory_filter();
+        }
+    }
 
-                        // --- Rename mode ---
-                        if self.rename_mode.as_deref() == Some(&entry_name) {
-                            ui.horizontal(|ui| {
-                                let text_edit = ui.text_edit_singleline(&mut self.rename_input);
-                                if text_edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                                    if !self.rename_input.is_empty() {
-                                        let new_name = self.rename_input.clone();
-                                        self.rename_entry(&entry_name, &new_name);
-                                    }
-                                    should_clear_rename_mode = true; // Delayed reset
-                                }
-                                if ui.button("Cancel").clicked() {
-                                    should_clear_rename_mode = true; // Delayed reset
-                                }
-                            });
-                        }
-                        // --- Normal entry ---
-                        else {
-                            let response = ui.button(entry);
+    /// Writes the labels and tags to disk after they changed.
+    fn save_tags(&self) {
+        if let Err(e) = self.tags.save() {
+            eprintln!("Error while saving tags: {}", e);
+        }
+    }
 
-                            // Double click: navigate folder, open file
-                            if response.double_clicked() {
-                                if is_dir {
-                                    self.navigate_to(&entry_name);
-                                } else {
-                                    let mut path = self.current_dir.clone();
-                                    path.push(&entry_name);
-                                    let _ = Command::new("cmd")
-                                        .args(&["/C", "start", "", &path.to_string_lossy()])
-                                        .spawn();
-                                }
-                            }
+    /// Switches to another directory. The filters set for the old one are dropped.
+    fn change_directory(&mut self, path: PathBuf) {
+        // Keep the complete listing of the directory left, for going back to it
+        if self.listing_receiver.is_none()
+            && let Some(modified) = self.listing_modified
+        {
+            let (entries, details) = (std::mem::take(&mut self.entries), std::mem::take(&mut self.entry_details));
+            self.listing_cache.store(self.current_dir.clone(), entries, details, modified);
+        }
+        self.current_dir = path;
+        self.apply_folder_view();
+        self.places.visit(&self.current_dir);
+        self.places_changed();
+        self.directory_filter.clear();
+        self.label_filter = None;
+        self.tag_filter = None;
+        self.thumbnails.clear(); // Free the textures of the old directory
+        self.read_current_directory_entries(); // Reload entries and reset search
+    }
 
-                            // Right-click context menu
-                            response.context_menu(|ui| {
-                                if ui.button("Open").clicked() {
-                                    if is_dir {
-                                        self.navigate_to(&entry_name);
-                                    } else {
-                                        let mut path = self.current_dir.clone();
-                                        path.push(&entry_name);
-                                        let _ = Command::new("cmd")
-                                            .args(&["/C", "start", "", &path.to_string_lossy()])
-                                            .spawn();
-                                    }
-                                    ui.close_menu();
-                                }
+    /// Navigates to the parent directory.
+    fn navigate_up(&mut self) {
+        if let Some(parent) = self.current_dir.parent() {
+            self.change_directory(parent.to_owned());
+        }
+    }
+
+    /// Runs an action whose keyboard shortcut was pressed. Actions that belong to a part of
+    /// the window (zoom, filter, preview) are handled there instead.
+    fn run_action(&mut self, action: Action, ctx: &egui::Context) {
+        let mut selection: Vec<OsString> = self.selected_entries.iter().cloned().collect();
+        selection.sort_unstable();
+        match action {
+            Action::NavigateUp => self.navigate_up(),
+            Action::Refresh => self.read_current_directory_entries(),
+            Action::NewFolder => self.create_new_folder(),
+            Action::Rename => {
+                if let [entry_name] = selection.as_slice() {
+                    self.rename_input = entry_name.to_string_lossy().to_string();
+                    self.rename_mode = Some(entry_name.clone());
+                }
+            }
+            Action::MoveToTrash => {
+                if self.trash_available && !selection.is_empty() {
+                    self.trash_entries(selection);
+                }
+            }
+            Action::DeletePermanently => {
+                if !selection.is_empty() {
+                    self.delete_entries(selection, ctx);
+                }
+            }
+            Action::SelectAll => {
+                let visible = self.filtered_entries.as_ref().unwrap_or(&self.entries);
+                self.selected_entries = visible.iter().cloned().collect();
+            }
+            Action::Copy | Action::Cut => {
+                if !selection.is_empty() {
+                    self.clipboard_entries = selection.iter().map(|name| self.current_dir.join(name)).collect();
+                    self.clipboard_mode = if action == Action::Cut { ClipboardMode::Cut } else { ClipboardMode::Copy };
+                }
+            }
+            Action::Paste => {
+                if !self.clipboard_entries.is_empty() && self.can_paste() {
+                    self.paste_clipboard(self.current_dir.clone(), ctx.clone());
+                }
+            }
+            Action::Search => {
+                self.show_search_popup = true;
+                self.search_query.clear();
+                self.clear_search_results();
+            }
+            Action::ToggleHidden => {
+                self.settings.show_hidden = !self.settings.show_hidden;
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Error while saving settings: {}", e);
+                }
+                self.apply_directory_filter();
+            }
+            Action::FocusFilter | Action::TogglePreview | Action::ZoomIn | Action::ZoomOut | Action::ResetZoom => {}
+        }
+    }
+
+    /// Creates a folder called "New folder" (numbered if that exists) and starts renaming it.
+    fn create_new_folder(&mut self) {
+        let mut name = "New folder".to_string();
+        let mut number = 2;
+        while self.current_dir.join(&name).exists() {
+            name = format!("New folder ({})", number);
+            number += 1;
+        }
+        let path = self.current_dir.join(&name);
+        if let Err(e) = std::fs::create_dir(fileops::extended_path(&path)) {
+            eprintln!("Error while creating folder {:?}: {}", path, e);
+            return;
+        }
+        self.read_current_directory_entries();
+        let name = OsString::from(name);
+        self.selected_entries.insert(name.clone());
+        self.scroll_to_entry = Some(name.clone());
+        self.rename_input = name.to_string_lossy().to_string();
+        self.rename_mode = Some(name);
+    }
+
+    /// Renames an entry, after asking if another entry has the new name already.
+    fn rename_entry(&mut self, old_name: &OsStr, new_name: &str) {
+        let mut old_path = self.current_dir.clone();
+        old_path.push(old_name);
+        let mut new_path = self.current_dir.clone();
+        new_path.push(new_name);
+
+        // Changing only the case finds the entry itself on case-insensitive file systems
+        let is_case_change = old_name.to_string_lossy().to_lowercase() == new_name.to_lowercase();
+        if self.settings.confirm_overwrite && !is_case_change && std::fs::symlink_metadata(&new_path).is_ok() {
+            let operation = ReplaceOperation::Rename { from: old_path, to: new_path.clone() };
+            self.pending_replace = Some((vec![new_path], operation));
+            return;
+        }
+        self.rename_path(old_path, new_path);
+    }
+
+    /// Renames `old_path` to `new_path`, replacing what has that name.
+    fn rename_path(&mut self, old_path: PathBuf, new_path: PathBuf) {
+        if let Err(e) = fileops::rename_path(&old_path, &new_path) {
+            eprintln!("Error while renaming {:?} to {:?}: {}", old_path, new_path, e);
+            if elevate::is_access_denied(&e) {
+                self.elevation_offer = Some(ElevatedOperation::Rename { from: old_path, to: new_path });
+            }
+        } else {
+            self.tags.rename(&old_path, &new_path);
+            self.save_tags();
+            self.read_current_directory_entries(); // Update entries after renaming and reset search
+        }
+    }
+
+    /// Moves entries to the trash, after asking if the settings say so.
+    fn trash_entries(&mut self, entry_names: Vec<OsString>) {
+        if self.settings.confirm_trash {
+            self.pending_trash = entry_names;
+        } else {
+            self.perform_trash(&entry_names);
+        }
+    }
+
+    /// Moves `entry_names` of the current directory to the trash of their volume.
+    fn perform_trash(&mut self, entry_names: &[OsString]) {
+        for entry_name in entry_names {
+            let path = self.current_dir.join(entry_name);
+            match recycle::move_to_trash(&path) {
+                Ok(()) => self.tags.remove(&path),
+                Err(e) => eprintln!("Error while moving {:?} to the trash: {}", path, e),
+            }
+        }
+        self.save_tags();
+        self.read_current_directory_entries();
+    }
+
+    /// Deletes entries permanently, after asking if the settings say so.
+    fn delete_entries(&mut self, entry_names: Vec<OsString>, ctx: &egui::Context) {
+        if self.settings.confirm_delete {
+            self.pending_delete = entry_names;
+        } else {
+            self.perform_delete(&entry_names, ctx);
+        }
+    }
+
+    /// Deletes entries (files or folders) of the current directory as a background job.
+    /// Its report arrives through `self.delete_receivers`.
+    fn perform_delete(&mut self, entry_names: &[OsString], ctx: &egui::Context) {
+        let paths: Vec<PathBuf> = entry_names.iter().map(|name| self.current_dir.join(name)).collect();
+        let (sender, receiver) = mpsc::channel();
+        self.delete_receivers.push(receiver);
+        self.jobs.spawn(JobKind::Delete, ctx, move |job| {
+            let _ = sender.send(fileops::delete_entries(paths, job));
+        });
+    }
+
+    /// Pastes the clipboard entries into `dest_dir`, moving or copying them
+    /// depending on `self.clipboard_mode`.
+    fn paste_clipboard(&mut self, dest_dir: PathBuf, ctx: egui::Context) {
+        match self.clipboard_mode {
+            ClipboardMode::Cut => {
+                let sources = std::mem::take(&mut self.clipboard_entries);
+                self.move_entries(sources, dest_dir, &ctx);
+            }
+            ClipboardMode::Copy => self.start_copy(self.clipboard_entries.clone(), dest_dir, ctx),
+        }
+    }
+
+    /// Moves `sources` into `dest_dir` as a background job. A folder or file that collides with
+    /// an existing one of the same name is queued for a merge or replace confirmation.
+    fn move_entries(&mut self, sources: Vec<PathBuf>, dest_dir: PathBuf, ctx: &egui::Context) {
+        let mut pairs = Vec::new();
+        for source in sources {
+            let Some(name) = source.file_name() else {
+                continue;
+            };
+            let destination = dest_dir.join(name);
+            if destination == source {
+                continue; // Pasted into the folder it came from
+            }
+            if destination.starts_with(&source) {
+                eprintln!("Error while moving {:?}: cannot move a folder into itself", source);
+                continue;
+            }
+
+            if !destination.exists() {
+                pairs.push((source, destination));
+            } else if (source.is_dir() && destination.is_dir()) || (source.is_file() && destination.is_file()) {
+                self.pending_merges.push((source, destination)); // Asked once this move is done
+            } else {
+                eprintln!("Error while moving {:?}: {:?} already exists", source, destination);
+            }
+        }
+        if !pairs.is_empty() {
+            self.spawn_move(ctx, move |_, job| fileops::move_entries(pairs, job));
+        }
+    }
+
+    /// Runs a move or merge as a background job. Its conflicts and its report arrive through
+    /// `self.move_receiver`, only one runs at a time.
+    fn spawn_move(
+        &mut self,
+        ctx: &egui::Context,
+        work: impl FnOnce(&mpsc::Sender<MoveEvent>, &JobContext) -> MoveReport + Send + 'static,
+    ) {
+        let (sender, receiver) = mpsc::channel();
+        self.move_receiver = Some(receiver);
+        self.jobs.spawn(JobKind::Move, ctx, move |job| {
+            let report = work(&sender, job);
+            let _ = sender.send(MoveEvent::Finished(report));
+        });
+    }
+
+    /// Whether the clipboard can be pasted now: only one copy and one move run at a time.
+    fn can_paste(&self) -> bool {
+        match self.clipboard_mode {
+            ClipboardMode::Cut => self.move_receiver.is_none(),
+            ClipboardMode::Copy => self.copy_receiver.is_none(),
+        }
+    }
+
+    /// Copies `sources` into `dest_dir` on a background thread.
+    /// Name collisions get a " - Copy" suffix instead of overwriting anything.
+    fn start_copy(&mut self, sources: Vec<PathBuf>, dest_dir: PathBuf, ctx: egui::Context) {
+        let mut pairs = Vec::new();
+        for source in &sources {
+            let Some(name) = source.file_name() else {
+                continue;
+            };
+            if dest_dir.starts_with(source) {
+                eprintln!("Error while copying {:?}: cannot copy a folder into itself", source);
+                continue;
+            }
+            pairs.push((source.clone(), fileops::unique_destination(&dest_dir.join(name))));
+        }
+        self.copy_pairs(pairs, &ctx);
+    }
+
+    /// Copies every (source, destination) pair as a background job.
+    fn copy_pairs(&mut self, pairs: Vec<(PathBuf, PathBuf)>, ctx: &egui::Context) {
+        if pairs.is_empty() {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.copy_receiver = Some(receiver);
+        let verify = self.verify_copies;
+
+        self.jobs.spawn(JobKind::Copy, ctx, move |job| {
+            let _ = sender.send(fileops::copy_entries(pairs, verify, job));
+        });
+    }
+
+    /// Re-runs a failed operation in an elevated helper process (UAC prompt).
+    /// The helper is awaited on a background thread; the result arrives via `self.elevation_receiver`.
+    fn retry_elevated(&mut self, operation: ElevatedOperation, ctx: egui::Context) {
+        let (sender, receiver) = mpsc::channel();
+        self.elevation_receiver = Some(receiver);
+
+        thread::spawn(move || {
+            let result = elevate::run_elevated(&operation)
+                .map_err(|e| format!("{}: {}", operation.description(), e));
+            if sender.send(result).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Checks a single path's name against `matcher` (not used for content search).
+    fn match_entry_name(
+        start_path: &Path,
+        path: &Path,
+        matcher: &SearchMatcher,
+    ) -> Option<(PathBuf, Vec<LineHit>, i64)> {
+        let file_name = path.file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("");
+        let relative_path = path.strip_prefix(start_path).unwrap_or(path);
+        // Check if current entry (file or folder name) matches the search term (case-insensitive)
+        matcher
+            .score(file_name, relative_path)
+            .map(|score| (path.to_owned(), Vec::new(), score))
+    }
+
+    /// Searches a list of known paths below `start_path` (from the persistent index or
+    /// from Everything) instead of the disk. Only valid for name searches. Such lists
+    /// don't say which paths are folders, so every path counts as an examined file.
+    fn find_entries_in_paths(
+        paths: impl Iterator<Item = PathBuf> + Send,
+        start_path: &Path,
+        matcher: &SearchMatcher,
+        options: &SearchOptions,
+        progress: &SearchProgress,
+        results: &ResultBatcher,
+        cancel: &AtomicBool,
+    ) {
+        paths
+            .take_while(|_| !cancel.load(Ordering::Relaxed))
+            .par_bridge()
+            .filter(|path| {
+                let relative = path.strip_prefix(start_path).unwrap_or(path);
+                options.is_within_depth(relative) && !options.is_excluded_path(relative)
+            })
+            .filter_map(|path| {
+                progress.entry_examined(false);
+                let found = Self::match_entry_name(start_path, &path, matcher)?;
+                // The index doesn't know entry kinds, and matches may have been deleted since it was built.
+                // Links count as files, like in the walk.
+                let is_dir = std::fs::symlink_metadata(fileops::extended_path(&found.0)).ok()?.is_dir();
+                (options.entry_kind.accepts(is_dir) && options.matches_content_type(&found.0, is_dir)).then_some(found)
+            })
+            .for_each(|found| results.add(found));
+    }
+
+    /// Walks the tree below `start_path`, yielding (path, file type) for every entry
+    /// that is not excluded by `options` (exclusion list and, optionally, ignore files).
+    /// Links are not followed, their type is that of the link itself.
+    fn walk_entries<'a>(
+        start_path: &'a Path,
+        options: &'a SearchOptions,
+    ) -> Box<dyn Iterator<Item = (PathBuf, std::fs::FileType)> + Send + 'a> {
+        if options.respect_ignore_files {
+            let exclusions = options.exclusions.clone();
+            let walker = ignore::WalkBuilder::new(start_path)
+                .hidden(false) // Hidden files are only skipped if an ignore file says so
+                .require_git(false) // Honor .gitignore outside of git repositories, too
+                .max_depth(options.max_depth)
+                .filter_entry(move |entry| entry.depth() == 0 || !exclusions.is_match(entry.file_name()))
+                .build();
+            Box::new(walker.filter_map(|e| e.ok()).filter_map(|entry| {
+                let file_type = entry.file_type()?; // Only missing for standard input
+                Some((entry.into_path(), file_type))
+            }))
+        } else {
+            let walker = WalkDir::new(start_path)
+                .max_depth(options.max_depth.unwrap_or(usize::MAX))
+                .into_iter()
+                .filter_entry(|entry| entry.depth() == 0 || !options.is_excluded(entry.file_name()));
+            Box::new(walker.filter_map(|e| e.ok()).map(|entry| {
+                let file_type = entry.file_type();
+                (entry.into_path(), file_type)
+            }))
+        }
+    }
+
+    /// Recursively searches from `start_path` for entries whose name (or content) matches `matcher`.
+    /// Uses `rayon` for parallelization. Stops early once `cancel` is set.
+    fn find_entries_recursively(
+        start_path: &Path,
+        matcher: &SearchMatcher,
+        options: &SearchOptions,
+        progress: &SearchProgress,
+        results: &ResultBatcher,
+        cancel: &AtomicBool,
+    ) {
+        // Walked in extended-length form, as folders deep down may have long paths
+        let start_path = &*fileops::extended_root(start_path);
+        Self::walk_entries(start_path, options)
+            .take_while(|_| !cancel.load(Ordering::Relaxed)) // Stop walking the disk when cancelled
+            .par_bridge() // Parallelize iteration using rayon
+            .filter_map(|(path, file_type)| {
+                if cancel.load(Ordering::Relaxed) {
+                    return None; // Drain remaining work quickly when cancelled
+                }
+                // Links aren't followed, so they count as files even if they point to a folder
+                let is_dir = file_type.is_dir();
+                progress.entry_examined(is_dir);
+                if !options.entry_kind.accepts(is_dir) {
+                    return None;
+                }
+                // Content search looks inside files instead of at their names
+                if let SearchMatcher::Content(query_lower) = matcher {
+                    if !file_type.is_file() {
+                        return None;
+                    }
+                    let hits = search::search_file_contents(&path, query_lower);
+                    (!hits.is_empty()).then_some((path, hits, 0))
+                } else {
+                    Self::match_entry_name(start_path, &path, matcher)
+                }
+                .filter(|(path, _, _)| options.matches_content_type(path, is_dir))
+            })
+            // Sent to the UI batch by batch, as plain paths
+            .for_each(|(path, hits, score)| results.add((fileops::plain_path(path), hits, score)));
+    }
+
+    /// Executes the recursive search based on `self.search_query`
+    /// and saves the results in `self.recursive_search_results`.
+    /// Every search root gets its own thread (with rayon parallelization inside),
+    /// results are streamed back root by root.
+    fn execute_search(&mut self, ctx: egui::Context) {
+        if self.search_query.is_empty() {
+            self.clear_search_results();
+            self.is_searching = false; // Reset search status
+            return;
+        }
+        let matcher = match SearchMatcher::new(&self.search_query, self.search_mode) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                eprintln!("Invalid search pattern {:?}: {}", self.search_query, e);
+                return;
+            }
+        };
+        let mut options = match SearchOptions::new(self.search_respect_ignore_files, &self.search_exclusions) {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("Invalid exclusion pattern {:?}: {}", self.search_exclusions, e);
+                return;
+            }
+        };
+        options.max_depth = self.search_max_depth;
+        if self.search_mode != SearchMode::Content {
+            options.entry_kind = self.search_entry_kind; // Content search only ever finds files
+        }
+        options.content_type = self.search_content_type;
+
+        self.cancel_search(); // Stop a previous search that may still be running
+
+        // Create new channel for this search operation
+        let (sender, receiver) = mpsc::channel();
+        self.search_receiver = Some(receiver);
+        self.is_searching = true;
+        self.clear_search_results(); // Immediately clear old results
+        self.search_line_hits.clear();
+        self.search_result_metadata.clear();
+        self.results_filter.clear();
+        self.results_display_limit = RESULTS_PAGE_SIZE;
+        self.search_roots_in_use = self.search_roots();
+        self.search_stats = SearchStats::default();
+
+        // Shared by all roots, holds the only sender: the channel closes once every root is done
+        let progress = Arc::new(SearchProgress::new(sender, ctx.clone()));
+        let matcher = Arc::new(matcher);
+        self.search_matcher = Some(matcher.clone());
+        let options = Arc::new(options);
+        // Plain name searches can be delegated to Everything (which knows nothing about ignore files)
+        let everything_query = (self.use_everything
+            && matches!(*matcher, SearchMatcher::Substring(_))
+            && !options.respect_ignore_files)
+            .then(|| self.search_query.clone());
+        for root in self.search_roots_in_use.clone() {
+            // Name searches inside indexed folders are answered from the index
+            // (which knows nothing about ignore files either)
+            let index_for_thread = (self.use_index
+                && !matches!(*matcher, SearchMatcher::Content(_))
+                && !options.respect_ignore_files
+                && self.file_index.covers(&root))
+            .then(|| self.file_index.clone());
+            let (progress, matcher, options, everything_query) =
+                (progress.clone(), matcher.clone(), options.clone(), everything_query.clone());
+
+            // One job per root, rayon handles parallelization *within* each job
+            self.jobs.spawn(JobKind::Search, &ctx, move |job| {
+                let cancel = job.cancel_flag();
+                // Everything first, then the index, then the disk
+                let everything_paths = everything_query.and_then(|query| everything::search(&query, &root));
+                let results = ResultBatcher::new(&progress, &matcher);
+                match (everything_paths, &index_for_thread) {
+                    (Some(paths), _) => Self::find_entries_in_paths(
+                        paths.into_iter(), &root, &matcher, &options, &progress, &results, cancel,
+                    ),
+                    (None, Some(index)) => Self::find_entries_in_paths(
+                        index.paths_under(&root), &root, &matcher, &options, &progress, &results, cancel,
+                    ),
+                    (None, None) => {
+                        Self::find_entries_recursively(&root, &matcher, &options, &progress, &results, cancel)
+                    }
+                }
+                if job.is_cancelled() {
+                    return; // The search was cancelled and its channel closed, nothing to send
+                }
+                results.finish();
+                // Sender is automatically dropped with the last job, which ends the search
+            });
+        }
+    }
+
+    /// Folders the next search starts in: every drive, or the current folder plus the extra roots.
+    fn search_roots(&self) -> Vec<PathBuf> {
+        if self.search_all_drives {
+            return folder_picker::filesystem_roots();
+        }
+        let mut roots = vec![self.current_dir.clone()];
+        for root in &self.search_extra_roots {
+            if !roots.contains(root) {
+                roots.push(root.clone());
+            }
+        }
+        roots
+    }
+
+    /// The search root `path` was found in (the innermost one if roots are nested).
+    fn search_origin(&self, path: &Path) -> Option<&Path> {
+        self.search_roots_in_use
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .map(PathBuf::as_path)
+    }
+
+    /// Loads the persistent index from disk on a background thread.
+    fn load_file_index(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        self.index_receiver = Some(receiver);
+        thread::spawn(move || {
+            let index = FileIndex::load().unwrap_or_else(|e| {
+                eprintln!("Error while loading file index: {}", e);
+                FileIndex::default()
+            });
+            let _ = sender.send(index);
+        });
+    }
+
+    /// Crawls `self.index_roots` as a background job and saves the new index to disk.
+    fn rebuild_file_index(&mut self, ctx: egui::Context) {
+        let (sender, receiver) = mpsc::channel();
+        self.index_receiver = Some(receiver);
+        let roots = self.index_roots.clone();
+
+        self.jobs.spawn(JobKind::Index, &ctx, move |job| {
+            let Some(index) = FileIndex::build(&roots, job.cancel_flag()) else {
+                return; // Cancelled, keep the old index
+            };
+            if let Err(e) = index.save() {
+                eprintln!("Error while saving file index: {}", e);
+            }
+            let _ = sender.send(index); // The finished job wakes the UI
+        });
+    }
+
+    /// Drops the results of the last search, including the ones spilled to disk.
+    fn clear_search_results(&mut self) {
+        self.recursive_search_results = None;
+        self.filtered_results = None;
+        self.search_spill = None;
+    }
+
+    /// Recomputes `filtered_results` after `results_filter` or the results changed.
+    fn apply_results_filter(&mut self) {
+        let filter_lower = self.results_filter.to_lowercase();
+        self.filtered_results = match &self.recursive_search_results {
+            Some(results) if !filter_lower.is_empty() => Some(
+                results
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, path)| matches_results_filter(path, &filter_lower))
+                    .map(|(index, _)| index)
+                    .collect(),
+            ),
+            _ => None,
+        };
+    }
+
+    /// Writes results that don't fit in memory anymore to the spill file, created on first use.
+    /// Their matching lines and metadata move to the file, too.
+    fn spill_search_results(
+        search_spill: &mut Option<ResultSpill>,
+        paths: &[PathBuf],
+        line_hits: &mut HashMap<PathBuf, Vec<LineHit>>,
+        metadata: &mut HashMap<PathBuf, ResultMetadata>,
+    ) {
+        let hits: Vec<Vec<LineHit>> = paths.iter().map(|path| line_hits.remove(path).unwrap_or_default()).collect();
+        let metadata: Vec<ResultMetadata> =
+            paths.iter().map(|path| metadata.remove(path).unwrap_or_default()).collect();
+        if search_spill.is_none() {
+            match ResultSpill::create() {
+                Ok(spill) => *search_spill = Some(spill),
+                Err(e) => {
+                    eprintln!("Error while creating a file for {} more search results: {}", paths.len(), e);
+                    return;
+                }
+            }
+        }
+        if let Some(spill) = search_spill
+            && let Err(e) = spill.write(paths, &hits, &metadata)
+        {
+            eprintln!("Error while writing search results to a temporary file: {}", e);
+        }
+    }
+
+    /// Stops the running search (if any): signals its jobs to stop and closes the channels.
+    fn cancel_search(&mut self) {
+        self.jobs.cancel_kind(JobKind::Search);
+        self.is_searching = false;
+        self.search_receiver = None; // Close channel
+    }
+
+    /// Path of the archive named in the ZIP popup, with ".zip" added if it's missing.
+    fn zip_archive_path(&self) -> Option<PathBuf> {
+        let mut archive_name = self.zip_archive_name.trim().to_string();
+        if archive_name.is_empty() {
+            return None;
+        }
+        if !archive_name.to_lowercase().ends_with(".zip") {
+            archive_name.push_str(".zip");
+        }
+        Some(self.current_dir.join(archive_name))
+    }
+
+    /// Packs `self.zip_sources` into `self.zip_archive_name` inside the current directory,
+    /// after asking if that replaces an existing file.
+    fn start_zip_archive(&mut self, ctx: &egui::Context) {
+        let Some(archive_path) = self.zip_archive_path() else {
+            return;
+        };
+        if self.zip_sources.is_empty() {
+            return;
+        }
+
+        let sources: Vec<PathBuf> = self.zip_sources.iter().map(|name| self.current_dir.join(name)).collect();
+        let level = self.zip_compression_level;
+        if self.settings.confirm_overwrite && archive_path.exists() {
+            let operation = ReplaceOperation::Zip { sources, archive_path: archive_path.clone(), level };
+            self.pending_replace = Some((vec![archive_path], operation));
+            return;
+        }
+        self.spawn_zip_archive(ctx, sources, archive_path, level);
+    }
+
+    /// Exports the search results to `self.export_path`, after asking if that replaces an existing file.
+    fn start_export(&mut self, ctx: &egui::Context) {
+        let destination = PathBuf::from(self.export_path.trim());
+        if self.settings.confirm_overwrite && destination.exists() {
+            let operation = ReplaceOperation::Export { destination: destination.clone() };
+            self.pending_replace = Some((vec![destination], operation));
+            return;
+        }
+        self.spawn_export(ctx, destination);
+    }
+
+    /// Writes the export on a background thread, its result arrives through `self.export_receiver`.
+    fn spawn_export(&mut self, ctx: &egui::Context, destination: PathBuf) {
+        // Export what is shown: the whole result list, narrowed by the results filter
+        let filter_lower = self.results_filter.to_lowercase();
+        let results = self.recursive_search_results.as_deref().unwrap_or_default();
+        let paths: Vec<PathBuf> = match &self.filtered_results {
+            Some(indices) => indices.iter().map(|&index| results[index].clone()).collect(),
+            None => results.to_vec(),
+        };
+        // Spilled results are streamed from their file instead of being loaded into memory
+        let spilled = self.search_spill.as_mut().and_then(|spill| {
+            spill.unread_reader().map_err(|e| eprintln!("Error while reading spilled search results: {}", e)).ok()
+        });
+        let (format, with_metadata) = (self.export_format, self.export_with_metadata);
+        let (sender, receiver) = mpsc::channel();
+        self.export_receiver = Some(receiver);
+        self.export_status = None;
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let spilled = spilled
+                .into_iter()
+                .flatten()
+                .map(|(path, _, _)| path)
+                .filter(|path| matches_results_filter(path, &filter_lower));
+            let result = export::export_results(paths.into_iter().chain(spilled), &destination, format, with_metadata)
+                .map(|()| destination)
+                .map_err(|e| e.to_string());
+            if sender.send(result).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Compression runs as a background job, its result arrives through `self.zip_receiver`.
+    fn spawn_zip_archive(&mut self, ctx: &egui::Context, sources: Vec<PathBuf>, archive_path: PathBuf, level: i32) {
+        let (sender, receiver) = mpsc::channel();
+        self.zip_receiver = Some(receiver);
+
+        self.jobs.spawn(JobKind::Compress, ctx, move |job| {
+            let _ = sender.send(archive::create_zip_archive(sources, archive_path, level, job));
+        });
+    }
+}
+
+// Helper function to open a file with the default application registered for it
+// (`start` on Windows, `open` on macOS, `xdg-open` on Linux and other Unix systems)
+fn open_with_default_app(path: &Path) {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    spawn_detached(command.arg(path), path);
+}
+
+// Helper function to look up the owner and EXIF date of `path` when their columns are shown
+// (`load_owner`, `load_date_taken`) and `details` doesn't have them yet
+fn read_optional_details(path: &Path, details: &mut EntryDetails, load_owner: bool, load_date_taken: bool) {
+    if load_owner && details.owner.is_none() {
+        details.owner = fileops::file_owner(path);
+    }
+    if load_date_taken && details.date_taken.is_none() && !details.is_dir() {
+        details.date_taken = photo::date_taken(path);
+    }
+}
+
+// Helper function to show a file selected in the system file manager
+fn show_in_file_manager(path: &Path) {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("explorer");
+        command.arg("/select,").arg(path);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    } else {
+        // xdg-open can't select a file, open its folder instead
+        let mut command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(path));
+        command
+    };
+    spawn_detached(&mut command, path);
+}
+
+/// Terminals tried in this order when none is configured and $TERMINAL is not set (Linux and other Unix systems).
+const UNIX_TERMINALS: [&str; 7] =
+    ["x-terminal-emulator", "gnome-terminal", "konsole", "xfce4-terminal", "kitty", "alacritty", "xterm"];
+
+// Helper function to open a terminal in `dir`: the command configured in the settings if any,
+// otherwise Windows Terminal / PowerShell / cmd, Terminal.app, or $TERMINAL and the usual Unix terminals
+fn open_terminal(dir: &Path, configured: &str) {
+    let mut words = configured.split_whitespace();
+    let mut command = if let Some(program) = words.next() {
+        let mut command = Command::new(program);
+        command.args(words);
+        command
+    } else if cfg!(windows) {
+        let shell: &[&str] = if find_in_path("wt.exe").is_some() {
+            &["wt", "-d", "."]
+        } else if find_in_path("powershell.exe").is_some() {
+            &["powershell", "-NoExit"]
+        } else {
+            &["cmd"]
+        };
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]).args(shell);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.args(["-a", "Terminal"]).arg(dir);
+        command
+    } else {
+        let terminal = std::env::var("TERMINAL")
+            .ok()
+            .filter(|terminal| !terminal.is_empty())
+            .or_else(|| UNIX_TERMINALS.iter().find(|terminal| find_in_path(terminal).is_some()).map(|t| t.to_string()));
+        let Some(terminal) = terminal else {
+            eprintln!("No terminal found to open {:?}, set one in the settings", dir);
+            return;
+        };
+        Command::new(terminal)
+    };
+    spawn_detached(command.current_dir(dir), dir);
+}
+
+/// Diff programs tried in this order when none is configured: (program, name in the menu).
+#[cfg(windows)]
+const DIFF_TOOLS: [(&str, &str); 3] =
+    [("WinMergeU.exe", "WinMerge"), ("BCompare.exe", "Beyond Compare"), ("code.cmd", "VS Code")];
+#[cfg(not(windows))]
+const DIFF_TOOLS: [(&str, &str); 5] = [
+    ("meld", "Meld"),
+    ("kdiff3", "KDiff3"),
+    ("bcompare", "Beyond Compare"),
+    ("opendiff", "FileMerge"),
+    ("code", "VS Code"),
+];
+
+// Helper function to find the program for "Compare with": the command configured in the settings if any,
+// otherwise the first installed one of the usual diff tools. Returns the command and its name for the menu.
+fn diff_tool(configured: &str) -> Option<(String, String)> {
+    if let Some(program) = open_with::split_command(configured).first() {
+        let name = Path::new(program).file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        return Some((configured.to_string(), name));
+    }
+    // WinMerge and Beyond Compare don't add themselves to PATH
+    let program_files = std::env::var_os("ProgramFiles").map(PathBuf::from).filter(|_| cfg!(windows));
+    DIFF_TOOLS.iter().find_map(|(program, name)| {
+        let found = find_in_path(program).or_else(|| {
+            let folders = ["WinMerge", "Beyond Compare 5", "Beyond Compare 4"];
+            let program_files = program_files.as_ref()?;
+            folders.iter().map(|folder| program_files.join(folder).join(program)).find(|path| path.is_file())
+        })?;
+        // VS Code compares files only with --diff
+        let arguments = if *name == "VS Code" { " --diff" } else { "" };
+        Some((format!("\"{}\"{}", found.display(), arguments), name.to_string()))
+    })
+}
+
+// Helper function to open two files side by side in the diff program `command`
+fn compare_files(command: &str, left: &Path, right: &Path) {
+    let words = open_with::split_command(command);
+    let Some((program, arguments)) = words.split_first() else {
+        return;
+    };
+    let mut command = Command::new(program);
+    command.args(arguments).arg(left).arg(right);
+    spawn_detached(&mut command, left);
+}
+
+// Helper function to start a launcher without blocking; it is waited for on a
+// background thread so it doesn't linger as a zombie process on Unix
+fn spawn_detached(command: &mut Command, path: &Path) {
+    match command.spawn() {
+        Ok(mut child) => {
+            thread::spawn(move || child.wait());
+        }
+        Err(e) => eprintln!("Error while opening {:?}: {}", path, e),
+    }
+}
+
+// Helper function to open a text file at a specific line.
+// Uses VS Code if it is installed, otherwise falls back to the default application.
+fn open_at_line(path: &Path, line_number: usize) {
+    let location = format!("{}:{}", path.display(), line_number);
+    let spawned = if cfg!(windows) {
+        find_in_path("code.cmd").and_then(|code| {
+            Command::new("cmd").arg("/C").arg(code).args(["--goto", &location]).spawn().ok()
+        })
+    } else {
+        find_in_path("code").and_then(|code| Command::new(code).args(["--goto", &location]).spawn().ok())
+    };
+    if spawned.is_none() {
+        open_with_default_app(path);
+    }
+}
+
+// Helper function to locate an executable in the directories listed in PATH
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+// Helper function for the results filter: case-insensitive match anywhere in the path
+fn matches_results_filter(path: &Path, filter_lower: &str) -> bool {
+    filter_lower.is_empty() || path.display().to_string().to_lowercase().contains(filter_lower)
+}
+
+// Helper function to build a label with the byte ranges `highlights` of `text` emphasized
+fn highlighted_text(ui: &egui::Ui, text: &str, highlights: &[Range<usize>], color: egui::Color32) -> LayoutJob {
+    let font_id = egui::TextStyle::Button.resolve(ui.style());
+    let normal = TextFormat { font_id: font_id.clone(), color, ..Default::default() };
+    let highlighted = TextFormat {
+        font_id,
+        color: ui.visuals().strong_text_color(),
+        background: ui.visuals().selection.bg_fill,
+        ..Default::default()
+    };
+
+    let mut job = LayoutJob::default();
+    let mut position = 0;
+    for range in highlights {
+        // Skip overlapping or invalid ranges instead of panicking on bad slices
+        if range.start < position || range.end > text.len() || !text.is_char_boundary(range.start) || !text.is_char_boundary(range.end) {
+            continue;
+        }
+        job.append(&text[position..range.start], 0.0, normal.clone());
+        job.append(&text[range.clone()], 0.0, highlighted.clone());
+        position = range.end;
+    }
+    job.append(&text[position..], 0.0, normal);
+    job
+}
+
+// Helper function to load PNG bytes into egui::ColorImage (for in-app display)
+fn load_egui_image_from_bytes(bytes: &'static [u8]) -> Option<egui::ColorImage> {
+    let image = image::load_from_memory_with_format(bytes, ImageFormat::Png).ok()?;
+    let size = [image.width() as _, image.height() as _];
+    let image_buffer = image.into_rgba8();
+    let pixels = image_buffer.as_flat_samples();
+    Some(egui::ColorImage::from_rgba_unmultiplied(size, pixels.as_slice()))
+}
+
+// --- Egui/Eframe Implementation ---
+
+impl eframe::App for MyExplorerApp {
+    fn on_close_event(&mut self) -> bool {
+        // With the tray, closing only hides the window until "Quit" is picked in the tray menu
+        match &self.tray {
+            Some(tray) if !tray.is_quitting() => {
+                tray.hide_window();
+                false
+            }
+            _ => {
+                self.save_session();
+                true
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // Flags for delayed state changes
+        let mut should_navigate_to_path: Option<PathBuf> = None;
+        let mut should_clear_recursive_results_after_interaction = false;
+        let mut should_clear_rename_mode = false;
+        let mut should_close_search_popup = false;
+        let mut should_drag_out_path: Option<PathBuf> = None;
+        let mut should_show_shell_menu: Option<Vec<PathBuf>> = None;
+        let mut should_sort_results_by: Option<ResultSortKey> = None;
+        let mut should_reveal_path: Option<PathBuf> = None;
+        let mut should_open_path: Option<PathBuf> = None;
+        let mut should_open_entry: Option<OsString> = None; // Opened once the listing is put back
+        let mut should_sort_entries_by: Option<SortColumn> = None;
+        let mut should_toggle_column: Option<SortColumn> = None;
+        let mut should_move_column: Option<(SortColumn, SortColumn)> = None;
+        let mut shown_without_metadata: Vec<PathBuf> = Vec::new();
+        let mut should_rename_entry: Option<(OsString, String)> = None;
+        let mut should_delete_entries: Option<Vec<OsString>> = None;
+        let mut should_trash_entries: Option<Vec<OsString>> = None;
+        let mut should_paste_into: Option<PathBuf> = None;
+        let mut should_apply_directory_filter = false;
+        let mut should_toggle_bookmark = false;
+
+        // Progress and completion of background jobs
+        self.jobs.poll();
+
+        // Folders of filefox:// links, handed over by the processes started for them
+        if let Some(receiver) = &self.link_receiver
+            && let Ok(folder) = receiver.try_recv()
+        {
+            should_navigate_to_path = Some(folder);
+            frame.set_minimized(false);
+            frame.focus();
+        }
+
+        // Folders picked in the tray menu, which already brought the window back
+        if let Some(folder) = self.tray.as_ref().and_then(Tray::picked_folder) {
+            should_navigate_to_path = Some(folder);
+        }
+
+        // Keep track of the window for the next start; a minimized window has no useful geometry
+        let window_info = frame.info().window_info;
+        if !window_info.minimized {
+            self.session.window_position = window_info.position.map(|position| [position.x, position.y]);
+            if !window_info.maximized {
+                self.session.window_size = Some([window_info.size.x, window_info.size.y]);
+            }
+            self.session.maximized = window_info.maximized;
+        }
+
+        // Check for search results from the background jobs (one message per root)
+        let mut results_arrived = false;
+        if let Some(receiver) = &self.search_receiver {
+            loop {
+                match receiver.try_recv() {
+                    Ok(SearchMessage::Progress(stats)) => {
+                        // Reports of different threads may arrive out of order, keep the highest counts
+                        self.search_stats.dirs_scanned = self.search_stats.dirs_scanned.max(stats.dirs_scanned);
+                        self.search_stats.files_examined = self.search_stats.files_examined.max(stats.files_examined);
+                        self.search_stats.hits = self.search_stats.hits.max(stats.hits);
+                    }
+                    Ok(SearchMessage::Results(output)) => {
+                        results_arrived = true;
+                        let results = self.recursive_search_results.get_or_insert_with(Vec::new);
+                        // Pages already loaded back from the spill stay in memory
+                        let limit = MAX_RESULTS_IN_MEMORY.max(results.len());
+                        self.search_result_metadata.extend(output.metadata);
+                        match self.results_sort {
+                            Some(key) => search::merge_results(
+                                results,
+                                output.paths,
+                                key,
+                                self.results_sort_descending,
+                                &self.search_result_metadata,
+                            ),
+                            None => results.extend(output.paths),
+                        }
+                        self.search_line_hits.extend(output.line_hits);
+                        // Keep the first results (the top ones when sorted) and set the rest aside
+                        if results.len() > limit {
+                            let overflow = results.split_off(limit);
+                            Self::spill_search_results(
+                                &mut self.search_spill,
+                                &overflow,
+                                &mut self.search_line_hits,
+                                &mut self.search_result_metadata,
+                            );
+                        }
+                    }
+                    Err(mpsc::TryRecvError::Empty) => {
+                        break; // Remaining roots still running
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        // All senders dropped, every root is finished
+                        if self.is_searching {
+                            self.recursive_search_results.get_or_insert_with(Vec::new);
+                        }
+                        self.is_searching = false;
+                        self.search_receiver = None;
+                        break;
+                    }
+                }
+            }
+        }
+        if results_arrived && !self.results_filter.is_empty() {
+            self.apply_results_filter();
+        }
+
+        // Check for the result of a running ZIP compression
+        if let Some(receiver) = &self.zip_receiver {
+            match receiver.try_recv() {
+                Ok(result) => {
+                    self.zip_receiver = None;
+                    match result {
+                        Ok(_) => self.read_current_directory_entries(), // Show the new archive
+                        Err(e) => eprintln!("Error while creating ZIP archive: {}", e),
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => self.zip_receiver = None,
+            }
+        }
+
+        // Check for a loaded or rebuilt file index
+        if let Some(receiver) = &self.index_receiver {
+            match receiver.try_recv() {
+                Ok(index) => {
+                    self.index_roots = index.roots.iter().map(|root| root.root.clone()).collect();
+                    self.next_index_refresh = Some(Instant::now() + index.refresh_due_in());
+                    self.file_index = Arc::new(index);
+                    self.index_receiver = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.index_receiver = None; // Rebuild cancelled
+                }
+            }
+        }
+
+        // Rebuild the index once it is out of date, so renamed and new files are found
+        if let Some(refresh_at) = self.next_index_refresh
+            && self.index_receiver.is_none()
+            && !self.index_roots.is_empty()
+        {
+            if Instant::now() >= refresh_at {
+                self.next_index_refresh = Some(Instant::now() + index::REFRESH_INTERVAL); // Also after a cancel
+                self.rebuild_file_index(ctx.clone());
+            } else {
+                ctx.request_repaint_after(refresh_at.saturating_duration_since(Instant::now()));
+            }
+        }
+
+        // Check for the report of a running copy
+        if let Some(receiver) = &self.copy_receiver
+            && let Ok(report) = receiver.try_recv()
+        {
+            self.copy_receiver = None;
+            for error in &report.errors {
+                eprintln!("Error while copying {}", error);
+            }
+            if let Some((from, to)) = report.access_denied.into_iter().next() {
+                self.elevation_offer = Some(ElevatedOperation::Copy { from, to });
+            }
+            self.copy_mismatches = report.mismatches;
+            self.read_current_directory_entries(); // Show the copied entries
+        }
+
+        // Check for conflicts and the report of a running move or merge
+        if let Some(receiver) = &self.move_receiver {
+            match receiver.try_recv() {
+                Ok(MoveEvent::Conflict(destination, answer)) => self.merge_conflict = Some((destination, answer)),
+                Ok(MoveEvent::Finished(report)) => {
+                    self.move_receiver = None;
+                    for error in &report.errors {
+                        eprintln!("Error while moving {}", error);
+                    }
+                    if let Some((from, to)) = report.access_denied.into_iter().next() {
+                        self.elevation_offer = Some(ElevatedOperation::Move { from, to });
+                    }
+                    self.read_current_directory_entries(); // Show the moved entries
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.move_receiver = None;
+                    self.merge_conflict = None;
+                    self.read_current_directory_entries();
+                }
+            }
+        }
+
+        // Check for the reports of running deletions
+        let mut delete_reports = Vec::new();
+        self.delete_receivers.retain(|receiver| match receiver.try_recv() {
+            Ok(report) => {
+                delete_reports.push(report);
+                false
+            }
+            Err(mpsc::TryRecvError::Empty) => true,
+            Err(mpsc::TryRecvError::Disconnected) => false,
+        });
+        if !delete_reports.is_empty() {
+            for report in delete_reports {
+                for error in &report.errors {
+                    eprintln!("Error while deleting {}", error);
+                }
+                if let Some(path) = report.access_denied.into_iter().next() {
+                    self.elevation_offer = Some(ElevatedOperation::Delete(path));
+                }
+                for path in &report.deleted {
+                    self.tags.remove(path);
+                }
+            }
+            self.save_tags();
+            self.read_current_directory_entries(); // Update entries after deletion and reset search
+        }
+
+        // Check for the result of an elevated retry
+        if let Some(receiver) = &self.elevation_receiver {
+            match receiver.try_recv() {
+                Ok(result) => {
+                    self.elevation_receiver = None;
+                    match result {
+                        Ok(()) => self.read_current_directory_entries(), // Show the result of the operation
+                        Err(e) => eprintln!("Error while running as administrator: {}", e),
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => self.elevation_receiver = None,
+            }
+        }
+
+        // Apply the theme (eframe may switch visuals itself when the system theme changes)
+        let visuals = self.settings.theme.visuals(frame.info().system_theme);
+        if ctx.style().visuals.dark_mode != visuals.dark_mode {
+            ctx.set_visuals(visuals);
+        }
+
+        // Keyboard shortcuts. A shortcut typed for the settings doesn't run its action.
+        if let Some(action) = self.recording_shortcut
+            && let Some(shortcut) = keymap::record_shortcut(ctx)
+        {
+            if shortcut.is_some() {
+                self.settings.keymap.set(action, shortcut);
+                if let Err(e) = self.settings.save() {
+                    eprintln!("Error while saving settings: {}", e);
+                }
+            }
+            self.recording_shortcut = None;
+        }
+        let pressed_actions =
+            if self.recording_shortcut.is_none() { self.settings.keymap.pressed(ctx) } else { Vec::new() };
+        let pressed = |action| pressed_actions.contains(&action);
+        for &action in &pressed_actions {
+            self.run_action(action, ctx);
+        }
+
+        // UI zoom: Ctrl+= / Ctrl+- step, Ctrl+0 resets (unless the shortcuts were changed)
+        let zoom_before = self.settings.zoom;
+        if pressed(Action::ZoomIn) {
+            self.settings.zoom += settings::ZOOM_STEP;
+        }
+        if pressed(Action::ZoomOut) {
+            self.settings.zoom -= settings::ZOOM_STEP;
+        }
+        if pressed(Action::ResetZoom) {
+            self.settings.zoom = 1.0;
+        }
+        self.settings.zoom = self.settings.zoom.clamp(settings::MIN_ZOOM, settings::MAX_ZOOM);
+        if self.settings.zoom != zoom_before && let Err(e) = self.settings.save() {
+            eprintln!("Error while saving settings: {}", e);
+        }
+        let pixels_per_point = frame.info().native_pixels_per_point.unwrap_or(1.0) * self.settings.zoom;
+        if (ctx.pixels_per_point() - pixels_per_point).abs() > f32::EPSILON {
+            ctx.set_pixels_per_point(pixels_per_point);
+        }
+
+        // Upload thumbnails generated since the last frame
+        self.thumbnails.poll(ctx);
+
+        // Add entries of the current directory read since the last frame
+        if self.poll_directory_listing(Duration::ZERO) {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+
+        // Apply the filter typed into the filter box once typing pauses
+        if let Some(edited) = self.directory_filter_edited {
+            let elapsed = edited.elapsed();
+            if elapsed >= FILTER_DEBOUNCE {
+                self.apply_directory_filter();
+            } else {
+                ctx.request_repaint_after(FILTER_DEBOUNCE - elapsed);
+            }
+        }
+
+        // Follow changes other programs made to the current directory (once it is read completely).
+        // Changes reported while the previous ones are read wait for the next batch.
+        if let Some(receiver) = &self.external_changes_receiver {
+            match receiver.try_recv() {
+                Ok(changes) => {
+                    self.external_changes_receiver = None;
+                    self.merge_external_changes(changes);
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => self.external_changes_receiver = None,
+            }
+        }
+        if self.listing_receiver.is_none() && self.external_changes_receiver.is_none() {
+            match self.watcher.poll(ctx) {
+                Some(DirectoryChanges::Paths(paths)) => self.apply_external_changes(paths, ctx),
+                Some(DirectoryChanges::Rescan) => self.read_current_directory_entries(),
+                None => {}
+            }
+        }
+
+        // Check for the git status of the current directory
+        if let Some(receiver) = &self.git_receiver {
+            match receiver.try_recv() {
+                Ok(status) => {
+                    self.git_status = status;
+                    self.git_receiver = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => ctx.request_repaint_after(std::time::Duration::from_millis(100)),
+                Err(mpsc::TryRecvError::Disconnected) => self.git_receiver = None,
+            }
+        }
+
+        // Check for the sync status of the current directory
+        if let Some(receiver) = &self.cloud_receiver {
+            match receiver.try_recv() {
+                Ok(status) => {
+                    self.cloud_status = status;
+                    self.cloud_receiver = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => ctx.request_repaint_after(std::time::Duration::from_millis(100)),
+                Err(mpsc::TryRecvError::Disconnected) => self.cloud_receiver = None,
+            }
+        }
+
+        // Show folder sizes calculated since the last frame
+        let mut folder_sizes_changed = false;
+        for (path, size) in self.folder_sizes.poll(ctx) {
+            if path.parent() == Some(self.current_dir.as_path())
+                && let Some(name) = path.file_name()
+                && let Some(details) = self.entry_details.get_mut(name)
+            {
+                details.size = size;
+                folder_sizes_changed = true;
+            }
+        }
+        if folder_sizes_changed && self.sort_order.column == SortColumn::Size {
+            self.resort_entries();
+        }
+
+        // Show metadata read since the last frame
+        let mut metadata_changed = false;
+        for (path, mut details) in self.metadata_loader.poll(ctx) {
+            if path.parent() == Some(self.current_dir.as_path())
+                && let Some(name) = path.file_name()
+                && let Some(old) = self.entry_details.get_mut(name)
+            {
+                // Keep what was looked up separately
+                details.size = details.size.or(old.size);
+                details.owner = old.owner.take();
+                details.date_taken = old.date_taken;
+                *old = details;
+                metadata_changed = true;
+            }
+        }
+        if metadata_changed && self.sort_order.column.needs_metadata() {
+            self.resort_entries();
+        }
+
+        // Check for the result of an export
+        if let Some(receiver) = &self.export_receiver {
+            match receiver.try_recv() {
+                Ok(result) => {
+                    self.export_receiver = None;
+                    self.export_status = Some(match result {
+                        Ok(path) => format!("Saved to {}", path.display()),
+                        Err(e) => {
+                            eprintln!("Error while exporting search results: {}", e);
+                            format!("Export failed: {}", e)
+                        }
+                    });
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => self.export_receiver = None,
+            }
+        }
+
+        // --- Status bar ---
+
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                match &self.filtered_entries {
+                    Some(filtered) => ui.label(format!("{} items ({} shown)", self.entries.len(), filtered.len())),
+                    None => ui.label(format!("{} items", self.entries.len())),
+                };
+                if !self.selected_entries.is_empty() {
+                    // Folders only count once their size was calculated
+                    let selected_size: u64 = self
+                        .selected_entries
+                        .iter()
+                        .filter_map(|name| self.entry_details.get(name).and_then(|details| details.size))
+                        .sum();
+                    ui.separator();
+                    ui.label(format!("{} selected ({})", self.selected_entries.len(), view::format_size(selected_size)));
+                }
+                if let Some(git_status) = &self.git_status {
+                    ui.separator();
+                    ui.label(format!("⎇ {}", git_status.branch.as_deref().unwrap_or("detached HEAD")));
+                }
+                if let Some(cloud_status) = &self.cloud_status {
+                    ui.separator();
+                    ui.label(format!("☁ {}", cloud_status.provider.label()));
+                }
+                if let Some((free, total)) = self.disk_space {
+                    ui.separator();
+                    ui.label(format!("{} free of {}", view::format_size(free), view::format_size(total)));
+                }
+                if fileops::is_long_path(&self.current_dir) {
+                    ui.separator();
+                    ui.colored_label(ui.visuals().warn_fg_color, "⚠ Long path").on_hover_text(
+                        "This folder's path is longer than 260 characters. FileFox handles it, \
+                         but other programs may fail to open files in here.",
+                    );
+                }
+
+                // Background operations
+                let mut activities = Vec::new();
+                if self.startup_pending || self.listing_receiver.is_some() {
+                    activities.push("Reading folder".to_string());
+                }
+                activities.extend(self.jobs.activities());
+                if self.folder_sizes.pending_count() > 0 {
+                    activities.push(format!("Calculating {} folder sizes", self.folder_sizes.pending_count()));
+                }
+                if self.export_receiver.is_some() {
+                    activities.push("Exporting".to_string());
+                }
+                if self.elevation_receiver.is_some() {
+                    activities.push("Waiting for administrator operation".to_string());
+                }
+                if !activities.is_empty() {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.label(activities.join(" · "));
+                        ui.spinner();
+                    });
+                }
+            });
+        });
+
+        // --- Preview pane ---
+
+        if pressed(Action::TogglePreview) {
+            self.show_preview = !self.show_preview;
+        }
+        if self.show_preview {
+            // Preview the selected entry if exactly one is selected
+            let target = match self.selected_entries.iter().next() {
+                Some(name) if self.selected_entries.len() == 1 => Some(self.current_dir.join(name)),
+                _ => None,
+            };
+            if target.as_ref() != self.preview.as_ref().map(|preview| &preview.path) {
+                self.preview = target.map(|path| Preview::load(&path, ctx));
+            }
+            egui::SidePanel::right("preview_pane").default_width(320.0).show(ctx, |ui| {
+                match &mut self.preview {
+                    Some(preview) => preview.show(ui),
+                    None if self.selected_entries.len() > 1 => {
+                        ui.weak(format!("{} items selected", self.selected_entries.len()));
+                    }
+                    None => {
+                        ui.weak("Select a file to preview it");
+                    }
+                }
+            });
+        } else {
+            self.preview = None;
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if let Some(receiver) = &self.app_icon_receiver
+                    && let Ok(icon) = receiver.try_recv()
+                {
+                    self.app_icon_texture = Some(ctx.load_texture("app_logo", icon, egui::TextureOptions::default()));
+                    self.app_icon_receiver = None;
+                }
+                if let Some(texture) = &self.app_icon_texture {
+                    ui.image(texture.id(), egui::vec2(24.0, 24.0)); // Adjust size
+                }
+                ui.heading("FileFox");
+            });
+
+            // --- Navigation bar ---
+            ui.horizontal(|ui| {
+                let up_shortcut = self.settings.keymap.describe(ctx, Action::NavigateUp);
+                if ui.button("⬆️ Up").on_hover_text(up_shortcut).clicked() {
+                    self.navigate_up();
+                }
+                let drives_menu = ui.menu_button("🖴 Drives", |ui| {
+                    if let Some(receiver) = &self.drives_receiver
+                        && let Ok((drives, devices)) = receiver.try_recv()
+                    {
+                        self.drives = drives;
+                        self.devices = devices;
+                        self.drives_receiver = None;
+                    }
+                    if self.drives_receiver.is_some() {
+                        ui.spinner(); // Network drives can take a moment to answer
+                    }
+                    for drive in &self.drives {
+                        let space = match drive.space {
+                            Some((free, total)) => {
+                                format!("  {} free of {}", view::format_size(free), view::format_size(total))
+                            }
+                            None => String::new(),
+                        };
+                        if ui.button(format!("{} {}{}", drive.kind.icon(), drive.label, space)).clicked() {
+                            should_navigate_to_path = Some(drive.path.clone());
+                            ui.close_menu();
+                        }
+                    }
+                    for device in &self.devices {
+                        if ui.button(format!("{} {}", DriveKind::Device.icon(), device.name)).clicked() {
+                            self.device_browser = Some(DeviceBrowser::new(device.clone(), ctx));
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.menu_button("⭐ Places", |ui| {
+                    let label = if self.places.is_bookmarked(&self.current_dir) {
+                        "☆ Remove bookmark"
+                    } else {
+                        "⭐ Bookmark this folder"
+                    };
+                    if ui.button(label).clicked() {
+                        should_toggle_bookmark = true;
+                        ui.close_menu();
+                    }
+                    let lists = [("Bookmarks", &self.places.bookmarks), ("Recent folders", &self.places.recent)];
+                    for (title, folders) in lists {
+                        if folders.is_empty() {
+                            continue;
+                        }
+                        ui.separator();
+                        ui.weak(title);
+                        for folder in folders {
+                            let button = ui.button(format!("📁 {}", places::folder_label(folder)));
+                            if button.on_hover_text(folder.display().to_string()).clicked() {
+                                should_navigate_to_path = Some(folder.clone());
+                                ui.close_menu();
+                            }
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("🕘 Recent files...").clicked() {
+                        self.show_recent_files = true;
+                        ui.close_menu();
+                    }
+                });
+                if drives_menu.response.clicked() {
+                    let (sender, receiver) = mpsc::channel();
+                    self.drives_receiver = Some(receiver);
+                    let ctx = ctx.clone();
+                    thread::spawn(move || {
+                        if sender.send((drives::list_drives(), mtp::list_devices())).is_ok() {
+                            ctx.request_repaint();
+                        }
+                    });
+                }
+                ui.label(format!("Current Path: {}", self.current_dir.display()));
+                if !self.clipboard_entries.is_empty() {
+                    let paste_button = egui::Button::new(format!("📋 Paste ({})", self.clipboard_entries.len()));
+                    if ui.add_enabled(self.can_paste(), paste_button).clicked() {
+                        self.paste_clipboard(self.current_dir.clone(), ctx.clone());
+                    }
+                }
+                let new_folder_shortcut = self.settings.keymap.describe(ctx, Action::NewFolder);
+                if ui.button("🗀 New folder").on_hover_text(new_folder_shortcut).clicked() {
+                    self.create_new_folder();
+                }
+                ui.separator();
+                let preview_shortcut = self.settings.keymap.describe(ctx, Action::TogglePreview);
+                ui.toggle_value(&mut self.show_preview, "👁 Preview").on_hover_text(preview_shortcut);
+                let view_mode_before = self.view_mode;
+                ui.selectable_value(&mut self.view_mode, ViewMode::Details, "☰ Details");
+                ui.selectable_value(&mut self.view_mode, ViewMode::List, "📄 List");
+                ui.selectable_value(&mut self.view_mode, ViewMode::Grid, "▦ Grid");
+                if self.view_mode != view_mode_before {
+                    self.folder_view_changed();
+                }
+                if self.view_mode == ViewMode::Grid {
+                    ui.add(
+                        egui::Slider::new(&mut self.grid_cell_size, view::MIN_GRID_CELL_SIZE..=view::MAX_GRID_CELL_SIZE)
+                            .show_value(false),
+                    )
+                    .on_hover_text("Icon size (Ctrl+mouse wheel)");
+                }
+                if ui.checkbox(&mut self.sort_order.folders_first, "Folders first").changed() {
+                    self.sort_order_changed();
+                }
+                let group_by_before = self.group_by;
+                egui::ComboBox::from_id_source("group_by")
+                    .selected_text(format!("Group by: {}", self.group_by.label()))
+                    .show_ui(ui, |ui| {
+                        for group_by in GroupBy::ALL {
+                            ui.selectable_value(&mut self.group_by, group_by, group_by.label());
+                        }
+                    });
+                if self.group_by != group_by_before {
+                    self.folder_view_changed();
+                }
+                ui.separator();
+                if ui.button("⚙ Settings").clicked() {
+                    self.show_settings_window = true;
+                }
+                if ui.button("🗂 Index").clicked() {
+                    self.show_index_window = true;
+                }
+                if ui.button("📊 Disk usage").on_hover_text("Analyze disk usage of this folder").clicked() {
+                    self.disk_usage = Some(DiskUsage::analyze(&self.current_dir, self.size_cache.clone(), ctx));
+                }
+                if ui.button("⇄ Compare").on_hover_text("Compare this folder with another one").clicked() {
+                    // With a single folder selected, it is compared with this folder
+                    let selected = match self.selected_entries.iter().collect::<Vec<_>>().as_slice() {
+                        [name] => Some(self.current_dir.join(name)).filter(|path| path.is_dir()),
+                        _ => None,
+                    };
+                    self.folder_comparison = Some(FolderComparison::new(&self.current_dir, selected.as_deref(), ctx));
+                }
+                if ui.button("🔁 Sync").on_hover_text("Synchronize this folder with another one").clicked() {
+                    let selected = match self.selected_entries.iter().collect::<Vec<_>>().as_slice() {
+                        [name] => Some(self.current_dir.join(name)).filter(|path| path.is_dir()),
+                        _ => None,
+                    };
+                    self.folder_sync = Some(FolderSync::new(&self.current_dir, selected.as_deref()));
+                }
+                if ui.button("☁ Remote").on_hover_text("Browse S3 and WebDAV storage").clicked() {
+                    self.remote_browser.get_or_insert_with(RemoteBrowser::default);
+                }
+                if ui.button(">_ Terminal").on_hover_text("Open a terminal in this folder").clicked() {
+                    open_terminal(&self.current_dir, &self.settings.terminal);
+                }
+                ui.checkbox(&mut self.verify_copies, "Verify copies")
+                    .on_hover_text("Compare SHA-256 hashes of every copied file with its source");
+            });
+
+            ui.separator();
+
+            // Loading indicator when searching, cancellable via button or Esc
+            if self.is_searching {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    let roots = self.search_roots_in_use.len();
+                    let scope = if roots > 1 { format!(" in {} folders", roots) } else { String::new() };
+                    ui.label(format!("Searching for: '{}'{}...", self.search_query, scope));
+                    let stats = self.search_stats;
+                    ui.weak(format!(
+                        "{} folders scanned, {} files examined, {} hits",
+                        stats.dirs_scanned, stats.files_examined, stats.hits
+                    ));
+                    if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        self.cancel_search();
+                    }
+                });
+                ui.separator();
+            }
+
+            // Loading indicator while the directory is read
+            if self.startup_pending || self.listing_receiver.is_some() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Reading folder... {} items so far", self.entries.len()));
+                });
+                ui.separator();
+            }
+
+            // Progress indicator while copying
+            if self.jobs.is_running(JobKind::Copy) {
+                let (done, total) = self.jobs.progress(JobKind::Copy).unwrap_or_default();
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    let verifying = if self.verify_copies { " (verifying)" } else { "" };
+                    ui.label(format!("Copying{}... {}/{} files", verifying, done, total));
+                    if ui.button("Cancel").clicked() {
+                        self.jobs.cancel_kind(JobKind::Copy);
+                    }
+                });
+                ui.separator();
+            }
+
+            // Progress indicator while moving
+            if self.jobs.is_running(JobKind::Move) {
+                let (done, total) = self.jobs.progress(JobKind::Move).unwrap_or_default();
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Moving... {}/{} items", done, total));
+                    if ui.button("Cancel").clicked() {
+                        self.jobs.cancel_kind(JobKind::Move);
+                        self.merge_conflict = None; // A merge waiting for an answer stops, too
+                    }
+                });
+                ui.separator();
+            }
+
+            // Progress indicator while deleting
+            if self.jobs.is_running(JobKind::Delete) {
+                let (done, total) = self.jobs.progress(JobKind::Delete).unwrap_or_default();
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Deleting... {}/{} items", done, total));
+                    if ui.button("Cancel").clicked() {
+                        self.jobs.cancel_kind(JobKind::Delete);
+                    }
+                });
+                ui.separator();
+            }
+
+            // Progress indicator while synchronizing folders
+            if self.jobs.is_running(JobKind::Sync) {
+                let (done, total) = self.jobs.progress(JobKind::Sync).unwrap_or_default();
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Synchronizing... {}/{} changes", done, total));
+                    if ui.button("Cancel").clicked() {
+                        self.jobs.cancel_kind(JobKind::Sync);
+                    }
+                });
+                ui.separator();
+            }
+
+            // Progress indicator while compressing
+            if self.jobs.is_running(JobKind::Compress) {
+                let (done, total) = self.jobs.progress(JobKind::Compress).unwrap_or_default();
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Compressing... {}/{} files", done, total));
+                    if ui.button("Cancel").clicked() {
+                        self.jobs.cancel_kind(JobKind::Compress);
+                    }
+                });
+                ui.separator();
+            }
+
+            // --- Display file entries / search results ---
+            let display_mode_is_recursive_search = self.recursive_search_results.is_some();
+
+            // Live filter of the current directory: Ctrl+F, or just start typing
+            if !display_mode_is_recursive_search {
+                let mut should_focus_filter = pressed(Action::FocusFilter);
+                let nothing_focused = ctx.memory(|m| m.focus().is_none());
+                if nothing_focused && !self.show_search_popup && self.rename_mode.is_none() && self.image_viewer.is_none() {
+                    let typed: String = ui.input(|i| {
+                        i.events
+                            .iter()
+                            .filter_map(|event| match event {
+                                egui::Event::Text(text) => Some(text.as_str()),
+                                _ => None,
+                            })
+                            .collect()
+                    });
+                    if !typed.is_empty() {
+                        self.directory_filter.push_str(&typed);
+                        self.directory_filter_changed();
+                        should_focus_filter = true;
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("🔍");
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.directory_filter).hint_text("Filter this folder (Ctrl+F)"),
+                    );
+                    if should_focus_filter {
+                        response.request_focus();
+                    }
+                    if response.changed() {
+                        self.directory_filter_changed();
+                    }
+                    // Esc clears the filter
+                    if !self.directory_filter.is_empty() && ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        self.directory_filter.clear();
+                        self.apply_directory_filter();
+                        response.surrender_focus();
+                    }
+
+                    // Label and tag filters
+                    let mut label_filter = self.label_filter;
+                    egui::ComboBox::from_id_source("label_filter")
+                        .selected_text(label_filter.map_or("Any label", ColorLabel::label))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut label_filter, None, "Any label");
+                            for label in ColorLabel::ALL {
+                                ui.selectable_value(&mut label_filter, Some(label), egui::RichText::new(label.label()).color(label.color()));
+                            }
+                        });
+                    let mut tag_filter = self.tag_filter.clone();
+                    egui::ComboBox::from_id_source("tag_filter")
+                        .selected_text(tag_filter.as_deref().unwrap_or("Any tag"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut tag_filter, None, "Any tag");
+                            for tag in self.tags.all_tags() {
+                                ui.selectable_value(&mut tag_filter, Some(tag.to_string()), tag);
+                            }
+                        });
+                    if label_filter != self.label_filter || tag_filter != self.tag_filter {
+                        self.label_filter = label_filter;
+                        self.tag_filter = tag_filter;
+                        self.apply_directory_filter();
+                    }
+
+                    if let Some(filtered) = &self.filtered_entries {
+                        ui.weak(format!("{} of {} items", filtered.len(), self.entries.len()));
+                    }
+                });
+            }
 
-                                if ui.button("Delete").clicked() {
-                                    self.delete_entry(&entry_name);
-                                    ui.close_menu();
+            // Secondary filter narrowing the existing results
+            if self.recursive_search_results.as_ref().is_some_and(|results| !results.is_empty()) {
+                ui.horizontal(|ui| {
+                    ui.label("Filter results:");
+                    let mut changed = ui.text_edit_singleline(&mut self.results_filter).changed();
+                    if !self.results_filter.is_empty() && ui.button("✖").clicked() {
+                        self.results_filter.clear();
+                        changed = true;
+                    }
+                    if changed {
+                        self.apply_results_filter();
+                    }
+                });
+            }
+
+            // Ctrl+mouse wheel (or pinch) over the grid scales the icons
+            if self.view_mode == ViewMode::Grid
+                && !display_mode_is_recursive_search
+                && ui.rect_contains_pointer(ui.available_rect_before_wrap())
+            {
+                let zoom = ui.input(|i| i.zoom_delta());
+                if zoom != 1.0 {
+                    self.grid_cell_size = (self.grid_cell_size * zoom).clamp(view::MIN_GRID_CELL_SIZE, view::MAX_GRID_CELL_SIZE);
+                }
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                self.settings.row_density.apply(ui);
+                if display_mode_is_recursive_search {
+                    // Show recursive search results (taken out while rendering, put back below)
+                    let mut results = self.recursive_search_results.take().unwrap_or_default();
+                    let filtered_results = self.filtered_results.take();
+                    let mut load_spilled_page = false;
+
+                    if results.is_empty() {
+                        if !self.is_searching {
+                            ui.label(format!("No results found for: '{}'", self.search_query));
+                        }
+                    } else {
+                        let limit = self.results_display_limit;
+                        let visible_results: Vec<&PathBuf> = match &filtered_results {
+                            Some(indices) => indices.iter().take(limit).map(|&index| &results[index]).collect(),
+                            None => results.iter().take(limit).collect(),
+                        };
+                        let visible_results_count = filtered_results.as_ref().map_or(results.len(), Vec::len);
+                        let hidden_results = visible_results_count - visible_results.len();
+
+                        ui.heading(format!("Results for: '{}'", self.search_query));
+                        if filtered_results.is_some() {
+                            ui.label(format!("Showing {} of {} results", visible_results_count, results.len()));
+                        }
+                        if hidden_results > 0 {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                format!(
+                                    "{} matches, showing the first {} (load more at the bottom)",
+                                    visible_results_count,
+                                    visible_results.len()
+                                ),
+                            );
+                        }
+                        ui.add_space(10.0);
+
+                        // Column headers: click to sort, click again to reverse
+                        ui.horizontal(|ui| {
+                            ui.label("Sort by:");
+                            for key in ResultSortKey::ALL {
+                                let is_active = self.results_sort == Some(key);
+                                let arrow = match (is_active, self.results_sort_descending) {
+                                    (false, _) => "",
+                                    (true, false) => " ⏶",
+                                    (true, true) => " ⏷",
+                                };
+                                if ui.selectable_label(is_active, format!("{}{}", key.label(), arrow)).clicked() {
+                                    should_sort_results_by = Some(key);
+                                }
+                            }
+                            ui.separator();
+                            if ui.button("💾 Export...").clicked() {
+                                if self.export_path.is_empty() {
+                                    let file_name = format!("search_results.{}", self.export_format.extension());
+                                    self.export_path = self.current_dir.join(file_name).display().to_string();
+                                }
+                                self.export_status = None;
+                                self.show_export_popup = true;
+                            }
+                        });
+
+                        let show_origin = self.search_roots_in_use.len() > 1;
+                        let matcher = self.search_matcher.clone();
+                        let is_content_search = matches!(matcher.as_deref(), Some(SearchMatcher::Content(_)));
+                        for path in visible_results {
+                            let path_str = path.display().to_string();
+                            let origin = self.search_origin(path).filter(|_| show_origin);
+                            // Several roots: show the path relative to the root it came from
+                            let shown_path = match origin {
+                                Some(origin) => path.strip_prefix(origin).unwrap_or(path).display().to_string(),
+                                None => path_str.clone(),
+                            };
+                            // Highlight the part of the file name that matched
+                            let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                            let highlights = match &matcher {
+                                Some(matcher) if !is_content_search && shown_path.ends_with(&file_name) => {
+                                    let name_start = shown_path.len() - file_name.len();
+                                    matcher
+                                        .highlight_ranges(&file_name)
+                                        .into_iter()
+                                        .map(|range| range.start + name_start..range.end + name_start)
+                                        .collect()
                                 }
+                                _ => Vec::new(),
+                            };
+                            let label = highlighted_text(ui, &shown_path, &highlights, ui.visuals().widgets.inactive.text_color());
+                            let response = match origin {
+                                Some(origin) => {
+                                    ui.horizontal(|ui| {
+                                        ui.weak(format!("[{}]", origin.display()));
+                                        ui.button(label)
+                                    })
+                                    .inner
+                                }
+                                None => ui.button(label),
+                            };
+                            // Shown paths may be clipped or relative, the tooltip has the full one
+                            let response = response.on_hover_ui(|ui| {
+                                let metadata = self.search_result_metadata.get(path).copied().unwrap_or_default();
+                                ui.label(view::path_tooltip(path, metadata.size, metadata.modified));
+                            });
 
-                                if ui.button("Rename").clicked() {
-                                    self.rename_mode = Some(entry_name.clone());
-                                    self.rename_input = entry_name.clone();
+                            // Dragging a result hands it over to other applications
+                            if response.interact(egui::Sense::drag()).drag_started() {
+                                should_drag_out_path = Some(path.clone());
+                            }
+
+                            // Double click: navigate or open
+                            if response.double_clicked() {
+                                if path.is_dir() && !fileops::is_package(path) {
+                                    should_navigate_to_path = Some(path.clone());
+                                    should_clear_recursive_results_after_interaction = true;
+                                } else {
+                                    should_open_path = Some(path.clone());
+                                }
+                            }
+                            // Right-click context menu for search results
+                            response.context_menu(|ui| {
+                                if ui.button("Open").clicked() {
+                                    if path.is_dir() && !fileops::is_package(path) {
+                                        should_navigate_to_path = Some(path.clone());
+                                        should_clear_recursive_results_after_interaction = true;
+                                    } else {
+                                        should_open_path = Some(path.clone());
+                                    }
                                     ui.close_menu();
                                 }
+                                if ui.button("Open containing folder").clicked() {
+                                    should_reveal_path = Some(path.clone());
+                                    ui.close_menu();
+                                }
+                                let file_manager = if cfg!(windows) { "Show in explorer" } else { "Show in file manager" };
+                                if ui.button(file_manager).clicked() {
+                                    show_in_file_manager(path);
+                                    ui.close_menu();
+                                }
+                            });
+
+                            // Matching lines of a content search, click opens the file at that line
+                            if let Some(hits) = self.search_line_hits.get(path) {
+                                ui.indent(path, |ui| {
+                                    for hit in hits {
+                                        let text = format!("{}: {}", hit.line_number, hit.snippet);
+                                        let prefix_len = text.len() - hit.snippet.len();
+                                        let highlights: Vec<_> = matcher
+                                            .as_deref()
+                                            .map(|matcher| matcher.highlight_ranges(&hit.snippet))
+                                            .unwrap_or_default()
+                                            .into_iter()
+                                            .map(|range| range.start + prefix_len..range.end + prefix_len)
+                                            .collect();
+                                        let label = highlighted_text(ui, &text, &highlights, ui.visuals().hyperlink_color);
+                                        if ui.link(label).clicked() {
+                                            open_at_line(path, hit.line_number);
+                                        }
+                                    }
+                                });
+                            }
+                        }
+
+                        // Only a page of results is rendered at a time to keep the UI responsive
+                        if hidden_results > 0 {
+                            ui.add_space(10.0);
+                            ui.horizontal(|ui| {
+                                ui.label(format!(
+                                    "Showing {} of {} matches",
+                                    visible_results_count - hidden_results,
+                                    visible_results_count
+                                ));
+                                if ui.button(format!("Load {} more", hidden_results.min(RESULTS_PAGE_SIZE))).clicked() {
+                                    self.results_display_limit += RESULTS_PAGE_SIZE;
+                                }
+                            });
+                        } else if let Some(spill) = self.search_spill.as_ref().filter(|spill| spill.unread() > 0) {
+                            // Everything in memory is shown, continue with the results spilled to disk
+                            ui.add_space(10.0);
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{} more matches were set aside to save memory", spill.unread()));
+                                if ui.button(format!("Load {} more", spill.unread().min(RESULTS_PAGE_SIZE))).clicked() {
+                                    load_spilled_page = true;
+                                }
+                            });
+                        }
+                    }
+                    if load_spilled_page && let Some(spill) = &mut self.search_spill {
+                        match spill.read_page(RESULTS_PAGE_SIZE) {
+                            Ok(page) => {
+                                let mut paths = Vec::with_capacity(page.len());
+                                for (path, hits, metadata) in page {
+                                    if !hits.is_empty() {
+                                        self.search_line_hits.insert(path.clone(), hits);
+                                    }
+                                    self.search_result_metadata.insert(path.clone(), metadata);
+                                    paths.push(path);
+                                }
+                                match self.results_sort {
+                                    Some(key) => search::merge_results(
+                                        &mut results,
+                                        paths,
+                                        key,
+                                        self.results_sort_descending,
+                                        &self.search_result_metadata,
+                                    ),
+                                    None => results.extend(paths),
+                                }
+                                self.results_display_limit += RESULTS_PAGE_SIZE;
+                            }
+                            Err(e) => eprintln!("Error while reading spilled search results: {}", e),
+                        }
+                    }
+                    self.recursive_search_results = Some(results);
+                    self.filtered_results = filtered_results;
+                    if load_spilled_page {
+                        self.apply_results_filter();
+                    }
+                } else {
+                    // Normal view of entries in current directory
+                    // Taken out while rendering and put back below; changes to the listing are deferred
+                    let entries = std::mem::take(&mut self.entries);
+                    let filtered_entries = self.filtered_entries.take();
+                    let entries_to_display = filtered_entries.as_deref().unwrap_or(&entries);
+
+                    // Column headers of the details view
+                    if self.view_mode == ViewMode::Details {
+                        // Click a header to sort by it, click again to reverse.
+                        // Drag it onto another header to move it, right-click to choose the columns.
+                        ui.horizontal(|ui| {
+                            let columns: Vec<SortColumn> =
+                                std::iter::once(SortColumn::Name).chain(self.settings.columns.iter().copied()).collect();
+                            let mut header_rects = Vec::new();
+                            for column in columns {
+                                let sorted = (self.sort_order.column == column).then_some(self.sort_order.descending);
+                                let response = view::header_cell(ui, column.width(), column.label(), sorted, column.right_aligned());
+                                if response.clicked() {
+                                    should_sort_entries_by = Some(column);
+                                }
+                                if response.drag_started() && column != SortColumn::Name {
+                                    self.dragged_column = Some(column);
+                                }
+                                header_rects.push((column, response.rect));
+                                response.context_menu(|ui| {
+                                    for column in SortColumn::OPTIONAL {
+                                        let mut visible = self.settings.columns.contains(&column);
+                                        if ui.checkbox(&mut visible, column.label()).changed() {
+                                            should_toggle_column = Some(column);
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                            }
+
+                            if let Some(dragged) = self.dragged_column {
+                                ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+                                if ui.input(|i| i.pointer.any_released()) {
+                                    let pointer = ui.input(|i| i.pointer.interact_pos());
+                                    let target = header_rects
+                                        .iter()
+                                        .find(|(column, rect)| *column != SortColumn::Name && pointer.is_some_and(|p| rect.contains(p)));
+                                    if let Some((target, _)) = target
+                                        && *target != dragged
+                                    {
+                                        should_move_column = Some((dragged, *target));
+                                    }
+                                    self.dragged_column = None;
+                                }
+                            }
+                        });
+                        ui.separator();
+                    }
+
+                    // The grid view wraps entries into rows, the other views stack them
+                    let layout = match self.view_mode {
+                        ViewMode::Grid => egui::Layout::left_to_right(egui::Align::TOP).with_main_wrap(true),
+                        ViewMode::Details | ViewMode::List => egui::Layout::top_down(egui::Align::Min),
+                    };
+                    // Grouped listings get a collapsible header per group
+                    let groups = view::group_entries(entries_to_display, &self.entry_details, self.group_by);
+                    for (title, group_entries) in &groups {
+                        if let Some(title) = *title {
+                            let collapsed = self.collapsed_groups.contains(title);
+                            let arrow = if collapsed { "⏵" } else { "⏷" };
+                            let header = egui::RichText::new(format!("{} {} ({})", arrow, title, group_entries.len())).strong();
+                            if ui.selectable_label(false, header).clicked() && !self.collapsed_groups.remove(title) {
+                                self.collapsed_groups.insert(title);
+                            }
+                            if collapsed {
+                                continue;
+                            }
+                        }
+                        ui.with_layout(layout, |ui| {
+                            for entry in group_entries {
+                                let entry_name: OsString = (*entry).clone();
+                                let display_name = entry_name.to_string_lossy().to_string();
+                                let is_dir = self.entry_details.get(&entry_name).is_some_and(EntryDetails::is_dir);
+                                let is_package = self.entry_details.get(&entry_name).is_some_and(EntryDetails::is_package);
+
+                                // --- Rename mode ---
+                                if self.rename_mode.as_ref() == Some(&entry_name) {
+                                    ui.horizontal(|ui| {
+                                        let text_edit = ui.text_edit_singleline(&mut self.rename_input);
+                                        if text_edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                                            if !self.rename_input.is_empty() {
+                                                should_rename_entry = Some((entry_name.clone(), self.rename_input.clone()));
+                                            }
+                                            should_clear_rename_mode = true; // Delayed reset
+                                        }
+                                        if ui.button("Cancel").clicked() {
+                                            should_clear_rename_mode = true; // Delayed reset
+                                        }
+                                    });
+                                }
+                                // --- Normal entry ---
+                                else {
+                                    let is_selected = self.selected_entries.contains(&entry_name);
+                                    let entry_tags = self.tags.get(&self.current_dir.join(&entry_name)).cloned();
+                                    let label_color = entry_tags.as_ref().and_then(|t| t.label).map(ColorLabel::color);
+                                    let link_target = self.entry_details.get(&entry_name).and_then(|d| d.link_target.clone());
+                                    let link_target_path = link_target.as_ref().map(|target| self.current_dir.join(target));
+                                    let link = link_target.as_deref().zip(link_target_path.as_ref().map(|p| p.exists()));
+                                    let git_file_status =
+                                        self.git_status.as_ref().and_then(|status| status.entries.get(&entry_name).copied());
+                                    let cloud_file_status =
+                                        self.cloud_status.as_ref().and_then(|status| status.entries.get(&entry_name).copied());
+                                    // Changes in git matter more than the sync state
+                                    let badge = git_file_status
+                                        .map(|status| status.badge())
+                                        .or_else(|| cloud_file_status.map(|status| status.badge()));
+                                    let label = view::entry_label(ui, &display_name, label_color, link, badge);
+                                    let row_id = ui.id().with(("entry_row", &entry_name));
+                                    let response = match self.view_mode {
+                                        ViewMode::Details => {
+                                            view::entry_row(ui, row_id, is_selected, |ui| {
+                                                view::column_cell(ui, view::NAME_COLUMN_WIDTH, label, false);
+                                                let details = self.entry_details.get(&entry_name);
+                                                for &column in &self.settings.columns {
+                                                    if column == SortColumn::Size
+                                                        && (is_dir || is_package)
+                                                        && self.folder_sizes.is_pending(&self.current_dir.join(&entry_name))
+                                                    {
+                                                        view::spinner_cell(ui, column.width(), column.right_aligned());
+                                                        continue;
+                                                    }
+                                                    let text = details.map(|d| d.column_text(column, &entry_name)).unwrap_or_default();
+                                                    view::column_cell(ui, column.width(), text, column.right_aligned());
+                                                }
+                                            })
+                                        }
+                                        ViewMode::List => view::entry_row(ui, row_id, is_selected, |ui| {
+                                            ui.add(egui::Label::new(label).wrap(false));
+                                        }),
+                                        ViewMode::Grid => {
+                                            let icon = view::entry_icon(&display_name, is_dir);
+                                            let path = self.current_dir.join(&entry_name);
+                                            let thumbnails = &mut self.thumbnails;
+                                            let response = view::grid_cell(ui, self.grid_cell_size, icon, &display_name, is_selected, || {
+                                                if is_dir { None } else { thumbnails.get(ctx, &path) }
+                                            });
+                                            if let Some(color) = label_color {
+                                                ui.painter().circle_filled(response.rect.left_top() + egui::vec2(8.0, 8.0), 5.0, color);
+                                            }
+                                            if let Some((badge, color)) = badge {
+                                                ui.painter().text(
+                                                    response.rect.left_top() + egui::vec2(4.0, self.grid_cell_size - 4.0),
+                                                    egui::Align2::LEFT_BOTTOM,
+                                                    badge,
+                                                    egui::TextStyle::Body.resolve(ui.style()),
+                                                    color,
+                                                );
+                                            }
+                                            if link_target.is_some() {
+                                                ui.painter().text(
+                                                    response.rect.right_top() + egui::vec2(-4.0, 4.0),
+                                                    egui::Align2::RIGHT_TOP,
+                                                    view::LINK_ICON,
+                                                    egui::TextStyle::Body.resolve(ui.style()),
+                                                    ui.visuals().text_color(),
+                                                );
+                                            }
+                                            response
+                                        }
+                                    };
+                                    // Tooltip: full path, size and date, plus tags and git status
+                                    let response = response.on_hover_ui(|ui| {
+                                        let details = self.entry_details.get(&entry_name);
+                                        let mut lines = vec![view::path_tooltip(
+                                            &self.current_dir.join(&entry_name),
+                                            details.and_then(|d| d.size),
+                                            details.and_then(|d| d.modified),
+                                        )];
+                                        if let Some(entry_tags) = entry_tags.filter(|t| !t.tags.is_empty()) {
+                                            lines.push(format!("Tags: {}", entry_tags.tags.join(", ")));
+                                        }
+                                        if let Some(status) = git_file_status {
+                                            lines.push(format!("Git: {}", status.label()));
+                                        }
+                                        if let Some(status) = cloud_file_status {
+                                            lines.push(format!("Cloud: {}", status.label()));
+                                        }
+                                        ui.label(lines.join("\n"));
+                                    });
+                                    if !self.entry_details.get(&entry_name).is_some_and(|d| d.loaded)
+                                        && ui.is_rect_visible(response.rect)
+                                    {
+                                        shown_without_metadata.push(self.current_dir.join(&entry_name));
+                                    }
+                                    if self.scroll_to_entry.as_ref() == Some(&entry_name) {
+                                        response.scroll_to_me(Some(egui::Align::Center));
+                                        self.scroll_to_entry = None;
+                                    }
+
+                                    // Click selects, Ctrl+click adds to / removes from the selection
+                                    if response.clicked() {
+                                        if ui.input(|i| i.modifiers.command) {
+                                            if !self.selected_entries.remove(&entry_name) {
+                                                self.selected_entries.insert(entry_name.clone());
+                                            }
+                                        } else {
+                                            self.selected_entries.clear();
+                                            self.selected_entries.insert(entry_name.clone());
+                                        }
+                                    }
+
+                                    // Dragging an entry hands it over to other applications
+                                    if response.interact(egui::Sense::drag()).drag_started() {
+                                        should_drag_out_path = Some(self.current_dir.join(&entry_name));
+                                    }
+
+                                    // Double click: navigate folder, open file
+                                    if response.double_clicked() {
+                                        if is_dir {
+                                            should_navigate_to_path = Some(self.current_dir.join(&entry_name));
+                                        } else {
+                                            should_open_entry = Some(entry_name.clone());
+                                        }
+                                    }
+
+                                    // Right-click context menu
+                                    response.context_menu(|ui| {
+                                        let targets = self.action_targets(&entry_name);
+                                        // Multi-selection launches the selected files; folders can't all be browsed
+                                        let files: Vec<PathBuf> = targets
+                                            .iter()
+                                            .map(|name| self.current_dir.join(name))
+                                            .filter(|path| {
+                                                let name = path.file_name().unwrap_or_default();
+                                                !self.entry_details.get(name).is_some_and(EntryDetails::is_dir)
+                                                    || fileops::is_package(path)
+                                            })
+                                            .collect();
+                                        let open_label = if targets.len() > 1 {
+                                            format!("Open {} files", files.len())
+                                        } else {
+                                            "Open".to_string()
+                                        };
+                                        let can_open = targets.len() == 1 || !files.is_empty();
+                                        if ui.add_enabled(can_open, egui::Button::new(open_label)).clicked() {
+                                            if targets.len() > 1 {
+                                                self.open_files(files);
+                                            } else if is_dir {
+                                                should_navigate_to_path = Some(self.current_dir.join(&entry_name));
+                                            } else {
+                                                should_open_entry = Some(entry_name.clone());
+                                            }
+                                            ui.close_menu();
+                                        }
+
+                                        if !is_dir && targets.len() == 1 {
+                                            let path = self.current_dir.join(&entry_name);
+                                            ui.menu_button("Open with", |ui| self.show_open_with_menu(ui, &path));
+                                        }
+
+                                        if !is_dir
+                                            && targets.len() == 1
+                                            && checksum::is_checksum_list(&self.current_dir.join(&entry_name))
+                                            && ui.button("Verify checksums").clicked()
+                                        {
+                                            let list = self.current_dir.join(&entry_name);
+                                            self.checksum_verifier = Some(ChecksumVerifier::verify(&list, ctx));
+                                            ui.close_menu();
+                                        }
+
+                                        let files: Vec<PathBuf> = targets
+                                            .iter()
+                                            .map(|name| self.current_dir.join(name))
+                                            .filter(|path| path.is_file())
+                                            .collect();
+                                        if let [left, right] = files.as_slice()
+                                            && targets.len() == 2
+                                        {
+                                            match diff_tool(&self.settings.diff_tool) {
+                                                Some((command, name)) => {
+                                                    if ui.button(format!("Compare with {}", name)).clicked() {
+                                                        compare_files(&command, left, right);
+                                                        ui.close_menu();
+                                                    }
+                                                }
+                                                None => {
+                                                    let button = egui::Button::new("Compare with diff tool");
+                                                    ui.add_enabled(false, button)
+                                                        .on_disabled_hover_text("No diff program found, set one in the settings");
+                                                }
+                                            }
+                                        }
+
+                                        if is_package && ui.button("Show package contents").clicked() {
+                                            should_navigate_to_path = Some(self.current_dir.join(&entry_name));
+                                            ui.close_menu();
+                                        }
+
+                                        if is_dir && ui.button("Open terminal here").clicked() {
+                                            open_terminal(&self.current_dir.join(&entry_name), &self.settings.terminal);
+                                            ui.close_menu();
+                                        }
+
+                                        if let Some(target) = &link_target_path
+                                            && ui.add_enabled(target.exists(), egui::Button::new("Go to target")).clicked()
+                                        {
+                                            // Folders are opened, files are shown in their folder
+                                            if target.is_dir() {
+                                                should_navigate_to_path = Some(target.clone());
+                                            } else {
+                                                should_reveal_path = Some(target.clone());
+                                            }
+                                            ui.close_menu();
+                                        }
+
+                                        if ui
+                                            .add_enabled(self.trash_available, egui::Button::new("🗑 Move to trash"))
+                                            .on_disabled_hover_text("This drive has no trash, entries can only be deleted permanently")
+                                            .clicked()
+                                        {
+                                            should_trash_entries = Some(self.action_targets(&entry_name));
+                                            ui.close_menu();
+                                        }
+                                        if ui.button("Delete permanently").clicked() {
+                                            should_delete_entries = Some(self.action_targets(&entry_name));
+                                            ui.close_menu();
+                                        }
+
+                                        if ui.button("Cut").clicked() {
+                                            self.clipboard_entries = self
+                                                .action_targets(&entry_name)
+                                                .iter()
+                                                .map(|name| self.current_dir.join(name))
+                                                .collect();
+                                            self.clipboard_mode = ClipboardMode::Cut;
+                                            ui.close_menu();
+                                        }
+
+                                        if ui.button("Copy").clicked() {
+                                            self.clipboard_entries = self
+                                                .action_targets(&entry_name)
+                                                .iter()
+                                                .map(|name| self.current_dir.join(name))
+                                                .collect();
+                                            self.clipboard_mode = ClipboardMode::Copy;
+                                            ui.close_menu();
+                                        }
+
+                                        if ui.button("Move to...").clicked() {
+                                            self.folder_picker_entries = targets.iter().map(|name| self.current_dir.join(name)).collect();
+                                            self.folder_picker_mode = ClipboardMode::Cut;
+                                            self.folder_picker = Some(FolderPicker::new(&self.current_dir));
+                                            ui.close_menu();
+                                        }
+
+                                        if ui.button("Copy to...").clicked() {
+                                            self.folder_picker_entries = targets.iter().map(|name| self.current_dir.join(name)).collect();
+                                            self.folder_picker_mode = ClipboardMode::Copy;
+                                            self.folder_picker = Some(FolderPicker::new(&self.current_dir));
+                                            ui.close_menu();
+                                        }
+
+                                        if is_dir
+                                            && !self.clipboard_entries.is_empty()
+                                            && self.can_paste()
+                                            && ui.button("Paste into folder").clicked()
+                                        {
+                                            should_paste_into = Some(self.current_dir.join(&entry_name));
+                                            ui.close_menu();
+                                        }
+
+                                        // Attribute toggles show the state of the clicked entry
+                                        // and apply the new value to the whole selection
+                                        let mut read_only = fileops::is_readonly(&self.current_dir.join(&entry_name));
+                                        if ui.checkbox(&mut read_only, "Read-only").changed() {
+                                            for name in &targets {
+                                                let path = self.current_dir.join(name);
+                                                if let Err(e) = fileops::set_readonly(&path, read_only) {
+                                                    eprintln!("Error while changing read-only flag of {:?}: {}", path, e);
+                                                }
+                                            }
+                                        }
+
+                                        #[cfg(windows)]
+                                        {
+                                            let mut hidden = fileops::is_hidden(&self.current_dir.join(&entry_name));
+                                            if ui.checkbox(&mut hidden, "Hidden").changed() {
+                                                for name in &targets {
+                                                    let path = self.current_dir.join(name);
+                                                    if let Err(e) = fileops::set_hidden(&path, hidden) {
+                                                        eprintln!("Error while changing hidden flag of {:?}: {}", path, e);
+                                                    }
+                                                }
+                                            }
+                                        }
+
+                                        if cloud::can_change_pin(self.cloud_status.as_ref()) {
+                                            let free_up_space = ui.button("☁ Free up space").clicked();
+                                            let keep = ui.button("📌 Always keep on this device").clicked();
+                                            if free_up_space || keep {
+                                                for name in &targets {
+                                                    let path = self.current_dir.join(name);
+                                                    if let Err(e) = cloud::set_pinned(&path, keep) {
+                                                        eprintln!("Error while changing sync state of {:?}: {}", path, e);
+                                                    }
+                                                }
+                                                self.load_cloud_status();
+                                                ui.close_menu();
+                                            }
+                                        }
+
+                                        let folders: Vec<&OsString> =
+                                            targets.iter().filter(|name| self.current_dir.join(name).is_dir()).collect();
+                                        if let [left, right] = folders.as_slice()
+                                            && ui.button("Compare folders...").clicked()
+                                        {
+                                            let (left, right) = (self.current_dir.join(left), self.current_dir.join(right));
+                                            self.folder_comparison = Some(FolderComparison::new(&left, Some(&right), ctx));
+                                            ui.close_menu();
+                                        }
+
+                                        if !folders.is_empty() && ui.button("Calculate size").clicked() {
+                                            for name in folders {
+                                                self.folder_sizes.calculate(self.current_dir.join(name), false);
+                                            }
+                                            ui.close_menu();
+                                        }
+
+                                        if ui.button("Properties...").clicked() {
+                                            self.properties = Some(Properties::open(&self.current_dir.join(&entry_name)));
+                                            ui.close_menu();
+                                        }
+
+                                        if shell_menu::is_available() && self.settings.shell_menu {
+                                            ui.separator();
+                                            if ui.button("Show more options").on_hover_text("Explorer's context menu").clicked() {
+                                                should_show_shell_menu =
+                                                    Some(targets.iter().map(|name| self.current_dir.join(name)).collect());
+                                                ui.close_menu();
+                                            }
+                                        }
+
+                                        // Label and tags, applied to the whole selection
+                                        let clicked_path = self.current_dir.join(&entry_name);
+                                        let clicked_tags = self.tags.get(&clicked_path).cloned().unwrap_or_default();
+                                        ui.menu_button("Label", |ui| {
+                                            let mut label = clicked_tags.label;
+                                            ui.radio_value(&mut label, None, "None");
+                                            for color_label in ColorLabel::ALL {
+                                                let text = egui::RichText::new(format!("● {}", color_label.label())).color(color_label.color());
+                                                ui.radio_value(&mut label, Some(color_label), text);
+                                            }
+                                            if label != clicked_tags.label {
+                                                for name in &targets {
+                                                    self.tags.set_label(&self.current_dir.join(name), label);
+                                                }
+                                                self.save_tags();
+                                                should_apply_directory_filter = true;
+                                                ui.close_menu();
+                                            }
+                                        });
+                                        ui.menu_button("Tags", |ui| {
+                                            let mut changed = false;
+                                            let all_tags: Vec<String> = self.tags.all_tags().into_iter().map(str::to_string).collect();
+                                            for tag in all_tags {
+                                                let mut has_tag = clicked_tags.tags.contains(&tag);
+                                                if ui.checkbox(&mut has_tag, &tag).changed() {
+                                                    for name in &targets {
+                                                        let path = self.current_dir.join(name);
+                                                        if has_tag {
+                                                            self.tags.add_tag(&path, &tag);
+                                                        } else {
+                                                            self.tags.remove_tag(&path, &tag);
+                                                        }
+                                                    }
+                                                    changed = true;
+                                                }
+                                            }
+                                            ui.horizontal(|ui| {
+                                                let response = ui.add(
+                                                    egui::TextEdit::singleline(&mut self.new_tag_input).hint_text("New tag").desired_width(120.0),
+                                                );
+                                                let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                                                if (ui.button("Add").clicked() || submitted) && !self.new_tag_input.trim().is_empty() {
+                                                    for name in &targets {
+                                                        self.tags.add_tag(&self.current_dir.join(name), &self.new_tag_input);
+                                                    }
+                                                    self.new_tag_input.clear();
+                                                    changed = true;
+                                                }
+                                            });
+                                            if changed {
+                                                self.save_tags();
+                                                should_apply_directory_filter = true;
+                                            }
+                                        });
+
+                                        if ui.button("Rename").clicked() {
+                                            self.rename_mode = Some(entry_name.clone());
+                                            self.rename_input = display_name.clone();
+                                            ui.close_menu();
+                                        }
+
+                                        if ui.button("Compress to ZIP...").clicked() {
+                                            self.zip_sources = self.action_targets(&entry_name);
+                                            self.zip_archive_name = if self.zip_sources.len() == 1 {
+                                                format!("{}.zip", display_name)
+                                            } else {
+                                                "Archive.zip".to_string()
+                                            };
+                                            self.show_zip_popup = true;
+                                            ui.close_menu();
+                                        }
+
+                                        if ui.button("Search").clicked() {
+                                            self.show_search_popup = true; // Show search popup
+                                            self.search_query.clear(); // Clear search field when opening
+                                            self.clear_search_results(); // Clear old search results
+                                            ui.close_menu();
+                                        }
+                                    });
+                                }
+                            }
+                        });
+                    }
+                    self.entries = entries;
+                    self.filtered_entries = filtered_entries;
+                }
+            });
+        });
+
+        // --- Render search popup ---
+
+        if self.show_search_popup {
+            egui::Window::new("What do you want to search?")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let response = ui.text_edit_singleline(&mut self.search_query);
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut self.search_mode, SearchMode::Substring, "Name contains");
+                        ui.radio_value(&mut self.search_mode, SearchMode::Glob, "Glob")
+                            .on_hover_text("e.g. *.rs, report_??.pdf or src/**/test_*");
+                        ui.radio_value(&mut self.search_mode, SearchMode::Regex, "Regex");
+                        ui.radio_value(&mut self.search_mode, SearchMode::Fuzzy, "Fuzzy")
+                            .on_hover_text("Characters in order, e.g. \"prjplan\" finds Project_Plan_2024.xlsx");
+                        ui.radio_value(&mut self.search_mode, SearchMode::Content, "File contents");
+                    });
+
+                    // Where to search: current folder plus extra folders, or every drive
+                    ui.checkbox(&mut self.search_all_drives, "All drives");
+                    ui.add_enabled_ui(!self.search_all_drives, |ui| {
+                        ui.label(format!("Search in: {}", self.current_dir.display()));
+                        let mut root_to_remove = None;
+                        for (i, root) in self.search_extra_roots.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("✖").clicked() {
+                                    root_to_remove = Some(i);
+                                }
+                                ui.label(format!("and: {}", root.display()));
+                            });
+                        }
+                        if let Some(i) = root_to_remove {
+                            self.search_extra_roots.remove(i);
+                        }
+                        if let Some(picker) = &mut self.search_root_picker {
+                            let mut picked_root = None;
+                            let mut should_close_root_picker = false;
+                            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                                picker.show(ui);
+                            });
+                            ui.horizontal(|ui| {
+                                if ui.button(format!("Add {}", picker.selected.display())).clicked() {
+                                    picked_root = Some(picker.selected.clone());
+                                } else if ui.button("Cancel").clicked() {
+                                    should_close_root_picker = true;
+                                }
+                            });
+                            if let Some(root) = picked_root {
+                                if !self.search_extra_roots.contains(&root) {
+                                    self.search_extra_roots.push(root);
+                                }
+                                should_close_root_picker = true;
+                            }
+                            if should_close_root_picker {
+                                self.search_root_picker = None;
+                            }
+                        } else if ui.button("Add folder...").clicked() {
+                            self.search_root_picker = Some(FolderPicker::new(&self.current_dir));
+                        }
+                    });
+
+                    ui.add_enabled_ui(self.search_mode != SearchMode::Content, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Find:");
+                            ui.radio_value(&mut self.search_entry_kind, EntryKind::All, "All");
+                            ui.radio_value(&mut self.search_entry_kind, EntryKind::Files, "Files");
+                            ui.radio_value(&mut self.search_entry_kind, EntryKind::Folders, "Folders");
+                        });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Type:");
+                        let selected_label = self.search_content_type.map_or("Any", ContentType::label);
+                        egui::ComboBox::from_id_source("search_content_type")
+                            .selected_text(selected_label)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.search_content_type, None, "Any");
+                                for content_type in ContentType::ALL {
+                                    ui.selectable_value(&mut self.search_content_type, Some(content_type), content_type.label());
+                                }
+                            })
+                            .response
+                            .on_hover_text("Detected from the file contents, not the extension");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Depth:");
+                        ui.radio_value(&mut self.search_max_depth, Some(1), "This folder only");
+                        ui.radio_value(&mut self.search_max_depth, Some(2), "2 levels");
+                        ui.radio_value(&mut self.search_max_depth, None, "Unlimited");
+                    });
+                    ui.checkbox(&mut self.search_respect_ignore_files, "Respect .gitignore / .ignore");
+                    ui.horizontal(|ui| {
+                        ui.label("Exclude:");
+                        ui.text_edit_singleline(&mut self.search_exclusions)
+                            .on_hover_text("Comma separated names or globs, e.g. node_modules, *.tmp");
+                    });
+
+                    let index_usable = self.search_mode != SearchMode::Content
+                        && !self.search_respect_ignore_files
+                        && self.search_roots().iter().any(|root| self.file_index.covers(root));
+                    ui.add_enabled(index_usable, egui::Checkbox::new(&mut self.use_index, "Use index"))
+                        .on_disabled_hover_text("Not available: folder not indexed, content search, or ignore files respected");
+                    let everything_usable = everything::is_available()
+                        && self.search_mode == SearchMode::Substring
+                        && !self.search_respect_ignore_files;
+                    ui.add_enabled(everything_usable, egui::Checkbox::new(&mut self.use_everything, "Use Everything"))
+                        .on_hover_text("Answer name searches machine-wide with voidtools Everything, if it is running")
+                        .on_disabled_hover_text("Needs Everything64.dll (Everything SDK) and a \"Name contains\" search");
+
+                    // Inline feedback for invalid glob / regex patterns
+                    let pattern_error = if self.search_mode == SearchMode::Substring {
+                        None
+                    } else {
+                        SearchMatcher::new(&self.search_query, self.search_mode).err()
+                    }
+                    .or_else(|| {
+                        SearchOptions::new(false, &self.search_exclusions)
+                            .err()
+                            .map(|e| format!("exclusions: {}", e))
+                    });
+                    if let Some(error) = &pattern_error {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("Invalid pattern: {}", error));
+                    }
+                    let can_search = !self.is_searching && pattern_error.is_none();
+
+                    ui.horizontal(|ui| {
+                        // "Search" button disabled if already searching or the pattern is invalid
+                        ui.add_enabled_ui(can_search, |ui| {
+                            if ui.button("Search").clicked() {
+                                self.execute_search(ctx.clone());
+                                should_close_search_popup = true; // Close popup after starting search
+                            }
+                        });
+                        if ui.button("Cancel").clicked() {
+                            self.clear_search_results(); // Clear results on cancel
+                            self.cancel_search(); // Stop search
+                            should_close_search_popup = true;
+                        }
+                    });
+
+                    // Trigger search on enter key if text box focused and not already searching
+                    if can_search && response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        self.execute_search(ctx.clone());
+                        should_close_search_popup = true; // Close popup after starting search
+                    }
+                });
+        }
+
+        // --- Render image viewer ---
+
+        if let Some(viewer) = &mut self.image_viewer
+            && !viewer.show(ctx)
+        {
+            self.image_viewer = None;
+        }
+
+        // --- Render text viewer ---
+
+        if let Some(viewer) = &mut self.text_viewer
+            && !viewer.show(ctx)
+        {
+            self.text_viewer = None;
+        }
+
+        // --- Render disk image browser ---
+
+        if let Some(browser) = &mut self.image_browser {
+            match browser.show(ctx, &self.current_dir) {
+                Some(ImageAction::Close) => self.image_browser = None,
+                Some(ImageAction::Extract(mut extraction)) => {
+                    let existing = extraction.existing_targets();
+                    if !self.settings.confirm_overwrite {
+                        extraction.set_on_conflict(ConflictChoice::Replace);
+                    }
+                    if self.settings.confirm_overwrite && !existing.is_empty() {
+                        self.pending_replace = Some((existing, ReplaceOperation::Extract(extraction)));
+                    } else {
+                        self.jobs.spawn(JobKind::Extract, ctx, move |job| extraction.run(job));
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // --- Render device browser ---
+
+        if let Some(browser) = &mut self.device_browser {
+            match browser.show(ctx, &self.current_dir) {
+                Some(DeviceAction::Close) => self.device_browser = None,
+                Some(DeviceAction::Copy(mut copy)) => {
+                    let existing = copy.existing_targets();
+                    if !self.settings.confirm_overwrite {
+                        copy.set_on_conflict(ConflictChoice::Replace);
+                    }
+                    if self.settings.confirm_overwrite && !existing.is_empty() {
+                        self.pending_replace = Some((existing, ReplaceOperation::DeviceCopy(copy)));
+                    } else {
+                        self.jobs.spawn(JobKind::Transfer, ctx, move |job| copy.run(job));
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // --- Render disk usage analyzer ---
+
+        if let Some(analyzer) = &mut self.disk_usage {
+            match analyzer.show(ctx) {
+                Some(UsageAction::Close) => self.disk_usage = None,
+                Some(UsageAction::Navigate(path)) => should_navigate_to_path = Some(path),
+                Some(UsageAction::Reveal(path)) => should_reveal_path = Some(path),
+                None => {}
+            }
+        }
+
+        // --- Render folder comparison ---
+
+        if let Some(comparison) = &mut self.folder_comparison {
+            match comparison.show(ctx) {
+                Some(CompareAction::Close) => self.folder_comparison = None,
+                Some(CompareAction::Copy(pairs)) => self.copy_pairs(pairs, ctx),
+                None => {}
+            }
+        }
+
+        // --- Render folder synchronization ---
+
+        if let Some(sync) = &mut self.folder_sync {
+            match sync.show(ctx) {
+                Some(SyncAction::Close) => self.folder_sync = None,
+                Some(SyncAction::Run(job)) => {
+                    self.jobs.spawn(JobKind::Sync, ctx, move |context| job.run(context));
+                }
+                None => {}
+            }
+        }
+
+        // --- Render remote storage browser ---
+
+        if let Some(browser) = &mut self.remote_browser {
+            let selection: Vec<PathBuf> = self.selected_entries.iter().map(|name| self.current_dir.join(name)).collect();
+            match browser.show(ctx, &self.current_dir, &selection) {
+                Some(RemoteAction::Close) => self.remote_browser = None,
+                Some(RemoteAction::Transfer(transfer)) => {
+                    self.jobs.spawn(JobKind::Transfer, ctx, move |job| transfer.run(job));
+                }
+                None => {}
+            }
+        }
+
+        // --- Render properties dialog ---
+
+        if let Some(properties) = &mut self.properties
+            && !properties.show(ctx)
+        {
+            self.properties = None;
+        }
+
+        // --- Render checksum verifier ---
+
+        if let Some(verifier) = &mut self.checksum_verifier
+            && !verifier.show(ctx)
+        {
+            self.checksum_verifier = None;
+        }
+
+        // --- Render program chooser ("Open with" > "Choose another program...") ---
+
+        if let Some(chooser) = &mut self.program_chooser
+            && let Some(action) = chooser.show(ctx)
+        {
+            if let ChooserAction::Open { path, command, remember } = action {
+                self.open_with_program(&path, command, remember);
+            }
+            self.program_chooser = None;
+        }
+
+        // --- Render recent files window ---
+
+        if self.show_recent_files {
+            let mut is_open = true;
+            let mut should_clear_recent_files = false;
+            egui::Window::new("Recent files").open(&mut is_open).collapsible(false).show(ctx, |ui| {
+                if self.places.recent_files.is_empty() {
+                    ui.label("Files opened through FileFox are listed here.");
+                    return;
+                }
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("recent_files").striped(true).show(ui, |ui| {
+                        for (path, opened) in &self.places.recent_files {
+                            // Files that were moved or deleted since are shown but can't be opened
+                            let exists = path.exists();
+                            let name = egui::Button::new(format!("📄 {}", places::folder_label(path))).frame(false);
+                            let button = ui
+                                .add_enabled(exists, name)
+                                .on_hover_text(path.display().to_string())
+                                .on_disabled_hover_text(format!("{} no longer exists", path.display()));
+                            if button.clicked() {
+                                should_open_path = Some(path.clone());
+                            }
+                            let reveal_button = egui::Button::new("📁").frame(false);
+                            if ui.add_enabled(exists, reveal_button).on_hover_text("Show in folder").clicked() {
+                                should_reveal_path = Some(path.clone());
+                            }
+                            ui.weak(view::format_time(*opened));
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.separator();
+                if ui.button("Clear").clicked() {
+                    should_clear_recent_files = true;
+                }
+            });
+            if should_clear_recent_files {
+                self.places.recent_files.clear();
+                self.places_changed();
+            }
+            self.show_recent_files = is_open;
+        }
 
-                                if ui.button("Search").clicked() {
-                                    self.show_search_popup = true; // Show search popup
-                                    self.search_query.clear(); // Clear search field when opening
-                                    self.recursive_search_results = None; // Clear old search results
-                                    ui.close_menu();
+        // --- Render settings window ---
+
+        if self.show_settings_window {
+            let mut is_open = true;
+            let mut settings_changed = false;
+            egui::Window::new("Settings")
+                .open(&mut is_open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Theme:");
+                        settings_changed |= ui.radio_value(&mut self.settings.theme, Theme::Light, "Light").changed();
+                        settings_changed |= ui.radio_value(&mut self.settings.theme, Theme::Dark, "Dark").changed();
+                        settings_changed |= ui.radio_value(&mut self.settings.theme, Theme::System, "Follow system").changed();
+                    });
+                    let zoom_slider = egui::Slider::new(&mut self.settings.zoom, settings::MIN_ZOOM..=settings::MAX_ZOOM)
+                        .text("Zoom")
+                        .custom_formatter(|zoom, _| format!("{:.0} %", zoom * 100.0));
+                    // Save once the slider is released, not on every step of the drag
+                    let zoom_shortcuts: Vec<String> = [Action::ZoomIn, Action::ZoomOut, Action::ResetZoom]
+                        .into_iter()
+                        .map(|action| self.settings.keymap.describe(ctx, action))
+                        .filter(|shortcut| !shortcut.is_empty())
+                        .collect();
+                    let zoom_response = ui.add(zoom_slider).on_hover_text(zoom_shortcuts.join(" / "));
+                    settings_changed |= zoom_response.drag_released() || (zoom_response.changed() && !zoom_response.dragged());
+                    if ui.checkbox(&mut self.settings.show_hidden, "Show hidden files").changed() {
+                        settings_changed = true;
+                        self.apply_directory_filter();
+                    }
+                    ui.collapsing("Confirmations", |ui| {
+                        settings_changed |=
+                            ui.checkbox(&mut self.settings.confirm_delete, "Ask before deleting permanently").changed();
+                        settings_changed |=
+                            ui.checkbox(&mut self.settings.confirm_trash, "Ask before moving to the trash").changed();
+                        settings_changed |= ui
+                            .checkbox(&mut self.settings.confirm_overwrite, "Ask before replacing existing files")
+                            .on_hover_text(
+                                "When moving, renaming, extracting, compressing or exporting onto existing files. \
+                                 Copies never replace anything, they get a new name.",
+                            )
+                            .changed();
+                        ui.horizontal(|ui| {
+                            let open_many_label = "Ask before opening more than";
+                            settings_changed |=
+                                ui.checkbox(&mut self.settings.confirm_open_many, open_many_label).changed();
+                            let threshold =
+                                egui::DragValue::new(&mut self.settings.open_confirm_threshold).clamp_range(1..=1000);
+                            let threshold_response = ui.add_enabled(self.settings.confirm_open_many, threshold);
+                            settings_changed |= threshold_response.drag_released()
+                                || (threshold_response.changed() && !threshold_response.dragged());
+                            ui.label("files at once");
+                        });
+                    });
+                    let auto_sizes_response = ui.checkbox(&mut self.settings.auto_folder_sizes, "Calculate folder sizes automatically");
+                    if auto_sizes_response.changed() {
+                        settings_changed = true;
+                        if self.settings.auto_folder_sizes {
+                            self.calculate_all_folder_sizes();
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Row density:");
+                        settings_changed |= ui
+                            .radio_value(&mut self.settings.row_density, RowDensity::Comfortable, "Comfortable")
+                            .changed();
+                        settings_changed |= ui.radio_value(&mut self.settings.row_density, RowDensity::Compact, "Compact").changed();
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Terminal:");
+                        let terminal_edit = egui::TextEdit::singleline(&mut self.settings.terminal).hint_text("Detect");
+                        let response = ui
+                            .add(terminal_edit.desired_width(200.0))
+                            .on_hover_text("Command opening a terminal, started in the folder. Empty picks one automatically.");
+                        settings_changed |= response.lost_focus(); // Saved once typing is done
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Diff tool:");
+                        let diff_edit = egui::TextEdit::singleline(&mut self.settings.diff_tool).hint_text("Detect");
+                        let response = ui
+                            .add(diff_edit.desired_width(200.0))
+                            .on_hover_text("Command comparing two files, e.g. \"code --diff\". Empty picks one automatically.");
+                        settings_changed |= response.lost_focus();
+                    });
+                    if shell_menu::is_available() {
+                        settings_changed |= ui
+                            .checkbox(&mut self.settings.shell_menu, "Offer Explorer's context menu (\"Show more options\")")
+                            .changed();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Start in:");
+                        let options = [
+                            (StartupFolder::LastSession, "Last folder"),
+                            (StartupFolder::Home, "Home folder"),
+                            (StartupFolder::Fixed, "This folder:"),
+                        ];
+                        for (startup_folder, label) in options {
+                            let radio = ui.radio_value(&mut self.settings.startup_folder, startup_folder, label);
+                            settings_changed |= radio.changed();
+                        }
+                    });
+                    if self.settings.startup_folder == StartupFolder::Fixed {
+                        ui.horizontal(|ui| {
+                            let mut path = self.settings.startup_path.display().to_string();
+                            let response = ui.add(egui::TextEdit::singleline(&mut path).desired_width(300.0));
+                            if response.changed() {
+                                self.settings.startup_path = PathBuf::from(path);
+                            }
+                            settings_changed |= response.lost_focus();
+                            if ui.button("Use current folder").clicked() {
+                                self.settings.startup_path = self.current_dir.clone();
+                                settings_changed = true;
+                            }
+                            if !self.settings.startup_path.is_dir() {
+                                let warning = "⚠ Not a folder, the home folder is used";
+                                ui.colored_label(ui.visuals().warn_fg_color, warning);
+                            }
+                        });
+                    }
+                    settings_changed |= ui
+                        .checkbox(&mut self.settings.reopen_session, "Reopen previous window and view")
+                        .on_hover_text("Start with the window size, position and view FileFox was closed with")
+                        .changed();
+                    if tray::is_available() {
+                        let tray_label = "Keep FileFox in the system tray when its window is closed";
+                        let tray_response = ui.checkbox(&mut self.settings.tray, tray_label);
+                        if tray_response.changed() {
+                            settings_changed = true;
+                            if self.settings.tray {
+                                self.start_tray(ctx);
+                            } else {
+                                self.tray = None;
+                            }
+                        }
+                    }
+                    if shell_integration::is_available() {
+                        for integration in Integration::ALL {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{}:", integration.label()));
+                                let result = if shell_integration::is_registered(integration) {
+                                    ui.button("Remove").clicked().then(|| shell_integration::unregister(integration))
+                                } else {
+                                    ui.button("Add").clicked().then(|| shell_integration::register(integration))
+                                };
+                                if let Some(Err(e)) = result {
+                                    eprintln!("Error while changing {:?}: {}", integration.label(), e);
                                 }
                             });
                         }
                     }
-                }
-            });
-        });
+                    ui.separator();
+                    ui.label("Details view columns:");
+                    ui.horizontal_wrapped(|ui| {
+                        for column in SortColumn::OPTIONAL {
+                            let mut visible = self.settings.columns.contains(&column);
+                            if ui.checkbox(&mut visible, column.label()).changed() {
+                                should_toggle_column = Some(column);
+                            }
+                        }
+                    });
+                    ui.weak("Drag the column headers to reorder them.");
+                    ui.horizontal(|ui| {
+                        settings_changed |= ui
+                            .checkbox(&mut self.settings.remember_folder_views, "Remember the view of each folder")
+                            .on_hover_text("View mode, sort order and grouping")
+                            .changed();
+                        let forget_button = egui::Button::new(format!("Forget ({} folders)", self.folder_views.len()));
+                        if ui.add_enabled(self.folder_views.len() > 0, forget_button).clicked() {
+                            self.folder_views.clear();
+                            if let Err(e) = self.folder_views.save() {
+                                eprintln!("Error while saving folder views: {}", e);
+                            }
+                        }
+                    });
+                    let recent_files = self.places.recent_files.len();
+                    let clear_button = egui::Button::new(format!("Clear recent files ({})", recent_files));
+                    if ui.add_enabled(recent_files > 0, clear_button).clicked() {
+                        self.places.recent_files.clear();
+                        self.places_changed();
+                    }
+                    ui.separator();
+                    ui.collapsing("Keyboard shortcuts", |ui| {
+                        egui::Grid::new("keyboard_shortcuts").striped(true).show(ui, |ui| {
+                            for action in Action::ALL {
+                                ui.label(action.label());
+                                let shortcut = self.settings.keymap.shortcut(action);
+                                let text = match shortcut {
+                                    _ if self.recording_shortcut == Some(action) => "Press a key...".to_string(),
+                                    Some(shortcut) => ctx.format_shortcut(&shortcut),
+                                    None => "None".to_string(),
+                                };
+                                let hint = "Click, then press the new shortcut. Escape cancels.";
+                                if ui.button(text).on_hover_text(hint).clicked() {
+                                    self.recording_shortcut = Some(action);
+                                }
+                                let remove = ui.add_enabled(shortcut.is_some(), egui::Button::new("✖"));
+                                if remove.on_hover_text("Remove the shortcut").clicked() {
+                                    self.settings.keymap.set(action, None);
+                                    settings_changed = true;
+                                }
+                                // Only the first of several actions with the same shortcut runs
+                                let conflicts: Vec<&str> =
+                                    self.settings.keymap.conflicts(action).into_iter().map(Action::label).collect();
+                                if conflicts.is_empty() {
+                                    ui.label("");
+                                } else {
+                                    let warning = format!("⚠ Also used by: {}", conflicts.join(", "));
+                                    ui.colored_label(ui.visuals().warn_fg_color, warning);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        let reset_button = egui::Button::new("Reset to defaults");
+                        if ui.add_enabled(!self.settings.keymap.is_default(), reset_button).clicked() {
+                            self.settings.keymap.reset();
+                            settings_changed = true;
+                        }
+                    });
+                });
+            if settings_changed && let Err(e) = self.settings.save() {
+                eprintln!("Error while saving settings: {}", e);
+            }
+            self.show_settings_window = is_open;
+            if !is_open {
+                self.recording_shortcut = None;
+            }
+        }
 
-        // --- Render search popup ---
+        // --- Render index window ---
 
-        if self.show_search_popup {
-            egui::Window::new("What do you want to search?")
+        if self.show_index_window {
+            let mut is_open = true;
+            egui::Window::new("File index")
+                .open(&mut is_open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "{} paths indexed in {} folder(s)",
+                        self.file_index.len(),
+                        self.file_index.roots.len()
+                    ));
+                    ui.label("The index is rebuilt automatically once a day.");
+                    ui.separator();
+
+                    ui.label("Indexed folders:");
+                    let mut root_to_remove = None;
+                    for (i, root) in self.index_roots.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("✖").clicked() {
+                                root_to_remove = Some(i);
+                            }
+                            ui.label(root.display().to_string());
+                        });
+                    }
+                    if let Some(i) = root_to_remove {
+                        self.index_roots.remove(i);
+                    }
+                    if !self.index_roots.contains(&self.current_dir) && ui.button("Add current folder").clicked() {
+                        self.index_roots.push(self.current_dir.clone());
+                    }
+                    ui.separator();
+
+                    if self.jobs.is_running(JobKind::Index) {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Indexing...");
+                            if ui.button("Cancel").clicked() {
+                                self.jobs.cancel_kind(JobKind::Index);
+                            }
+                        });
+                    } else if ui.add_enabled(self.index_receiver.is_none(), egui::Button::new("Rebuild index")).clicked() {
+                        self.rebuild_file_index(ctx.clone());
+                    }
+                });
+            self.show_index_window = is_open;
+        }
+
+        // --- Render export popup ---
+
+        if self.show_export_popup {
+            let mut is_open = true;
+            egui::Window::new("Export search results")
+                .open(&mut is_open)
                 .collapsible(false)
                 .resizable(false)
                 .show(ctx, |ui| {
-                    let response = ui.text_edit_singleline(&mut self.search_query);
+                    ui.horizontal(|ui| {
+                        ui.label("Save to:");
+                        ui.text_edit_singleline(&mut self.export_path);
+                    });
+                    ui.horizontal(|ui| {
+                        let previous_format = self.export_format;
+                        ui.radio_value(&mut self.export_format, ExportFormat::Csv, "CSV");
+                        ui.radio_value(&mut self.export_format, ExportFormat::Text, "Text");
+                        if self.export_format != previous_format {
+                            let path = Path::new(&self.export_path).with_extension(self.export_format.extension());
+                            self.export_path = path.display().to_string();
+                        }
+                    });
+                    ui.checkbox(&mut self.export_with_metadata, "Include size and modified columns");
 
+                    if self.export_receiver.is_some() {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Exporting...");
+                        });
+                    } else if ui.add_enabled(!self.export_path.trim().is_empty(), egui::Button::new("Export")).clicked() {
+                        self.start_export(ctx);
+                    }
+                    if let Some(status) = &self.export_status {
+                        ui.label(status);
+                    }
+                });
+            self.show_export_popup = is_open;
+        }
+
+        // --- Render ZIP popup ---
+
+        if self.show_zip_popup {
+            let mut should_close_zip_popup = false;
+            egui::Window::new("Compress to ZIP")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("{} item(s) selected", self.zip_sources.len()));
                     ui.horizontal(|ui| {
-                        // "Search" button disabled if already searching
-                        ui.add_enabled_ui(!self.is_searching, |ui| {
-                            if ui.button("Search").clicked() {
-                                self.execute_search(ctx.clone());
-                                should_close_search_popup = true; // Close popup after starting search
+                        ui.label("Archive name:");
+                        ui.text_edit_singleline(&mut self.zip_archive_name);
+                    });
+                    ui.add(egui::Slider::new(&mut self.zip_compression_level, 0..=9).text("Compression level"));
+                    let existing = self.zip_archive_path().and_then(|path| std::fs::metadata(path).ok());
+                    let is_folder = existing.as_ref().is_some_and(|metadata| metadata.is_dir());
+                    if is_folder {
+                        ui.colored_label(ui.visuals().warn_fg_color, "⚠ A folder with this name already exists");
+                    } else if existing.is_some() {
+                        ui.colored_label(ui.visuals().warn_fg_color, "⚠ A file with this name will be replaced");
+                    }
+
+                    ui.horizontal(|ui| {
+                        // "Create" button disabled while another archive is being written
+                        ui.add_enabled_ui(self.zip_receiver.is_none() && !is_folder, |ui| {
+                            if ui.button("Create").clicked() {
+                                self.start_zip_archive(ctx);
+                                should_close_zip_popup = true;
                             }
                         });
                         if ui.button("Cancel").clicked() {
-                            self.recursive_search_results = None; // Clear results on cancel
-                            self.is_searching = false; // Stop search
-                            self.search_sender = None; // Close channels
-                            self.search_receiver = None; // Close channels
-                            should_close_search_popup = true;
+                            should_close_zip_popup = true;
                         }
                     });
+                });
+            if should_close_zip_popup {
+                self.show_zip_popup = false;
+            }
+        }
 
-                    // Trigger search on enter key if text box focused and not already searching
-                    if !self.is_searching && response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                        self.execute_search(ctx.clone());
-                        should_close_search_popup = true; // Close popup after starting search
+        // --- Render replace prompt ---
+
+        if let Some((existing, operation)) = &self.pending_replace {
+            let mut replace_decision = None;
+            let mut should_cancel_replace = false;
+            // Only extractions and device copies can leave some files alone and still finish
+            let can_skip = matches!(operation, ReplaceOperation::Extract(_) | ReplaceOperation::DeviceCopy(_));
+            egui::Window::new("Replace existing items?")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    for path in existing.iter().take(5) {
+                        ui.label(format!("{} already exists.", path.display()));
+                    }
+                    if existing.len() > 5 {
+                        ui.label(format!("...and {} more", existing.len() - 5));
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Replace").clicked() {
+                            replace_decision = Some(ConflictChoice::Replace);
+                        }
+                        if can_skip && ui.button("Skip existing files").clicked() {
+                            replace_decision = Some(ConflictChoice::Skip);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            should_cancel_replace = true;
+                        }
+                    });
+                });
+            if should_cancel_replace {
+                self.pending_replace = None;
+            } else if let Some(choice) = replace_decision
+                && let Some((_, operation)) = self.pending_replace.take()
+            {
+                match operation {
+                    ReplaceOperation::Zip { sources, archive_path, level } => {
+                        self.spawn_zip_archive(ctx, sources, archive_path, level);
+                    }
+                    ReplaceOperation::Extract(mut extraction) => {
+                        extraction.set_on_conflict(choice);
+                        self.jobs.spawn(JobKind::Extract, ctx, move |job| extraction.run(job));
+                    }
+                    ReplaceOperation::DeviceCopy(mut copy) => {
+                        copy.set_on_conflict(choice);
+                        self.jobs.spawn(JobKind::Transfer, ctx, move |job| copy.run(job));
+                    }
+                    ReplaceOperation::Rename { from, to } => self.rename_path(from, to),
+                    ReplaceOperation::Export { destination } => self.spawn_export(ctx, destination),
+                }
+            }
+        }
+
+        // --- Render merge prompts ---
+
+        if self.move_receiver.is_none()
+            && let Some((source, destination)) = self.pending_merges.first().cloned()
+        {
+            // A file onto a file is a merge that conflicts right away, asked as "Replace file?"
+            let mut merge_decision = source.is_file().then_some(true);
+            if merge_decision.is_none() {
+                egui::Window::new("Folder already exists")
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.label(format!(
+                            "{} already exists. Merge the contents of {} into it?",
+                            destination.display(),
+                            source.display()
+                        ));
+                        ui.horizontal(|ui| {
+                            if ui.button("Merge").clicked() {
+                                merge_decision = Some(true);
+                            }
+                            if ui.button("Skip").clicked() {
+                                merge_decision = Some(false);
+                            }
+                        });
+                    });
+            }
+            if let Some(merge) = merge_decision {
+                self.pending_merges.remove(0);
+                if merge {
+                    self.merge_apply_to_all = false;
+                    let choice_for_all = (!self.settings.confirm_overwrite).then_some(ConflictChoice::Replace);
+                    let merge = MergeMove::new(source, destination, choice_for_all);
+                    self.spawn_move(ctx, move |events, job| merge.run(events, job));
+                }
+            }
+        }
+
+        if let Some((conflicting_file, _)) = &self.merge_conflict {
+            let conflicting_file = conflicting_file.clone();
+            let mut conflict_decision = None;
+            let mut should_cancel_merge = false;
+            egui::Window::new("Replace file?")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("{} already exists.", conflicting_file.display()));
+                    ui.checkbox(&mut self.merge_apply_to_all, "Do this for all remaining conflicts");
+                    ui.horizontal(|ui| {
+                        if ui.button("Replace").clicked() {
+                            conflict_decision = Some(ConflictChoice::Replace);
+                        }
+                        if ui.button("Skip").clicked() {
+                            conflict_decision = Some(ConflictChoice::Skip);
+                        }
+                        if ui.button("Cancel merge").clicked() {
+                            should_cancel_merge = true;
+                        }
+                    });
+                });
+            if let Some(choice) = conflict_decision
+                && let Some((_, answer)) = self.merge_conflict.take()
+            {
+                let _ = answer.send(Some((choice, self.merge_apply_to_all)));
+            } else if should_cancel_merge && let Some((_, answer)) = self.merge_conflict.take() {
+                let _ = answer.send(None); // Already moved entries stay where they are
+            }
+        }
+
+        // --- Render "Move to..." / "Copy to..." folder picker ---
+
+        if let Some(picker) = &mut self.folder_picker {
+            let is_move = self.folder_picker_mode == ClipboardMode::Cut;
+            let mut picked_destination = None;
+            let mut should_close_picker = false;
+            egui::Window::new(if is_move { "Move to" } else { "Copy to" })
+                .collapsible(false)
+                .default_height(400.0)
+                .show(ctx, |ui| {
+                    ui.label(format!("{} item(s)", self.folder_picker_entries.len()));
+                    ui.separator();
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        picker.show(ui);
+                    });
+                    ui.separator();
+                    ui.label(format!("Destination: {}", picker.selected.display()));
+                    ui.horizontal(|ui| {
+                        let confirm_label = if is_move { "Move here" } else { "Copy here" };
+                        // Only one copy and one move run at a time
+                        let enabled = if is_move { self.move_receiver.is_none() } else { self.copy_receiver.is_none() };
+                        if ui.add_enabled(enabled, egui::Button::new(confirm_label)).clicked() {
+                            picked_destination = Some(picker.selected.clone());
+                        }
+                        if ui.button("Cancel").clicked() {
+                            should_close_picker = true;
+                        }
+                    });
+                });
+            if let Some(destination) = picked_destination {
+                let sources = std::mem::take(&mut self.folder_picker_entries);
+                if is_move {
+                    self.move_entries(sources, destination, ctx);
+                } else {
+                    self.start_copy(sources, destination, ctx.clone());
+                }
+                should_close_picker = true;
+            }
+            if should_close_picker {
+                self.folder_picker = None;
+            }
+        }
+
+        // --- Render "open many files" confirmation ---
+
+        if !self.pending_open.is_empty() {
+            let mut open_confirmed = None;
+            egui::Window::new("Open files")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("Open {} files at once?", self.pending_open.len()));
+                    ui.horizontal(|ui| {
+                        if ui.button("Open all").clicked() {
+                            open_confirmed = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            open_confirmed = Some(false);
+                        }
+                    });
+                });
+            if let Some(confirmed) = open_confirmed {
+                let paths = std::mem::take(&mut self.pending_open);
+                if confirmed {
+                    for path in &paths {
+                        self.open_file(path);
+                    }
+                }
+            }
+        }
+
+        // --- Render "delete permanently" confirmation ---
+
+        if !self.pending_delete.is_empty() {
+            let mut delete_confirmed = None;
+            egui::Window::new("Delete permanently")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let question = match self.pending_delete.as_slice() {
+                        [entry_name] => format!("Delete {:?} permanently?", entry_name),
+                        entries => format!("Delete {} items permanently?", entries.len()),
+                    };
+                    ui.label(format!("{} It can't be restored from the trash.", question));
+                    ui.horizontal(|ui| {
+                        if ui.button("Delete").clicked() {
+                            delete_confirmed = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            delete_confirmed = Some(false);
+                        }
+                    });
+                });
+            if let Some(confirmed) = delete_confirmed {
+                let entries = std::mem::take(&mut self.pending_delete);
+                if confirmed {
+                    self.perform_delete(&entries, ctx);
+                }
+            }
+        }
+
+        // --- Render "move to trash" confirmation ---
+
+        if !self.pending_trash.is_empty() {
+            let mut trash_confirmed = None;
+            egui::Window::new("Move to trash")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    let question = match self.pending_trash.as_slice() {
+                        [entry_name] => format!("Move {:?} to the trash?", entry_name),
+                        entries => format!("Move {} items to the trash?", entries.len()),
+                    };
+                    ui.label(question);
+                    ui.horizontal(|ui| {
+                        if ui.button("Move to trash").clicked() {
+                            trash_confirmed = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            trash_confirmed = Some(false);
+                        }
+                    });
+                });
+            if let Some(confirmed) = trash_confirmed {
+                let entries = std::mem::take(&mut self.pending_trash);
+                if confirmed {
+                    self.perform_trash(&entries);
+                }
+            }
+        }
+
+        // --- Render copy verification report ---
+
+        if !self.copy_mismatches.is_empty() {
+            let mut should_close_mismatch_report = false;
+            egui::Window::new("Copy verification failed")
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label("These copies differ from their source:");
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        for path in &self.copy_mismatches {
+                            ui.label(path.display().to_string());
+                        }
+                    });
+                    if ui.button("OK").clicked() {
+                        should_close_mismatch_report = true;
                     }
                 });
+            if should_close_mismatch_report {
+                self.copy_mismatches.clear();
+            }
+        }
+
+        // --- Render "Retry as administrator" prompt ---
+
+        if let Some(operation) = self.elevation_offer.clone() {
+            let mut should_close_elevation_prompt = false;
+            egui::Window::new("Access denied")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!("{} failed: access denied.", operation.description()));
+                    ui.horizontal(|ui| {
+                        if ui.button("🛡 Retry as administrator").clicked() {
+                            self.retry_elevated(operation.clone(), ctx.clone());
+                            should_close_elevation_prompt = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            should_close_elevation_prompt = true;
+                        }
+                    });
+                });
+            if should_close_elevation_prompt {
+                self.elevation_offer = None;
+            }
         }
 
         // --- Apply delayed state changes ---
+        if self.startup_pending {
+            self.finish_startup(ctx);
+        }
+        self.metadata_loader.request(shown_without_metadata);
         if should_close_search_popup {
             self.show_search_popup = false;
         }
+        if let Some((old_name, new_name)) = should_rename_entry {
+            self.rename_entry(&old_name, &new_name);
+        }
+        if let Some(entry_names) = should_trash_entries {
+            self.trash_entries(entry_names);
+        }
+        if let Some(entry_names) = should_delete_entries {
+            self.delete_entries(entry_names, ctx);
+        }
+        if let Some(dest_dir) = should_paste_into {
+            self.paste_clipboard(dest_dir, ctx.clone());
+        }
+        if should_apply_directory_filter {
+            self.apply_directory_filter();
+        }
         if let Some(path_to_navigate) = should_navigate_to_path {
-            self.current_dir = path_to_navigate;
-            self.read_current_directory_entries();
+            self.change_directory(path_to_navigate);
+        }
+        if should_toggle_bookmark {
+            self.places.toggle_bookmark(&self.current_dir);
+            self.places_changed();
         }
         if should_clear_recursive_results_after_interaction {
-            self.recursive_search_results = None;
+            self.clear_search_results();
         }
         if should_clear_rename_mode {
             self.rename_mode = None;
         }
+        if let Some(paths) = should_show_shell_menu
+            && !shell_menu::show(&paths)
+        {
+            eprintln!("Error while showing the shell context menu of {:?}", paths);
+        }
+        if let Some(path_to_drag) = should_drag_out_path {
+            drag::start_file_drag(&path_to_drag);
+        }
+        if let Some(entry_name) = should_open_entry {
+            self.open_entry_file(&entry_name, ctx);
+        }
+        if let Some(path) = should_open_path {
+            self.open_file(&path);
+        }
+        if let Some(path) = should_reveal_path
+            && let (Some(parent), Some(name)) = (path.parent(), path.file_name())
+        {
+            // Navigate to the parent folder and highlight the found entry
+            self.change_directory(parent.to_owned());
+            let name = name.to_owned();
+            self.selected_entries.insert(name.clone());
+            self.scroll_to_entry = Some(name);
+        }
+        if let Some(column) = should_toggle_column {
+            self.settings.toggle_column(column);
+            self.columns_changed();
+        }
+        if let Some((column, target)) = should_move_column {
+            self.settings.move_column(column, target);
+            self.columns_changed();
+        }
+        if let Some(column) = should_sort_entries_by {
+            // Same column again reverses the order
+            self.sort_order.descending = self.sort_order.column == column && !self.sort_order.descending;
+            self.sort_order.column = column;
+            self.sort_order_changed();
+        }
+        if let Some(key) = should_sort_results_by {
+            // Same column again reverses the order
+            self.results_sort_descending = self.results_sort == Some(key) && !self.results_sort_descending;
+            self.results_sort = Some(key);
+            if let Some(results) = &mut self.recursive_search_results {
+                search::sort_results(results, key, self.results_sort_descending, &self.search_result_metadata);
+            }
+            self.apply_results_filter();
+        }
     }
 }
 
 // --- Main function to start the application ---
 
 fn main() {
+    // Elevated helper mode: perform a single operation and exit without opening a window
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == elevate::ELEVATED_OPERATION_FLAG) {
+        let result = ElevatedOperation::from_args(&args[1..]).map(|operation| operation.perform());
+        std::process::exit(if matches!(result, Some(Ok(()))) { 0 } else { 1 });
+    }
+
+    // Links like filefox://open?path=... open in the running instance, if there is one
+    let link_dir = args.first().and_then(|arg| arg.to_str()).and_then(instance::parse_uri);
+    if let Some(dir) = &link_dir
+        && instance::forward(dir)
+    {
+        return;
+    }
+
+    // Folder to start in, e.g. from "Open FileFox here" in another file manager
+    let start_dir = link_dir.or_else(|| {
+        args.first().map(|arg| std::path::absolute(arg).unwrap_or_else(|_| PathBuf::from(arg)))
+    });
+    if let Some(dir) = &start_dir
+        && !dir.is_dir()
+    {
+        eprintln!("{:?} is not a folder, starting in the folder chosen in the settings", dir);
+    }
+
     // Load PNG bytes directly for window icon
     let window_icon_data = eframe::IconData::try_from_png_bytes(
         include_bytes!("./icon.png")
@@ -472,9 +4603,43 @@ fn main() {
         native_options.icon_data = Some(icon_data);
     }
 
+    let mut app = MyExplorerApp::default();
+    let session = Session::load().unwrap_or_else(|e| {
+        eprintln!("Error while loading the session: {}", e);
+        Session::default()
+    });
+
+    // The working directory is arbitrary when started from a shortcut, so FileFox starts in the
+    // folder chosen in the settings, or the home folder if that no longer exists
+    let startup_dir = match app.settings.startup_folder {
+        StartupFolder::LastSession => session.current_dir.clone(),
+        StartupFolder::Home => dirs::home_dir(),
+        StartupFolder::Fixed => Some(app.settings.startup_path.clone()),
+    };
+    if let Some(dir) = startup_dir.filter(|dir| dir.is_dir()).or_else(dirs::home_dir) {
+        app.current_dir = dir;
+    }
+
+    // Reopen the window and view of the last run
+    if app.settings.reopen_session {
+        if let Some(view_mode) = session.view_mode {
+            app.view_mode = view_mode;
+        }
+        native_options.initial_window_pos = session.window_position.map(|[x, y]| egui::pos2(x, y));
+        native_options.initial_window_size = session.window_size.map(|[width, height]| egui::vec2(width, height));
+        native_options.maximized = session.maximized;
+    }
+    app.session = session;
+
     let _ = eframe::run_native(
         "FileFox", // Application name
         native_options,
-        Box::new(|_cc| Box::new(MyExplorerApp::default())), // Expected closure
+        Box::new(move |cc| {
+            if let Some(dir) = start_dir.filter(|dir| dir.is_dir()) {
+                app.current_dir = dir;
+            }
+            app.link_receiver = instance::listen(&cc.egui_ctx);
+            Box::new(app)
+        }), // Expected closure
     );
 }
\ No newline at end of file
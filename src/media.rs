@@ -0,0 +1,117 @@
+// --- Audio / video metadata (shells out to `ffprobe` if it is installed) ---
+
+use std::{collections::BTreeMap, path::Path, process::Command, str::FromStr};
+
+/// One audio or video stream of a media file.
+pub struct MediaStream {
+    pub kind: String, // "video", "audio", "subtitle", ...
+    pub codec: String,
+    pub resolution: Option<(u32, u32)>, // Video
+    pub sample_rate: Option<u32>, // Audio
+    pub channels: Option<u32>, // Audio
+}
+
+/// Container-level information and the streams of a media file.
+pub struct MediaInfo {
+    pub duration: Option<f64>, // Seconds
+    pub bit_rate: Option<u64>, // Bits per second
+    pub streams: Vec<MediaStream>,
+}
+
+impl MediaStream {
+    /// One-line description, e.g. "h264, 1920 × 1080" or "aac, 48000 Hz, 2 ch".
+    pub fn describe(&self) -> String {
+        let mut parts = vec![self.codec.clone()];
+        if let Some((width, height)) = self.resolution {
+            parts.push(format!("{} × {}", width, height));
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            parts.push(format!("{} Hz", sample_rate));
+        }
+        if let Some(channels) = self.channels {
+            parts.push(format!("{} ch", channels));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Formats seconds as "m:ss" or "h:mm:ss".
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Formats a bit rate, e.g. "320 kbit/s" or "4.5 Mbit/s".
+pub fn format_bit_rate(bits_per_second: u64) -> String {
+    if bits_per_second >= 1_000_000 {
+        format!("{:.1} Mbit/s", bits_per_second as f64 / 1_000_000.0)
+    } else {
+        format!("{} kbit/s", bits_per_second / 1000)
+    }
+}
+
+/// Reads the metadata of `path` with ffprobe. `None` if ffprobe is not installed
+/// or the file is not a media file it understands.
+pub fn probe(path: &Path) -> Option<MediaInfo> {
+    let mut command = Command::new("ffprobe");
+    command
+        .args(["-v", "error", "-of", "flat", "-show_format", "-show_streams"])
+        .arg(path);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW); // No console window flashing up
+    }
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // "flat" output: one `section.key="value"` line per field,
+    // with streams numbered as `streams.stream.0.codec_name`
+    let output = String::from_utf8_lossy(&output.stdout);
+    let mut format = BTreeMap::new();
+    let mut streams: BTreeMap<usize, BTreeMap<&str, &str>> = BTreeMap::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim_matches('"');
+        if let Some(key) = key.strip_prefix("format.") {
+            format.insert(key, value);
+        } else if let Some(rest) = key.strip_prefix("streams.stream.")
+            && let Some((index, key)) = rest.split_once('.')
+            && let Ok(index) = index.parse()
+        {
+            streams.entry(index).or_default().insert(key, value);
+        }
+    }
+    if streams.is_empty() {
+        return None;
+    }
+
+    Some(MediaInfo {
+        duration: number(&format, "duration"),
+        bit_rate: number(&format, "bit_rate"),
+        streams: streams
+            .values()
+            .map(|fields| MediaStream {
+                kind: fields.get("codec_type").unwrap_or(&"unknown").to_string(),
+                codec: fields.get("codec_name").unwrap_or(&"unknown").to_string(),
+                resolution: number(fields, "width").zip(number(fields, "height")),
+                sample_rate: number(fields, "sample_rate"),
+                channels: number(fields, "channels"),
+            })
+            .collect(),
+    })
+}
+
+/// Parses the field `key`; ffprobe writes "N/A" for unknown values.
+fn number<T: FromStr>(fields: &BTreeMap<&str, &str>, key: &str) -> Option<T> {
+    fields.get(key).and_then(|value| value.parse().ok())
+}
@@ -0,0 +1,104 @@
+// --- Metadata of listed entries, read in the background once they are shown ---
+
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+};
+
+use eframe::egui;
+use rayon::prelude::*;
+
+use crate::{repaint, view::EntryDetails};
+
+/// Reads the size, dates and attributes of entries the listing shows, so large folders
+/// don't have to stat every entry up front. Finished details are collected by `poll`.
+/// Once a listing is complete, the rest is prefetched on rayon's thread pool.
+pub struct MetadataLoader {
+    requested: HashSet<PathBuf>,
+    cancel: Arc<AtomicBool>, // Shared by the running prefetch, replaced by `clear`
+    repaint: Arc<OnceLock<egui::Context>>, // Set on the first `poll`, wakes the UI on results
+    sender: mpsc::Sender<(PathBuf, EntryDetails)>,
+    receiver: mpsc::Receiver<(PathBuf, EntryDetails)>,
+}
+
+impl Default for MetadataLoader {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            requested: HashSet::new(),
+            cancel: Arc::new(AtomicBool::new(false)),
+            repaint: Arc::default(),
+            sender,
+            receiver,
+        }
+    }
+}
+
+impl MetadataLoader {
+    /// Paths of `paths` not requested before, marked as requested now.
+    fn new_paths(&mut self, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        paths.into_iter().filter(|path| self.requested.insert(path.clone())).collect()
+    }
+
+    /// Starts reading the details of `paths` on a background thread, skipping paths requested before.
+    pub fn request(&mut self, paths: Vec<PathBuf>) {
+        let batch = self.new_paths(paths);
+        if batch.is_empty() {
+            return;
+        }
+        let (sender, repaint) = (self.sender.clone(), self.repaint.clone());
+        std::thread::spawn(move || {
+            for path in batch {
+                let details = EntryDetails::read(&path);
+                if sender.send((path, details)).is_err() {
+                    return;
+                }
+            }
+            if let Some(ctx) = repaint.get() {
+                repaint::request_throttled_repaint(ctx);
+            }
+        });
+    }
+
+    /// Reads the details of all `paths` in parallel, so sorting by a metadata column
+    /// works without scrolling through the listing first. Skips paths requested before.
+    pub fn prefetch(&mut self, paths: Vec<PathBuf>) {
+        let batch = self.new_paths(paths);
+        if batch.is_empty() {
+            return;
+        }
+        let (sender, cancel, repaint) = (self.sender.clone(), self.cancel.clone(), self.repaint.clone());
+        rayon::spawn(move || {
+            batch.into_par_iter().for_each_with(sender, |sender, path| {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let details = EntryDetails::read(&path);
+                if sender.send((path, details)).is_ok()
+                    && let Some(ctx) = repaint.get()
+                {
+                    repaint::request_throttled_repaint(ctx);
+                }
+            });
+        });
+    }
+
+    /// Returns the details read since the last frame. They may belong to a directory
+    /// that was left in the meantime.
+    pub fn poll(&mut self, ctx: &egui::Context) -> Vec<(PathBuf, EntryDetails)> {
+        self.repaint.get_or_init(|| ctx.clone());
+        self.receiver.try_iter().collect()
+    }
+
+    /// Stops prefetching and forgets the requested paths, e.g. when the listing is read again.
+    pub fn clear(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancel = Arc::new(AtomicBool::new(false));
+        self.requested.clear();
+    }
+}
@@ -0,0 +1,563 @@
+// --- Phones and cameras connected over MTP ---
+//
+// Linux desktops mount these devices as folders through GVfs or FUSE tools like jmtpfs,
+// and drives.rs lists those mounts with the other drives. Windows only exposes them
+// through the Windows Portable Devices (WPD) API, so there they get their own browser
+// window. WPD addresses objects by ids instead of paths.
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use eframe::egui;
+
+use crate::{
+    fileops::{self, ConflictChoice},
+    jobs::JobContext,
+    view,
+};
+
+/// A connected device, by its Plug and Play id.
+#[derive(Clone)]
+pub struct Device {
+    pub id: String,
+    pub name: String,
+}
+
+/// A folder or file on a device.
+#[derive(Clone)]
+pub struct DeviceObject {
+    id: String,
+    name: String,
+    is_dir: bool, // Also storages like "Internal shared storage"
+    size: Option<u64>,
+}
+
+/// Devices with a WPD driver.
+#[cfg(windows)]
+pub fn list_devices() -> Vec<Device> {
+    native::list_devices()
+}
+
+/// Devices are mounted as folders here, see drives.rs.
+#[cfg(not(windows))]
+pub fn list_devices() -> Vec<Device> {
+    Vec::new()
+}
+
+#[cfg(windows)]
+fn list_objects(device_id: &str, parent_id: Option<&str>) -> io::Result<Vec<DeviceObject>> {
+    native::Connection::open(device_id)?.list(parent_id)
+}
+
+#[cfg(not(windows))]
+fn list_objects(_device_id: &str, _parent_id: Option<&str>) -> io::Result<Vec<DeviceObject>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "MTP devices are browsed as mounted folders here"))
+}
+
+/// Copies `objects` into `destination`, with everything inside folders. Files that exist already
+/// are replaced or skipped as `on_conflict` says. Returns the number of files written.
+#[cfg(windows)]
+fn copy_objects(
+    device_id: &str,
+    objects: &[DeviceObject],
+    destination: &Path,
+    on_conflict: ConflictChoice,
+    job: &JobContext,
+) -> io::Result<usize> {
+    let connection = native::Connection::open(device_id)?;
+    let mut count = 0;
+    for (done, object) in objects.iter().enumerate() {
+        count += connection.copy(object, destination, on_conflict, job)?;
+        job.progress(done + 1, objects.len());
+    }
+    Ok(count)
+}
+
+#[cfg(not(windows))]
+fn copy_objects(
+    _device_id: &str,
+    _objects: &[DeviceObject],
+    _destination: &Path,
+    _on_conflict: ConflictChoice,
+    _job: &JobContext,
+) -> io::Result<usize> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "MTP devices are browsed as mounted folders here"))
+}
+
+/// Copy off a device, ready to run as a background job.
+pub struct DeviceCopyJob {
+    device: Device,
+    objects: Vec<DeviceObject>,
+    destination: PathBuf,
+    on_conflict: ConflictChoice,
+}
+
+impl DeviceCopyJob {
+    /// Objects that already exist in the destination.
+    pub fn existing_targets(&self) -> Vec<PathBuf> {
+        self.objects
+            .iter()
+            .filter_map(|object| fileops::safe_child_path(&self.destination, &object.name).ok())
+            .filter(|target| fs::symlink_metadata(target).is_ok())
+            .collect()
+    }
+
+    /// Decides what happens to files that exist already; they are skipped unless this says otherwise.
+    pub fn set_on_conflict(&mut self, choice: ConflictChoice) {
+        self.on_conflict = choice;
+    }
+
+    pub fn run(self, job: &JobContext) {
+        if let Err(e) = copy_objects(&self.device.id, &self.objects, &self.destination, self.on_conflict, job)
+            && e.kind() != io::ErrorKind::Interrupted
+        {
+            eprintln!("Error while copying from {}: {}", self.device.name, e);
+        }
+    }
+}
+
+pub enum DeviceAction {
+    Close,
+    /// Copy these objects as a background job.
+    Copy(DeviceCopyJob),
+}
+
+/// Window browsing the folders of one device. Click selects (Ctrl+click adds to the
+/// selection), double-clicking a folder opens it. Listings are read on rayon's thread
+/// pool, folders with thousands of photos take a while.
+pub struct DeviceBrowser {
+    device: Device,
+    folders: Vec<DeviceObject>, // From the device root down to the listed folder
+    listing: Result<Vec<DeviceObject>, String>,
+    selected: HashSet<String>, // Ids in the listed folder
+    receiver: Option<mpsc::Receiver<Result<Vec<DeviceObject>, String>>>,
+}
+
+impl DeviceBrowser {
+    pub fn new(device: Device, ctx: &egui::Context) -> Self {
+        let mut browser = Self {
+            device,
+            folders: Vec::new(),
+            listing: Ok(Vec::new()),
+            selected: HashSet::new(),
+            receiver: None,
+        };
+        browser.navigate(Vec::new(), ctx);
+        browser
+    }
+
+    fn navigate(&mut self, folders: Vec<DeviceObject>, ctx: &egui::Context) {
+        let (sender, receiver) = mpsc::channel();
+        self.receiver = Some(receiver);
+        let device_id = self.device.id.clone();
+        let parent_id = folders.last().map(|folder| folder.id.clone());
+        let ctx = ctx.clone();
+        rayon::spawn(move || {
+            let listing = list_objects(&device_id, parent_id.as_deref()).map_err(|e| e.to_string());
+            if sender.send(listing).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+        self.folders = folders;
+        self.selected.clear();
+    }
+
+    /// Renders the window. Copied objects go to `destination`.
+    pub fn show(&mut self, ctx: &egui::Context, destination: &Path) -> Option<DeviceAction> {
+        if let Some(receiver) = &self.receiver
+            && let Ok(listing) = receiver.try_recv()
+        {
+            self.listing = listing;
+            self.receiver = None;
+        }
+
+        let mut action = None;
+        let mut is_open = true;
+        egui::Window::new(format!("📱 {}", self.device.name))
+            .id(egui::Id::new("device_browser"))
+            .open(&mut is_open)
+            .default_size([600.0, 450.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                let go_up = ui
+                    .horizontal(|ui| {
+                        let up = ui.add_enabled(!self.folders.is_empty(), egui::Button::new("⬆ Up")).clicked();
+                        let names: Vec<&str> = self.folders.iter().map(|folder| folder.name.as_str()).collect();
+                        ui.label(format!("/{}", names.join("/")));
+                        up
+                    })
+                    .inner;
+                if go_up {
+                    self.navigate(self.folders[..self.folders.len() - 1].to_vec(), ctx);
+                }
+
+                if self.receiver.is_some() {
+                    ui.spinner();
+                    return;
+                }
+                let objects = match &self.listing {
+                    Ok(objects) => objects,
+                    Err(e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                        return;
+                    }
+                };
+                ui.horizontal(|ui| {
+                    let selection: Vec<DeviceObject> =
+                        objects.iter().filter(|object| self.selected.contains(&object.id)).cloned().collect();
+                    let hover = format!("Into {}", destination.display());
+                    let copy_selected = egui::Button::new(format!("Copy {} items", selection.len()));
+                    let copy_selected = ui.add_enabled(!selection.is_empty(), copy_selected).on_hover_text(&hover);
+                    // Copying the device root would mean copying every storage
+                    let copy_folder = egui::Button::new("Copy this folder");
+                    let copy_folder = ui.add_enabled(!self.folders.is_empty(), copy_folder).on_hover_text(&hover);
+                    let objects = if copy_selected.clicked() {
+                        Some(selection)
+                    } else if copy_folder.clicked() {
+                        Some(objects.clone())
+                    } else {
+                        None
+                    };
+                    if let Some(objects) = objects {
+                        let (device, destination) = (self.device.clone(), destination.to_owned());
+                        let on_conflict = ConflictChoice::Skip;
+                        action = Some(DeviceAction::Copy(DeviceCopyJob { device, objects, destination, on_conflict }));
+                    }
+                });
+                ui.separator();
+
+                if objects.is_empty() {
+                    ui.weak("(empty folder)");
+                }
+                let mut navigate = None;
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                    egui::Grid::new("device_listing").num_columns(2).striped(true).show(ui, |ui| {
+                        for object in objects {
+                            let icon = if object.is_dir { "📁" } else { "📄" };
+                            let selected = self.selected.contains(&object.id);
+                            let response = ui.selectable_label(selected, format!("{} {}", icon, object.name));
+                            if response.double_clicked() && object.is_dir {
+                                let mut folders = self.folders.clone();
+                                folders.push(object.clone());
+                                navigate = Some(folders);
+                            } else if response.clicked() {
+                                if !ui.input(|i| i.modifiers.command) {
+                                    self.selected.clear();
+                                }
+                                if !self.selected.insert(object.id.clone()) {
+                                    self.selected.remove(&object.id);
+                                }
+                            }
+                            ui.label(object.size.map(view::format_size).unwrap_or_default());
+                            ui.end_row();
+                        }
+                    });
+                });
+                if let Some(folders) = navigate {
+                    self.navigate(folders, ctx);
+                }
+            });
+        if !is_open {
+            action = Some(DeviceAction::Close);
+        }
+        action
+    }
+}
+
+#[cfg(windows)]
+#[allow(non_upper_case_globals)]
+mod native {
+    use std::{
+        fs::{self, File},
+        io::{self, Write},
+        path::Path,
+        ptr, slice,
+    };
+
+    use winapi::ctypes::c_void;
+    use winapi::shared::guiddef::{GUID, IsEqualGUID};
+    use winapi::shared::minwindef::{DWORD, ULONG};
+    use winapi::shared::winerror::{S_OK, SUCCEEDED};
+    use winapi::shared::wtypes::PROPERTYKEY;
+    use winapi::shared::wtypesbase::CLSCTX_INPROC_SERVER;
+    use winapi::um::combaseapi::{CoCreateInstance, CoInitializeEx, CoTaskMemFree, CoUninitialize};
+    use winapi::um::coml2api::STGM_READ;
+    use winapi::um::objbase::COINIT_MULTITHREADED;
+    use winapi::um::objidlbase::IStream;
+    use winapi::um::portabledevice::{WPD_OBJECT_NAME, WPD_OBJECT_ORIGINAL_FILE_NAME, WPD_OBJECT_SIZE};
+    use winapi::um::portabledeviceapi::{
+        CLSID_PortableDeviceFTM, CLSID_PortableDeviceManager, IEnumPortableDeviceObjectIDs, IPortableDevice,
+        IPortableDeviceContent, IPortableDeviceManager, IPortableDeviceProperties, IPortableDeviceResources,
+    };
+    use winapi::um::portabledevicetypes::{CLSID_PortableDeviceValues, IPortableDeviceValues};
+    use winapi::um::winnt::{HRESULT, LPWSTR};
+    use winapi::{DEFINE_GUID, DEFINE_PROPERTYKEY, Interface};
+
+    use super::{Device, DeviceObject};
+    use crate::{
+        fileops::{self, ConflictChoice},
+        jobs::JobContext,
+    };
+
+    // Not part of winapi 0.3
+    DEFINE_PROPERTYKEY! {WPD_OBJECT_CONTENT_TYPE,
+    0xEF6B490D, 0x5CD8, 0x437A, 0xAF, 0xFC, 0xDA, 0x8B, 0x60, 0xEE, 0x4A, 0x3C, 7}
+    DEFINE_PROPERTYKEY! {WPD_RESOURCE_DEFAULT,
+    0xE81E79BE, 0x34F0, 0x41BF, 0xB5, 0x3F, 0xF1, 0xA0, 0x6A, 0xE8, 0x78, 0x42, 0}
+    DEFINE_GUID! {WPD_CONTENT_TYPE_FOLDER,
+    0x27E2E392, 0xA111, 0x48E0, 0xAB, 0x0C, 0xE1, 0x77, 0x05, 0xA0, 0x5F, 0x85}
+    DEFINE_GUID! {WPD_CONTENT_TYPE_FUNCTIONAL_OBJECT,
+    0x99ED0160, 0x17FF, 0x4C44, 0x9D, 0x98, 0x1D, 0x7A, 0x6F, 0x94, 0x19, 0x21}
+
+    /// Parent id of the objects at the root of every device, usually its storages.
+    const DEVICE_OBJECT_ID: &str = "DEVICE";
+
+    fn check(result: HRESULT) -> io::Result<()> {
+        if SUCCEEDED(result) { Ok(()) } else { Err(io::Error::from_raw_os_error(result)) }
+    }
+
+    fn wide(value: &str) -> Vec<u16> {
+        value.encode_utf16().chain(Some(0)).collect()
+    }
+
+    /// Reads and frees a string allocated by COM.
+    unsafe fn take_string(string: LPWSTR) -> String {
+        unsafe {
+            let len = (0..).take_while(|&i| *string.add(i) != 0).count();
+            let value = String::from_utf16_lossy(slice::from_raw_parts(string, len));
+            CoTaskMemFree(string as *mut c_void);
+            value
+        }
+    }
+
+    /// Keeps COM initialized on the current thread while alive. Every thread using
+    /// the device API holds one, so that pooled threads don't stay initialized.
+    struct ComGuard {
+        initialized: bool, // Fails if the thread uses another apartment model, COM works all the same then
+    }
+
+    impl ComGuard {
+        fn new() -> Self {
+            let initialized = unsafe { SUCCEEDED(CoInitializeEx(ptr::null_mut(), COINIT_MULTITHREADED)) };
+            Self { initialized }
+        }
+    }
+
+    impl Drop for ComGuard {
+        fn drop(&mut self) {
+            if self.initialized {
+                unsafe { CoUninitialize() };
+            }
+        }
+    }
+
+    /// Creates a COM object. The thread must hold a `ComGuard`.
+    unsafe fn create<T: Interface>(class: &GUID) -> io::Result<*mut T> {
+        let mut instance: *mut T = ptr::null_mut();
+        unsafe {
+            let result = CoCreateInstance(
+                class,
+                ptr::null_mut(),
+                CLSCTX_INPROC_SERVER,
+                &T::uuidof(),
+                &mut instance as *mut *mut T as *mut *mut c_void,
+            );
+            check(result)?;
+        }
+        Ok(instance)
+    }
+
+    pub fn list_devices() -> Vec<Device> {
+        let mut devices = Vec::new();
+        let _com = ComGuard::new();
+        unsafe {
+            let Ok(manager) = create::<IPortableDeviceManager>(&CLSID_PortableDeviceManager) else {
+                return devices;
+            };
+            let mut count: DWORD = 0;
+            if SUCCEEDED((*manager).GetDevices(ptr::null_mut(), &mut count)) && count > 0 {
+                let mut ids: Vec<LPWSTR> = vec![ptr::null_mut(); count as usize];
+                if SUCCEEDED((*manager).GetDevices(ids.as_mut_ptr(), &mut count)) {
+                    for &id in &ids[..count as usize] {
+                        let mut name = [0u16; 256];
+                        let mut name_len = name.len() as DWORD;
+                        let has_name =
+                            SUCCEEDED((*manager).GetDeviceFriendlyName(id, name.as_mut_ptr(), &mut name_len));
+                        let id = take_string(id);
+                        let name_len = if has_name { name.iter().position(|&c| c == 0).unwrap_or(0) } else { 0 };
+                        let name = String::from_utf16_lossy(&name[..name_len]);
+                        let name = if name.is_empty() { "Portable device".to_string() } else { name };
+                        devices.push(Device { id, name });
+                    }
+                }
+            }
+            (*manager).Release();
+        }
+        devices
+    }
+
+    /// An open device. Opened again for every listing and copy job, on the thread using it.
+    pub struct Connection {
+        device: *mut IPortableDevice,
+        content: *mut IPortableDeviceContent,
+        properties: *mut IPortableDeviceProperties,
+        _com: ComGuard, // Last, so that COM is uninitialized after the interfaces are released
+    }
+
+    impl Connection {
+        pub fn open(device_id: &str) -> io::Result<Self> {
+            let com = ComGuard::new();
+            unsafe {
+                let device = create::<IPortableDevice>(&CLSID_PortableDeviceFTM)?;
+                // Identifying the client is optional, an empty set of values will do
+                let client = match create::<IPortableDeviceValues>(&CLSID_PortableDeviceValues) {
+                    Ok(client) => client,
+                    Err(e) => {
+                        (*device).Release();
+                        return Err(e);
+                    }
+                };
+                let opened = check((*device).Open(wide(device_id).as_ptr(), client));
+                (*client).Release();
+                let mut connection =
+                    Connection { device, content: ptr::null_mut(), properties: ptr::null_mut(), _com: com };
+                opened?;
+                check((*device).Content(&mut connection.content))?;
+                check((*connection.content).Properties(&mut connection.properties))?;
+                Ok(connection)
+            }
+        }
+
+        /// The objects inside `parent_id`, or at the root of the device.
+        pub fn list(&self, parent_id: Option<&str>) -> io::Result<Vec<DeviceObject>> {
+            let parent_id = wide(parent_id.unwrap_or(DEVICE_OBJECT_ID));
+            let mut objects = Vec::new();
+            unsafe {
+                let mut ids: *mut IEnumPortableDeviceObjectIDs = ptr::null_mut();
+                check((*self.content).EnumObjects(0, parent_id.as_ptr(), ptr::null_mut(), &mut ids))?;
+                loop {
+                    let mut id: LPWSTR = ptr::null_mut();
+                    let mut fetched: ULONG = 0;
+                    if (*ids).Next(1, &mut id, &mut fetched) != S_OK || fetched == 0 {
+                        break;
+                    }
+                    let id = take_string(id);
+                    if let Some(object) = self.object(id) {
+                        objects.push(object);
+                    }
+                }
+                (*ids).Release();
+            }
+            objects.sort_by_cached_key(|object| (!object.is_dir, object.name.to_lowercase()));
+            Ok(objects)
+        }
+
+        fn object(&self, id: String) -> Option<DeviceObject> {
+            unsafe {
+                let mut values: *mut IPortableDeviceValues = ptr::null_mut();
+                // Without a list of keys, every property is read
+                if !SUCCEEDED((*self.properties).GetValues(wide(&id).as_ptr(), ptr::null_mut(), &mut values)) {
+                    return None;
+                }
+                let string = |key: &PROPERTYKEY| {
+                    let mut value: LPWSTR = ptr::null_mut();
+                    (SUCCEEDED((*values).GetStringValue(key, &mut value)) && !value.is_null())
+                        .then(|| take_string(value))
+                        .filter(|value| !value.is_empty())
+                };
+                let mut content_type: GUID = std::mem::zeroed();
+                let is_dir = SUCCEEDED((*values).GetGuidValue(&WPD_OBJECT_CONTENT_TYPE, &mut content_type))
+                    && (IsEqualGUID(&content_type, &WPD_CONTENT_TYPE_FOLDER)
+                        || IsEqualGUID(&content_type, &WPD_CONTENT_TYPE_FUNCTIONAL_OBJECT));
+                // The name of files may lack the extension
+                let name = if is_dir { None } else { string(&WPD_OBJECT_ORIGINAL_FILE_NAME) };
+                let name = name.or_else(|| string(&WPD_OBJECT_NAME)).unwrap_or_else(|| id.clone());
+                let mut size = 0u64;
+                let has_size = !is_dir && SUCCEEDED((*values).GetUnsignedLargeIntegerValue(&WPD_OBJECT_SIZE, &mut size));
+                (*values).Release();
+                Some(DeviceObject { id, name, is_dir, size: has_size.then_some(size) })
+            }
+        }
+
+        /// Copies `object` into `destination`. Returns the number of files written.
+        pub fn copy(
+            &self,
+            object: &DeviceObject,
+            destination: &Path,
+            on_conflict: ConflictChoice,
+            job: &JobContext,
+        ) -> io::Result<usize> {
+            if job.is_cancelled() {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "copy cancelled"));
+            }
+            // Names come from the device and must not escape the destination
+            let target = fileops::safe_child_path(destination, &object.name)?;
+            if object.is_dir {
+                fs::create_dir_all(&target)?;
+                let mut count = 0;
+                for child in self.list(Some(&object.id))? {
+                    count += self.copy(&child, &target, on_conflict, job)?;
+                }
+                return Ok(count);
+            }
+            let written = fileops::write_new_file(&target, on_conflict, |output| self.read(&object.id, output, job))?;
+            Ok(written as usize)
+        }
+
+        fn read(&self, id: &str, output: &mut File, job: &JobContext) -> io::Result<()> {
+            unsafe {
+                let mut resources: *mut IPortableDeviceResources = ptr::null_mut();
+                check((*self.content).Transfer(&mut resources))?;
+                let mut buffer_size: DWORD = 0;
+                let mut stream: *mut IStream = ptr::null_mut();
+                let opened = check((*resources).GetStream(
+                    wide(id).as_ptr(),
+                    &WPD_RESOURCE_DEFAULT,
+                    STGM_READ,
+                    &mut buffer_size,
+                    &mut stream,
+                ));
+                (*resources).Release();
+                opened?;
+                let mut buffer = vec![0u8; (buffer_size as usize).clamp(64 * 1024, 4 * 1024 * 1024)];
+                let result = loop {
+                    if job.is_cancelled() {
+                        break Err(io::Error::new(io::ErrorKind::Interrupted, "copy cancelled"));
+                    }
+                    let mut read: ULONG = 0;
+                    let result = (*stream).Read(buffer.as_mut_ptr() as *mut c_void, buffer.len() as ULONG, &mut read);
+                    if let Err(e) = check(result) {
+                        break Err(e);
+                    }
+                    if read == 0 {
+                        break Ok(());
+                    }
+                    if let Err(e) = output.write_all(&buffer[..read as usize]) {
+                        break Err(e);
+                    }
+                };
+                (*stream).Release();
+                result
+            }
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            unsafe {
+                if !self.properties.is_null() {
+                    (*self.properties).Release();
+                }
+                if !self.content.is_null() {
+                    (*self.content).Release();
+                }
+                (*self.device).Close();
+                (*self.device).Release();
+            }
+        }
+    }
+}
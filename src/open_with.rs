@@ -0,0 +1,412 @@
+// --- "Open with": programs registered for a file type, and a chooser for any other program ---
+//
+// Programs are stored as command lines. Registered handlers use their own command
+// (the executable on Windows, the desktop entry's `Exec` line on Linux), programs
+// picked by hand use the quoted path of the executable.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+};
+
+use eframe::egui;
+
+use crate::{folder_picker::FolderPicker, view};
+
+/// A program offered in the "Open with" menu.
+pub struct Handler {
+    pub name: String,    // As shown to the user, e.g. "Image Viewer"
+    pub command: String, // Command line passed to `launch`
+}
+
+/// Key under which the program for `path`'s type is remembered: its lowercase extension.
+pub fn extension_key(path: &Path) -> Option<String> {
+    path.extension().map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+/// Programs registered for the type of `path`. Empty on macOS, where programs are picked by hand.
+#[cfg(windows)]
+pub fn handlers_for(path: &Path) -> Vec<Handler> {
+    match extension_key(path) {
+        Some(extension) => native::handlers_for(&extension),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn handlers_for(_path: &Path) -> Vec<Handler> {
+    Vec::new()
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn handlers_for(path: &Path) -> Vec<Handler> {
+    let Some(mime_type) = mime_type(path) else {
+        return Vec::new();
+    };
+    let mut seen = std::collections::HashSet::new();
+    let mut handlers = Vec::new();
+    for dir in application_dirs() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            // A desktop entry hides the ones with the same name in later directories
+            let id = entry.file_name();
+            if !id.as_encoded_bytes().ends_with(b".desktop") || !seen.insert(id) {
+                continue;
+            }
+            if let Ok(content) = fs::read_to_string(entry.path())
+                && let Some(handler) = parse_desktop_entry(&content, &mime_type)
+            {
+                handlers.push(handler);
+            }
+        }
+    }
+    handlers.sort_by(|a, b| view::natural_cmp(&a.name, &b.name));
+    handlers
+}
+
+/// Asks `xdg-mime` for the MIME type of `path`, e.g. "image/png".
+#[cfg(all(unix, not(target_os = "macos")))]
+fn mime_type(path: &Path) -> Option<String> {
+    let output = Command::new("xdg-mime").args(["query", "filetype"]).arg(path).output().ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    // Some fallbacks append parameters, e.g. "text/plain; charset=us-ascii"
+    let mime_type = output.split(';').next().unwrap_or_default().trim();
+    (!mime_type.is_empty()).then(|| mime_type.to_string())
+}
+
+/// The `applications` folders of the XDG data directories, most important first.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn application_dirs() -> Vec<PathBuf> {
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs::data_dir()
+        .into_iter()
+        .chain(std::env::split_paths(&data_dirs))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// Reads a desktop entry and returns it as a handler if it is a visible application for `mime_type`.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn parse_desktop_entry(content: &str, mime_type: &str) -> Option<Handler> {
+    let mut in_entry = false;
+    let (mut name, mut exec, mut mime_types) = (None, None, "");
+    let (mut is_application, mut is_hidden) = (false, false);
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_entry) else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "Name" => name = Some(value),
+            "Exec" => exec = Some(value),
+            "MimeType" => mime_types = value,
+            "Type" => is_application = value == "Application",
+            "NoDisplay" | "Hidden" => is_hidden |= value == "true",
+            _ => {}
+        }
+    }
+    if !is_application || is_hidden || !mime_types.split(';').any(|t| t.trim() == mime_type) {
+        return None;
+    }
+    Some(Handler { name: name?.to_string(), command: exec?.to_string() })
+}
+
+/// Command line for a program picked by hand.
+pub fn command_for_program(program: &Path) -> String {
+    format!("\"{}\"", program.display())
+}
+
+/// Starts `command` with `path`. The path replaces the %f / %F / %u / %U codes of desktop
+/// entries, or is appended if there are none. Application bundles start through `open -a`.
+pub fn launch(command: &str, path: &Path) -> io::Result<()> {
+    let words = split_command(command);
+    let Some((program, args)) = words.split_first() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no program given"));
+    };
+    let mut process;
+    if cfg!(target_os = "macos") && program.trim_end_matches('/').ends_with(".app") {
+        process = Command::new("open");
+        process.arg("-a").arg(program).arg(path);
+    } else {
+        process = Command::new(program);
+        let mut has_file_code = false;
+        for arg in args {
+            match arg.as_str() {
+                "%f" | "%F" | "%u" | "%U" => {
+                    process.arg(path);
+                    has_file_code = true;
+                }
+                "%i" | "%c" | "%k" => {} // Icon, name and entry location aren't passed
+                _ => {
+                    process.arg(arg.replace("%%", "%"));
+                }
+            }
+        }
+        if !has_file_code {
+            process.arg(path);
+        }
+    }
+    let mut child = process.spawn()?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Splits a command line at whitespace outside of double quotes.
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let (mut in_word, mut in_quotes) = (false, false);
+    for c in command.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// What the user did in the program chooser.
+pub enum ChooserAction {
+    Cancel,
+    Open { path: PathBuf, command: String, remember: bool },
+}
+
+/// Dialog to open a file with any program: a folder tree, the programs in the selected
+/// folder and the command line, which can also be typed in.
+pub struct ProgramChooser {
+    path: PathBuf, // File to open
+    picker: FolderPicker,
+    programs: Option<(PathBuf, Vec<PathBuf>)>, // Programs in the folder selected in `picker`
+    command: String,
+    remember: bool, // Whether to always use the program for files with this extension
+}
+
+impl ProgramChooser {
+    pub fn new(path: &Path, remember: bool) -> Self {
+        Self {
+            path: path.to_owned(),
+            picker: FolderPicker::new(&programs_folder()),
+            programs: None,
+            command: String::new(),
+            remember,
+        }
+    }
+
+    /// Renders the dialog. Returns what the user chose once it was closed.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<ChooserAction> {
+        let mut action = None;
+        let mut is_open = true;
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        egui::Window::new(format!("Open {} with", name))
+            .id(egui::Id::new("program_chooser"))
+            .open(&mut is_open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().id_source("program_folders").max_height(200.0).show(ui, |ui| {
+                    self.picker.show(ui);
+                });
+                ui.separator();
+
+                if self.programs.as_ref().is_none_or(|(folder, _)| *folder != self.picker.selected) {
+                    self.programs = Some((self.picker.selected.clone(), list_programs(&self.picker.selected)));
+                }
+                if let Some((_, programs)) = &self.programs {
+                    if programs.is_empty() {
+                        ui.weak("No programs in this folder");
+                    }
+                    let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                    egui::ScrollArea::vertical().id_source("program_files").max_height(150.0).show_rows(
+                        ui,
+                        row_height,
+                        programs.len(),
+                        |ui, rows| {
+                            for program in &programs[rows] {
+                                let command = command_for_program(program);
+                                let name = program.file_name().unwrap_or_default().to_string_lossy();
+                                if ui.selectable_label(self.command == command, name).clicked() {
+                                    self.command = command;
+                                }
+                            }
+                        },
+                    );
+                }
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Program:");
+                    ui.text_edit_singleline(&mut self.command);
+                });
+                if let Some(extension) = extension_key(&self.path) {
+                    ui.checkbox(&mut self.remember, format!("Always use it for .{} files", extension));
+                }
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!self.command.trim().is_empty(), egui::Button::new("Open")).clicked() {
+                        action = Some(ChooserAction::Open {
+                            path: self.path.clone(),
+                            command: self.command.trim().to_string(),
+                            remember: self.remember,
+                        });
+                    }
+                    if ui.button("Cancel").clicked() {
+                        action = Some(ChooserAction::Cancel);
+                    }
+                });
+            });
+        if !is_open {
+            action = Some(ChooserAction::Cancel);
+        }
+        action
+    }
+}
+
+/// Folder the chooser starts in: where programs are usually installed.
+fn programs_folder() -> PathBuf {
+    if cfg!(windows) {
+        std::env::var_os("ProgramFiles").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("C:\\Program Files"))
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from("/Applications")
+    } else {
+        PathBuf::from("/usr/bin")
+    }
+}
+
+/// Lists the programs in `folder`, sorted by name.
+fn list_programs(folder: &Path) -> Vec<PathBuf> {
+    let mut programs: Vec<PathBuf> = fs::read_dir(folder)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| is_program(path)).collect())
+        .unwrap_or_default();
+    programs.sort_by(|a, b| view::natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    programs
+}
+
+#[cfg(windows)]
+fn is_program(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| ["exe", "bat", "cmd", "com"].iter().any(|e| extension.eq_ignore_ascii_case(e)))
+}
+
+/// Executable files, plus application bundles on macOS.
+#[cfg(unix)]
+fn is_program(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    if cfg!(target_os = "macos") && path.extension().is_some_and(|extension| extension == "app") {
+        return path.is_dir();
+    }
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(windows)]
+#[allow(non_snake_case)]
+mod native {
+    use std::{ptr, slice};
+
+    use winapi::RIDL;
+    use winapi::ctypes::{c_int, c_void};
+    use winapi::shared::minwindef::ULONG;
+    use winapi::shared::winerror::{S_OK, SUCCEEDED};
+    use winapi::um::combaseapi::CoTaskMemFree;
+    use winapi::um::objidl::IDataObject;
+    use winapi::um::ole2::OleInitialize;
+    use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+    use winapi::um::winnt::{HRESULT, LPCWSTR, LPWSTR};
+
+    use super::Handler;
+
+    // Not part of winapi 0.3
+    RIDL! {#[uuid(0xf04061ac, 0x1659, 0x4a3f, 0xa9, 0x54, 0x77, 0x5a, 0xa5, 0x7f, 0xc0, 0x83)]
+    interface IAssocHandler(IAssocHandlerVtbl): IUnknown(IUnknownVtbl) {
+        fn GetName(ppsz: *mut LPWSTR,) -> HRESULT,
+        fn GetUIName(ppsz: *mut LPWSTR,) -> HRESULT,
+        fn GetIconLocation(ppszPath: *mut LPWSTR, pIndex: *mut c_int,) -> HRESULT,
+        fn IsRecommended() -> HRESULT,
+        fn MakeDefault(pszDescription: LPCWSTR,) -> HRESULT,
+        fn Invoke(pdo: *mut IDataObject,) -> HRESULT,
+        fn CreateInvoker(pdo: *mut IDataObject, ppInvoker: *mut *mut c_void,) -> HRESULT,
+    }}
+
+    RIDL! {#[uuid(0x973810ae, 0x9599, 0x4b88, 0x9e, 0x4d, 0x6e, 0xe9, 0x8c, 0x95, 0x52, 0xda)]
+    interface IEnumAssocHandlers(IEnumAssocHandlersVtbl): IUnknown(IUnknownVtbl) {
+        fn Next(celt: ULONG, rgelt: *mut *mut IAssocHandler, pceltFetched: *mut ULONG,) -> HRESULT,
+    }}
+
+    const ASSOC_FILTER_RECOMMENDED: c_int = 0x1;
+
+    #[link(name = "shell32")]
+    unsafe extern "system" {
+        fn SHAssocEnumHandlers(
+            pszExtra: LPCWSTR,
+            afFilter: c_int,
+            ppEnumHandler: *mut *mut IEnumAssocHandlers,
+        ) -> HRESULT;
+    }
+
+    /// The recommended handlers of `.extension`, as listed by Explorer's "Open with".
+    pub fn handlers_for(extension: &str) -> Vec<Handler> {
+        let extension: Vec<u16> = format!(".{}", extension).encode_utf16().chain(Some(0)).collect();
+        let mut handlers = Vec::new();
+        unsafe {
+            OleInitialize(ptr::null_mut());
+            let mut handler_list: *mut IEnumAssocHandlers = ptr::null_mut();
+            if !SUCCEEDED(SHAssocEnumHandlers(extension.as_ptr(), ASSOC_FILTER_RECOMMENDED, &mut handler_list)) {
+                return handlers;
+            }
+            loop {
+                let mut handler: *mut IAssocHandler = ptr::null_mut();
+                let mut fetched: ULONG = 0;
+                if (*handler_list).Next(1, &mut handler, &mut fetched) != S_OK || handler.is_null() {
+                    break;
+                }
+                // The name is the executable, which is what gets launched
+                let program = take_string(|string| (*handler).GetName(string));
+                let name = take_string(|string| (*handler).GetUIName(string));
+                if let (Some(program), Some(name)) = (program, name) {
+                    handlers.push(Handler { name, command: format!("\"{}\"", program) });
+                }
+                (*handler).Release();
+            }
+            (*handler_list).Release();
+        }
+        handlers
+    }
+
+    /// Calls a getter that returns a string allocated by COM, and frees it.
+    unsafe fn take_string(get: impl FnOnce(*mut LPWSTR) -> HRESULT) -> Option<String> {
+        let mut string: LPWSTR = ptr::null_mut();
+        unsafe {
+            if !SUCCEEDED(get(&mut string)) || string.is_null() {
+                return None;
+            }
+            let len = (0..).take_while(|&i| *string.add(i) != 0).count();
+            let value = String::from_utf16_lossy(slice::from_raw_parts(string, len));
+            CoTaskMemFree(string as *mut c_void);
+            Some(value)
+        }
+    }
+}
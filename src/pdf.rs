@@ -0,0 +1,47 @@
+// --- PDF first-page rendering (via the pdfium library, if it is installed) ---
+
+use std::{path::Path, sync::OnceLock};
+
+use image::RgbaImage;
+use pdfium_render::prelude::*;
+
+/// pdfium, bound on first use. `None` if the library couldn't be loaded.
+static PDFIUM: OnceLock<Option<Pdfium>> = OnceLock::new();
+
+/// Whether `path` has a PDF extension.
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("pdf"))
+}
+
+/// Binds to pdfium the first time it is needed, looking next to the executable first, then in
+/// the system library paths. A missing library is reported once.
+fn pdfium() -> Option<&'static Pdfium> {
+    PDFIUM
+        .get_or_init(|| {
+            let exe_dir = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_owned));
+            let bindings = exe_dir
+                .map(|dir| Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&dir)))
+                .filter(|bindings| bindings.is_ok())
+                .unwrap_or_else(Pdfium::bind_to_system_library);
+            match bindings {
+                Ok(bindings) => Some(Pdfium::new(bindings)),
+                Err(e) => {
+                    eprintln!("PDF pages can't be shown, pdfium could not be loaded: {}", e);
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+/// Renders the first page of a PDF to fit into `max_size` × `max_size`.
+/// `None` if pdfium is not available or the document can't be rendered.
+pub fn render_first_page(path: &Path, max_size: u32) -> Option<RgbaImage> {
+    let document = pdfium()?.load_pdf_from_file(path, None).ok()?;
+    let page = document.pages().get(0).ok()?;
+    let config = PdfRenderConfig::new()
+        .set_target_width(max_size as Pixels)
+        .set_maximum_height(max_size as Pixels);
+    let bitmap = page.render_with_config(&config).ok()?;
+    RgbaImage::from_raw(bitmap.width() as u32, bitmap.height() as u32, bitmap.as_rgba_bytes())
+}
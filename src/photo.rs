@@ -0,0 +1,104 @@
+// --- EXIF metadata of photos ---
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use chrono::NaiveDateTime;
+use exif::{Exif, In, Tag, Value};
+
+/// Extensions that may carry EXIF data (JPEG, TIFF-based RAW formats, HEIF, PNG, WebP).
+const EXIF_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "tif", "tiff", "heic", "heif", "png", "webp", "dng", "nef", "cr2", "arw", "orf", "rw2", "pef",
+];
+
+/// What the preview pane shows about a photo.
+pub struct PhotoInfo {
+    pub camera: Option<String>,
+    pub taken: Option<NaiveDateTime>,
+    pub gps: Option<(f64, f64)>, // Latitude, longitude in degrees (south / west negative)
+    pub orientation: Option<&'static str>,
+}
+
+fn has_exif_extension(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| EXIF_EXTENSIONS.contains(&extension.as_str()))
+}
+
+fn read_exif(path: &Path) -> Option<Exif> {
+    if !has_exif_extension(path) {
+        return None;
+    }
+    let file = File::open(path).ok()?;
+    exif::Reader::new().read_from_container(&mut BufReader::new(file)).ok()
+}
+
+/// Reads the EXIF data of `path`. `None` for other files and photos without EXIF data.
+pub fn read_info(path: &Path) -> Option<PhotoInfo> {
+    let exif = read_exif(path)?;
+    let make = ascii(&exif, Tag::Make);
+    let model = ascii(&exif, Tag::Model);
+    // Most models already start with the make ("Canon EOS 80D")
+    let camera = match (make, model) {
+        (Some(make), Some(model)) if !model.to_lowercase().starts_with(&make.to_lowercase()) => {
+            Some(format!("{} {}", make, model))
+        }
+        (make, model) => model.or(make),
+    };
+    Some(PhotoInfo {
+        camera,
+        taken: taken(&exif),
+        gps: coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")
+            .zip(coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")),
+        orientation: exif
+            .get_field(Tag::Orientation, In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            .and_then(describe_orientation),
+    })
+}
+
+/// When the photo was taken, for sorting. Cheaper than `read_info`.
+pub fn date_taken(path: &Path) -> Option<NaiveDateTime> {
+    read_exif(path).and_then(|exif| taken(&exif))
+}
+
+fn taken(exif: &Exif) -> Option<NaiveDateTime> {
+    let text = ascii(exif, Tag::DateTimeOriginal).or_else(|| ascii(exif, Tag::DateTime))?;
+    NaiveDateTime::parse_from_str(&text, "%Y:%m:%d %H:%M:%S").ok()
+}
+
+/// First string of an ASCII field, trimmed.
+fn ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string())
+            .filter(|text| !text.is_empty()),
+        _ => None,
+    }
+}
+
+/// GPS coordinate from degrees / minutes / seconds, negative if the reference is `negative_ref`.
+fn coordinate(exif: &Exif, tag: Tag, ref_tag: Tag, negative_ref: &str) -> Option<f64> {
+    let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let [degrees, minutes, seconds] = parts.as_slice() else {
+        return None;
+    };
+    let value = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+    if ascii(exif, ref_tag).as_deref() == Some(negative_ref) { Some(-value) } else { Some(value) }
+}
+
+fn describe_orientation(orientation: u32) -> Option<&'static str> {
+    match orientation {
+        1 => Some("Normal"),
+        2 => Some("Mirrored"),
+        3 => Some("Rotated 180°"),
+        4 => Some("Mirrored, rotated 180°"),
+        5 => Some("Mirrored, rotated 90° counter-clockwise"),
+        6 => Some("Rotated 90° clockwise"),
+        7 => Some("Mirrored, rotated 90° clockwise"),
+        8 => Some("Rotated 90° counter-clockwise"),
+        _ => None,
+    }
+}
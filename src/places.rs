@@ -0,0 +1,123 @@
+// --- Bookmarked and recently visited folders, recently opened files ---
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// At most this many recent folders are kept, the oldest one is dropped first.
+const MAX_RECENT_FOLDERS: usize = 10;
+/// Same for files, which are opened more often than folders are visited.
+const MAX_RECENT_FILES: usize = 50;
+
+#[derive(Default)]
+pub struct Places {
+    pub bookmarks: Vec<PathBuf>, // In the order they were added
+    pub recent: Vec<PathBuf>, // Most recently visited first
+    pub recent_files: Vec<(PathBuf, SystemTime)>, // Opened through FileFox, most recent first
+}
+
+impl Places {
+    /// Location of the list on disk.
+    fn places_file() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("FileFox").join("places.txt"))
+    }
+
+    /// Loads the folders and files written by `save`. A missing file yields none.
+    pub fn load() -> io::Result<Self> {
+        let mut places = Self::default();
+        let Some(path) = Self::places_file() else {
+            return Ok(places);
+        };
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(places),
+            Err(e) => return Err(e),
+        };
+        for line in BufReader::new(file).lines() {
+            // "bookmark = <path>", "recent = <path>" or "file = <seconds since 1970> <path>"
+            let line = line?;
+            let Some((kind, path)) = line.split_once(" = ") else {
+                continue;
+            };
+            match kind {
+                "bookmark" => places.bookmarks.push(PathBuf::from(path)),
+                "recent" => places.recent.push(PathBuf::from(path)),
+                "file" => {
+                    if let Some((seconds, path)) = path.split_once(' ')
+                        && let Ok(seconds) = seconds.parse()
+                    {
+                        let opened = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+                        places.recent_files.push((PathBuf::from(path), opened));
+                    }
+                }
+                _ => {}
+            }
+        }
+        places.recent.truncate(MAX_RECENT_FOLDERS);
+        places.recent_files.truncate(MAX_RECENT_FILES);
+        Ok(places)
+    }
+
+    /// Writes the folders and files to disk. Paths that are not valid UTF-8 are left out.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::places_file() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no data directory available"));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = BufWriter::new(fs::File::create(&path)?);
+        let bookmarks = self.bookmarks.iter().map(|path| ("bookmark", path));
+        let folders = bookmarks.chain(self.recent.iter().map(|path| ("recent", path)));
+        for (kind, path) in folders {
+            if let Some(path) = path.to_str() {
+                writeln!(writer, "{} = {}", kind, path)?;
+            }
+        }
+        for (path, opened) in &self.recent_files {
+            let seconds = opened.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs();
+            if let Some(path) = path.to_str() {
+                writeln!(writer, "file = {} {}", seconds, path)?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Moves `dir` to the front of the recent folders.
+    pub fn visit(&mut self, dir: &Path) {
+        self.recent.retain(|recent| recent != dir);
+        self.recent.insert(0, dir.to_owned());
+        self.recent.truncate(MAX_RECENT_FOLDERS);
+    }
+
+    /// Moves `file` to the front of the recent files, opened now.
+    pub fn open_file(&mut self, file: &Path) {
+        self.recent_files.retain(|(recent, _)| recent != file);
+        self.recent_files.insert(0, (file.to_owned(), SystemTime::now()));
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    pub fn is_bookmarked(&self, dir: &Path) -> bool {
+        self.bookmarks.iter().any(|bookmark| bookmark == dir)
+    }
+
+    /// Bookmarks `dir`, or removes its bookmark.
+    pub fn toggle_bookmark(&mut self, dir: &Path) {
+        if self.is_bookmarked(dir) {
+            self.bookmarks.retain(|bookmark| bookmark != dir);
+        } else {
+            self.bookmarks.push(dir.to_owned());
+        }
+    }
+}
+
+/// Name of a folder in menus: the last component, or the whole path for drive roots.
+pub fn folder_label(dir: &Path) -> String {
+    match dir.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => dir.display().to_string(),
+    }
+}
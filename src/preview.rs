@@ -0,0 +1,244 @@
+// --- Preview pane for the selected file ---
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use eframe::egui;
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+
+use crate::{
+    fileops,
+    filetype::{self, ContentType},
+    media::{self, MediaInfo},
+    pdf,
+    photo::{self, PhotoInfo},
+    view::{self, EntryDetails, FileCategory},
+};
+
+/// At most this much of a text file is shown.
+const TEXT_PREVIEW_BYTES: u64 = 16 * 1024;
+/// Images are scaled down to fit this size before being uploaded as a texture.
+const IMAGE_PREVIEW_SIZE: u32 = 1024;
+
+/// What the preview pane shows below the metadata.
+enum PreviewContent {
+    Loading,
+    Image(egui::TextureHandle),
+    Text { text: String, truncated: bool },
+    Markdown { text: String, truncated: bool }, // Rendered, not shown as source
+    Media(MediaInfo),
+    Nothing, // Folders, binary files, unreadable files
+}
+
+/// Content decoded on the loader thread.
+enum LoadedContent {
+    Image(egui::ColorImage),
+    Text { text: String, truncated: bool },
+    Markdown { text: String, truncated: bool },
+    Media(MediaInfo),
+    Nothing,
+}
+
+/// Preview of a single file or folder. Content is loaded on a background thread.
+pub struct Preview {
+    pub path: PathBuf,
+    details: EntryDetails,
+    created: Option<std::time::SystemTime>,
+    photo: Option<PhotoInfo>, // EXIF data of photos
+    content: PreviewContent,
+    markdown_cache: CommonMarkCache,
+    receiver: Option<mpsc::Receiver<LoadedContent>>,
+}
+
+impl Preview {
+    /// Starts loading the preview of `path`.
+    pub fn load(path: &Path, ctx: &egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let (thread_path, ctx) = (path.to_owned(), ctx.clone());
+        std::thread::spawn(move || {
+            if sender.send(load_content(&thread_path)).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+        Self {
+            path: path.to_owned(),
+            details: EntryDetails::read(path),
+            created: fs::metadata(fileops::extended_path(path)).and_then(|m| m.created()).ok(),
+            photo: photo::read_info(path),
+            content: PreviewContent::Loading,
+            markdown_cache: CommonMarkCache::default(),
+            receiver: Some(receiver),
+        }
+    }
+
+    /// Renders the pane, picking up the loaded content once it is ready.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if let Some(receiver) = &self.receiver
+            && let Ok(loaded) = receiver.try_recv()
+        {
+            self.content = match loaded {
+                LoadedContent::Image(image) => PreviewContent::Image(ui.ctx().load_texture(
+                    format!("preview:{}", self.path.display()),
+                    image,
+                    egui::TextureOptions::LINEAR,
+                )),
+                LoadedContent::Text { text, truncated } => PreviewContent::Text { text, truncated },
+                LoadedContent::Markdown { text, truncated } => PreviewContent::Markdown { text, truncated },
+                LoadedContent::Media(info) => PreviewContent::Media(info),
+                LoadedContent::Nothing => PreviewContent::Nothing,
+            };
+            self.receiver = None;
+        }
+
+        let name = self.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        ui.heading(name);
+        egui::Grid::new("preview_metadata").num_columns(2).show(ui, |ui| {
+            ui.label("Type:");
+            ui.label(&self.details.kind);
+            ui.end_row();
+            if let Some(size) = self.details.size {
+                ui.label("Size:");
+                ui.label(format!("{} ({} bytes)", view::format_size(size), size));
+                ui.end_row();
+            }
+            if let Some(modified) = self.details.modified {
+                ui.label("Modified:");
+                ui.label(view::format_time(modified));
+                ui.end_row();
+            }
+            if let Some(created) = self.created {
+                ui.label("Created:");
+                ui.label(view::format_time(created));
+                ui.end_row();
+            }
+            if let Some(photo) = &self.photo {
+                if let Some(camera) = &photo.camera {
+                    ui.label("Camera:");
+                    ui.label(camera);
+                    ui.end_row();
+                }
+                if let Some(taken) = photo.taken {
+                    ui.label("Taken:");
+                    ui.label(taken.format("%Y-%m-%d %H:%M:%S").to_string());
+                    ui.end_row();
+                }
+                if let Some((latitude, longitude)) = photo.gps {
+                    ui.label("Location:");
+                    ui.label(format!("{:.5}, {:.5}", latitude, longitude));
+                    ui.end_row();
+                }
+                if let Some(orientation) = photo.orientation {
+                    ui.label("Orientation:");
+                    ui.label(orientation);
+                    ui.end_row();
+                }
+            }
+        });
+        ui.separator();
+
+        match &self.content {
+            PreviewContent::Loading => {
+                ui.spinner();
+            }
+            PreviewContent::Image(texture) => {
+                // Fit into the pane width, never scale up
+                let size = texture.size_vec2();
+                let scale = (ui.available_width() / size.x).min(1.0);
+                ui.image(texture.id(), size * scale);
+            }
+            PreviewContent::Text { text, truncated } => {
+                egui::ScrollArea::both().show(ui, |ui| {
+                    ui.add(egui::Label::new(egui::RichText::new(text).monospace()).wrap(false));
+                    if *truncated {
+                        ui.weak(format!("(only the first {} shown)", view::format_size(TEXT_PREVIEW_BYTES)));
+                    }
+                });
+            }
+            PreviewContent::Markdown { text, truncated } => {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    CommonMarkViewer::new("preview_markdown").show(ui, &mut self.markdown_cache, text);
+                    if *truncated {
+                        ui.weak(format!("(only the first {} shown)", view::format_size(TEXT_PREVIEW_BYTES)));
+                    }
+                });
+            }
+            PreviewContent::Media(info) => {
+                egui::Grid::new("preview_media").num_columns(2).show(ui, |ui| {
+                    if let Some(duration) = info.duration {
+                        ui.label("Duration:");
+                        ui.label(media::format_duration(duration));
+                        ui.end_row();
+                    }
+                    if let Some(bit_rate) = info.bit_rate {
+                        ui.label("Bitrate:");
+                        ui.label(media::format_bit_rate(bit_rate));
+                        ui.end_row();
+                    }
+                    for stream in &info.streams {
+                        ui.label(format!("{}:", capitalized(&stream.kind)));
+                        ui.label(stream.describe());
+                        ui.end_row();
+                    }
+                });
+            }
+            PreviewContent::Nothing => {
+                ui.weak("No preview available");
+            }
+        }
+    }
+}
+
+/// Decodes an image, or reads the start of a text file.
+fn load_content(path: &Path) -> LoadedContent {
+    let extended = fileops::extended_path(path);
+    if extended.is_dir() {
+        return LoadedContent::Nothing;
+    }
+    if filetype::detect_content_type(path) == Some(ContentType::Image)
+        && let Ok(image) = image::open(&*extended)
+    {
+        let image = image.thumbnail(IMAGE_PREVIEW_SIZE, IMAGE_PREVIEW_SIZE).into_rgba8();
+        let size = [image.width() as usize, image.height() as usize];
+        return LoadedContent::Image(egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw()));
+    }
+
+    if pdf::is_pdf(path)
+        && let Some(page) = pdf::render_first_page(path, IMAGE_PREVIEW_SIZE)
+    {
+        let size = [page.width() as usize, page.height() as usize];
+        return LoadedContent::Image(egui::ColorImage::from_rgba_unmultiplied(size, page.as_raw()));
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    if matches!(FileCategory::of(&name, false), FileCategory::Audio | FileCategory::Video) {
+        return media::probe(path).map_or(LoadedContent::Nothing, LoadedContent::Media);
+    }
+
+    let Ok(file) = File::open(&*extended) else {
+        return LoadedContent::Nothing;
+    };
+    let mut bytes = Vec::new();
+    if file.take(TEXT_PREVIEW_BYTES + 1).read_to_end(&mut bytes).is_err() || bytes.contains(&0) {
+        return LoadedContent::Nothing; // Unreadable or binary
+    }
+    let truncated = bytes.len() as u64 > TEXT_PREVIEW_BYTES;
+    bytes.truncate(TEXT_PREVIEW_BYTES as usize);
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    let is_markdown = path
+        .extension()
+        .is_some_and(|e| ["md", "markdown"].contains(&e.to_string_lossy().to_lowercase().as_str()));
+    if is_markdown {
+        LoadedContent::Markdown { text, truncated }
+    } else {
+        LoadedContent::Text { text, truncated }
+    }
+}
+
+/// "video" -> "Video".
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
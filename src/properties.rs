@@ -0,0 +1,206 @@
+// --- Properties dialog of a single entry ---
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use crate::{
+    checksum::{Algorithm, ChecksumTask},
+    fileops,
+    view::{self, EntryDetails},
+};
+
+/// Details of one file or folder. On Unix, its permissions can be edited here
+/// with checkboxes or as an octal number, like `chmod`. The checksums of files
+/// can be computed and compared with a pasted one.
+pub struct Properties {
+    path: PathBuf,
+    details: EntryDetails,
+    owner: Option<String>,
+    checksum_task: Option<ChecksumTask>, // While the checksums are computed
+    checksums: Option<Result<Vec<(Algorithm, String)>, String>>,
+    expected_checksum: String, // Pasted to compare with the computed ones
+    #[cfg(unix)]
+    mode: u32, // Permission bits being edited, applied with "Apply"
+    #[cfg(unix)]
+    mode_input: String, // `mode` as octal digits, e.g. "755"
+}
+
+impl Properties {
+    pub fn open(path: &Path) -> Self {
+        #[cfg(unix)]
+        let mode = fileops::file_mode(path).unwrap_or(0);
+        Self {
+            path: path.to_owned(),
+            details: EntryDetails::read(path),
+            owner: fileops::file_owner(path),
+            checksum_task: None,
+            checksums: None,
+            expected_checksum: String::new(),
+            #[cfg(unix)]
+            mode,
+            #[cfg(unix)]
+            mode_input: format!("{:03o}", mode),
+        }
+    }
+
+    /// Reads the details again, e.g. after the permissions were changed.
+    fn reload(&mut self) {
+        self.details = EntryDetails::read(&self.path);
+        self.owner = fileops::file_owner(&self.path);
+        #[cfg(unix)]
+        {
+            self.mode = fileops::file_mode(&self.path).unwrap_or(0);
+            self.mode_input = format!("{:03o}", self.mode);
+        }
+    }
+
+    /// Renders the dialog. Returns `false` once it was closed.
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut is_open = true;
+        let title = match self.path.file_name() {
+            Some(name) => format!("Properties of {}", name.to_string_lossy()),
+            None => format!("Properties of {}", self.path.display()),
+        };
+        egui::Window::new(title).id(egui::Id::new("properties")).open(&mut is_open).resizable(false).show(ctx, |ui| {
+            egui::Grid::new("properties_details").num_columns(2).show(ui, |ui| {
+                let rows = [
+                    ("Location", self.path.parent().map(|parent| parent.display().to_string()).unwrap_or_default()),
+                    ("Type", self.details.kind.clone()),
+                    ("Size", self.details.size.map(view::format_size).unwrap_or_default()),
+                    ("Modified", self.details.modified.map(view::format_time).unwrap_or_default()),
+                    ("Created", self.details.created.map(view::format_time).unwrap_or_default()),
+                    ("Owner", self.owner.clone().unwrap_or_default()),
+                    (if cfg!(unix) { "Permissions" } else { "Attributes" }, self.details.attributes.clone()),
+                ];
+                for (label, value) in rows {
+                    if !value.is_empty() {
+                        ui.label(label);
+                        ui.label(value);
+                        ui.end_row();
+                    }
+                }
+            });
+
+            if self.details.size.is_some() && !self.path.is_dir() {
+                self.show_checksums(ui, ctx);
+            }
+
+            #[cfg(unix)]
+            self.show_permissions_editor(ui);
+        });
+        is_open
+    }
+
+    /// MD5 / SHA-1 / SHA-256 / BLAKE3 of the file, computed on demand since large files take a while.
+    fn show_checksums(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.separator();
+        if let Some(task) = &self.checksum_task {
+            if let Some(result) = task.poll(ctx) {
+                self.checksums = Some(result);
+                self.checksum_task = None;
+            } else {
+                let cancel = ui
+                    .horizontal(|ui| {
+                        ui.add(egui::ProgressBar::new(task.progress()).show_percentage().desired_width(200.0));
+                        ui.button("Cancel").clicked()
+                    })
+                    .inner;
+                if cancel {
+                    self.checksum_task = None; // Dropping the task stops it
+                }
+                return;
+            }
+        }
+
+        let checksums = match &self.checksums {
+            None => {
+                if ui.button("Compute checksums").clicked() {
+                    let size = self.details.size.unwrap_or(0);
+                    self.checksum_task = Some(ChecksumTask::start(&self.path, size, ctx));
+                }
+                return;
+            }
+            Some(Err(e)) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Couldn't compute the checksums: {}", e));
+                if ui.button("Retry").clicked() {
+                    self.checksums = None;
+                }
+                return;
+            }
+            Some(Ok(checksums)) => checksums,
+        };
+        egui::Grid::new("properties_checksums").num_columns(3).show(ui, |ui| {
+            for (algorithm, checksum) in checksums {
+                ui.label(algorithm.label());
+                ui.monospace(checksum);
+                if ui.small_button("📋").on_hover_text("Copy to the clipboard").clicked() {
+                    ui.output_mut(|output| output.copied_text = checksum.clone());
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Compare with:");
+            ui.add(egui::TextEdit::singleline(&mut self.expected_checksum).hint_text("Paste a checksum"));
+        });
+        let expected = self.expected_checksum.trim();
+        if !expected.is_empty() {
+            match checksums.iter().find(|(_, checksum)| checksum.eq_ignore_ascii_case(expected)) {
+                Some((algorithm, _)) => {
+                    let matched = format!("✔ Matches the {}", algorithm.label());
+                    ui.colored_label(egui::Color32::from_rgb(80, 190, 90), matched);
+                }
+                None => {
+                    ui.colored_label(ui.visuals().error_fg_color, "✖ Doesn't match any checksum");
+                }
+            }
+        }
+    }
+
+    /// Read / write / execute checkboxes for owner, group and others, plus the octal number.
+    #[cfg(unix)]
+    fn show_permissions_editor(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        egui::Grid::new("properties_permissions").show(ui, |ui| {
+            ui.label("");
+            for column in ["Read", "Write", "Execute"] {
+                ui.label(column);
+            }
+            ui.end_row();
+            for (row, who) in ["Owner", "Group", "Others"].into_iter().enumerate() {
+                ui.label(who);
+                for column in 0..3 {
+                    let bit = 0o400 >> (row * 3 + column);
+                    let mut set = self.mode & bit != 0;
+                    if ui.checkbox(&mut set, "").changed() {
+                        self.mode ^= bit;
+                        self.mode_input = format!("{:03o}", self.mode);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Numeric:");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.mode_input).desired_width(50.0));
+            let parsed = u32::from_str_radix(self.mode_input.trim(), 8).ok().filter(|mode| *mode <= 0o7777);
+            if response.changed()
+                && let Some(mode) = parsed
+            {
+                self.mode = mode;
+            }
+            if parsed.is_none() {
+                ui.colored_label(ui.visuals().error_fg_color, "Not an octal mode");
+            }
+            if ui.add_enabled(parsed.is_some(), egui::Button::new("Apply")).clicked() {
+                if let Err(e) = fileops::set_file_mode(&self.path, self.mode) {
+                    eprintln!("Error while changing permissions of {:?}: {}", self.path, e);
+                }
+                self.reload();
+            }
+        });
+    }
+}
@@ -0,0 +1,75 @@
+// --- Moving entries to the trash / Recycle Bin instead of deleting them ---
+
+use std::path::Path;
+
+use crate::fileops;
+
+/// Moves `path` (a file or a whole folder) to the trash of its volume:
+/// the Recycle Bin on Windows, the Finder trash on macOS, the XDG trash elsewhere.
+pub fn move_to_trash(path: &Path) -> Result<(), trash::Error> {
+    trash::delete(fileops::extended_path(path))
+}
+
+/// Whether entries of `dir` can be moved to the trash, or could only be deleted permanently.
+/// The Recycle Bin only exists on fixed drives (not on network shares or most USB sticks).
+#[cfg(windows)]
+pub fn is_available(dir: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::GetDriveTypeW;
+    use winapi::um::winbase::DRIVE_FIXED;
+
+    let Some(root) = dir.ancestors().last() else {
+        return false;
+    };
+    let mut wide_root: Vec<u16> = root.as_os_str().encode_wide().collect();
+    if wide_root.last() != Some(&(b'\\' as u16)) {
+        wide_root.push(b'\\' as u16); // GetDriveTypeW wants "C:\", not "C:"
+    }
+    wide_root.push(0);
+    unsafe { GetDriveTypeW(wide_root.as_ptr()) == DRIVE_FIXED }
+}
+
+/// Whether entries of `dir` can be moved to the trash, or could only be deleted permanently.
+/// Network volumes have no trash.
+#[cfg(target_os = "macos")]
+pub fn is_available(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe {
+        let mut stat: libc::statfs = std::mem::zeroed();
+        libc::statfs(c_path.as_ptr(), &mut stat) == 0 && stat.f_flags & libc::MNT_LOCAL as u32 != 0
+    }
+}
+
+/// Whether entries of `dir` can be moved to the trash, or could only be deleted permanently.
+/// On the home volume the home trash is used; other volumes need a writable top folder
+/// (or an existing trash folder there) to hold their own `.Trash-<uid>`.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn is_available(dir: &Path) -> bool {
+    use std::os::unix::{ffi::OsStrExt, fs::MetadataExt};
+
+    let Ok(device) = std::fs::metadata(dir).map(|m| m.dev()) else {
+        return false;
+    };
+    if dirs::data_local_dir()
+        .and_then(|data| std::fs::metadata(data).ok())
+        .is_some_and(|data| data.dev() == device)
+    {
+        return true;
+    }
+    // The mount point: the topmost ancestor still on the same device
+    let top = dir
+        .ancestors()
+        .take_while(|ancestor| std::fs::metadata(ancestor).is_ok_and(|m| m.dev() == device))
+        .last()
+        .unwrap_or(dir);
+    let uid = unsafe { libc::getuid() };
+    if top.join(".Trash").join(uid.to_string()).is_dir() || top.join(format!(".Trash-{}", uid)).is_dir() {
+        return true;
+    }
+    std::ffi::CString::new(top.as_os_str().as_bytes())
+        .is_ok_and(|c_path| unsafe { libc::access(c_path.as_ptr(), libc::W_OK) } == 0)
+}
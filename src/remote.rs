@@ -0,0 +1,860 @@
+// --- Remote storage: S3-compatible buckets and WebDAV servers, browsed as folders ---
+//
+// Every location is addressed with "/" separated paths relative to its root, folders
+// end with "/". Each protocol only implements the basic requests (list, get, put,
+// create folder, delete, rename); transfers of whole folders are built on top of them.
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc},
+    time::SystemTime,
+};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::{jobs::JobContext, s3::S3Client, view, webdav::WebDavClient};
+
+/// Protocol of a saved location.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteKind {
+    #[default]
+    S3,
+    WebDav,
+}
+
+impl RemoteKind {
+    pub const ALL: [RemoteKind; 2] = [RemoteKind::S3, RemoteKind::WebDav];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RemoteKind::S3 => "S3",
+            RemoteKind::WebDav => "WebDAV",
+        }
+    }
+}
+
+/// A saved S3 endpoint or WebDAV server with its credentials.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteLocation {
+    pub kind: RemoteKind,
+    pub name: String,
+    pub url: String,    // S3 endpoint like "https://s3.amazonaws.com", or the WebDAV folder to start in
+    pub region: String, // S3 only, "us-east-1" for most S3-compatible services
+    pub user: String,   // Access key for S3
+    pub secret: String, // Secret key for S3, password for WebDAV
+}
+
+/// Layout of the locations file.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LocationsFile {
+    locations: Vec<RemoteLocation>,
+}
+
+impl RemoteLocation {
+    /// Whether this is a WebDAV location that would send its password in the clear. Basic authentication only
+    /// encodes the credentials, so they are only sent over HTTPS.
+    pub fn sends_password_unencrypted(&self) -> bool {
+        let url = self.url.trim();
+        self.kind == RemoteKind::WebDav
+            && !self.user.trim().is_empty()
+            && url.get(..7).is_some_and(|scheme| scheme.eq_ignore_ascii_case("http://"))
+    }
+
+    /// Location of the saved locations on disk.
+    fn locations_file() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("FileFox").join("remote_locations.toml"))
+    }
+
+    /// Loads the saved locations. A missing or unreadable file yields none.
+    pub fn load_all() -> Vec<Self> {
+        let Some(path) = Self::locations_file() else {
+            return Vec::new();
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse_all(&content).unwrap_or_else(|e| {
+                eprintln!("Error while reading remote locations from {:?}: {}", path, e);
+                Vec::new()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                eprintln!("Error while reading remote locations from {:?}: {}", path, e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn parse_all(content: &str) -> Result<Vec<Self>, toml::de::Error> {
+        toml::from_str::<LocationsFile>(content).map(|file| file.locations)
+    }
+
+    /// Saves `locations`. The file holds passwords and keys, so on Unix only the user may read it;
+    /// elsewhere it relies on the user's own config folder not being readable by others.
+    pub fn save_all(locations: &[Self]) -> io::Result<()> {
+        let Some(path) = Self::locations_file() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no config directory available"));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = Self::to_toml(locations)?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+        // The mode above only applies to new files, an existing one keeps its own
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        file.write_all(content.as_bytes())
+    }
+
+    /// The TOML form written by `save_all`.
+    fn to_toml(locations: &[Self]) -> io::Result<String> {
+        let file = LocationsFile { locations: locations.to_vec() };
+        toml::to_string_pretty(&file).map_err(io::Error::other)
+    }
+}
+
+/// A folder or file in a listing.
+#[derive(Clone)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub path: String, // Relative to the location, folders end with "/"
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    pub modified: Option<SystemTime>,
+}
+
+impl RemoteEntry {
+    /// Entry for `path`, named after its last part.
+    pub fn new(path: String, is_dir: bool, size: Option<u64>, modified: Option<SystemTime>) -> Self {
+        let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string();
+        Self { name, path, is_dir, size, modified }
+    }
+}
+
+/// The folder containing `path`, e.g. "photos/" for "photos/2024/" and "photos/a.jpg".
+pub fn parent_folder(path: &str) -> &str {
+    match path.trim_end_matches('/').rfind('/') {
+        Some(index) => &path[..=index],
+        None => "",
+    }
+}
+
+/// Connection to one location.
+pub enum RemoteClient {
+    S3(S3Client),
+    WebDav(WebDavClient),
+}
+
+impl RemoteClient {
+    pub fn connect(location: &RemoteLocation) -> Self {
+        match location.kind {
+            RemoteKind::S3 => RemoteClient::S3(S3Client::new(location)),
+            RemoteKind::WebDav => RemoteClient::WebDav(WebDavClient::new(location)),
+        }
+    }
+
+    /// Lists the folders and files directly inside `folder` ("" for the root), folders first.
+    pub fn list(&self, folder: &str) -> io::Result<Vec<RemoteEntry>> {
+        let mut entries = match self {
+            RemoteClient::S3(client) => client.list(folder)?,
+            RemoteClient::WebDav(client) => client.list(folder)?,
+        };
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| view::natural_cmp(&a.name, &b.name)));
+        Ok(entries)
+    }
+
+    /// Whether files can be put into `folder`. The root of S3 only holds buckets.
+    pub fn can_upload_to(&self, folder: &str) -> bool {
+        !matches!(self, RemoteClient::S3(_)) || !folder.is_empty()
+    }
+
+    /// Every file and folder `entries` stand for, including everything below the folders.
+    fn expand(&self, entries: &[RemoteEntry]) -> io::Result<Vec<RemoteEntry>> {
+        match self {
+            RemoteClient::S3(client) => client.expand(entries),
+            RemoteClient::WebDav(client) => client.expand(entries),
+        }
+    }
+
+    /// Opens the file at `path` for reading.
+    fn get(&self, path: &str) -> io::Result<Box<dyn Read + Send + Sync>> {
+        match self {
+            RemoteClient::S3(client) => client.get(path),
+            RemoteClient::WebDav(client) => client.get(path),
+        }
+    }
+
+    /// Uploads the local file `source` (of `size` bytes) to `path`.
+    fn put(&self, path: &str, source: &Path, size: u64, progress: &mut Progress) -> io::Result<()> {
+        match self {
+            RemoteClient::S3(client) => client.put(path, source, size, progress),
+            RemoteClient::WebDav(client) => client.put(path, source, size, progress),
+        }
+    }
+
+    /// Creates the folder `path` (ending with "/"). Folders that already exist are fine.
+    fn create_folder(&self, path: &str) -> io::Result<()> {
+        match self {
+            RemoteClient::S3(client) => client.create_folder(path),
+            RemoteClient::WebDav(client) => client.create_folder(path),
+        }
+    }
+
+    /// Deletes `entry` with everything inside it. Returns the number of deleted items.
+    fn delete(&self, entry: &RemoteEntry, job: &JobContext) -> io::Result<usize> {
+        match self {
+            RemoteClient::S3(client) => client.delete(entry, job),
+            RemoteClient::WebDav(client) => client.delete(entry),
+        }
+    }
+
+    /// Moves `entry` to `new_path` (which ends with "/" for folders).
+    fn rename(&self, entry: &RemoteEntry, new_path: &str) -> io::Result<()> {
+        match self {
+            RemoteClient::S3(client) => client.rename(entry, new_path),
+            RemoteClient::WebDav(client) => client.rename(entry, new_path),
+        }
+    }
+}
+
+/// An operation on a location, run as a background job.
+pub enum Transfer {
+    /// Downloads entries of one listing into a local folder.
+    Download { entries: Vec<RemoteEntry>, destination: PathBuf },
+    /// Uploads local files and folders into `folder`.
+    Upload { folder: String, sources: Vec<PathBuf> },
+    Delete { entries: Vec<RemoteEntry> },
+    Rename { entry: RemoteEntry, name: String },
+    CreateFolder { folder: String, name: String },
+}
+
+impl Transfer {
+    /// Performs the operation. Returns a message for the user, e.g. "Downloaded 3 files".
+    pub fn run(&self, client: &RemoteClient, job: &JobContext) -> io::Result<String> {
+        match self {
+            Self::Download { entries, destination } => {
+                let count = download(client, entries, destination, job)?;
+                Ok(format!("Downloaded {} files to {}", count, destination.display()))
+            }
+            Self::Upload { folder, sources } => {
+                let count = upload(client, folder, sources, job)?;
+                Ok(format!("Uploaded {} items to /{}", count, folder))
+            }
+            Self::Delete { entries } => {
+                let mut count = 0;
+                for entry in entries {
+                    count += client.delete(entry, job)?;
+                }
+                Ok(format!("Deleted {} items", count))
+            }
+            Self::Rename { entry, name } => {
+                let suffix = if entry.is_dir { "/" } else { "" };
+                client.rename(entry, &format!("{}{}{}", parent_folder(&entry.path), name, suffix))?;
+                Ok(format!("Renamed {} to {}", entry.name, name))
+            }
+            Self::CreateFolder { folder, name } => {
+                client.create_folder(&format!("{}{}/", folder, name))?;
+                Ok(format!("Created {}", name))
+            }
+        }
+    }
+}
+
+/// Downloads `entries` into `destination`, keeping the folder structure. Returns the number of files.
+fn download(client: &RemoteClient, entries: &[RemoteEntry], destination: &Path, job: &JobContext) -> io::Result<usize> {
+    let items = client.expand(entries)?;
+    let mut progress = Progress::new(job, items.iter().filter_map(|item| item.size).sum());
+    // Local paths are relative to the listed folder
+    let base = entries.first().map_or("", |entry| parent_folder(&entry.path));
+    let mut downloaded = 0;
+    for item in &items {
+        let mut target = destination.to_owned();
+        // ".." in a path must not escape the destination
+        for part in item.path[base.len()..].split('/').filter(|part| !part.is_empty() && *part != "..") {
+            target.push(part);
+        }
+        if item.is_dir {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut reader = client.get(&item.path)?;
+        let mut file = File::create(&target)?;
+        let mut buffer = vec![0; 256 * 1024];
+        loop {
+            if progress.is_cancelled() {
+                drop(file);
+                let _ = fs::remove_file(&target); // Don't leave a partial file behind
+                return Err(cancelled());
+            }
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read])?;
+            progress.add(read as u64);
+        }
+        downloaded += 1;
+    }
+    Ok(downloaded)
+}
+
+/// Uploads `sources` (files and folders) into `folder`. Returns the number of files and folders.
+fn upload(client: &RemoteClient, folder: &str, sources: &[PathBuf], job: &JobContext) -> io::Result<usize> {
+    // Collect everything first so progress can be reported for the whole transfer.
+    // Folders come before their contents, so they exist before files are put into them.
+    let mut items: Vec<(PathBuf, String, u64)> = Vec::new();
+    for source in sources {
+        let base = source.parent().unwrap_or(source);
+        for entry in WalkDir::new(source).into_iter().filter_map(|e| e.ok()) {
+            let relative = entry.path().strip_prefix(base).unwrap_or(entry.path());
+            let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            let path = format!("{}{}", folder, parts.join("/"));
+            if entry.file_type().is_dir() {
+                items.push((entry.path().to_owned(), path + "/", 0));
+            } else {
+                items.push((entry.path().to_owned(), path, entry.metadata().map_or(0, |m| m.len())));
+            }
+        }
+    }
+
+    let mut progress = Progress::new(job, items.iter().map(|(_, _, size)| size).sum());
+    for (source, path, size) in &items {
+        if progress.is_cancelled() {
+            return Err(cancelled());
+        }
+        if path.ends_with('/') {
+            client.create_folder(path)?;
+        } else {
+            client.put(path, source, *size, &mut progress)?;
+        }
+    }
+    Ok(items.len())
+}
+
+pub fn cancelled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "transfer cancelled")
+}
+
+/// Reports transferred bytes to the job, in MiB to keep the status bar short.
+pub struct Progress<'a> {
+    job: &'a JobContext,
+    done: u64,
+    total: u64,
+    reported: Option<usize>, // Last MiB count sent to the job
+}
+
+impl<'a> Progress<'a> {
+    fn new(job: &'a JobContext, total: u64) -> Self {
+        Self { job, done: 0, total, reported: None }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.job.is_cancelled()
+    }
+
+    pub fn add(&mut self, bytes: u64) {
+        self.done += bytes;
+        let mib = |bytes: u64| bytes.div_ceil(1024 * 1024) as usize;
+        if self.reported != Some(mib(self.done)) {
+            self.reported = Some(mib(self.done));
+            self.job.progress(mib(self.done), mib(self.total).max(1));
+        }
+    }
+}
+
+/// Reads a file for an upload, reporting progress and failing once the job was cancelled.
+pub struct UploadReader<'a, 'b> {
+    pub file: File,
+    pub progress: &'a mut Progress<'b>,
+}
+
+impl Read for UploadReader<'_, '_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.progress.is_cancelled() {
+            return Err(cancelled());
+        }
+        let read = self.file.read(buffer)?;
+        self.progress.add(read as u64);
+        Ok(read)
+    }
+}
+
+pub fn parse_xml(body: &str) -> io::Result<roxmltree::Document<'_>> {
+    roxmltree::Document::parse(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Text of the first child element called `name` (in any namespace).
+pub fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children().find(|child| child.has_tag_name(name)).and_then(|child| child.text())
+}
+
+/// A job ready to run in the background. Reports its result back to the browser.
+pub struct TransferJob {
+    client: Arc<RemoteClient>,
+    transfer: Transfer,
+    sender: mpsc::Sender<Result<String, String>>,
+    ctx: egui::Context,
+}
+
+impl TransferJob {
+    pub fn run(self, job: &JobContext) {
+        let result = self.transfer.run(&self.client, job).map_err(|e| e.to_string());
+        if self.sender.send(result).is_ok() {
+            self.ctx.request_repaint();
+        }
+    }
+}
+
+pub enum RemoteAction {
+    Close,
+    /// Run this download, upload, deletion... as a background job.
+    Transfer(TransferJob),
+}
+
+/// Window listing the folders of one saved location at a time.
+pub struct RemoteBrowser {
+    locations: Vec<RemoteLocation>,
+    form: Option<(Option<usize>, RemoteLocation)>, // Location being added (`None`) or edited (its index)
+    current: Option<usize>,                        // Index of the open location
+    client: Option<Arc<RemoteClient>>,
+    folder: String, // Folder being listed: "" or ending with "/"
+    listing: Result<Vec<RemoteEntry>, String>,
+    receiver: Option<mpsc::Receiver<io::Result<Vec<RemoteEntry>>>>, // Listing being loaded
+    selected: HashSet<String>,                                       // Paths of the selected entries
+    confirm_delete: bool,
+    rename: Option<(RemoteEntry, String)>, // Entry being renamed, with the new name
+    new_folder: Option<String>,            // Name of the folder being created
+    transfer_sender: mpsc::Sender<Result<String, String>>,
+    transfer_receiver: mpsc::Receiver<Result<String, String>>,
+    message: Option<Result<String, String>>, // Outcome of the last finished job
+}
+
+impl Default for RemoteBrowser {
+    fn default() -> Self {
+        let (transfer_sender, transfer_receiver) = mpsc::channel();
+        Self {
+            locations: RemoteLocation::load_all(),
+            form: None,
+            current: None,
+            client: None,
+            folder: String::new(),
+            listing: Ok(Vec::new()),
+            receiver: None,
+            selected: HashSet::new(),
+            confirm_delete: false,
+            rename: None,
+            new_folder: None,
+            transfer_sender,
+            transfer_receiver,
+            message: None,
+        }
+    }
+}
+
+impl RemoteBrowser {
+    /// Opens the saved location at `index`, starting at its root.
+    fn connect(&mut self, index: usize, ctx: &egui::Context) {
+        self.current = Some(index);
+        self.client = Some(Arc::new(RemoteClient::connect(&self.locations[index])));
+        self.folder.clear();
+        self.reload(ctx);
+    }
+
+    /// Loads the listing of the current folder on rayon's thread pool.
+    fn reload(&mut self, ctx: &egui::Context) {
+        let Some(client) = self.client.clone() else {
+            return;
+        };
+        self.selected.clear();
+        self.confirm_delete = false;
+        self.rename = None;
+        let (sender, receiver) = mpsc::channel();
+        self.receiver = Some(receiver);
+        let (folder, ctx) = (self.folder.clone(), ctx.clone());
+        rayon::spawn(move || {
+            if sender.send(client.list(&folder)).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    fn save_locations(&self) {
+        if let Err(e) = RemoteLocation::save_all(&self.locations) {
+            eprintln!("Error while saving remote locations: {}", e);
+        }
+    }
+
+    /// Wraps `transfer` into a job for the main window to start.
+    fn transfer(&self, transfer: Transfer, ctx: &egui::Context) -> Option<RemoteAction> {
+        let client = self.client.clone()?;
+        let sender = self.transfer_sender.clone();
+        Some(RemoteAction::Transfer(TransferJob { client, transfer, sender, ctx: ctx.clone() }))
+    }
+
+    /// Renders the browser. Downloads go to `destination`, "Upload" sends `upload_sources`.
+    pub fn show(&mut self, ctx: &egui::Context, destination: &Path, upload_sources: &[PathBuf]) -> Option<RemoteAction> {
+        if let Some(receiver) = &self.receiver
+            && let Ok(listing) = receiver.try_recv()
+        {
+            self.listing = listing.map_err(|e| e.to_string());
+            self.receiver = None;
+        }
+        if let Ok(result) = self.transfer_receiver.try_recv() {
+            self.message = Some(result);
+            self.reload(ctx); // Show uploaded, renamed and deleted entries
+        }
+
+        let mut action = None;
+        let mut is_open = true;
+        egui::Window::new("☁ Remote storage")
+            .open(&mut is_open)
+            .default_size([700.0, 500.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                self.show_location_bar(ui, ctx);
+                if self.form.is_some() {
+                    ui.separator();
+                    self.show_location_form(ui, ctx);
+                }
+                let Some(client) = self.client.clone() else {
+                    return;
+                };
+                ui.separator();
+
+                // Current folder and the actions on it
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!self.folder.is_empty(), egui::Button::new("⬆ Up")).clicked() {
+                        self.folder = parent_folder(&self.folder).to_string();
+                        self.reload(ctx);
+                    }
+                    if ui.button("⟳ Refresh").clicked() {
+                        self.reload(ctx);
+                    }
+                    ui.label(format!("/{}", self.folder));
+                });
+                action = self.show_actions(ui, ctx, &client, destination, upload_sources);
+                match &self.message {
+                    Some(Ok(message)) => {
+                        ui.label(message);
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("Failed: {}", e));
+                    }
+                    None => {}
+                }
+                ui.separator();
+
+                self.show_listing(ui, ctx);
+            });
+        if !is_open {
+            action = Some(RemoteAction::Close);
+        }
+        action
+    }
+
+    /// Choice of the saved location, plus adding, editing and removing locations.
+    fn show_location_bar(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            ui.label("Location:");
+            let selected_text = self.current.map_or("Choose...", |index| self.locations[index].name.as_str());
+            let mut connect = None;
+            egui::ComboBox::from_id_source("remote_location").selected_text(selected_text).show_ui(ui, |ui| {
+                for (index, location) in self.locations.iter().enumerate() {
+                    let label = format!("{} ({})", location.name, location.kind.label());
+                    if ui.selectable_label(self.current == Some(index), label).clicked() {
+                        connect = Some(index);
+                    }
+                }
+            });
+            if let Some(index) = connect {
+                self.connect(index, ctx);
+            }
+            if ui.button("Add...").clicked() {
+                let location = RemoteLocation { region: "us-east-1".to_string(), ..RemoteLocation::default() };
+                self.form = Some((None, location));
+            }
+            if let Some(index) = self.current {
+                if ui.button("Edit...").clicked() {
+                    self.form = Some((Some(index), self.locations[index].clone()));
+                }
+                if ui.button("Remove").clicked() {
+                    self.locations.remove(index);
+                    self.save_locations();
+                    self.current = None;
+                    self.client = None;
+                    self.form = None;
+                }
+            }
+        });
+    }
+
+    /// Fields of the location being added or edited.
+    fn show_location_form(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let Some((_, location)) = &mut self.form else {
+            return;
+        };
+        let is_s3 = location.kind == RemoteKind::S3;
+        egui::Grid::new("remote_location_form").num_columns(2).show(ui, |ui| {
+            ui.label("Type:");
+            ui.horizontal(|ui| {
+                for kind in RemoteKind::ALL {
+                    ui.selectable_value(&mut location.kind, kind, kind.label());
+                }
+            });
+            ui.end_row();
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut location.name);
+            ui.end_row();
+            ui.label(if is_s3 { "Endpoint:" } else { "URL:" });
+            let hint =
+                if is_s3 { "https://s3.amazonaws.com" } else { "https://cloud.example.com/remote.php/dav/files/me" };
+            ui.add(egui::TextEdit::singleline(&mut location.url).hint_text(hint));
+            ui.end_row();
+            if is_s3 {
+                ui.label("Region:");
+                ui.text_edit_singleline(&mut location.region);
+                ui.end_row();
+            }
+            ui.label(if is_s3 { "Access key:" } else { "User name:" });
+            ui.text_edit_singleline(&mut location.user);
+            ui.end_row();
+            ui.label(if is_s3 { "Secret key:" } else { "Password:" });
+            ui.add(egui::TextEdit::singleline(&mut location.secret).password(true));
+            ui.end_row();
+        });
+
+        let insecure = location.sends_password_unencrypted();
+        if insecure {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                "The password would be sent unencrypted. Use an https:// URL or leave the user name empty.",
+            );
+        }
+        let can_save = !location.name.trim().is_empty() && !location.url.trim().is_empty() && !insecure;
+        let mut saved = None;
+        ui.horizontal(|ui| {
+            if ui.add_enabled(can_save, egui::Button::new("Save")).clicked() {
+                saved = self.form.take();
+            }
+            if ui.button("Cancel").clicked() {
+                self.form = None;
+            }
+        });
+        if let Some((index, location)) = saved {
+            let index = match index {
+                Some(index) => {
+                    self.locations[index] = location;
+                    index
+                }
+                None => {
+                    self.locations.push(location);
+                    self.locations.len() - 1
+                }
+            };
+            self.save_locations();
+            self.connect(index, ctx);
+        }
+    }
+
+    /// Buttons for the selected entries and the current folder.
+    fn show_actions(
+        &mut self,
+        ui: &mut egui::Ui,
+        ctx: &egui::Context,
+        client: &RemoteClient,
+        destination: &Path,
+        upload_sources: &[PathBuf],
+    ) -> Option<RemoteAction> {
+        let selection: Vec<RemoteEntry> = match &self.listing {
+            Ok(entries) => entries.iter().filter(|entry| self.selected.contains(&entry.path)).cloned().collect(),
+            Err(_) => Vec::new(),
+        };
+        let mut action = None;
+        ui.horizontal(|ui| {
+            let download = ui
+                .add_enabled(!selection.is_empty(), egui::Button::new(format!("⬇ Download {} items", selection.len())))
+                .on_hover_text(format!("Into {}", destination.display()));
+            if download.clicked() {
+                let transfer = Transfer::Download { entries: selection.clone(), destination: destination.to_owned() };
+                action = self.transfer(transfer, ctx);
+            }
+
+            let can_upload = !upload_sources.is_empty() && client.can_upload_to(&self.folder);
+            let upload = ui
+                .add_enabled(can_upload, egui::Button::new(format!("⬆ Upload {} items", upload_sources.len())))
+                .on_hover_text("Uploads the items selected in the file list");
+            if upload.clicked() {
+                let transfer = Transfer::Upload { folder: self.folder.clone(), sources: upload_sources.to_vec() };
+                action = self.transfer(transfer, ctx);
+            }
+
+            if ui.button("New folder").clicked() {
+                self.new_folder = Some(String::new());
+            }
+            if ui.add_enabled(selection.len() == 1, egui::Button::new("Rename")).clicked() {
+                self.rename = selection.first().map(|entry| (entry.clone(), entry.name.clone()));
+            }
+
+            if self.confirm_delete {
+                ui.label(format!("Delete {} items and everything inside them?", selection.len()));
+                if ui.button("Delete").clicked() {
+                    action = self.transfer(Transfer::Delete { entries: selection.clone() }, ctx);
+                    self.confirm_delete = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_delete = false;
+                }
+            } else if ui.add_enabled(!selection.is_empty(), egui::Button::new("🗑 Delete")).clicked() {
+                self.confirm_delete = true;
+            }
+        });
+
+        // Name input of "New folder" and "Rename"
+        let mut submitted = None;
+        let mut cancelled = false;
+        if let Some(name) = &mut self.new_folder {
+            let folder = &self.folder;
+            ui.horizontal(|ui| {
+                ui.label("New folder:");
+                let response = ui.text_edit_singleline(name);
+                if is_valid_name(name) && (ui.button("Create").clicked() || pressed_enter(ui, &response)) {
+                    submitted = Some(Transfer::CreateFolder { folder: folder.clone(), name: name.trim().to_string() });
+                }
+                cancelled |= ui.button("Cancel").clicked();
+            });
+        }
+        if let Some((entry, name)) = &mut self.rename {
+            ui.horizontal(|ui| {
+                ui.label(format!("Rename {} to:", entry.name));
+                let response = ui.text_edit_singleline(name);
+                if is_valid_name(name) && (ui.button("Rename").clicked() || pressed_enter(ui, &response)) {
+                    submitted = Some(Transfer::Rename { entry: entry.clone(), name: name.trim().to_string() });
+                }
+                cancelled |= ui.button("Cancel").clicked();
+            });
+        }
+        if cancelled {
+            self.new_folder = None;
+            self.rename = None;
+        }
+        if let Some(transfer) = submitted {
+            self.new_folder = None;
+            self.rename = None;
+            action = self.transfer(transfer, ctx);
+        }
+        action
+    }
+
+    /// The folders and files of the current folder. Click selects
+    /// (Ctrl+click adds to the selection), double-clicking a folder opens it.
+    fn show_listing(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        if self.receiver.is_some() {
+            ui.spinner();
+            return;
+        }
+        let entries = match &self.listing {
+            Ok(entries) => entries,
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                return;
+            }
+        };
+        if entries.is_empty() {
+            ui.weak("(empty folder)");
+        }
+
+        let mut open = None;
+        egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+            egui::Grid::new("remote_listing").num_columns(3).striped(true).show(ui, |ui| {
+                for entry in entries {
+                    let icon = if entry.is_dir { "📁" } else { "📄" };
+                    let is_selected = self.selected.contains(&entry.path);
+                    let response = ui.selectable_label(is_selected, format!("{} {}", icon, entry.name));
+                    if response.clicked() {
+                        if !ui.input(|i| i.modifiers.command) {
+                            self.selected.clear();
+                        }
+                        if is_selected {
+                            self.selected.remove(&entry.path);
+                        } else {
+                            self.selected.insert(entry.path.clone());
+                        }
+                    }
+                    if entry.is_dir && response.double_clicked() {
+                        open = Some(entry.path.clone());
+                    }
+                    ui.label(entry.size.map(view::format_size).unwrap_or_default());
+                    ui.label(entry.modified.map(view::format_time).unwrap_or_default());
+                    ui.end_row();
+                }
+            });
+        });
+
+        if let Some(folder) = open {
+            self.folder = folder;
+            self.reload(ctx);
+        }
+    }
+}
+
+/// Names of new folders and renamed entries can't be empty or contain a "/".
+fn is_valid_name(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/')
+}
+
+fn pressed_enter(ui: &egui::Ui, response: &egui::Response) -> bool {
+    response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_survive_saving() {
+        let location = RemoteLocation {
+            kind: RemoteKind::WebDav,
+            name: "Work\tshare".to_string(),
+            url: "https://dav.example.com/files/".to_string(),
+            region: String::new(),
+            user: "me".to_string(),
+            secret: "p\\a\ts\ns\rword\\t".to_string(),
+        };
+        let saved = RemoteLocation::to_toml(std::slice::from_ref(&location)).unwrap();
+        let loaded = RemoteLocation::parse_all(&saved).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded[0].kind == RemoteKind::WebDav);
+        assert_eq!(loaded[0].name, location.name);
+        assert_eq!(loaded[0].secret, location.secret);
+    }
+
+    #[test]
+    fn webdav_passwords_need_https() {
+        let mut location = RemoteLocation {
+            kind: RemoteKind::WebDav,
+            url: "HTTP://dav.example.com/files".to_string(),
+            user: "me".to_string(),
+            ..Default::default()
+        };
+        assert!(location.sends_password_unencrypted());
+        location.url = "https://dav.example.com/files".to_string();
+        assert!(!location.sends_password_unencrypted());
+        location.kind = RemoteKind::S3;
+        location.url = "http://localhost:9000".to_string();
+        assert!(!location.sends_password_unencrypted());
+    }
+}
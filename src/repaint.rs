@@ -0,0 +1,15 @@
+// --- Repaint throttling for background workers ---
+
+use std::time::Duration;
+
+use eframe::egui;
+
+/// Workers streaming updates wake the UI at most this often, so a big search or copy
+/// doesn't repaint (and keep the GPU busy) once per item.
+pub const REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Asks for a repaint within `REPAINT_INTERVAL`. Requests made in the meantime are
+/// merged into that one repaint. Final results should still use `request_repaint`.
+pub fn request_throttled_repaint(ctx: &egui::Context) {
+    ctx.request_repaint_after(REPAINT_INTERVAL);
+}
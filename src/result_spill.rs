@@ -0,0 +1,179 @@
+// --- Search results beyond the in-memory cap, kept in a temporary file ---
+
+use std::{
+    env,
+    ffi::OsStr,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use crate::search::{LineHit, ResultMetadata};
+
+/// At most this many results of one search are kept in memory, the rest is spilled to disk.
+pub const MAX_RESULTS_IN_MEMORY: usize = 1_000_000;
+
+/// Stored for a missing size or modification time.
+const NO_VALUE: u64 = u64::MAX;
+
+/// Makes the file names of spills unique within the process.
+static NEXT_SPILL: AtomicUsize = AtomicUsize::new(0);
+
+/// Results of a pathological search (e.g. "a" over a whole drive) that didn't fit in memory.
+/// They are read back a page at a time as the user asks for more; the file is deleted on drop.
+pub struct ResultSpill {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    written: usize,
+    read: usize,
+    read_offset: u64, // Byte offset of the first result not read back yet
+}
+
+impl ResultSpill {
+    pub fn create() -> io::Result<Self> {
+        let name = format!("FileFox-results-{}-{}.bin", std::process::id(), NEXT_SPILL.fetch_add(1, Ordering::Relaxed));
+        let path = env::temp_dir().join(name);
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(Self { path, writer, written: 0, read: 0, read_offset: 0 })
+    }
+
+    /// Number of spilled results not read back yet.
+    pub fn unread(&self) -> usize {
+        self.written - self.read
+    }
+
+    /// Appends `paths` to the file, each with its matching lines from `line_hits` and its `metadata`.
+    pub fn write(
+        &mut self,
+        paths: &[PathBuf],
+        line_hits: &[Vec<LineHit>],
+        metadata: &[ResultMetadata],
+    ) -> io::Result<()> {
+        for ((path, hits), metadata) in paths.iter().zip(line_hits).zip(metadata) {
+            // Length-prefixed, as paths may contain line breaks
+            write_bytes(&mut self.writer, path.as_os_str().as_encoded_bytes())?;
+            // Modification times before 1970 are left out
+            let modified = metadata.modified.and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok());
+            self.writer.write_all(&metadata.size.unwrap_or(NO_VALUE).to_le_bytes())?;
+            self.writer.write_all(&modified.map_or(NO_VALUE, |age| age.as_nanos() as u64).to_le_bytes())?;
+            self.writer.write_all(&(hits.len() as u32).to_le_bytes())?;
+            for hit in hits {
+                self.writer.write_all(&(hit.line_number as u64).to_le_bytes())?;
+                write_bytes(&mut self.writer, hit.snippet.as_bytes())?;
+            }
+            self.written += 1;
+        }
+        Ok(())
+    }
+
+    /// Reads back the next `count` results with their matching lines and metadata.
+    pub fn read_page(&mut self, count: usize) -> io::Result<Vec<(PathBuf, Vec<LineHit>, ResultMetadata)>> {
+        let mut reader = self.unread_reader()?;
+        let page: Vec<_> = reader.by_ref().take(count).collect();
+        self.read += page.len();
+        self.read_offset = reader.offset;
+        Ok(page)
+    }
+
+    /// Iterates over the results not read back yet, without marking them as read (e.g. to export them).
+    pub fn unread_reader(&mut self) -> io::Result<SpillReader> {
+        self.writer.flush()?;
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.read_offset))?;
+        Ok(SpillReader { reader: BufReader::new(file), offset: self.read_offset })
+    }
+}
+
+impl Drop for ResultSpill {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Helper function
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+/// Reads spilled results, their matching lines and metadata back in the order they were written.
+pub struct SpillReader {
+    reader: BufReader<File>,
+    offset: u64,
+}
+
+impl SpillReader {
+    fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let mut array = [0; N];
+        self.reader.read_exact(&mut array).ok()?;
+        self.offset += N as u64;
+        Some(array)
+    }
+
+    fn read_bytes(&mut self) -> Option<Vec<u8>> {
+        let length = u32::from_le_bytes(self.read_array()?) as usize;
+        let mut bytes = vec![0; length];
+        self.reader.read_exact(&mut bytes).ok()?;
+        self.offset += length as u64;
+        Some(bytes)
+    }
+}
+
+impl Iterator for SpillReader {
+    type Item = (PathBuf, Vec<LineHit>, ResultMetadata);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.read_bytes()?;
+        // Safety: the bytes were written by this process with `as_encoded_bytes`
+        let path = PathBuf::from(unsafe { OsStr::from_encoded_bytes_unchecked(&bytes) });
+        let size = Some(u64::from_le_bytes(self.read_array()?)).filter(|&size| size != NO_VALUE);
+        let modified = Some(u64::from_le_bytes(self.read_array()?)).filter(|&nanos| nanos != NO_VALUE);
+        let modified = modified.map(|nanos| SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos));
+        let metadata = ResultMetadata { size, modified };
+        let hit_count = u32::from_le_bytes(self.read_array()?);
+        let mut hits = Vec::with_capacity(hit_count as usize);
+        for _ in 0..hit_count {
+            let line_number = u64::from_le_bytes(self.read_array()?) as usize;
+            let snippet = String::from_utf8(self.read_bytes()?).ok()?;
+            hits.push(LineHit { line_number, snippet });
+        }
+        Some((path, hits, metadata))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_keep_order_line_hits_and_metadata() {
+        let mut spill = ResultSpill::create().unwrap();
+        let paths: Vec<PathBuf> = (0..5).map(|i| PathBuf::from(format!("/data/file {i}\nname.txt"))).collect();
+        let hits: Vec<Vec<LineHit>> = (0..5)
+            .map(|i| (0..i).map(|line| LineHit { line_number: line + 1, snippet: format!("match {line}") }).collect())
+            .collect();
+        let metadata: Vec<ResultMetadata> = (0..5)
+            .map(|i| ResultMetadata {
+                size: (i % 2 == 0).then_some(i * 1000),
+                modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(i * 86_400)),
+            })
+            .collect();
+        spill.write(&paths, &hits, &metadata).unwrap();
+        assert_eq!(spill.unread(), 5);
+
+        let first = spill.read_page(2).unwrap();
+        assert_eq!(first.iter().map(|(path, _, _)| path.clone()).collect::<Vec<_>>(), paths[..2]);
+        assert_eq!(first[1].2.size, None);
+        let rest = spill.read_page(10).unwrap();
+        assert_eq!(rest.len(), 3);
+        assert_eq!(spill.unread(), 0);
+        let (path, hits, read_metadata) = &rest[2];
+        assert_eq!(path, &paths[4]);
+        assert_eq!(read_metadata.size, Some(4000));
+        assert_eq!(read_metadata.modified, metadata[4].modified);
+        assert_eq!(hits.len(), 4);
+        assert_eq!((hits[3].line_number, hits[3].snippet.as_str()), (4, "match 3"));
+    }
+}
@@ -0,0 +1,595 @@
+// --- Search matching ---
+
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs::{self, File},
+    io::Read,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::SystemTime,
+};
+
+use eframe::egui;
+
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use globset::{GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
+use memchr::{memchr, memchr_iter, memchr2_iter, memrchr};
+use memmap2::Mmap;
+use rayon::slice::ParallelSliceMut;
+
+use crate::{
+    fileops,
+    filetype::{self, ContentType},
+    repaint, view,
+};
+use regex::{Regex, RegexBuilder};
+
+/// How the search query is interpreted.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Name contains the query.
+    Substring,
+    /// Shell-style pattern like `*.rs` or `src/**/test_*`.
+    Glob,
+    /// Regular expression matched against the name.
+    Regex,
+    /// Name contains the query's characters in order ("prjplan" finds "Project_Plan.xlsx").
+    Fuzzy,
+    /// Text files containing the query.
+    Content,
+}
+
+/// Decides whether a file or folder matches the search query.
+pub enum SearchMatcher {
+    /// Case-insensitive substring match (query stored lowercase).
+    Substring(String),
+    /// Case-insensitive glob. Patterns containing `/` are matched against
+    /// the path relative to the search root, others against the name only.
+    Glob { glob: GlobMatcher, match_path: bool },
+    /// Case-insensitive regular expression.
+    Regex(Regex),
+    /// Case-insensitive fuzzy match, results are ranked by score.
+    Fuzzy { matcher: Box<SkimMatcherV2>, query: String },
+    /// Case-insensitive search inside text files (query stored lowercase).
+    Content(String),
+}
+
+impl SearchMatcher {
+    /// Builds the matcher for `query`. Fails with a readable message if
+    /// the glob or regex pattern is invalid.
+    pub fn new(query: &str, mode: SearchMode) -> Result<Self, String> {
+        match mode {
+            SearchMode::Substring => Ok(Self::Substring(query.to_lowercase())),
+            SearchMode::Glob => {
+                let pattern = query.replace('\\', "/"); // Accept Windows-style separators
+                GlobBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .literal_separator(true) // `*` stays within one folder, `**` crosses folders
+                    .build()
+                    .map(|glob| Self::Glob { glob: glob.compile_matcher(), match_path: pattern.contains('/') })
+                    .map_err(|e| e.to_string())
+            }
+            SearchMode::Regex => RegexBuilder::new(query)
+                .case_insensitive(true)
+                .build()
+                .map(Self::Regex)
+                .map_err(|e| e.to_string()),
+            SearchMode::Fuzzy => Ok(Self::Fuzzy { matcher: Box::new(SkimMatcherV2::default().ignore_case()), query: query.to_string() }),
+            SearchMode::Content => Ok(Self::Content(query.to_lowercase())),
+        }
+    }
+
+    /// Returns `Some(score)` if the entry matches the query, higher is better.
+    /// Only fuzzy matching produces meaningful scores, every other match scores 0.
+    /// `relative_path` is the entry's path relative to the folder the search started in.
+    pub fn score(&self, file_name: &str, relative_path: &Path) -> Option<i64> {
+        match self {
+            Self::Fuzzy { matcher, query } => matcher.fuzzy_match(file_name, query),
+            _ => self.is_match(file_name, relative_path).then_some(0),
+        }
+    }
+
+    /// Returns `true` if the entry matches the query. `relative_path` is the
+    /// entry's path relative to the folder the search started in.
+    pub fn is_match(&self, file_name: &str, relative_path: &Path) -> bool {
+        match self {
+            Self::Substring(query_lower) => file_name.to_lowercase().contains(query_lower),
+            Self::Glob { glob, match_path: true } => glob.is_match(relative_path),
+            Self::Glob { glob, match_path: false } => glob.is_match(file_name),
+            Self::Regex(regex) => regex.is_match(file_name),
+            Self::Fuzzy { matcher, query } => matcher.fuzzy_match(file_name, query).is_some(),
+            Self::Content(_) => false, // Decided by the file contents, see `search_file_contents`
+        }
+    }
+
+    /// Byte ranges of `text` (a file name, or a line snippet for content searches)
+    /// that made it match, for highlighting in the results.
+    pub fn highlight_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            Self::Substring(query_lower) | Self::Content(query_lower) => {
+                find_case_insensitive(text, query_lower).into_iter().collect()
+            }
+            Self::Glob { .. } => std::iter::once(0..text.len()).collect(), // Globs match as a whole
+            Self::Regex(regex) => regex.find_iter(text).map(|m| m.range()).collect(),
+            Self::Fuzzy { matcher, query } => {
+                let Some((_, char_indices)) = matcher.fuzzy_indices(text, query) else {
+                    return Vec::new();
+                };
+                text.char_indices()
+                    .enumerate()
+                    .filter(|(char_index, _)| char_indices.contains(char_index))
+                    .map(|(_, (start, c))| start..start + c.len_utf8())
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Finds the first case-insensitive occurrence of `query_lower` in `text`.
+/// Works char by char, so the returned range is valid for `text` even where
+/// lowercasing changes byte lengths.
+fn find_case_insensitive(text: &str, query_lower: &str) -> Option<Range<usize>> {
+    if query_lower.is_empty() {
+        return None;
+    }
+    text.char_indices().find_map(|(start, _)| {
+        let mut expected = query_lower.chars();
+        for (offset, c) in text[start..].char_indices() {
+            if !c.to_lowercase().all(|lower| expected.next() == Some(lower)) {
+                return None;
+            }
+            if expected.as_str().is_empty() {
+                return Some(start..start + offset + c.len_utf8());
+            }
+        }
+        None
+    })
+}
+
+/// Which kinds of entries a search returns.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    All,
+    Files,
+    Folders,
+}
+
+impl EntryKind {
+    /// Returns `true` if an entry of this kind may be part of the results.
+    pub fn accepts(self, is_dir: bool) -> bool {
+        match self {
+            Self::All => true,
+            Self::Files => !is_dir,
+            Self::Folders => is_dir,
+        }
+    }
+}
+
+/// Exclusion patterns used until the user changes them.
+pub const DEFAULT_EXCLUSIONS: &str = "node_modules, target, .git, $RECYCLE.BIN";
+
+/// Settings limiting which entries a search looks at.
+pub struct SearchOptions {
+    /// Honor `.gitignore` / `.ignore` files while walking the disk.
+    pub respect_ignore_files: bool,
+    /// Names (globs) of files and folders to skip entirely.
+    pub exclusions: GlobSet,
+    /// How many folder levels below the start folder are searched (`None` = unlimited).
+    pub max_depth: Option<usize>,
+    /// Only files, only folders, or both.
+    pub entry_kind: EntryKind,
+    /// Only files whose content is of this type (`None` = any).
+    pub content_type: Option<ContentType>,
+}
+
+impl SearchOptions {
+    /// Builds the options from the comma separated exclusion list typed by the user.
+    pub fn new(respect_ignore_files: bool, exclusions: &str) -> Result<Self, String> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in exclusions.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let glob = GlobBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| e.to_string())?;
+            builder.add(glob);
+        }
+        let exclusions = builder.build().map_err(|e| e.to_string())?;
+        Ok(Self { respect_ignore_files, exclusions, max_depth: None, entry_kind: EntryKind::All, content_type: None })
+    }
+
+    /// Returns `true` if an entry with this name must be skipped (with everything below it).
+    pub fn is_excluded(&self, name: &OsStr) -> bool {
+        self.exclusions.is_match(name)
+    }
+
+    /// Returns `true` if `relative_path` is not deeper than `max_depth`.
+    pub fn is_within_depth(&self, relative_path: &Path) -> bool {
+        self.max_depth.is_none_or(|max_depth| relative_path.components().count() <= max_depth)
+    }
+
+    /// Returns `true` if the entry passes the content type filter. Folders never do
+    /// while a type is set. Reads the start of the file, so call it after cheaper checks.
+    pub fn matches_content_type(&self, path: &Path, is_dir: bool) -> bool {
+        match self.content_type {
+            None => true,
+            Some(_) if is_dir => false,
+            Some(content_type) => filetype::detect_content_type(path) == Some(content_type),
+        }
+    }
+
+    /// Returns `true` if any component of `relative_path` is excluded.
+    pub fn is_excluded_path(&self, relative_path: &Path) -> bool {
+        relative_path.iter().any(|component| self.is_excluded(component))
+    }
+}
+
+/// Column the search results are sorted by.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ResultSortKey {
+    Name,
+    Path,
+    Size,
+    Modified,
+}
+
+impl ResultSortKey {
+    pub const ALL: [Self; 4] = [Self::Name, Self::Path, Self::Size, Self::Modified];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Path => "Path",
+            Self::Size => "Size",
+            Self::Modified => "Modified",
+        }
+    }
+}
+
+/// Size and modification time of a result, read by the search thread so that sorting
+/// the results doesn't touch the disk.
+#[derive(Clone, Copy, Default)]
+pub struct ResultMetadata {
+    pub size: Option<u64>, // Files only
+    pub modified: Option<SystemTime>,
+}
+
+impl ResultMetadata {
+    pub fn read(path: &Path) -> Self {
+        match fs::metadata(fileops::extended_path(path)) {
+            Ok(metadata) => {
+                Self { size: metadata.is_file().then_some(metadata.len()), modified: metadata.modified().ok() }
+            }
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// Sorts search results by `key`, looking up sizes and modification times in `metadata`.
+/// Entries without them sort first.
+pub fn sort_results(
+    paths: &mut [PathBuf],
+    key: ResultSortKey,
+    descending: bool,
+    metadata: &HashMap<PathBuf, ResultMetadata>,
+) {
+    paths.par_sort_by(|a, b| compare_results(a, b, key, metadata));
+    if descending {
+        paths.reverse();
+    }
+}
+
+/// Adds the results of another search root to `paths`, which are sorted by `key` already.
+pub fn merge_results(
+    paths: &mut Vec<PathBuf>,
+    mut batch: Vec<PathBuf>,
+    key: ResultSortKey,
+    descending: bool,
+    metadata: &HashMap<PathBuf, ResultMetadata>,
+) {
+    sort_results(&mut batch, key, descending, metadata);
+    view::merge_sorted(paths, batch, |a, b| {
+        let order = compare_results(a, b, key, metadata);
+        if descending { order.reverse() } else { order }
+    });
+}
+
+fn compare_results(
+    a: &Path,
+    b: &Path,
+    key: ResultSortKey,
+    metadata: &HashMap<PathBuf, ResultMetadata>,
+) -> std::cmp::Ordering {
+    let lookup = |path: &Path| metadata.get(path).copied().unwrap_or_default();
+    let (a_metadata, b_metadata) = (lookup(a), lookup(b));
+    match key {
+        ResultSortKey::Name => compare_names(a, b),
+        ResultSortKey::Path => compare_paths(a, b),
+        ResultSortKey::Size => a_metadata.size.cmp(&b_metadata.size),
+        ResultSortKey::Modified => a_metadata.modified.cmp(&b_metadata.modified),
+    }
+}
+
+fn compare_names(a: &Path, b: &Path) -> std::cmp::Ordering {
+    let (a_name, b_name) = (a.file_name().unwrap_or_default(), b.file_name().unwrap_or_default());
+    view::natural_cmp(&a_name.to_string_lossy(), &b_name.to_string_lossy())
+}
+
+fn compare_paths(a: &Path, b: &Path) -> std::cmp::Ordering {
+    view::natural_cmp(&a.to_string_lossy(), &b.to_string_lossy())
+}
+
+/// A line inside a file that contains the searched text.
+#[derive(Clone)]
+pub struct LineHit {
+    pub line_number: usize, // 1-based
+    pub snippet: String,
+}
+
+/// A batch of results a search sends back to the UI.
+pub struct SearchOutput {
+    pub paths: Vec<PathBuf>,
+    /// Matching lines per file (content search only).
+    pub line_hits: HashMap<PathBuf, Vec<LineHit>>,
+    pub metadata: HashMap<PathBuf, ResultMetadata>,
+}
+
+impl SearchOutput {
+    /// Turns raw (path, line hits, score) matches into the output sent to the UI,
+    /// reading the metadata of every match.
+    fn from_matches(mut found: Vec<(PathBuf, Vec<LineHit>, i64)>, fuzzy: bool) -> Self {
+        // Fuzzy results are only useful with the best matches first
+        if fuzzy {
+            found.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        }
+
+        let mut output = Self {
+            paths: Vec::with_capacity(found.len()),
+            line_hits: HashMap::new(),
+            metadata: HashMap::with_capacity(found.len()),
+        };
+        for (path, hits, _) in found {
+            if !hits.is_empty() {
+                output.line_hits.insert(path.clone(), hits);
+            }
+            output.metadata.insert(path.clone(), ResultMetadata::read(&path));
+            output.paths.push(path);
+        }
+        output
+    }
+}
+
+/// Messages sent from the search threads to the UI.
+pub enum SearchMessage {
+    /// Counters of the whole search so far.
+    Progress(SearchStats),
+    /// The next batch of results of one search root.
+    Results(SearchOutput),
+}
+
+/// How far a running search has come.
+#[derive(Clone, Copy, Default)]
+pub struct SearchStats {
+    pub dirs_scanned: usize,
+    pub files_examined: usize,
+    pub hits: usize,
+}
+
+/// A progress report is sent every time this many more folders or files were examined.
+const PROGRESS_INTERVAL: usize = 500;
+
+/// Counts examined entries and hits for all threads of one search and
+/// reports them to the UI from time to time.
+pub struct SearchProgress {
+    dirs_scanned: AtomicUsize,
+    files_examined: AtomicUsize,
+    hits: AtomicUsize,
+    sender: mpsc::Sender<SearchMessage>,
+    ctx: egui::Context,
+}
+
+impl SearchProgress {
+    pub fn new(sender: mpsc::Sender<SearchMessage>, ctx: egui::Context) -> Self {
+        Self {
+            dirs_scanned: AtomicUsize::new(0),
+            files_examined: AtomicUsize::new(0),
+            hits: AtomicUsize::new(0),
+            sender,
+            ctx,
+        }
+    }
+
+    /// Counts one examined folder or file.
+    pub fn entry_examined(&self, is_dir: bool) {
+        let counter = if is_dir { &self.dirs_scanned } else { &self.files_examined };
+        if (counter.fetch_add(1, Ordering::Relaxed) + 1) % PROGRESS_INTERVAL == 0 {
+            self.report();
+        }
+    }
+
+    /// Counts one matching entry.
+    fn hit_found(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> SearchStats {
+        SearchStats {
+            dirs_scanned: self.dirs_scanned.load(Ordering::Relaxed),
+            files_examined: self.files_examined.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Sends the current counters to the UI.
+    pub fn report(&self) {
+        if self.sender.send(SearchMessage::Progress(self.stats())).is_ok() {
+            repaint::request_throttled_repaint(&self.ctx);
+        }
+    }
+
+    /// Sends a batch of results to the UI.
+    fn send_results(&self, output: SearchOutput) {
+        if self.sender.send(SearchMessage::Results(output)).is_ok() {
+            self.ctx.request_repaint(); // Request repaint in main thread when results sent
+        }
+    }
+}
+
+/// Results are sent to the UI as soon as this many have been found.
+const RESULT_BATCH_SIZE: usize = 10_000;
+
+/// Collects the matches of one search root and hands them to the UI in batches, so a search
+/// matching millions of entries never holds them all at once. Fuzzy matches come best first
+/// within each batch.
+pub struct ResultBatcher<'a> {
+    progress: &'a SearchProgress,
+    fuzzy: bool,
+    pending: Mutex<Vec<(PathBuf, Vec<LineHit>, i64)>>,
+}
+
+impl<'a> ResultBatcher<'a> {
+    pub fn new(progress: &'a SearchProgress, matcher: &SearchMatcher) -> Self {
+        let fuzzy = matches!(matcher, SearchMatcher::Fuzzy { .. });
+        Self { progress, fuzzy, pending: Mutex::new(Vec::new()) }
+    }
+
+    /// Adds one (path, line hits, score) match, sending a full batch on.
+    pub fn add(&self, found: (PathBuf, Vec<LineHit>, i64)) {
+        self.progress.hit_found();
+        let batch = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            pending.push(found);
+            (pending.len() >= RESULT_BATCH_SIZE).then(|| std::mem::take(&mut *pending))
+        };
+        if let Some(batch) = batch {
+            self.progress.send_results(SearchOutput::from_matches(batch, self.fuzzy));
+        }
+    }
+
+    /// Sends the remaining matches and the final counters of this root.
+    pub fn finish(self) {
+        self.progress.report();
+        let batch = self.pending.into_inner().unwrap_or_else(|e| e.into_inner());
+        self.progress.send_results(SearchOutput::from_matches(batch, self.fuzzy));
+    }
+}
+
+/// At most this many matching lines are kept per file.
+const MAX_LINE_HITS_PER_FILE: usize = 100;
+/// Snippets longer than this are cut around the match.
+const MAX_SNIPPET_CHARS: usize = 120;
+
+/// Only the first this many bytes are checked for NUL bytes to detect binary files.
+const BINARY_CHECK_LEN: usize = 8192;
+/// Files at least this large are memory-mapped instead of read into memory.
+const MMAP_MIN_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Returns the lines of `path` containing `query_lower` (case-insensitive).
+/// Binary files (NUL byte within the first 8 KiB) are skipped.
+pub fn search_file_contents(path: &Path, query_lower: &str) -> Vec<LineHit> {
+    let Ok(mut file) = File::open(fileops::extended_path(path)) else {
+        return Vec::new();
+    };
+    let size = file.metadata().map_or(0, |metadata| metadata.len());
+    let (mapped, mut read);
+    let data: &[u8] = if size >= MMAP_MIN_SIZE {
+        // Mapping a large file lets the OS page it in while it is scanned, instead of holding all of it
+        // in memory. Safety: the mapping stays valid while `mapped` lives, but if another program
+        // truncates the file meanwhile, touching the pages past its new end raises SIGBUS on Unix and
+        // kills FileFox (Windows refuses to truncate mapped files). That risk is only taken for large
+        // files, small ones are read, which is also faster for them.
+        let Ok(map) = (unsafe { Mmap::map(&file) }) else {
+            return Vec::new();
+        };
+        mapped = map;
+        &mapped
+    } else {
+        read = Vec::with_capacity(size as usize);
+        if file.read_to_end(&mut read).is_err() {
+            return Vec::new();
+        }
+        &read
+    };
+
+    // Binary heuristic: text files practically never contain NUL bytes
+    if memchr(0, &data[..data.len().min(BINARY_CHECK_LEN)]).is_some() {
+        return Vec::new();
+    }
+
+    if !query_lower.is_empty() && query_lower.is_ascii() {
+        search_ascii(data, query_lower)
+    } else {
+        search_lines(data, query_lower)
+    }
+}
+
+/// Fast path for ASCII queries: candidate positions are found with SIMD-accelerated `memchr`,
+/// only lines containing a match are decoded and lowercased.
+fn search_ascii(data: &[u8], query_lower: &str) -> Vec<LineHit> {
+    let query = query_lower.as_bytes();
+    let first = query[0];
+    let mut hits = Vec::new();
+    let mut line_number = 1;
+    let mut counted_to = 0; // Newlines before this offset are counted in `line_number`
+    let mut next_line_start = 0; // One hit per line, skip candidates before this offset
+    for position in memchr2_iter(first, first.to_ascii_uppercase(), data) {
+        if position < next_line_start {
+            continue;
+        }
+        if !data[position..].get(..query.len()).is_some_and(|candidate| candidate.eq_ignore_ascii_case(query)) {
+            continue;
+        }
+        let line_start = memrchr(b'\n', &data[..position]).map_or(0, |i| i + 1);
+        let line_end = memchr(b'\n', &data[position..]).map_or(data.len(), |i| position + i + 1);
+        line_number += memchr_iter(b'\n', &data[counted_to..line_start]).count();
+        counted_to = line_start;
+        next_line_start = line_end;
+
+        let text = String::from_utf8_lossy(&data[line_start..line_end]);
+        let text_lower = text.to_lowercase();
+        if let Some(position) = text_lower.find(query_lower) {
+            hits.push(LineHit { line_number, snippet: make_snippet(&text_lower, &text, position) });
+            if hits.len() >= MAX_LINE_HITS_PER_FILE {
+                break;
+            }
+        }
+    }
+    hits
+}
+
+/// Lowercases every line, for queries whose case can't be folded byte by byte.
+fn search_lines(data: &[u8], query_lower: &str) -> Vec<LineHit> {
+    let mut hits = Vec::new();
+    for (index, line) in data.split_inclusive(|&b| b == b'\n').enumerate() {
+        let text = String::from_utf8_lossy(line);
+        let text_lower = text.to_lowercase();
+        if let Some(position) = text_lower.find(query_lower) {
+            hits.push(LineHit { line_number: index + 1, snippet: make_snippet(&text_lower, &text, position) });
+            if hits.len() >= MAX_LINE_HITS_PER_FILE {
+                break;
+            }
+        }
+    }
+    hits
+}
+
+/// Cuts `text` down to roughly `MAX_SNIPPET_CHARS` around the match at byte `position` of `text_lower`.
+fn make_snippet(text_lower: &str, text: &str, position: usize) -> String {
+    let text = text.trim_end();
+    // Lowercasing can change byte lengths, so translate the position via char counts
+    let match_char = text_lower[..position].chars().count();
+    let total_chars = text.chars().count();
+    if total_chars <= MAX_SNIPPET_CHARS {
+        return text.trim_start().to_string();
+    }
+    let start = match_char.saturating_sub(MAX_SNIPPET_CHARS / 3);
+    let snippet: String = text.chars().skip(start).take(MAX_SNIPPET_CHARS).collect();
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if start + MAX_SNIPPET_CHARS < total_chars { "…" } else { "" };
+    format!("{}{}{}", prefix, snippet.trim(), suffix)
+}
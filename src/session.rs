@@ -0,0 +1,52 @@
+// --- Folder, window and view of the last run ---
+//
+// Saved when the window closes. The next start reopens the folder if it starts in the
+// last folder, and the window and view if "Reopen previous window and view" is on.
+// FileFox has one folder per window, so the session is that folder.
+
+use std::{fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::view::ViewMode;
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub current_dir: Option<PathBuf>,
+    pub view_mode: Option<ViewMode>,
+    pub window_position: Option<[f32; 2]>, // Outer top left corner, in points
+    pub window_size: Option<[f32; 2]>, // Before the window was maximized
+    pub maximized: bool,
+}
+
+impl Session {
+    /// Location of the session on disk.
+    fn session_file() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("FileFox").join("session.toml"))
+    }
+
+    /// Loads the session written by `save`. A missing file yields an empty session.
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = Self::session_file() else {
+            return Ok(Self::default());
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::session_file() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no data directory available"));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+}
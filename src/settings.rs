@@ -0,0 +1,174 @@
+// --- Persistent user settings ---
+
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::fileops;
+use crate::keymap::Keymap;
+use crate::view::{RowDensity, SortColumn, SortOrder};
+
+/// Color scheme of the UI.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Follow the operating system's light / dark setting.
+    System,
+}
+
+impl Theme {
+    /// Visuals for this theme. `system_theme` is what the OS reports (if anything).
+    pub fn visuals(self, system_theme: Option<eframe::Theme>) -> egui::Visuals {
+        match self {
+            Self::Light => egui::Visuals::light(),
+            Self::Dark => egui::Visuals::dark(),
+            Self::System => system_theme.unwrap_or(eframe::Theme::Dark).egui_visuals(),
+        }
+    }
+}
+
+/// Folder FileFox starts in when none is given on the command line.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupFolder {
+    /// The folder FileFox was closed in.
+    LastSession,
+    Home,
+    /// `Settings::startup_path`.
+    Fixed,
+}
+
+/// Zoom limits and step for Ctrl+= / Ctrl+-.
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 3.0;
+pub const ZOOM_STEP: f32 = 0.1;
+
+/// Settings kept across runs, stored as TOML. Keys missing from the file keep their default.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: Theme,
+    pub zoom: f32, // Multiplies the monitor's native pixels per point
+    pub columns: Vec<SortColumn>, // Details view columns after the name, in display order
+    pub show_hidden: bool, // List dot files and entries with the hidden attribute
+    pub confirm_delete: bool, // Ask before deleting permanently
+    pub confirm_trash: bool, // Ask before moving to the trash
+    pub confirm_overwrite: bool, // Ask before a move, rename, archive, extraction, export or device copy replaces a file
+    pub confirm_open_many: bool, // Ask before opening more than `open_confirm_threshold` files at once
+    pub open_confirm_threshold: usize,
+    pub auto_folder_sizes: bool, // Calculate the size of every listed folder
+    pub row_density: RowDensity,
+    pub shell_menu: bool, // Offer Explorer's context menu in FileFox's own (Windows only)
+    pub tray: bool, // Keep running in the system tray when the window is closed (Windows only)
+    pub startup_folder: StartupFolder,
+    pub startup_path: PathBuf, // Folder to start in with `StartupFolder::Fixed`
+    pub reopen_session: bool, // Start with the window and view of the last run
+    pub terminal: String, // Command opening a terminal, e.g. "alacritty"; empty to detect one
+    pub diff_tool: String, // Command comparing two files, e.g. "code --diff"; empty to detect one
+    pub sort_order: SortOrder, // Of the listing, as last chosen
+    pub remember_folder_views: bool, // Show each folder with the view mode, sort order and grouping chosen for it
+    pub keymap: Keymap,
+    pub open_with: BTreeMap<String, String>, // Program chosen in "Open with" per lowercase extension
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: Theme::System,
+            zoom: 1.0,
+            columns: SortColumn::DEFAULT_VISIBLE.to_vec(),
+            show_hidden: true,
+            confirm_delete: true,
+            confirm_trash: false,
+            confirm_overwrite: true,
+            confirm_open_many: true,
+            open_confirm_threshold: 10,
+            auto_folder_sizes: false,
+            row_density: RowDensity::Comfortable,
+            shell_menu: true,
+            tray: false,
+            startup_folder: StartupFolder::LastSession,
+            startup_path: PathBuf::new(),
+            reopen_session: true,
+            terminal: String::new(),
+            diff_tool: String::new(),
+            sort_order: SortOrder::default(),
+            remember_folder_views: true,
+            keymap: Keymap::default(),
+            open_with: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Location of the settings file.
+    fn settings_file() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("FileFox").join("settings.toml"))
+    }
+
+    /// Loads the saved settings. A missing or unreadable file yields the defaults, unknown keys are ignored.
+    pub fn load() -> Self {
+        let Some(path) = Self::settings_file() else {
+            return Self::default();
+        };
+        let mut settings = match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("Error while reading settings from {:?}: {}", path, e);
+                Self::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                eprintln!("Error while reading settings from {:?}: {}", path, e);
+                Self::default()
+            }
+        };
+        settings.zoom = settings.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        settings.open_confirm_threshold = settings.open_confirm_threshold.max(1);
+        // The name column is always shown first, and every column at most once
+        let mut columns = Vec::new();
+        for column in settings.columns.drain(..) {
+            if SortColumn::OPTIONAL.contains(&column) && !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        settings.columns = columns;
+        settings
+    }
+
+    /// Writes the settings to disk, replacing the old file only once the new one is complete.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = Self::settings_file() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no config directory available"));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fileops::replace_file(&path, &content)
+    }
+
+    /// Shows `column` in the details view (at the end), or hides it.
+    pub fn toggle_column(&mut self, column: SortColumn) {
+        if let Some(index) = self.columns.iter().position(|c| *c == column) {
+            self.columns.remove(index);
+        } else {
+            self.columns.push(column);
+        }
+    }
+
+    /// Moves the visible column `column` to where `target` is.
+    pub fn move_column(&mut self, column: SortColumn, target: SortColumn) {
+        let (Some(from), Some(to)) = (
+            self.columns.iter().position(|c| *c == column),
+            self.columns.iter().position(|c| *c == target),
+        ) else {
+            return;
+        };
+        let column = self.columns.remove(from);
+        self.columns.insert(to, column);
+    }
+}
+
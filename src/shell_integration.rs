@@ -0,0 +1,254 @@
+// --- Integration with the desktop: "Open FileFox here" and filefox:// links ---
+//
+// Registered for the current user only: under HKEY_CURRENT_USER\Software\Classes on
+// Windows, as desktop entries on Linux. Both start FileFox with the clicked folder or
+// the opened link as its command line argument.
+
+use std::io;
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::{fs, path::PathBuf, process::Command};
+
+use crate::instance;
+
+/// Something FileFox can register with the desktop.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Integration {
+    /// "Open FileFox here" in the context menu of folders in other file managers.
+    OpenHere,
+    /// Opening links like "filefox://open?path=C:\Reports" from other programs.
+    UriScheme,
+}
+
+impl Integration {
+    pub const ALL: [Self; 2] = [Self::OpenHere, Self::UriScheme];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::OpenHere => "\"Open FileFox here\" on folders in other file managers",
+            Self::UriScheme => "Open filefox:// links",
+        }
+    }
+}
+
+/// Whether this platform has a desktop FileFox can register with.
+pub fn is_available() -> bool {
+    cfg!(any(windows, all(unix, not(target_os = "macos"))))
+}
+
+/// Keys written for `integration` with the argument of their command: folders and the
+/// background of the folder shown in Explorer, or the URI scheme.
+#[cfg(windows)]
+fn registry_keys(integration: Integration) -> Vec<(String, &'static str)> {
+    match integration {
+        Integration::OpenHere => vec![
+            (r"Software\Classes\Directory\shell\FileFox".to_string(), "%1"),
+            (r"Software\Classes\Directory\Background\shell\FileFox".to_string(), "%V"),
+        ],
+        Integration::UriScheme => vec![(format!(r"Software\Classes\{}", instance::URI_SCHEME), "%1")],
+    }
+}
+
+#[cfg(windows)]
+pub fn is_registered(integration: Integration) -> bool {
+    registry_keys(integration).iter().all(|(key, _)| native::key_exists(key))
+}
+
+#[cfg(windows)]
+pub fn register(integration: Integration) -> io::Result<()> {
+    let program = std::env::current_exe()?;
+    for (key, argument) in registry_keys(integration) {
+        let command = format!("\"{}\" \"{}\"", program.display(), argument);
+        match integration {
+            Integration::OpenHere => {
+                native::set_value(&key, None, "Open FileFox here")?;
+                native::set_value(&key, Some("Icon"), &program.display().to_string())?;
+                native::set_value(&format!(r"{}\command", key), None, &command)?;
+            }
+            Integration::UriScheme => {
+                native::set_value(&key, None, "URL:FileFox")?;
+                native::set_value(&key, Some("URL Protocol"), "")?; // Marks the key as a URI scheme
+                native::set_value(&format!(r"{}\DefaultIcon", key), None, &program.display().to_string())?;
+                native::set_value(&format!(r"{}\shell\open\command", key), None, &command)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn unregister(integration: Integration) -> io::Result<()> {
+    for (key, _) in registry_keys(integration) {
+        native::delete_key(&key)?;
+    }
+    Ok(())
+}
+
+/// The desktop entry of `integration` and the MIME type it handles. File managers list
+/// the programs of "inode/directory" in "Open with" on folders.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop_entry(integration: Integration) -> (&'static str, String) {
+    match integration {
+        Integration::OpenHere => ("filefox-open-here.desktop", "inode/directory".to_string()),
+        Integration::UriScheme => ("filefox-url.desktop", format!("x-scheme-handler/{}", instance::URI_SCHEME)),
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn desktop_file(integration: Integration) -> Option<PathBuf> {
+    let (name, _) = desktop_entry(integration);
+    dirs::data_dir().map(|dir| dir.join("applications").join(name))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn is_registered(integration: Integration) -> bool {
+    desktop_file(integration).is_some_and(|path| path.exists())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn register(integration: Integration) -> io::Result<()> {
+    let Some(path) = desktop_file(integration) else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no data directory available"));
+    };
+    let (name, mime_type) = desktop_entry(integration);
+    let program = std::env::current_exe()?;
+    let argument = if integration == Integration::UriScheme { "%u" } else { "%f" };
+    // Hidden from application menus, but still offered for the MIME type
+    let entry = format!(
+        "[Desktop Entry]\nType=Application\nName=FileFox\nComment={}\nExec={} {}\n\
+         Icon=system-file-manager\nMimeType={};\nNoDisplay=true\nTerminal=false\n",
+        integration.label(),
+        quote_exec_argument(&program.display().to_string()),
+        argument,
+        mime_type
+    );
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, entry)?;
+    refresh_desktop_database(&path);
+    // Folders keep opening in the default file manager, links have no other handler
+    if integration == Integration::UriScheme {
+        let _ = Command::new("xdg-mime").args(["default", name, &mime_type]).status();
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn unregister(integration: Integration) -> io::Result<()> {
+    let Some(path) = desktop_file(integration) else {
+        return Ok(());
+    };
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    refresh_desktop_database(&path);
+    Ok(())
+}
+
+/// Updates the MIME type cache of the folder holding `desktop_file`, if the tool is installed.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn refresh_desktop_database(desktop_file: &std::path::Path) {
+    if let Some(folder) = desktop_file.parent() {
+        let _ = Command::new("update-desktop-database").arg(folder).status();
+    }
+}
+
+/// Quotes an argument of an `Exec` line, as the desktop entry specification asks.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn quote_exec_argument(argument: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in argument.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted.replace('%', "%%")
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_registered(_integration: Integration) -> bool {
+    false
+}
+
+#[cfg(target_os = "macos")]
+pub fn register(_integration: Integration) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "not supported on macOS"))
+}
+
+#[cfg(target_os = "macos")]
+pub fn unregister(_integration: Integration) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(windows)]
+mod native {
+    use std::{io, ptr};
+
+    use winapi::shared::minwindef::{DWORD, HKEY};
+    use winapi::shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+    use winapi::um::winnt::{KEY_READ, KEY_WRITE, REG_SZ};
+    use winapi::um::winreg::{
+        HKEY_CURRENT_USER, RegCloseKey, RegCreateKeyExW, RegDeleteTreeW, RegOpenKeyExW, RegSetValueExW,
+    };
+
+    fn wide(value: &str) -> Vec<u16> {
+        value.encode_utf16().chain(Some(0)).collect()
+    }
+
+    fn check(status: i32) -> io::Result<()> {
+        if status as DWORD == ERROR_SUCCESS { Ok(()) } else { Err(io::Error::from_raw_os_error(status)) }
+    }
+
+    pub fn key_exists(path: &str) -> bool {
+        let mut key: HKEY = ptr::null_mut();
+        unsafe {
+            if RegOpenKeyExW(HKEY_CURRENT_USER, wide(path).as_ptr(), 0, KEY_READ, &mut key) as DWORD != ERROR_SUCCESS {
+                return false;
+            }
+            RegCloseKey(key);
+        }
+        true
+    }
+
+    /// Sets a string value below HKEY_CURRENT_USER, creating the key if needed. `None` is the default value.
+    pub fn set_value(path: &str, name: Option<&str>, value: &str) -> io::Result<()> {
+        let mut key: HKEY = ptr::null_mut();
+        let name = name.map(wide);
+        let value = wide(value);
+        unsafe {
+            check(RegCreateKeyExW(
+                HKEY_CURRENT_USER,
+                wide(path).as_ptr(),
+                0,
+                ptr::null_mut(),
+                0,
+                KEY_WRITE,
+                ptr::null_mut(),
+                &mut key,
+                ptr::null_mut(),
+            ))?;
+            let result = check(RegSetValueExW(
+                key,
+                name.as_ref().map_or(ptr::null(), |name| name.as_ptr()),
+                0,
+                REG_SZ,
+                value.as_ptr() as *const u8,
+                (value.len() * 2) as DWORD,
+            ));
+            RegCloseKey(key);
+            result
+        }
+    }
+
+    /// Deletes a key below HKEY_CURRENT_USER with everything inside. Missing keys are fine.
+    pub fn delete_key(path: &str) -> io::Result<()> {
+        let status = unsafe { RegDeleteTreeW(HKEY_CURRENT_USER, wide(path).as_ptr()) };
+        if status as DWORD == ERROR_FILE_NOT_FOUND {
+            return Ok(());
+        }
+        check(status)
+    }
+}
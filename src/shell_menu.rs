@@ -0,0 +1,279 @@
+// --- Explorer's own context menu ("Open with", "Send to", 7-Zip, TortoiseGit...) ---
+//
+// The menu is built by the shell from the registered handlers of the selected items,
+// so third-party verbs show up exactly as they do in Explorer. Submenus like "Send to"
+// are filled in lazily through menu messages, which a hidden helper window forwards
+// to the handlers while the menu is open.
+
+use std::path::PathBuf;
+
+/// Returns `true` where the shell menu exists (Windows).
+pub fn is_available() -> bool {
+    cfg!(windows)
+}
+
+/// Shows the shell context menu of `paths` (entries of one folder) at the mouse cursor and
+/// runs the command picked in it. Blocks until the menu is closed.
+/// Returns `false` if the menu could not be built.
+#[cfg(windows)]
+pub fn show(paths: &[PathBuf]) -> bool {
+    native::show(paths).is_some()
+}
+
+#[cfg(not(windows))]
+pub fn show(_paths: &[PathBuf]) -> bool {
+    false
+}
+
+#[cfg(windows)]
+#[allow(non_snake_case, non_camel_case_types)]
+mod native {
+    use std::{
+        cell::Cell,
+        os::windows::ffi::OsStrExt,
+        path::PathBuf,
+        ptr,
+        sync::Once,
+    };
+
+    use winapi::RIDL;
+    use winapi::Interface;
+    use winapi::ctypes::c_void;
+    use winapi::shared::basetsd::UINT_PTR;
+    use winapi::shared::guiddef::GUID;
+    use winapi::shared::minwindef::{DWORD, LPARAM, LRESULT, UINT, ULONG, WPARAM};
+    use winapi::shared::windef::{HMENU, HWND, POINT};
+    use winapi::shared::winerror::SUCCEEDED;
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winapi::um::ole2::OleInitialize;
+    use winapi::um::shobjidl_core::IShellItemArray;
+    use winapi::um::shtypes::{PCIDLIST_ABSOLUTE, PIDLIST_ABSOLUTE};
+    use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+    use winapi::um::winnt::{CHAR, HANDLE, HRESULT, LPCSTR, LPCWSTR};
+    use winapi::um::winuser::{
+        CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, DestroyWindow, GetActiveWindow, GetCursorPos,
+        RegisterClassW, SW_SHOWNORMAL, SetForegroundWindow, TPM_RETURNCMD, TPM_RIGHTBUTTON, TrackPopupMenuEx,
+        WM_DRAWITEM, WM_INITMENUPOPUP, WM_MEASUREITEM, WM_MENUCHAR, WNDCLASSW, WS_POPUP,
+    };
+
+    // Not exported by winapi 0.3
+    RIDL! {#[uuid(0x000214e4, 0x0000, 0x0000, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46)]
+    interface IContextMenu(IContextMenuVtbl): IUnknown(IUnknownVtbl) {
+        fn QueryContextMenu(hmenu: HMENU, indexMenu: UINT, idCmdFirst: UINT, idCmdLast: UINT, uFlags: UINT,) -> HRESULT,
+        fn InvokeCommand(pici: *mut CMINVOKECOMMANDINFOEX,) -> HRESULT,
+        fn GetCommandString(idCmd: UINT_PTR, uType: UINT, pReserved: *mut UINT, pszName: *mut CHAR, cchMax: UINT,) -> HRESULT,
+    }}
+    RIDL! {#[uuid(0x000214f4, 0x0000, 0x0000, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46)]
+    interface IContextMenu2(IContextMenu2Vtbl): IContextMenu(IContextMenuVtbl) {
+        fn HandleMenuMsg(uMsg: UINT, wParam: WPARAM, lParam: LPARAM,) -> HRESULT,
+    }}
+    RIDL! {#[uuid(0xbcfce0a0, 0xec17, 0x11d0, 0x8d, 0x10, 0x00, 0xa0, 0xc9, 0x0f, 0x27, 0x19)]
+    interface IContextMenu3(IContextMenu3Vtbl): IContextMenu2(IContextMenu2Vtbl) {
+        fn HandleMenuMsg2(uMsg: UINT, wParam: WPARAM, lParam: LPARAM, plResult: *mut LRESULT,) -> HRESULT,
+    }}
+
+    #[repr(C)]
+    struct CMINVOKECOMMANDINFOEX {
+        cbSize: DWORD,
+        fMask: DWORD,
+        hwnd: HWND,
+        lpVerb: LPCSTR,
+        lpParameters: LPCSTR,
+        lpDirectory: LPCSTR,
+        nShow: i32,
+        dwHotKey: DWORD,
+        hIcon: HANDLE,
+        lpTitle: LPCSTR,
+        lpVerbW: LPCWSTR,
+        lpParametersW: LPCWSTR,
+        lpDirectoryW: LPCWSTR,
+        lpTitleW: LPCWSTR,
+        ptInvoke: POINT,
+    }
+
+    const CMF_NORMAL: UINT = 0x0;
+    const CMIC_MASK_UNICODE: DWORD = 0x4000;
+    const CMIC_MASK_PTINVOKE: DWORD = 0x2000_0000;
+    /// Command ids handed to the shell; the picked id minus `FIRST_COMMAND` is the verb to invoke.
+    const FIRST_COMMAND: UINT = 1;
+    const LAST_COMMAND: UINT = 0x7fff;
+
+    // BHID_SFUIObject: {3981E225-F559-11D3-8E3A-00C04F6837D5}
+    const BHID_SF_UI_OBJECT: GUID = GUID {
+        Data1: 0x3981_e225,
+        Data2: 0xf559,
+        Data3: 0x11d3,
+        Data4: [0x8e, 0x3a, 0x00, 0xc0, 0x4f, 0x68, 0x37, 0xd5],
+    };
+
+    #[link(name = "shell32")]
+    unsafe extern "system" {
+        fn SHParseDisplayName(
+            pszName: LPCWSTR,
+            pbc: *mut c_void,
+            ppidl: *mut PIDLIST_ABSOLUTE,
+            sfgaoIn: ULONG,
+            psfgaoOut: *mut ULONG,
+        ) -> HRESULT;
+        fn SHCreateShellItemArrayFromIDLists(
+            cidl: UINT,
+            rgpidl: *const PCIDLIST_ABSOLUTE,
+            ppsiItemArray: *mut *mut IShellItemArray,
+        ) -> HRESULT;
+        fn ILFree(pidl: PIDLIST_ABSOLUTE);
+    }
+
+    thread_local! {
+        /// Handlers of the open menu, for the helper window to forward submenu messages to.
+        static OPEN_MENU: Cell<(*mut IContextMenu2, *mut IContextMenu3)> =
+            const { Cell::new((ptr::null_mut(), ptr::null_mut())) };
+    }
+
+    pub fn show(paths: &[PathBuf]) -> Option<()> {
+        unsafe {
+            // Safe to call repeatedly; winit already initializes OLE for drop support.
+            OleInitialize(ptr::null_mut());
+
+            let mut pidls: Vec<PIDLIST_ABSOLUTE> = Vec::with_capacity(paths.len());
+            for path in paths {
+                // The shell parses display names and doesn't know extended-length paths
+                let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+                let mut pidl: PIDLIST_ABSOLUTE = ptr::null_mut();
+                if !SUCCEEDED(SHParseDisplayName(wide_path.as_ptr(), ptr::null_mut(), &mut pidl, 0, ptr::null_mut())) {
+                    break;
+                }
+                pidls.push(pidl);
+            }
+            let mut items: *mut IShellItemArray = ptr::null_mut();
+            let created = pidls.len() == paths.len()
+                && SUCCEEDED(SHCreateShellItemArrayFromIDLists(
+                    pidls.len() as UINT,
+                    pidls.as_ptr() as *const PCIDLIST_ABSOLUTE,
+                    &mut items,
+                ));
+            for pidl in pidls {
+                ILFree(pidl); // The array keeps its own copies
+            }
+            if !created {
+                return None;
+            }
+
+            let mut menu: *mut c_void = ptr::null_mut();
+            let bound = (*items).BindToHandler(ptr::null_mut(), &BHID_SF_UI_OBJECT, &IContextMenu::uuidof(), &mut menu);
+            (*items).Release();
+            if !SUCCEEDED(bound) {
+                return None;
+            }
+            let menu = menu as *mut IContextMenu;
+            let result = track(menu);
+            (*menu).Release();
+            result
+        }
+    }
+
+    /// Pops up the menu of `menu` and runs the picked command.
+    unsafe fn track(menu: *mut IContextMenu) -> Option<()> {
+        unsafe {
+            let popup = CreatePopupMenu();
+            if popup.is_null() {
+                return None;
+            }
+            if !SUCCEEDED((*menu).QueryContextMenu(popup, 0, FIRST_COMMAND, LAST_COMMAND, CMF_NORMAL)) {
+                DestroyMenu(popup);
+                return None;
+            }
+
+            // Newer handlers implement IContextMenu3, older ones only IContextMenu2
+            let mut menu3: *mut c_void = ptr::null_mut();
+            let mut menu2: *mut c_void = ptr::null_mut();
+            if !SUCCEEDED((*menu).QueryInterface(&IContextMenu3::uuidof(), &mut menu3)) {
+                (*menu).QueryInterface(&IContextMenu2::uuidof(), &mut menu2);
+            }
+            OPEN_MENU.with(|open| open.set((menu2 as *mut IContextMenu2, menu3 as *mut IContextMenu3)));
+
+            let owner = GetActiveWindow();
+            let helper = helper_window(owner);
+            let mut cursor = POINT { x: 0, y: 0 };
+            GetCursorPos(&mut cursor);
+            SetForegroundWindow(helper); // Otherwise the menu doesn't close when clicking elsewhere
+            let command = TrackPopupMenuEx(popup, TPM_RETURNCMD | TPM_RIGHTBUTTON, cursor.x, cursor.y, helper, ptr::null_mut());
+
+            let result = if command >= FIRST_COMMAND as i32 {
+                // The verb is passed as an offset in place of a string pointer
+                let verb = (command as UINT - FIRST_COMMAND) as usize;
+                let mut info: CMINVOKECOMMANDINFOEX = std::mem::zeroed();
+                info.cbSize = std::mem::size_of::<CMINVOKECOMMANDINFOEX>() as DWORD;
+                info.fMask = CMIC_MASK_UNICODE | CMIC_MASK_PTINVOKE;
+                info.hwnd = owner;
+                info.lpVerb = verb as LPCSTR;
+                info.lpVerbW = verb as LPCWSTR;
+                info.nShow = SW_SHOWNORMAL;
+                info.ptInvoke = cursor;
+                SUCCEEDED((*menu).InvokeCommand(&mut info)).then_some(())
+            } else {
+                Some(()) // Closed without picking anything
+            };
+
+            OPEN_MENU.with(|open| open.set((ptr::null_mut(), ptr::null_mut())));
+            if !menu3.is_null() {
+                (*(menu3 as *mut IContextMenu3)).Release();
+            }
+            if !menu2.is_null() {
+                (*(menu2 as *mut IContextMenu2)).Release();
+            }
+            if !helper.is_null() {
+                DestroyWindow(helper);
+            }
+            DestroyMenu(popup);
+            result
+        }
+    }
+
+    /// Creates the invisible window that owns the popup menu and receives its messages.
+    unsafe fn helper_window(owner: HWND) -> HWND {
+        static REGISTER_CLASS: Once = Once::new();
+        let class_name: Vec<u16> = "FileFoxShellMenu".encode_utf16().chain(Some(0)).collect();
+        unsafe {
+            let instance = GetModuleHandleW(ptr::null());
+            REGISTER_CLASS.call_once(|| {
+                let mut class: WNDCLASSW = std::mem::zeroed();
+                class.lpfnWndProc = Some(helper_window_proc);
+                class.hInstance = instance;
+                class.lpszClassName = class_name.as_ptr();
+                RegisterClassW(&class);
+            });
+            CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                ptr::null(),
+                WS_POPUP,
+                0,
+                0,
+                0,
+                0,
+                owner,
+                ptr::null_mut(),
+                instance,
+                ptr::null_mut(),
+            )
+        }
+    }
+
+    /// Lets the handlers draw and fill in their submenus ("Send to", "Open with"...).
+    unsafe extern "system" fn helper_window_proc(hwnd: HWND, message: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        unsafe {
+            if matches!(message, WM_INITMENUPOPUP | WM_DRAWITEM | WM_MEASUREITEM | WM_MENUCHAR) {
+                let (menu2, menu3) = OPEN_MENU.with(Cell::get);
+                if !menu3.is_null() {
+                    let mut result: LRESULT = 0;
+                    if SUCCEEDED((*menu3).HandleMenuMsg2(message, wparam, lparam, &mut result)) {
+                        return result;
+                    }
+                } else if !menu2.is_null() && SUCCEEDED((*menu2).HandleMenuMsg(message, wparam, lparam)) {
+                    return 0;
+                }
+            }
+            DefWindowProcW(hwnd, message, wparam, lparam)
+        }
+    }
+}
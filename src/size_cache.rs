@@ -0,0 +1,121 @@
+// --- Persistent cache of calculated folder sizes ---
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// File format marker, bumped whenever the layout changes.
+const SIZES_HEADER: &str = "# FileFox folder sizes v1";
+/// New folders are not cached anymore once this many are known.
+const MAX_CACHED_SIZES: usize = 100_000;
+
+struct CachedSize {
+    modified: SystemTime, // Of the folder when its size was calculated
+    size: u64,
+}
+
+/// Folder sizes from earlier calculations, shared by the size column and the disk usage
+/// analyzer so both warm-start on later runs. A size is reused while the folder's
+/// modification time is unchanged. That time only reflects direct entries, so changes
+/// deep inside a folder show up once it is calculated again explicitly.
+#[derive(Default)]
+pub struct SizeCache {
+    sizes: Mutex<HashMap<PathBuf, CachedSize>>,
+    changed: AtomicBool, // Sizes were added since the last save
+}
+
+impl SizeCache {
+    /// Location of the cache on disk.
+    fn cache_file() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("FileFox").join("folder_sizes.txt"))
+    }
+
+    fn sizes(&self) -> MutexGuard<'_, HashMap<PathBuf, CachedSize>> {
+        self.sizes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Adds the sizes written by `save_if_changed` to the cache. Runs in the background at startup,
+    /// sizes calculated in the meantime are kept. A missing file adds nothing.
+    pub fn load(&self) -> io::Result<()> {
+        let Some(path) = Self::cache_file() else {
+            return Ok(());
+        };
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let mut loaded = Vec::new();
+        let mut lines = BufReader::new(file).lines();
+        if lines.next().transpose()?.as_deref() != Some(SIZES_HEADER) {
+            return Ok(()); // Unknown format
+        }
+        for line in lines {
+            // "<size>\t<modified, ns since 1970>\t<path>", the path last as it may contain anything
+            let line = line?;
+            let mut fields = line.splitn(3, '\t');
+            let (Some(Ok(size)), Some(Ok(modified)), Some(path)) =
+                (fields.next().map(str::parse), fields.next().map(str::parse), fields.next())
+            else {
+                continue;
+            };
+            let modified = UNIX_EPOCH + Duration::from_nanos(modified);
+            loaded.push((PathBuf::from(path), CachedSize { modified, size }));
+        }
+        let mut sizes = self.sizes();
+        for (path, cached) in loaded {
+            sizes.entry(path).or_insert(cached);
+        }
+        Ok(())
+    }
+
+    /// Writes the cache to disk if sizes were added since it was loaded or last saved.
+    /// Paths that are not valid UTF-8 are left out.
+    pub fn save_if_changed(&self) -> io::Result<()> {
+        if !self.changed.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let Some(path) = Self::cache_file() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no cache directory available"));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let sizes = self.sizes(); // Held while writing, so concurrent saves don't interleave
+        let mut writer = BufWriter::new(fs::File::create(&path)?);
+        writeln!(writer, "{}", SIZES_HEADER)?;
+        for (path, cached) in sizes.iter() {
+            let (Some(path), Ok(modified)) = (path.to_str(), cached.modified.duration_since(UNIX_EPOCH)) else {
+                continue;
+            };
+            writeln!(writer, "{}\t{}\t{}", cached.size, modified.as_nanos(), path)?;
+        }
+        writer.flush()
+    }
+
+    /// Size of the folder `path` if it was calculated while its modification time was `modified`.
+    pub fn get(&self, path: &Path, modified: SystemTime) -> Option<u64> {
+        self.sizes().get(path).filter(|cached| cached.modified == modified).map(|cached| cached.size)
+    }
+
+    /// Remembers the size of the folder `path`, calculated while its modification time was `modified`.
+    pub fn insert(&self, path: PathBuf, modified: SystemTime, size: u64) {
+        let mut sizes = self.sizes();
+        match sizes.get(&path) {
+            Some(cached) if cached.modified == modified && cached.size == size => return, // Nothing new to save
+            None if sizes.len() >= MAX_CACHED_SIZES => return,
+            _ => {}
+        }
+        sizes.insert(path, CachedSize { modified, size });
+        self.changed.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,542 @@
+// --- Folder synchronization (one-way mirror or two-way, previewed before running) ---
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, SystemTime},
+};
+
+use eframe::egui;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use walkdir::WalkDir;
+
+use crate::jobs::JobContext;
+
+/// Files whose modification times differ by less than this count as unchanged,
+/// since FAT drives store times with a 2 second precision.
+const TIME_TOLERANCE: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Make the right folder match the left one.
+    OneWay,
+    /// Copy new files both ways; changed files are replaced by the newer side.
+    /// Files that differ without one being newer are conflicts and left alone.
+    TwoWay,
+}
+
+/// Type, size and modification time of an entry found on one side.
+struct EntryInfo {
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// One change of the plan. Deletions and conflicts have no source.
+struct SyncStep {
+    relative: PathBuf, // Shown in the preview
+    source: Option<PathBuf>,
+    target: PathBuf,
+    is_dir: bool,
+    direction: &'static str, // "➡" from left to right, "⬅" the other way, "🗑" delete, "⚠" conflict
+}
+
+/// Everything a synchronization will change, shown before running it.
+#[derive(Default)]
+pub struct SyncPlan {
+    copies: Vec<SyncStep>,  // New files and folders, parents before their contents
+    updates: Vec<SyncStep>, // Changed files, overwritten by the other side
+    deletes: Vec<SyncStep>, // Entries missing on the source side, contents before their folders
+    // Two-way: files that differ in size but were modified at the same time. Both modes: a file on
+    // one side and a folder of the same name on the other, whose contents are left alone too
+    conflicts: Vec<SyncStep>,
+}
+
+impl SyncPlan {
+    /// Number of changes the synchronization will make. Conflicts are not changed.
+    fn len(&self) -> usize {
+        self.copies.len() + self.updates.len() + self.deletes.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A planned synchronization ready to run in the background. Reports its result back to the window.
+pub struct SyncJob {
+    plan: SyncPlan,
+    sender: mpsc::Sender<String>,
+    ctx: egui::Context,
+}
+
+impl SyncJob {
+    pub fn run(self, job: &JobContext) {
+        let total = self.plan.len();
+        let mut errors = Vec::new();
+        let steps = self.plan.copies.iter().chain(&self.plan.updates).chain(&self.plan.deletes);
+        for (done, step) in steps.enumerate() {
+            if job.is_cancelled() {
+                errors.push(format!("cancelled after {} of {} changes", done, total));
+                break;
+            }
+            if let Err(e) = apply(step) {
+                errors.push(format!("{}: {}", step.target.display(), e));
+            }
+            job.progress(done + 1, total);
+        }
+        for error in &errors {
+            eprintln!("Error while synchronizing {}", error);
+        }
+        let summary = match errors.len() {
+            0 => format!("Synchronized, {} changes made", total),
+            count => format!("Finished with {} errors, the first one: {}", count, errors[0]),
+        };
+        if self.sender.send(summary).is_ok() {
+            self.ctx.request_repaint();
+        }
+    }
+}
+
+/// Carries out one step of the plan.
+fn apply(step: &SyncStep) -> io::Result<()> {
+    match &step.source {
+        Some(_) if step.is_dir => fs::create_dir_all(&step.target),
+        Some(source) => {
+            if let Some(parent) = step.target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(source, &step.target).map(|_| ())
+        }
+        // Folders still holding excluded entries are kept
+        None if step.is_dir => match fs::remove_dir(&step.target) {
+            Err(e) if e.kind() == io::ErrorKind::DirectoryNotEmpty => Ok(()),
+            result => result,
+        },
+        None => fs::remove_file(&step.target),
+    }
+}
+
+pub enum SyncAction {
+    Close,
+    /// Run this synchronization as a background job.
+    Run(SyncJob),
+}
+
+/// Window to synchronize two folders: choose them and the mode, preview the plan, then run it.
+pub struct FolderSync {
+    left: String,
+    right: String,
+    mode: SyncMode,
+    delete_extra: bool, // One-way: delete what's only on the right
+    exclusions: String, // Comma separated globs of names, e.g. "*.tmp, node_modules"
+    plan: Option<Result<SyncPlan, String>>,
+    plan_receiver: Option<mpsc::Receiver<Result<SyncPlan, String>>>,
+    result_sender: mpsc::Sender<String>,
+    result_receiver: mpsc::Receiver<String>,
+    running: bool,
+    status: Option<String>, // Outcome of the last run
+}
+
+impl FolderSync {
+    pub fn new(left: &Path, right: Option<&Path>) -> Self {
+        let (result_sender, result_receiver) = mpsc::channel();
+        Self {
+            left: left.display().to_string(),
+            right: right.map(|right| right.display().to_string()).unwrap_or_default(),
+            mode: SyncMode::OneWay,
+            delete_extra: false,
+            exclusions: String::new(),
+            plan: None,
+            plan_receiver: None,
+            result_sender,
+            result_receiver,
+            running: false,
+            status: None,
+        }
+    }
+
+    /// Plans the synchronization on rayon's thread pool.
+    fn preview(&mut self, ctx: &egui::Context) {
+        let (sender, receiver) = mpsc::channel();
+        self.plan_receiver = Some(receiver);
+        self.plan = None;
+        self.status = None;
+        let (left, right) = (PathBuf::from(self.left.trim()), PathBuf::from(self.right.trim()));
+        let (mode, delete_extra, exclusions, ctx) = (self.mode, self.delete_extra, self.exclusions.clone(), ctx.clone());
+        rayon::spawn(move || {
+            let plan = plan(&left, &right, mode, delete_extra, &exclusions);
+            if sender.send(plan).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Renders the window.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<SyncAction> {
+        if let Some(receiver) = &self.plan_receiver
+            && let Ok(plan) = receiver.try_recv()
+        {
+            self.plan = Some(plan);
+            self.plan_receiver = None;
+        }
+        if let Ok(status) = self.result_receiver.try_recv() {
+            self.status = Some(status);
+            self.running = false;
+        }
+
+        let mut action = None;
+        let mut is_open = true;
+        egui::Window::new("🔁 Synchronize folders")
+            .open(&mut is_open)
+            .default_size([700.0, 500.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                let mut changed = false;
+                egui::Grid::new("sync_folders").num_columns(2).show(ui, |ui| {
+                    let (left_label, right_label) = match self.mode {
+                        SyncMode::OneWay => ("Source:", "Target:"),
+                        SyncMode::TwoWay => ("Left:", "Right:"),
+                    };
+                    ui.label(left_label);
+                    changed |= ui.add(egui::TextEdit::singleline(&mut self.left).desired_width(500.0)).changed();
+                    ui.end_row();
+                    ui.label(right_label);
+                    changed |= ui.add(egui::TextEdit::singleline(&mut self.right).desired_width(500.0)).changed();
+                    ui.end_row();
+                    ui.label("Exclude:");
+                    let exclusions = egui::TextEdit::singleline(&mut self.exclusions).hint_text("*.tmp, node_modules");
+                    changed |= ui
+                        .add(exclusions.desired_width(500.0))
+                        .on_hover_text("Comma separated names of files and folders to leave alone, * and ? allowed")
+                        .changed();
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    changed |= ui.radio_value(&mut self.mode, SyncMode::OneWay, "One-way").changed();
+                    changed |= ui.radio_value(&mut self.mode, SyncMode::TwoWay, "Two-way").changed();
+                    if self.mode == SyncMode::OneWay {
+                        changed |= ui
+                            .checkbox(&mut self.delete_extra, "Delete files missing in the source")
+                            .on_hover_text("Deleted permanently, not moved to the trash")
+                            .changed();
+                    } else {
+                        ui.weak("Newer files replace older ones, nothing is deleted");
+                    }
+                });
+                if changed {
+                    self.plan = None; // Outdated, has to be previewed again
+                }
+
+                ui.horizontal(|ui| {
+                    let ready = !self.left.trim().is_empty() && !self.right.trim().is_empty() && !self.running;
+                    if ui.add_enabled(ready, egui::Button::new("Preview")).clicked() {
+                        self.preview(ctx);
+                    }
+                    let plan_ready = matches!(&self.plan, Some(Ok(plan)) if !plan.is_empty());
+                    if ui.add_enabled(plan_ready && !self.running, egui::Button::new("Synchronize")).clicked()
+                        && let Some(Ok(plan)) = self.plan.take()
+                    {
+                        self.running = true;
+                        let sender = self.result_sender.clone();
+                        action = Some(SyncAction::Run(SyncJob { plan, sender, ctx: ctx.clone() }));
+                    }
+                    if self.running {
+                        ui.spinner();
+                        ui.label("Synchronizing...");
+                    } else if let Some(status) = &self.status {
+                        ui.label(status);
+                    }
+                });
+                ui.separator();
+
+                if self.plan_receiver.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Comparing the folders...");
+                    });
+                    return;
+                }
+                match &self.plan {
+                    None => {}
+                    Some(Err(e)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                    Some(Ok(plan)) if plan.is_empty() && plan.conflicts.is_empty() => {
+                        ui.label("The folders are already in sync.");
+                    }
+                    Some(Ok(plan)) => show_plan(ui, plan),
+                }
+            });
+        if !is_open {
+            action = Some(SyncAction::Close);
+        }
+        action
+    }
+}
+
+/// The copy, update, delete and conflict lists of the preview.
+fn show_plan(ui: &mut egui::Ui, plan: &SyncPlan) {
+    egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+        let sections = [
+            ("Copy", &plan.copies),
+            ("Update", &plan.updates),
+            ("Delete", &plan.deletes),
+            ("Conflicts, left alone", &plan.conflicts),
+        ];
+        for (label, steps) in sections {
+            if label.starts_with("Conflicts") && steps.is_empty() {
+                continue;
+            }
+            egui::CollapsingHeader::new(format!("{} ({})", label, steps.len()))
+                .id_source(label)
+                .default_open(steps.len() <= 100)
+                .show(ui, |ui| {
+                    for step in steps {
+                        let icon = if step.is_dir { "📁" } else { "📄" };
+                        ui.label(format!("{} {} {}", step.direction, icon, step.relative.display()));
+                    }
+                });
+        }
+    });
+}
+
+/// Builds the exclusion globs from the comma separated list typed by the user.
+fn exclusion_set(exclusions: &str) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in exclusions.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        builder.add(GlobBuilder::new(pattern).case_insensitive(true).build().map_err(|e| e.to_string())?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Every entry below `root` that isn't excluded, by path relative to it. Symlinks are not followed.
+/// Fails on the first entry that can't be read: an incomplete listing of the source would make
+/// the files of the target look extra, and they would be deleted.
+fn scan(root: &Path, exclusions: &GlobSet) -> io::Result<BTreeMap<PathBuf, EntryInfo>> {
+    let walker = WalkDir::new(root).min_depth(1).into_iter();
+    let mut entries = BTreeMap::new();
+    for entry in walker.filter_entry(|entry| !exclusions.is_match(entry.file_name())) {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let info = EntryInfo {
+            is_dir: entry.file_type().is_dir(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        };
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_owned();
+        entries.insert(relative, info);
+    }
+    Ok(entries)
+}
+
+/// Whether `a` was modified noticeably later than `b`.
+fn is_newer(a: &EntryInfo, b: &EntryInfo) -> bool {
+    match (a.modified, b.modified) {
+        (Some(a), Some(b)) => a.duration_since(b).is_ok_and(|difference| difference > TIME_TOLERANCE),
+        _ => false,
+    }
+}
+
+fn plan(left: &Path, right: &Path, mode: SyncMode, delete_extra: bool, exclusions: &str) -> Result<SyncPlan, String> {
+    for folder in [left, right] {
+        if !folder.is_dir() {
+            return Err(format!("{} is not a folder", folder.display()));
+        }
+    }
+    if left.starts_with(right) || right.starts_with(left) {
+        return Err("One folder is inside the other".to_string());
+    }
+    let exclusions = exclusion_set(exclusions)?;
+    let (left_entries, right_entries) = rayon::join(|| scan(left, &exclusions), || scan(right, &exclusions));
+    let scan_error = |e: io::Error| format!("Couldn't read every entry, nothing is changed: {}", e);
+    let (left_entries, right_entries) = (left_entries.map_err(scan_error)?, right_entries.map_err(scan_error)?);
+    Ok(compare(left, right, &left_entries, &right_entries, mode, delete_extra))
+}
+
+/// The plan for the scanned entries of both sides.
+fn compare(
+    left: &Path,
+    right: &Path,
+    left_entries: &BTreeMap<PathBuf, EntryInfo>,
+    right_entries: &BTreeMap<PathBuf, EntryInfo>,
+    mode: SyncMode,
+    delete_extra: bool,
+) -> SyncPlan {
+    let step = |relative: &Path, from: &Path, to: &Path, is_dir: bool| SyncStep {
+        relative: relative.to_owned(),
+        source: Some(from.join(relative)),
+        target: to.join(relative),
+        is_dir,
+        direction: if from == left { "➡" } else { "⬅" },
+    };
+    let conflict = |relative: &Path, is_dir: bool| SyncStep {
+        relative: relative.to_owned(),
+        source: None,
+        target: right.join(relative),
+        is_dir,
+        direction: "⚠",
+    };
+    let mut plan = SyncPlan::default();
+    // A file on one side and a folder on the other: neither can replace the other
+    let mut mismatched: Vec<&Path> = Vec::new();
+    let is_mismatched = |mismatched: &[&Path], relative: &Path| mismatched.iter().any(|path| relative.starts_with(path));
+    for (relative, info) in left_entries {
+        if is_mismatched(&mismatched, relative) {
+            continue;
+        }
+        match right_entries.get(relative) {
+            None => plan.copies.push(step(relative, left, right, info.is_dir)),
+            Some(other) if info.is_dir != other.is_dir => {
+                plan.conflicts.push(conflict(relative, info.is_dir));
+                mismatched.push(relative);
+            }
+            Some(_) if info.is_dir => {} // Folders on both sides
+            Some(other) => {
+                let changed = info.size != other.size || is_newer(info, other) || is_newer(other, info);
+                if !changed {
+                    continue;
+                }
+                match mode {
+                    SyncMode::TwoWay if is_newer(other, info) => plan.updates.push(step(relative, right, left, false)),
+                    SyncMode::TwoWay if !is_newer(info, other) => plan.conflicts.push(conflict(relative, false)),
+                    _ => plan.updates.push(step(relative, left, right, false)),
+                }
+            }
+        }
+    }
+    for (relative, info) in right_entries {
+        if left_entries.contains_key(relative) || is_mismatched(&mismatched, relative) {
+            continue;
+        }
+        match mode {
+            SyncMode::TwoWay => plan.copies.push(step(relative, right, left, info.is_dir)),
+            SyncMode::OneWay if delete_extra => {
+                plan.deletes.push(SyncStep {
+                    relative: relative.clone(),
+                    source: None,
+                    target: right.join(relative),
+                    is_dir: info.is_dir,
+                    direction: "🗑",
+                });
+            }
+            SyncMode::OneWay => {}
+        }
+    }
+    plan.copies.sort_by(|a, b| a.relative.cmp(&b.relative));
+    plan.deletes.reverse(); // Contents before their folders
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty folder below the system temp folder. The tests remove it when done.
+    fn temp_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("filefox-sync-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    fn file(size: u64, modified: u64) -> EntryInfo {
+        EntryInfo { is_dir: false, size, modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(modified)) }
+    }
+
+    #[test]
+    fn two_way_reports_same_time_different_size_as_conflict() {
+        let (left, right) = (Path::new("/left"), Path::new("/right"));
+        let left_entries = BTreeMap::from([
+            (PathBuf::from("same_time.txt"), file(10, 1000)),
+            (PathBuf::from("left_newer.txt"), file(10, 2000)),
+        ]);
+        let right_entries = BTreeMap::from([
+            (PathBuf::from("same_time.txt"), file(20, 1001)),
+            (PathBuf::from("left_newer.txt"), file(10, 1000)),
+        ]);
+        let plan = compare(left, right, &left_entries, &right_entries, SyncMode::TwoWay, false);
+        assert_eq!(plan.conflicts.len(), 1);
+        assert_eq!(plan.conflicts[0].relative, Path::new("same_time.txt"));
+        assert_eq!(plan.updates.len(), 1);
+        assert_eq!(plan.updates[0].target, right.join("left_newer.txt"));
+        assert_eq!(plan.len(), 1); // Conflicts are not changed
+
+        // One-way mirrors the source regardless of the times
+        let plan = compare(left, right, &left_entries, &right_entries, SyncMode::OneWay, false);
+        assert!(plan.conflicts.is_empty());
+        assert_eq!(plan.updates.len(), 2);
+    }
+
+    #[test]
+    fn one_way_deletes_only_entries_missing_in_the_source() {
+        let (left, right) = (Path::new("/left"), Path::new("/right"));
+        let left_entries = BTreeMap::from([(PathBuf::from("kept.txt"), file(1, 0))]);
+        let right_entries =
+            BTreeMap::from([(PathBuf::from("kept.txt"), file(1, 0)), (PathBuf::from("extra.txt"), file(1, 0))]);
+        let plan = compare(left, right, &left_entries, &right_entries, SyncMode::OneWay, true);
+        assert_eq!(plan.deletes.len(), 1);
+        assert_eq!(plan.deletes[0].target, right.join("extra.txt"));
+        assert!(plan.copies.is_empty() && plan.updates.is_empty());
+    }
+
+    #[test]
+    fn files_facing_folders_are_conflicts_in_both_modes() {
+        let (left, right) = (Path::new("/left"), Path::new("/right"));
+        let folder = EntryInfo { is_dir: true, size: 0, modified: None };
+        let left_entries = BTreeMap::from([
+            (PathBuf::from("report"), file(1, 0)),
+            (PathBuf::from("photos"), EntryInfo { is_dir: true, ..folder }),
+            (PathBuf::from("photos/1.jpg"), file(1, 0)),
+        ]);
+        let right_entries = BTreeMap::from([
+            (PathBuf::from("report"), EntryInfo { is_dir: true, ..folder }),
+            (PathBuf::from("report/draft.txt"), file(1, 0)),
+            (PathBuf::from("photos"), file(1, 0)),
+        ]);
+        for mode in [SyncMode::OneWay, SyncMode::TwoWay] {
+            let plan = compare(left, right, &left_entries, &right_entries, mode, true);
+            let conflicts: Vec<&Path> = plan.conflicts.iter().map(|step| step.relative.as_path()).collect();
+            assert_eq!(conflicts, [Path::new("photos"), Path::new("report")]);
+            assert!(plan.is_empty()); // Nothing is copied into or deleted from below them either
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_source_folder_plans_nothing() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let left = temp_folder("unreadable-left");
+        let right = temp_folder("unreadable-right");
+        fs::create_dir(left.join("locked")).unwrap();
+        fs::create_dir(right.join("locked")).unwrap();
+        fs::write(right.join("locked").join("file.txt"), "kept").unwrap();
+        fs::set_permissions(left.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+
+        // Root can read the folder anyway, then there is no error to provoke
+        let readable = fs::read_dir(left.join("locked")).is_ok();
+        let result = plan(&left, &right, SyncMode::OneWay, true, "");
+        fs::set_permissions(left.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+        if !readable {
+            assert!(result.is_err());
+        }
+        let _ = fs::remove_dir_all(&left);
+        let _ = fs::remove_dir_all(&right);
+    }
+
+    #[test]
+    fn scan_skips_excluded_entries() {
+        let folder = temp_folder("scan");
+        fs::create_dir(folder.join("node_modules")).unwrap();
+        fs::write(folder.join("node_modules").join("package.json"), "{}").unwrap();
+        fs::write(folder.join("notes.txt"), "text").unwrap();
+        fs::write(folder.join("notes.tmp"), "text").unwrap();
+        let entries = scan(&folder, &exclusion_set("*.tmp, node_modules").unwrap()).unwrap();
+        assert_eq!(entries.keys().collect::<Vec<_>>(), [Path::new("notes.txt")]);
+        assert_eq!(entries[Path::new("notes.txt")].size, 4);
+        let _ = fs::remove_dir_all(&folder);
+    }
+}
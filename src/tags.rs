@@ -0,0 +1,246 @@
+// --- Color labels and tags on files and folders (kept in a sidecar database) ---
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::fileops;
+
+/// File format version, bumped whenever the layout changes.
+const TAGS_VERSION: u32 = 1;
+
+/// Color label of an entry, shown as a dot in front of its name.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorLabel {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Gray,
+}
+
+impl ColorLabel {
+    pub const ALL: [Self; 7] = [Self::Red, Self::Orange, Self::Yellow, Self::Green, Self::Blue, Self::Purple, Self::Gray];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Red => "Red",
+            Self::Orange => "Orange",
+            Self::Yellow => "Yellow",
+            Self::Green => "Green",
+            Self::Blue => "Blue",
+            Self::Purple => "Purple",
+            Self::Gray => "Gray",
+        }
+    }
+
+    pub fn color(self) -> egui::Color32 {
+        match self {
+            Self::Red => egui::Color32::from_rgb(230, 70, 70),
+            Self::Orange => egui::Color32::from_rgb(240, 150, 50),
+            Self::Yellow => egui::Color32::from_rgb(230, 200, 50),
+            Self::Green => egui::Color32::from_rgb(80, 190, 90),
+            Self::Blue => egui::Color32::from_rgb(70, 130, 230),
+            Self::Purple => egui::Color32::from_rgb(160, 90, 210),
+            Self::Gray => egui::Color32::from_rgb(140, 140, 140),
+        }
+    }
+}
+
+/// Label and tags of one entry.
+#[derive(Clone, Default)]
+pub struct EntryTags {
+    pub label: Option<ColorLabel>,
+    pub tags: Vec<String>, // Sorted, no duplicates
+}
+
+impl EntryTags {
+    fn is_empty(&self) -> bool {
+        self.label.is_none() && self.tags.is_empty()
+    }
+}
+
+/// Label and tags of one path, as stored on disk.
+#[derive(Serialize, Deserialize)]
+struct TaggedPath {
+    path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<ColorLabel>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Layout of the database file.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TagsFile {
+    version: u32,
+    entries: Vec<TaggedPath>,
+}
+
+/// Labels and tags of all entries, by absolute path.
+#[derive(Default)]
+pub struct TagDatabase {
+    entries: HashMap<PathBuf, EntryTags>,
+    not_loaded: bool, // The file on disk couldn't be read, so saving must not replace it
+}
+
+impl TagDatabase {
+    /// Location of the database on disk.
+    fn database_file() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("FileFox").join("tags.toml"))
+    }
+
+    /// Loads the database written by `save`. A missing file yields an empty database.
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = Self::database_file() else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Empty database used when `load` failed. Changes are kept for this run only,
+    /// the tags on disk stay as they are.
+    pub fn not_loaded() -> Self {
+        Self { entries: HashMap::new(), not_loaded: true }
+    }
+
+    /// Reads a database from its TOML form.
+    fn parse(content: &str) -> io::Result<Self> {
+        let file: TagsFile = toml::from_str(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if file.version != TAGS_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown tags version {}", file.version)));
+        }
+        let entries = file
+            .entries
+            .into_iter()
+            .map(|tagged| (tagged.path, EntryTags { label: tagged.label, tags: tagged.tags }))
+            .filter(|(_, entry)| !entry.is_empty())
+            .collect();
+        Ok(Self { entries, not_loaded: false })
+    }
+
+    /// Writes the database to disk, replacing the old file only once the new one is complete.
+    /// Paths that are not valid UTF-8 are left out.
+    pub fn save(&self) -> io::Result<()> {
+        if self.not_loaded {
+            return Err(io::Error::other("the tags could not be loaded, not overwriting them"));
+        }
+        let Some(path) = Self::database_file() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no data directory available"));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fileops::replace_file(&path, &self.to_toml()?)
+    }
+
+    /// The TOML form written by `save`, sorted by path.
+    fn to_toml(&self) -> io::Result<String> {
+        let mut entries: Vec<TaggedPath> = self
+            .entries
+            .iter()
+            .filter(|(path, _)| path.to_str().is_some())
+            .map(|(path, entry)| TaggedPath { path: path.clone(), label: entry.label, tags: entry.tags.clone() })
+            .collect();
+        entries.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+        toml::to_string(&TagsFile { version: TAGS_VERSION, entries }).map_err(io::Error::other)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&EntryTags> {
+        self.entries.get(path)
+    }
+
+    /// Every tag in use, sorted.
+    pub fn all_tags(&self) -> BTreeSet<&str> {
+        self.entries.values().flat_map(|entry| entry.tags.iter().map(String::as_str)).collect()
+    }
+
+    pub fn set_label(&mut self, path: &Path, label: Option<ColorLabel>) {
+        self.update(path, |entry| entry.label = label);
+    }
+
+    /// Adds `tag` to `path`, without surrounding whitespace.
+    pub fn add_tag(&mut self, path: &Path, tag: &str) {
+        let tag = tag.trim().to_string();
+        if tag.is_empty() {
+            return;
+        }
+        self.update(path, |entry| {
+            if let Err(index) = entry.tags.binary_search(&tag) {
+                entry.tags.insert(index, tag);
+            }
+        });
+    }
+
+    pub fn remove_tag(&mut self, path: &Path, tag: &str) {
+        self.update(path, |entry| entry.tags.retain(|t| t != tag));
+    }
+
+    /// Moves the labels and tags of `from` (and everything below it) to `to`, after a rename or move.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<PathBuf> = self.entries.keys().filter(|path| path.starts_with(from)).cloned().collect();
+        for old_path in moved {
+            if let (Some(entry), Ok(relative)) = (self.entries.remove(&old_path), old_path.strip_prefix(from)) {
+                let new_path = if relative.as_os_str().is_empty() { to.to_owned() } else { to.join(relative) };
+                self.entries.insert(new_path, entry);
+            }
+        }
+    }
+
+    /// Forgets `path` and everything below it, after it was deleted.
+    pub fn remove(&mut self, path: &Path) {
+        self.entries.retain(|p, _| !p.starts_with(path));
+    }
+
+    /// Applies `change` to the entry of `path`, dropping it once it has neither label nor tags.
+    fn update(&mut self, path: &Path, change: impl FnOnce(&mut EntryTags)) {
+        let entry = self.entries.entry(path.to_owned()).or_default();
+        change(entry);
+        if entry.is_empty() {
+            self.entries.remove(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_and_tags_with_separators_survive_saving() {
+        let mut database = TagDatabase::default();
+        let path = PathBuf::from("/home/me/odd\tname\nwith, commas");
+        database.set_label(&path, Some(ColorLabel::Purple));
+        database.add_tag(&path, "  to do, later\t");
+        database.add_tag(Path::new("/home/me/plain"), "work");
+
+        let loaded = TagDatabase::parse(&database.to_toml().unwrap()).unwrap();
+        assert_eq!(loaded.entries.len(), 2);
+        let entry = loaded.get(&path).unwrap();
+        assert!(entry.label == Some(ColorLabel::Purple));
+        assert_eq!(entry.tags, ["to do, later"]);
+        assert_eq!(loaded.get(Path::new("/home/me/plain")).unwrap().tags, ["work"]);
+    }
+
+    #[test]
+    fn unreadable_files_are_not_overwritten() {
+        assert!(TagDatabase::parse("not = [valid").is_err());
+        assert!(TagDatabase::parse("version = 99").is_err());
+        let mut database = TagDatabase::not_loaded();
+        database.add_tag(Path::new("/home/me/plain"), "work");
+        assert!(database.save().is_err());
+    }
+}
@@ -0,0 +1,177 @@
+// --- Built-in read-only text viewer with syntax highlighting ---
+
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{OnceLock, mpsc},
+};
+
+use eframe::egui::{
+    self,
+    text::{LayoutJob, TextFormat},
+};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, ThemeSet},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+/// At most this much of a file is shown, larger files are cut off.
+const MAX_TEXT_BYTES: u64 = 512 * 1024;
+
+/// Extensions opened in the viewer although there is no syntax for them.
+const PLAIN_TEXT_EXTENSIONS: &[&str] = &["txt", "log", "ini", "cfg", "conf", "toml", "env", "csv", "gitignore"];
+
+/// Syntax definitions, loaded once on first use.
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme_set() -> &'static ThemeSet {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    THEME_SET.get_or_init(ThemeSet::load_defaults)
+}
+
+/// Whether `path` looks like a source or config file the viewer can show.
+pub fn can_view(path: &Path) -> bool {
+    let Some(extension) = path.extension().map(|e| e.to_string_lossy().to_lowercase()) else {
+        return false;
+    };
+    PLAIN_TEXT_EXTENSIONS.contains(&extension.as_str()) || syntax_set().find_syntax_by_extension(&extension).is_some()
+}
+
+/// Text highlighted on the loader thread.
+struct LoadedText {
+    job: LayoutJob,
+    syntax_name: String,
+    truncated: bool,
+}
+
+/// Viewer window for one text file. Reading and highlighting run on a background thread.
+pub struct TextViewer {
+    pub path: PathBuf,
+    text: Option<LoadedText>,
+    error: Option<String>,
+    receiver: Option<mpsc::Receiver<Result<LoadedText, String>>>,
+}
+
+impl TextViewer {
+    /// Starts loading `path`, highlighted for a dark or light background.
+    pub fn open(path: &Path, ctx: &egui::Context) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let style = ctx.style();
+        let font_id = egui::TextStyle::Monospace.resolve(&style);
+        let (dark_mode, weak_color) = (style.visuals.dark_mode, style.visuals.weak_text_color());
+        let (thread_path, ctx) = (path.to_owned(), ctx.clone());
+        std::thread::spawn(move || {
+            let text = load_text(&thread_path, font_id, dark_mode, weak_color);
+            if sender.send(text).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+        Self { path: path.to_owned(), text: None, error: None, receiver: Some(receiver) }
+    }
+
+    /// Renders the viewer window. Returns `false` once it was closed.
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        if let Some(receiver) = &self.receiver
+            && let Ok(loaded) = receiver.try_recv()
+        {
+            self.receiver = None;
+            match loaded {
+                Ok(text) => self.text = Some(text),
+                Err(e) => self.error = Some(e),
+            }
+        }
+
+        let title = self.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let mut is_open = true;
+        egui::Window::new(format!("📃 {}", title))
+            .id(egui::Id::new("text_viewer"))
+            .open(&mut is_open)
+            .default_size([800.0, 600.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if let Some(text) = &self.text {
+                        ui.weak(&text.syntax_name);
+                        if text.truncated {
+                            ui.weak(format!("· only the first {} KB shown", MAX_TEXT_BYTES / 1024));
+                        }
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Open externally").clicked() {
+                            crate::open_with_default_app(&self.path);
+                        }
+                    });
+                });
+                ui.separator();
+
+                match (&self.text, &self.error) {
+                    (_, Some(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("Could not open the file: {}", error));
+                    }
+                    (None, None) => {
+                        ui.spinner();
+                    }
+                    (Some(text), None) => {
+                        egui::ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                            ui.add(egui::Label::new(text.job.clone()).wrap(false));
+                        });
+                    }
+                }
+            });
+        is_open
+    }
+}
+
+/// Reads the start of `path` and highlights it, with line numbers in `weak_color`.
+fn load_text(path: &Path, font_id: egui::FontId, dark_mode: bool, weak_color: egui::Color32) -> Result<LoadedText, String> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(MAX_TEXT_BYTES + 1).read_to_end(&mut bytes))
+        .map_err(|e| e.to_string())?;
+    if bytes.contains(&0) {
+        return Err("this is not a text file".to_string());
+    }
+    let truncated = bytes.len() as u64 > MAX_TEXT_BYTES;
+    bytes.truncate(MAX_TEXT_BYTES as usize);
+    let text = String::from_utf8_lossy(&bytes);
+
+    let syntax_set = syntax_set();
+    let syntax = path
+        .extension()
+        .and_then(|e| syntax_set.find_syntax_by_extension(&e.to_string_lossy()))
+        .or_else(|| text.lines().next().and_then(|line| syntax_set.find_syntax_by_first_line(line)))
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+    let theme_name = if dark_mode { "base16-ocean.dark" } else { "InspiredGitHub" };
+    let mut highlighter = HighlightLines::new(syntax, &theme_set().themes[theme_name]);
+
+    let line_count = text.lines().count().max(1);
+    let number_width = line_count.to_string().len();
+    let mut job = LayoutJob::default();
+    for (index, line) in LinesWithEndings::from(&text).enumerate() {
+        let number = format!("{:>width$}  ", index + 1, width = number_width);
+        job.append(&number, 0.0, TextFormat { font_id: font_id.clone(), color: weak_color, ..Default::default() });
+        let ranges = highlighter.highlight_line(line, syntax_set).map_err(|e| e.to_string())?;
+        for (style, piece) in ranges {
+            let color = egui::Color32::from_rgb(style.foreground.r, style.foreground.g, style.foreground.b);
+            let format = TextFormat {
+                font_id: font_id.clone(),
+                color,
+                italics: style.font_style.contains(FontStyle::ITALIC),
+                underline: if style.font_style.contains(FontStyle::UNDERLINE) {
+                    egui::Stroke::new(1.0, color)
+                } else {
+                    egui::Stroke::NONE
+                },
+                ..Default::default()
+            };
+            job.append(piece, 0.0, format);
+        }
+    }
+    Ok(LoadedText { job, syntax_name: syntax.name.clone(), truncated })
+}
@@ -0,0 +1,130 @@
+// --- Image thumbnails for the grid view (generated in the background, cached on disk) ---
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::UNIX_EPOCH,
+};
+
+use eframe::egui;
+use image::{ImageFormat, RgbaImage};
+use sha2::{Digest, Sha256};
+
+use crate::{fileops, pdf, repaint};
+
+/// Thumbnails are generated at this size (the largest grid cell) and scaled down when drawn.
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// Extensions thumbnails are generated for.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff", "ico"];
+
+/// Thumbnails of the images in the current directory.
+/// Generation runs on rayon's thread pool; finished images are uploaded as textures by `poll`.
+pub struct ThumbnailCache {
+    textures: HashMap<PathBuf, Option<egui::TextureHandle>>, // `None` while pending or if generation failed
+    sender: mpsc::Sender<(PathBuf, Option<egui::ColorImage>)>,
+    receiver: mpsc::Receiver<(PathBuf, Option<egui::ColorImage>)>,
+}
+
+impl Default for ThumbnailCache {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { textures: HashMap::new(), sender, receiver }
+    }
+}
+
+impl ThumbnailCache {
+    /// Returns the thumbnail of `path` if it is ready. The first call for an image or PDF
+    /// queues its generation; other files never get a thumbnail.
+    pub fn get(&mut self, ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+        if let Some(texture) = self.textures.get(path) {
+            return texture.clone();
+        }
+        self.textures.insert(path.to_owned(), None);
+        if !is_image(path) && !pdf::is_pdf(path) {
+            return None;
+        }
+
+        let (path, sender, ctx) = (path.to_owned(), self.sender.clone(), ctx.clone());
+        rayon::spawn(move || {
+            let image = load_thumbnail(&path).map(|image| {
+                egui::ColorImage::from_rgba_unmultiplied([image.width() as usize, image.height() as usize], image.as_raw())
+            });
+            if sender.send((path, image)).is_ok() {
+                repaint::request_throttled_repaint(&ctx); // Many thumbnails finish at once while scrolling
+            }
+        });
+        None
+    }
+
+    /// Uploads the thumbnails finished since the last frame.
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        while let Ok((path, image)) = self.receiver.try_recv() {
+            // Skip results for a directory that was left in the meantime
+            if let Some(slot) = self.textures.get_mut(&path) {
+                *slot = image.map(|image| ctx.load_texture(path.display().to_string(), image, egui::TextureOptions::LINEAR));
+            }
+        }
+    }
+
+    /// Drops the thumbnail of `path`, so it is generated again on the next `get`.
+    pub fn remove(&mut self, path: &Path) {
+        self.textures.remove(path);
+    }
+
+    /// Drops all textures, e.g. when leaving the directory.
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+}
+
+/// Whether `path` has the extension of an image format that can be decoded.
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str()))
+}
+
+/// Loads the thumbnail from the disk cache, or generates and caches it.
+fn load_thumbnail(path: &Path) -> Option<RgbaImage> {
+    let cache_file = cache_file(path);
+    if let Some(cache_file) = &cache_file
+        && let Ok(image) = image::open(cache_file)
+    {
+        return Some(image.into_rgba8());
+    }
+
+    let thumbnail = if pdf::is_pdf(path) {
+        pdf::render_first_page(path, THUMBNAIL_SIZE)? // First page
+    } else {
+        image::open(fileops::extended_path(path)).ok()?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).into_rgba8()
+    };
+    if let Some(cache_file) = &cache_file {
+        let saved = cache_file
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(image::ImageError::IoError)
+            .and_then(|()| thumbnail.save_with_format(cache_file, ImageFormat::Png));
+        if let Err(e) = saved {
+            eprintln!("Error while caching thumbnail of {:?}: {}", path, e);
+        }
+    }
+    Some(thumbnail)
+}
+
+/// Cache location of the thumbnail of `path`. The key includes the modification time
+/// and size, so edited images get a fresh thumbnail.
+fn cache_file(path: &Path) -> Option<PathBuf> {
+    let metadata = fs::metadata(fileops::extended_path(path)).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+
+    let mut hasher = Sha256::new();
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.update(modified.to_le_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    let key: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    dirs::cache_dir().map(|dir| dir.join("FileFox").join("thumbnails").join(format!("{}.png", key)))
+}
@@ -0,0 +1,340 @@
+// --- System tray icon: FileFox keeps running when its window is closed ---
+//
+// Windows only. The icon lives on its own thread with a hidden window receiving its
+// messages. As eframe doesn't update hidden windows, that thread also shows the main
+// window again before handing over the folder picked in its menu.
+
+#[cfg(not(windows))]
+use std::path::PathBuf;
+
+#[cfg(not(windows))]
+use eframe::egui;
+
+#[cfg(not(windows))]
+use crate::places::Places;
+
+/// Whether this platform has a tray FileFox can live in.
+pub fn is_available() -> bool {
+    cfg!(windows)
+}
+
+#[cfg(windows)]
+pub use native::Tray;
+
+/// Without a tray, `start` yields nothing.
+#[cfg(not(windows))]
+pub struct Tray;
+
+#[cfg(not(windows))]
+impl Tray {
+    pub fn start(_ctx: &egui::Context) -> Option<Self> {
+        None
+    }
+
+    pub fn set_places(&self, _places: &Places) {}
+
+    pub fn picked_folder(&self) -> Option<PathBuf> {
+        None
+    }
+
+    pub fn is_quitting(&self) -> bool {
+        false
+    }
+
+    pub fn hide_window(&self) {}
+}
+
+#[cfg(windows)]
+mod native {
+    use std::{
+        mem,
+        path::PathBuf,
+        ptr,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            mpsc,
+        },
+        thread,
+    };
+
+    use eframe::egui;
+    use winapi::ctypes::c_int;
+    use winapi::shared::minwindef::{BOOL, FALSE, LPARAM, LRESULT, TRUE, UINT, WPARAM};
+    use winapi::shared::windef::{HMENU, HWND, POINT};
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winapi::um::processthreadsapi::GetCurrentProcessId;
+    use winapi::um::shellapi::{NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW, Shell_NotifyIconW};
+    use winapi::um::winuser::{
+        AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, DestroyWindow, DispatchMessageW,
+        EnumWindows, GWLP_USERDATA, GetCursorPos, GetMessageW, GetWindowLongPtrW, GetWindowTextW,
+        GetWindowThreadProcessId, IDI_APPLICATION, IsIconic, LoadIconW, MAKEINTRESOURCEW, MF_GRAYED, MF_SEPARATOR,
+        MF_STRING, MSG, PostMessageW, PostQuitMessage, RegisterClassW, SW_HIDE, SW_RESTORE, SW_SHOW,
+        SetForegroundWindow, SetWindowLongPtrW, ShowWindow, TPM_NONOTIFY, TPM_RETURNCMD, TPM_RIGHTBUTTON,
+        TrackPopupMenu, TranslateMessage, WM_APP, WM_CLOSE, WM_DESTROY, WM_LBUTTONUP, WM_NULL, WM_RBUTTONUP,
+        WNDCLASSW, WS_OVERLAPPEDWINDOW,
+    };
+
+    use crate::places::{self, Places};
+
+    /// Sent to the hidden window for clicks on the icon.
+    const WM_TRAY: UINT = WM_APP + 1;
+
+    // Menu item ids, folders are numbered from FIRST_FOLDER_ID
+    const SHOW_ID: usize = 1;
+    const QUIT_ID: usize = 2;
+    const FIRST_FOLDER_ID: usize = 100;
+
+    /// Read by the tray thread.
+    struct Shared {
+        sender: mpsc::Sender<PathBuf>, // Folders picked in the menu
+        places: Mutex<(Vec<PathBuf>, Vec<PathBuf>)>, // Bookmarks and recent folders listed in the menu
+        ctx: egui::Context,
+        quitting: AtomicBool, // "Quit" was picked, closing the window exits
+        window: AtomicUsize, // The hidden window, once created
+    }
+
+    pub struct Tray {
+        shared: Arc<Shared>,
+        receiver: mpsc::Receiver<PathBuf>,
+    }
+
+    impl Tray {
+        /// Adds the icon. None if the tray is not available, e.g. without Explorer.
+        pub fn start(ctx: &egui::Context) -> Option<Self> {
+            let (sender, receiver) = mpsc::channel();
+            let shared = Arc::new(Shared {
+                sender,
+                places: Mutex::new((Vec::new(), Vec::new())),
+                ctx: ctx.clone(),
+                quitting: AtomicBool::new(false),
+                window: AtomicUsize::new(0),
+            });
+            let (created_sender, created) = mpsc::channel();
+            let thread_shared = shared.clone();
+            thread::spawn(move || unsafe { run(thread_shared, created_sender) });
+            // Wait for the icon, so that a failure turns the option off right away
+            if !created.recv().unwrap_or(false) {
+                eprintln!("Error while adding the tray icon");
+                return None;
+            }
+            Some(Self { shared, receiver })
+        }
+
+        pub fn set_places(&self, places: &Places) {
+            *self.shared.places.lock().unwrap() = (places.bookmarks.clone(), places.recent.clone());
+        }
+
+        pub fn picked_folder(&self) -> Option<PathBuf> {
+            self.receiver.try_recv().ok()
+        }
+
+        pub fn is_quitting(&self) -> bool {
+            self.shared.quitting.load(Ordering::Relaxed)
+        }
+
+        pub fn hide_window(&self) {
+            if let Some(window) = main_window() {
+                unsafe { ShowWindow(window, SW_HIDE) };
+            }
+        }
+    }
+
+    impl Drop for Tray {
+        fn drop(&mut self) {
+            let window = self.shared.window.load(Ordering::Relaxed) as HWND;
+            if window.is_null() {
+                return;
+            }
+            unsafe {
+                // Right away, the process may be exiting
+                Shell_NotifyIconW(NIM_DELETE, &mut icon_data(window));
+                PostMessageW(window, WM_CLOSE, 0, 0); // Ends the thread
+            }
+        }
+    }
+
+    fn wide(value: &str) -> Vec<u16> {
+        value.encode_utf16().chain(Some(0)).collect()
+    }
+
+    /// Identifies the icon of `window`.
+    fn icon_data(window: HWND) -> NOTIFYICONDATAW {
+        let mut data: NOTIFYICONDATAW = unsafe { mem::zeroed() };
+        data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+        data.hWnd = window;
+        data.uID = 1;
+        data
+    }
+
+    /// Creates the hidden window and the icon, then handles their messages until the window is closed.
+    unsafe fn run(shared: Arc<Shared>, created: mpsc::Sender<bool>) {
+        unsafe {
+            let instance = GetModuleHandleW(ptr::null());
+            let class_name = wide("FileFoxTray");
+            let class = WNDCLASSW {
+                style: 0,
+                lpfnWndProc: Some(window_proc),
+                cbClsExtra: 0,
+                cbWndExtra: 0,
+                hInstance: instance,
+                hIcon: ptr::null_mut(),
+                hCursor: ptr::null_mut(),
+                hbrBackground: ptr::null_mut(),
+                lpszMenuName: ptr::null(),
+                lpszClassName: class_name.as_ptr(),
+            };
+            RegisterClassW(&class); // Fails harmlessly if the tray was on before
+            let window = CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                class_name.as_ptr(),
+                WS_OVERLAPPEDWINDOW,
+                0,
+                0,
+                0,
+                0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                instance,
+                ptr::null_mut(),
+            );
+            if window.is_null() {
+                let _ = created.send(false);
+                return;
+            }
+            // `shared` outlives the window, it is only dropped when this function returns
+            SetWindowLongPtrW(window, GWLP_USERDATA, Arc::as_ptr(&shared) as isize);
+            shared.window.store(window as usize, Ordering::Relaxed);
+
+            let mut data = icon_data(window);
+            data.uFlags = NIF_ICON | NIF_MESSAGE | NIF_TIP;
+            data.uCallbackMessage = WM_TRAY;
+            // The icon embedded by build.rs, which winres gives id 1
+            data.hIcon = LoadIconW(instance, MAKEINTRESOURCEW(1));
+            if data.hIcon.is_null() {
+                data.hIcon = LoadIconW(ptr::null_mut(), IDI_APPLICATION);
+            }
+            for (slot, c) in data.szTip.iter_mut().zip("FileFox".encode_utf16()) {
+                *slot = c;
+            }
+            let added = Shell_NotifyIconW(NIM_ADD, &mut data) != 0;
+            let _ = created.send(added);
+            if !added {
+                DestroyWindow(window);
+                return;
+            }
+            let mut message: MSG = mem::zeroed();
+            while GetMessageW(&mut message, ptr::null_mut(), 0, 0) > 0 {
+                TranslateMessage(&message);
+                DispatchMessageW(&message);
+            }
+        }
+    }
+
+    unsafe extern "system" fn window_proc(window: HWND, message: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        unsafe {
+            let shared = GetWindowLongPtrW(window, GWLP_USERDATA) as *const Shared;
+            match message {
+                WM_TRAY if !shared.is_null() => {
+                    match lparam as UINT {
+                        WM_LBUTTONUP => show_main_window(),
+                        WM_RBUTTONUP => show_menu(window, &*shared),
+                        _ => {}
+                    }
+                    0
+                }
+                WM_DESTROY => {
+                    Shell_NotifyIconW(NIM_DELETE, &mut icon_data(window));
+                    PostQuitMessage(0);
+                    0
+                }
+                _ => DefWindowProcW(window, message, wparam, lparam),
+            }
+        }
+    }
+
+    unsafe fn append(menu: HMENU, flags: UINT, id: usize, text: &str) {
+        // "&" marks the access key in menus
+        let text = wide(&text.replace('&', "&&"));
+        unsafe { AppendMenuW(menu, flags, id, text.as_ptr()) };
+    }
+
+    /// The menu of the icon: open FileFox, bookmarks, recent folders and quit.
+    unsafe fn show_menu(window: HWND, shared: &Shared) {
+        let (bookmarks, recent) = shared.places.lock().unwrap().clone();
+        let folders: Vec<PathBuf> = bookmarks.iter().chain(&recent).cloned().collect();
+        unsafe {
+            let menu = CreatePopupMenu();
+            append(menu, MF_STRING, SHOW_ID, "Open FileFox");
+            for (title, list, first) in [("Bookmarks", &bookmarks, 0), ("Recent folders", &recent, bookmarks.len())] {
+                if list.is_empty() {
+                    continue;
+                }
+                append(menu, MF_SEPARATOR, 0, "");
+                append(menu, MF_STRING | MF_GRAYED, 0, title);
+                for (index, folder) in list.iter().enumerate() {
+                    append(menu, MF_STRING, FIRST_FOLDER_ID + first + index, &places::folder_label(folder));
+                }
+            }
+            append(menu, MF_SEPARATOR, 0, "");
+            append(menu, MF_STRING, QUIT_ID, "Quit");
+
+            let mut cursor: POINT = mem::zeroed();
+            GetCursorPos(&mut cursor);
+            SetForegroundWindow(window); // Otherwise the menu stays open when clicking elsewhere
+            let flags = TPM_RETURNCMD | TPM_NONOTIFY | TPM_RIGHTBUTTON;
+            let chosen = TrackPopupMenu(menu, flags, cursor.x, cursor.y, 0, window, ptr::null()) as usize;
+            PostMessageW(window, WM_NULL, 0, 0);
+            DestroyMenu(menu);
+
+            match chosen {
+                0 => {}
+                SHOW_ID => show_main_window(),
+                QUIT_ID => {
+                    shared.quitting.store(true, Ordering::Relaxed);
+                    if let Some(main) = main_window() {
+                        PostMessageW(main, WM_CLOSE, 0, 0);
+                    }
+                }
+                id => {
+                    if let Some(folder) = id.checked_sub(FIRST_FOLDER_ID).and_then(|index| folders.get(index)) {
+                        show_main_window();
+                        if shared.sender.send(folder.clone()).is_ok() {
+                            shared.ctx.request_repaint();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The FileFox window of this process.
+    fn main_window() -> Option<HWND> {
+        unsafe extern "system" fn check(window: HWND, found: LPARAM) -> BOOL {
+            unsafe {
+                let mut process = 0;
+                GetWindowThreadProcessId(window, &mut process);
+                let mut title = [0u16; 16];
+                let len = GetWindowTextW(window, title.as_mut_ptr(), title.len() as c_int).max(0) as usize;
+                if process == GetCurrentProcessId() && String::from_utf16_lossy(&title[..len]) == "FileFox" {
+                    *(found as *mut HWND) = window;
+                    return FALSE; // Stop enumerating
+                }
+                TRUE
+            }
+        }
+        let mut found: HWND = ptr::null_mut();
+        unsafe { EnumWindows(Some(check), &mut found as *mut HWND as LPARAM) };
+        (!found.is_null()).then_some(found)
+    }
+
+    fn show_main_window() {
+        if let Some(window) = main_window() {
+            unsafe {
+                ShowWindow(window, if IsIconic(window) != 0 { SW_RESTORE } else { SW_SHOW });
+                SetForegroundWindow(window);
+            }
+        }
+    }
+}
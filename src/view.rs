@@ -0,0 +1,710 @@
+// --- Listing views (details columns and value formatting) ---
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDateTime};
+use eframe::egui::{
+    self,
+    text::{LayoutJob, TextFormat},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::fileops;
+
+/// How the entries of the current directory are displayed.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewMode {
+    /// Table with name, size, type and modified columns.
+    Details,
+    /// One button per entry, names only.
+    List,
+    /// Wrapping grid of large icons.
+    Grid,
+}
+
+/// Row height of the listing and the search results.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RowDensity {
+    /// Roomy rows that are easy to hit on touch screens.
+    Comfortable,
+    /// Tight rows, more entries per screen.
+    Compact,
+}
+
+impl RowDensity {
+    /// Adjusts the spacing of `ui` (the listing) to this density.
+    pub fn apply(self, ui: &mut egui::Ui) {
+        let spacing = ui.spacing_mut();
+        match self {
+            Self::Comfortable => {
+                spacing.button_padding.y = 4.0;
+                spacing.item_spacing.y = 4.0;
+                spacing.interact_size.y = 24.0;
+            }
+            Self::Compact => {
+                spacing.button_padding.y = 0.0;
+                spacing.item_spacing.y = 1.0;
+                spacing.interact_size.y = 16.0;
+            }
+        }
+    }
+}
+
+/// How the listing is split into collapsible groups.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    None,
+    /// Folders, images, documents, ...
+    Kind,
+    /// Today, yesterday, this week, ...
+    Modified,
+}
+
+impl GroupBy {
+    pub const ALL: [Self; 3] = [Self::None, Self::Kind, Self::Modified];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Kind => "Kind",
+            Self::Modified => "Date modified",
+        }
+    }
+}
+
+/// Splits listing entries into titled groups, in group order. The order within a group is kept.
+/// Without grouping, everything ends up in one untitled group.
+pub fn group_entries<'a>(
+    entries: &'a [OsString],
+    details: &HashMap<OsString, EntryDetails>,
+    group_by: GroupBy,
+) -> Vec<(Option<&'static str>, Vec<&'a OsString>)> {
+    let mut groups: Vec<(usize, &'static str, Vec<&OsString>)> = Vec::new();
+    for entry in entries {
+        let (order, title) = match group_by {
+            GroupBy::None => return vec![(None, entries.iter().collect())],
+            GroupBy::Kind => {
+                let is_dir = details.get(entry).is_some_and(EntryDetails::is_dir);
+                let category = FileCategory::of(&entry.to_string_lossy(), is_dir);
+                (category as usize, category.group_title())
+            }
+            GroupBy::Modified => date_group(details.get(entry).and_then(|d| d.modified)),
+        };
+        match groups.iter_mut().find(|(group_order, _, _)| *group_order == order) {
+            Some((_, _, group)) => group.push(entry),
+            None => groups.push((order, title, vec![entry])),
+        }
+    }
+    groups.sort_by_key(|(order, _, _)| *order);
+    groups.into_iter().map(|(_, title, group)| (Some(title), group)).collect()
+}
+
+/// Date bucket of a modification time, as (order, title).
+fn date_group(modified: Option<SystemTime>) -> (usize, &'static str) {
+    let Some(modified) = modified else {
+        return (6, "Unknown");
+    };
+    let date = DateTime::<Local>::from(modified).date_naive();
+    let today = Local::now().date_naive();
+    if date > today {
+        (0, "In the future") // Clock skew, or files from another time zone
+    } else if date == today {
+        (1, "Today")
+    } else if today.checked_sub_days(Days::new(1)) == Some(date) {
+        (2, "Yesterday")
+    } else if date.iso_week() == today.iso_week() {
+        (3, "Earlier this week")
+    } else if (date.year(), date.month()) == (today.year(), today.month()) {
+        (4, "Earlier this month")
+    } else {
+        (5, "Older")
+    }
+}
+
+/// Cell size limits of the grid view (the label below the icon comes on top).
+pub const MIN_GRID_CELL_SIZE: f32 = 48.0;
+pub const MAX_GRID_CELL_SIZE: f32 = 256.0;
+pub const DEFAULT_GRID_CELL_SIZE: f32 = 96.0;
+
+/// Width of the name column in the details view; the other columns follow it.
+pub const NAME_COLUMN_WIDTH: f32 = 320.0;
+
+/// Column of the details view. The listing can be sorted by any of them.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortColumn {
+    Name,
+    Size,
+    Type,
+    Modified,
+    Created,
+    Extension,
+    Attributes,
+    Owner,
+    Taken,
+}
+
+impl SortColumn {
+    /// Columns that can be shown or hidden; the name column is always first.
+    pub const OPTIONAL: [Self; 8] = [
+        Self::Size,
+        Self::Type,
+        Self::Modified,
+        Self::Created,
+        Self::Extension,
+        Self::Attributes,
+        Self::Owner,
+        Self::Taken,
+    ];
+
+    /// Columns shown when nothing else was chosen.
+    pub const DEFAULT_VISIBLE: [Self; 3] = [Self::Size, Self::Type, Self::Modified];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Size => "Size",
+            Self::Type => "Type",
+            Self::Modified => "Modified",
+            Self::Created => "Created",
+            Self::Extension => "Extension",
+            Self::Attributes if cfg!(unix) => "Permissions",
+            Self::Attributes => "Attributes",
+            Self::Owner => "Owner",
+            Self::Taken => "Date taken",
+        }
+    }
+
+    pub fn width(self) -> f32 {
+        match self {
+            Self::Name => NAME_COLUMN_WIDTH,
+            Self::Size | Self::Attributes => 90.0,
+            Self::Type | Self::Owner => 120.0,
+            Self::Modified | Self::Created | Self::Taken => 140.0,
+            Self::Extension => 80.0,
+        }
+    }
+
+    /// Whether the column shows metadata that is read lazily (see `EntryDetails::loaded`).
+    pub fn needs_metadata(self) -> bool {
+        matches!(self, SortColumn::Size | SortColumn::Modified | SortColumn::Created | SortColumn::Attributes)
+    }
+
+    pub fn right_aligned(self) -> bool {
+        self == Self::Size
+    }
+
+    /// Key used in the settings file, as serialized.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Size => "size",
+            Self::Type => "type",
+            Self::Modified => "modified",
+            Self::Created => "created",
+            Self::Extension => "extension",
+            Self::Attributes => "attributes",
+            Self::Owner => "owner",
+            Self::Taken => "taken",
+        }
+    }
+}
+
+/// How the directory listing is sorted.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SortOrder {
+    pub column: SortColumn,
+    pub descending: bool,
+    pub folders_first: bool, // Folders stay on top regardless of the column and direction
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        Self { column: SortColumn::Name, descending: false, folders_first: true }
+    }
+}
+
+impl SortOrder {
+    /// Sorts listing entries (names) using their `details`.
+    pub fn sort_entries(&self, entries: &mut [OsString], details: &HashMap<OsString, EntryDetails>) {
+        entries.sort_by(|a, b| self.compare(a, b, details));
+    }
+
+    /// Merges a batch of entries read while the listing streams in into the sorted `entries`,
+    /// instead of sorting all of them again.
+    pub fn merge_entries(
+        &self,
+        entries: &mut Vec<OsString>,
+        mut batch: Vec<OsString>,
+        details: &HashMap<OsString, EntryDetails>,
+    ) {
+        self.sort_entries(&mut batch, details);
+        merge_sorted(entries, batch, |a, b| self.compare(a, b, details));
+    }
+
+    /// Order of two listing entries. Entries that compare equal are in ascending name order.
+    fn compare(&self, a: &OsStr, b: &OsStr, details: &HashMap<OsString, EntryDetails>) -> Ordering {
+        let (a_details, b_details) = (details.get(a), details.get(b));
+        let (a_is_dir, b_is_dir) = (a_details.is_some_and(EntryDetails::is_dir), b_details.is_some_and(EntryDetails::is_dir));
+        if self.folders_first {
+            let folder_order = b_is_dir.cmp(&a_is_dir);
+            if folder_order != Ordering::Equal {
+                return folder_order;
+            }
+        }
+        let (a_name, b_name) = (a.to_string_lossy(), b.to_string_lossy());
+        let order = match self.column {
+            SortColumn::Name => natural_cmp(&a_name, &b_name),
+            SortColumn::Size => a_details.and_then(|d| d.size).cmp(&b_details.and_then(|d| d.size)),
+            SortColumn::Type => a_details.map(|d| &d.kind).cmp(&b_details.map(|d| &d.kind)),
+            SortColumn::Modified => a_details.and_then(|d| d.modified).cmp(&b_details.and_then(|d| d.modified)),
+            SortColumn::Created => a_details.and_then(|d| d.created).cmp(&b_details.and_then(|d| d.created)),
+            SortColumn::Extension => extension(a, a_is_dir).cmp(&extension(b, b_is_dir)),
+            SortColumn::Attributes => a_details.map(|d| &d.attributes).cmp(&b_details.map(|d| &d.attributes)),
+            SortColumn::Owner => a_details.and_then(|d| d.owner.as_ref()).cmp(&b_details.and_then(|d| d.owner.as_ref())),
+            SortColumn::Taken => a_details.and_then(|d| d.date_taken).cmp(&b_details.and_then(|d| d.date_taken)),
+        };
+        let order = if self.descending { order.reverse() } else { order };
+        order.then_with(|| natural_cmp(&a_name, &b_name))
+    }
+}
+
+/// Merges the sorted `batch` into `sorted`, keeping it sorted by `compare`. Takes linear time,
+/// so results streaming in don't have to be sorted all over again.
+pub fn merge_sorted<T>(sorted: &mut Vec<T>, batch: Vec<T>, mut compare: impl FnMut(&T, &T) -> Ordering) {
+    // Often the batch simply belongs behind everything so far
+    if sorted.last().zip(batch.first()).is_none_or(|(last, first)| compare(last, first) != Ordering::Greater) {
+        sorted.extend(batch);
+        return;
+    }
+    let old = std::mem::take(sorted);
+    sorted.reserve(old.len() + batch.len());
+    let (mut old, mut batch) = (old.into_iter().peekable(), batch.into_iter().peekable());
+    loop {
+        let from_batch = match (old.peek(), batch.peek()) {
+            (Some(a), Some(b)) => compare(a, b) == Ordering::Greater,
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (None, None) => break,
+        };
+        sorted.extend(if from_batch { batch.next() } else { old.next() });
+    }
+}
+
+/// Compares names the way people read them: ignoring case, with runs of digits compared
+/// by their value, so "file2" comes before "File10".
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let order = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return a.cmp(b), // Same apart from case, keep the order stable
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x_digits, y_digits) = (take_digits(&mut a_chars), take_digits(&mut b_chars));
+                let (x_value, y_value) = (x_digits.trim_start_matches('0'), y_digits.trim_start_matches('0'));
+                // Longer number is larger; "02" sorts after "2"
+                x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x_digits.len().cmp(&y_digits.len()))
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                a_chars.next();
+                b_chars.next();
+                order
+            }
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        digits.push(digit);
+    }
+    digits
+}
+
+/// Metadata shown in the details view. Listing a directory only fills in the type and link target,
+/// the rest is read in the background once the entry is shown.
+pub struct EntryDetails {
+    pub size: Option<u64>, // Files, and folders once their size was calculated
+    pub modified: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    pub kind: String,
+    pub attributes: String,
+    pub owner: Option<String>, // Only looked up while the owner column is shown
+    pub date_taken: Option<NaiveDateTime>, // From EXIF data, only read while the column is shown
+    pub link_target: Option<PathBuf>, // Symlinks and junctions, as stored in the link
+    pub hidden: bool, // Dot file, or hidden attribute (Windows)
+    pub loaded: bool, // Size, dates and attributes were read
+}
+
+impl EntryDetails {
+    /// Details known from the directory listing alone, without reading the entry's metadata.
+    pub fn basic(path: &Path, is_dir: bool, is_symlink: bool) -> Self {
+        Self {
+            size: None,
+            modified: None,
+            created: None,
+            kind: describe_kind(path, is_dir),
+            attributes: String::new(),
+            owner: None,
+            date_taken: None,
+            link_target: if is_symlink { fs::read_link(path).ok() } else { None },
+            hidden: false,
+            loaded: false,
+        }
+    }
+
+    /// Reads the details of `path` (following symlinks).
+    pub fn read(path: &Path) -> Self {
+        let metadata = fs::metadata(path).ok();
+        let is_dir = metadata.as_ref().is_some_and(|m| m.is_dir());
+        let is_symlink = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+        Self {
+            size: metadata.as_ref().filter(|m| m.is_file()).map(|m| m.len()),
+            modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+            created: metadata.as_ref().and_then(|m| m.created().ok()),
+            attributes: metadata.as_ref().map(fileops::file_attributes).unwrap_or_default(),
+            loaded: true,
+            ..Self::basic(path, is_dir, is_symlink)
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == FOLDER_KIND
+    }
+
+    /// macOS bundles are folders, but listed and opened like files.
+    pub fn is_package(&self) -> bool {
+        self.kind == APPLICATION_KIND || self.kind.ends_with(PACKAGE_KIND_SUFFIX)
+    }
+
+    /// Text of `column` for the entry called `name`.
+    pub fn column_text(&self, column: SortColumn, name: &OsStr) -> String {
+        match column {
+            SortColumn::Name => name.to_string_lossy().to_string(),
+            SortColumn::Size => self.size.map(format_size).unwrap_or_default(),
+            SortColumn::Type => self.kind.clone(),
+            SortColumn::Modified => self.modified.map(format_time).unwrap_or_default(),
+            SortColumn::Created => self.created.map(format_time).unwrap_or_default(),
+            SortColumn::Extension => extension(name, self.is_dir()),
+            SortColumn::Attributes => self.attributes.clone(),
+            SortColumn::Owner => self.owner.clone().unwrap_or_default(),
+            SortColumn::Taken => self.date_taken.map(|taken| taken.format(TIME_FORMAT).to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+const FOLDER_KIND: &str = "Folder";
+const APPLICATION_KIND: &str = "Application";
+const PACKAGE_KIND_SUFFIX: &str = " package";
+
+/// "Folder", "PDF file", or just "File" for names without extension.
+/// macOS bundles are an "Application" or e.g. a "FRAMEWORK package".
+fn describe_kind(path: &Path, is_dir: bool) -> String {
+    if is_dir && fileops::is_package(path) {
+        return match path.extension().map(|e| e.to_string_lossy().to_uppercase()) {
+            Some(extension) if extension == "APP" => APPLICATION_KIND.to_string(),
+            Some(extension) => format!("{}{}", extension, PACKAGE_KIND_SUFFIX),
+            None => FOLDER_KIND.to_string(),
+        };
+    }
+    if is_dir {
+        return FOLDER_KIND.to_string();
+    }
+    match path.extension() {
+        Some(extension) => format!("{} file", extension.to_string_lossy().to_uppercase()),
+        None => "File".to_string(),
+    }
+}
+
+/// Lowercase extension of a listing entry, empty for folders and names without one.
+fn extension(name: &OsStr, is_dir: bool) -> String {
+    if is_dir {
+        return String::new();
+    }
+    Path::new(name).extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+/// Formats a byte count for humans: "512 B", "1.5 KB", "3.2 GB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Formats a timestamp in local time, e.g. "2024-03-01 14:05".
+pub fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).format(TIME_FORMAT).to_string()
+}
+
+/// Tooltip text of an entry: its full path, and its size and modification time where known.
+pub fn path_tooltip(path: &Path, size: Option<u64>, modified: Option<SystemTime>) -> String {
+    let mut text = path.display().to_string();
+    if let Some(size) = size {
+        text.push_str(&format!("\nSize: {}", format_size(size)));
+    }
+    if let Some(modified) = modified {
+        text.push_str(&format!("\nModified: {}", format_time(modified)));
+    }
+    text
+}
+
+/// Renders a column header that can be clicked and dragged. `sorted` is `Some(descending)` for the active column.
+pub fn header_cell(ui: &mut egui::Ui, width: f32, label: &str, sorted: Option<bool>, right_aligned: bool) -> egui::Response {
+    let arrow = match sorted {
+        None => "",
+        Some(false) => " ⏶",
+        Some(true) => " ⏷",
+    };
+    let text = egui::RichText::new(format!("{}{}", label, arrow)).strong();
+    let layout = if right_aligned {
+        egui::Layout::right_to_left(egui::Align::Center)
+    } else {
+        egui::Layout::left_to_right(egui::Align::Center)
+    };
+    ui.allocate_ui_with_layout(egui::vec2(width, ui.spacing().interact_size.y), layout, |ui| {
+        ui.set_min_width(width);
+        ui.selectable_label(sorted.is_some(), text).interact(egui::Sense::drag())
+    })
+    .inner
+}
+
+/// Renders `text` in a fixed-width cell of the details view.
+pub fn column_cell(ui: &mut egui::Ui, width: f32, text: impl Into<egui::WidgetText>, right_aligned: bool) {
+    let layout = if right_aligned {
+        egui::Layout::right_to_left(egui::Align::Center)
+    } else {
+        egui::Layout::left_to_right(egui::Align::Center)
+    };
+    ui.allocate_ui_with_layout(egui::vec2(width, ui.spacing().interact_size.y), layout, |ui| {
+        ui.set_min_width(width);
+        ui.add(egui::Label::new(text).wrap(false));
+    });
+}
+
+/// Full-width row of the details and list views. `add_cells` lays out the cells;
+/// the whole row reacts to clicks and gets a hover / selection background behind them.
+pub fn entry_row(ui: &mut egui::Ui, id: egui::Id, selected: bool, add_cells: impl FnOnce(&mut egui::Ui)) -> egui::Response {
+    let background = ui.painter().add(egui::Shape::Noop); // Filled in once the row's size is known
+    let cells_rect = ui.horizontal(add_cells).response.rect;
+    let rect = egui::Rect::from_x_y_ranges(
+        ui.max_rect().left()..=ui.max_rect().right().max(cells_rect.right()),
+        cells_rect.y_range(),
+    );
+    let response = ui.interact(rect, id, egui::Sense::click());
+    if selected || response.hovered() {
+        let visuals = ui.style().interact_selectable(&response, selected);
+        let background_rect = rect.expand2(egui::vec2(0.0, ui.spacing().item_spacing.y / 2.0)); // Close the gaps between rows
+        ui.painter().set(background, egui::Shape::rect_filled(background_rect, visuals.rounding, visuals.weak_bg_fill));
+    }
+    response
+}
+
+/// Renders a spinner in a fixed-width cell of the details view, while its value is computed.
+pub fn spinner_cell(ui: &mut egui::Ui, width: f32, right_aligned: bool) {
+    let layout = if right_aligned {
+        egui::Layout::right_to_left(egui::Align::Center)
+    } else {
+        egui::Layout::left_to_right(egui::Align::Center)
+    };
+    ui.allocate_ui_with_layout(egui::vec2(width, ui.spacing().interact_size.y), layout, |ui| {
+        ui.set_min_width(width);
+        ui.spinner();
+    });
+}
+
+/// Rough category of an entry, picked by extension. Declared in group order.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FileCategory {
+    Folder,
+    Image,
+    Video,
+    Audio,
+    Document,
+    Archive,
+    Program,
+    Other,
+}
+
+impl FileCategory {
+    pub fn of(name: &str, is_dir: bool) -> Self {
+        if is_dir {
+            return Self::Folder;
+        }
+        let extension = Path::new(name)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "tif" | "tiff" | "ico" | "heic" | "svg" => Self::Image,
+            "mp4" | "mkv" | "avi" | "mov" | "webm" | "wmv" | "flv" | "m4v" => Self::Video,
+            "mp3" | "wav" | "flac" | "ogg" | "m4a" | "aac" | "wma" => Self::Audio,
+            "zip" | "rar" | "7z" | "tar" | "gz" | "bz2" | "xz" | "zst" | "cab" => Self::Archive,
+            "exe" | "msi" | "bat" | "cmd" | "ps1" | "sh" | "app" => Self::Program,
+            "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "odt" | "ods" | "rtf" => Self::Document,
+            _ => Self::Other,
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            Self::Folder => "📁",
+            Self::Image => "🖼",
+            Self::Video => "🎞",
+            Self::Audio => "🎵",
+            Self::Document => "📄",
+            Self::Archive => "📦",
+            Self::Program => "⚙",
+            Self::Other => "📃",
+        }
+    }
+
+    fn group_title(self) -> &'static str {
+        match self {
+            Self::Folder => "Folders",
+            Self::Image => "Images",
+            Self::Video => "Videos",
+            Self::Audio => "Audio",
+            Self::Document => "Documents",
+            Self::Archive => "Archives",
+            Self::Program => "Programs",
+            Self::Other => "Other files",
+        }
+    }
+}
+
+/// Button text of an entry in the details and list views: a dot in the color of its label,
+/// a link icon and the link target if it is a symlink or junction, and a colored badge (git status).
+/// `link_target` is relative to the entry's folder; broken links show it in red.
+pub fn entry_label(
+    ui: &egui::Ui,
+    entry: &str,
+    label_color: Option<egui::Color32>,
+    link_target: Option<(&Path, bool)>,
+    badge: Option<(&str, egui::Color32)>,
+) -> egui::WidgetText {
+    if label_color.is_none() && link_target.is_none() && badge.is_none() {
+        return entry.into();
+    }
+    let font_id = egui::TextStyle::Button.resolve(ui.style());
+    let text_color = ui.visuals().widgets.inactive.text_color();
+    let format = |color| TextFormat { font_id: font_id.clone(), color, ..Default::default() };
+    let mut job = LayoutJob::default();
+    if let Some(color) = label_color {
+        job.append("● ", 0.0, format(color));
+    }
+    if link_target.is_some() {
+        job.append(LINK_ICON, 0.0, format(text_color));
+        job.append(" ", 0.0, format(text_color));
+    }
+    job.append(entry, 0.0, format(text_color));
+    if let Some((badge, color)) = badge {
+        job.append(&format!(" {}", badge), 0.0, format(color));
+    }
+    if let Some((target, exists)) = link_target {
+        let color = if exists { ui.visuals().weak_text_color() } else { ui.visuals().error_fg_color };
+        job.append(&format!("  → {}", target.display()), 0.0, format(color));
+    }
+    job.into()
+}
+
+/// Marks symlinks and junctions.
+pub const LINK_ICON: &str = "🔗";
+
+/// Large icon for an entry in the grid view, picked by extension.
+pub fn entry_icon(name: &str, is_dir: bool) -> &'static str {
+    FileCategory::of(name, is_dir).icon()
+}
+
+/// Renders one cell of the grid view: a thumbnail or large icon with the name below it.
+/// `thumbnail` is only asked for when the cell is visible.
+/// The whole cell reacts to clicks like the buttons of the other views.
+pub fn grid_cell(
+    ui: &mut egui::Ui,
+    cell_size: f32,
+    icon: &str,
+    name: &str,
+    selected: bool,
+    thumbnail: impl FnOnce() -> Option<egui::TextureHandle>,
+) -> egui::Response {
+    let label_height = ui.text_style_height(&egui::TextStyle::Small) * 2.0;
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(cell_size, cell_size + label_height), egui::Sense::click());
+    if ui.is_rect_visible(rect) {
+        let visuals = ui.style().interact_selectable(&response, selected);
+        if selected || response.hovered() {
+            ui.painter().rect_filled(rect, visuals.rounding, visuals.weak_bg_fill);
+        }
+        let icon_rect = egui::Rect::from_min_size(rect.min, egui::vec2(cell_size, cell_size));
+        match thumbnail() {
+            Some(texture) => {
+                // Fit the thumbnail into the cell, keeping its aspect ratio
+                let texture_size = texture.size_vec2();
+                let scale = (icon_rect.shrink(4.0).size() / texture_size).min_elem();
+                let image_rect = egui::Rect::from_center_size(icon_rect.center(), texture_size * scale);
+                egui::Image::new(texture.id(), image_rect.size()).paint_at(ui, image_rect);
+            }
+            None => {
+                ui.painter().text(
+                    icon_rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    icon,
+                    egui::FontId::proportional(cell_size * 0.6),
+                    visuals.text_color(),
+                );
+            }
+        }
+        // Name below the icon, cut to fit the cell
+        let font_id = egui::TextStyle::Small.resolve(ui.style());
+        let galley = ui.painter().layout(name.to_string(), font_id, visuals.text_color(), cell_size - 4.0);
+        let label_rect = egui::Rect::from_min_max(egui::pos2(rect.left(), icon_rect.bottom()), rect.max);
+        let position = egui::pos2(label_rect.center().x - galley.size().x / 2.0, label_rect.top());
+        ui.painter().with_clip_rect(label_rect).galley(position, galley);
+    }
+    response.on_hover_text(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_order_compares_numbers_by_value_and_ignores_case() {
+        let mut names = vec!["file10.txt", "File2.txt", "file1.txt", "file02.txt", "alpha", "Beta", "file"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["alpha", "Beta", "file", "file1.txt", "File2.txt", "file02.txt", "file10.txt"]);
+        assert_eq!(natural_cmp("a99999999999999999999999", "a100000000000000000000000"), Ordering::Less);
+        // Names that only differ in case still have a fixed order
+        assert_eq!(natural_cmp("Readme", "readme"), Ordering::Less);
+        assert_eq!(natural_cmp("same", "same"), Ordering::Equal);
+    }
+}
@@ -0,0 +1,81 @@
+// --- Automatic refresh: watches the current directory for changes by other programs ---
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock, mpsc},
+};
+
+use eframe::egui;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::repaint;
+
+/// What changed in the watched directory since the last `poll`.
+pub enum DirectoryChanges {
+    /// Entries added, removed, renamed or modified (by full path).
+    Paths(HashSet<PathBuf>),
+    /// Events were lost, the whole directory has to be read again.
+    Rescan,
+}
+
+/// Watches one directory (not its subfolders) at a time.
+pub struct DirectoryWatcher {
+    watcher: Option<RecommendedWatcher>, // `None` if the platform watcher couldn't be created
+    watched: Option<PathBuf>,
+    receiver: mpsc::Receiver<notify::Result<Event>>,
+    repaint: Arc<OnceLock<egui::Context>>, // Set on the first `poll`, wakes the UI on events
+}
+
+impl Default for DirectoryWatcher {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let repaint: Arc<OnceLock<egui::Context>> = Arc::default();
+        let handler_repaint = repaint.clone();
+        let watcher = notify::recommended_watcher(move |event| {
+            if sender.send(event).is_ok()
+                && let Some(ctx) = handler_repaint.get()
+            {
+                repaint::request_throttled_repaint(ctx);
+            }
+        })
+        .map_err(|e| eprintln!("Error while starting the file system watcher: {}", e))
+        .ok();
+        Self { watcher, watched: None, receiver, repaint }
+    }
+}
+
+impl DirectoryWatcher {
+    /// Watches `dir` instead of the previous directory.
+    pub fn watch(&mut self, dir: &Path) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        if self.watched.as_deref() == Some(dir) {
+            return;
+        }
+        if let Some(old) = self.watched.take() {
+            let _ = watcher.unwatch(&old); // Fails if the old directory is gone, nothing to do then
+        }
+        match watcher.watch(dir, RecursiveMode::NonRecursive) {
+            Ok(()) => self.watched = Some(dir.to_owned()),
+            Err(e) => eprintln!("Error while watching {:?} for changes: {}", dir, e),
+        }
+        self.receiver.try_iter().for_each(drop); // Events of the old directory
+    }
+
+    /// Collects the changes reported since the last call. Changed paths are deduplicated,
+    /// downloads and builds touch the same file many times in a row.
+    pub fn poll(&mut self, ctx: &egui::Context) -> Option<DirectoryChanges> {
+        self.repaint.get_or_init(|| ctx.clone());
+        let mut paths = HashSet::new();
+        for event in self.receiver.try_iter() {
+            match event {
+                Ok(event) if event.need_rescan() => return Some(DirectoryChanges::Rescan),
+                Ok(event) => paths.extend(event.paths),
+                Err(e) => eprintln!("Error while watching for changes: {}", e),
+            }
+        }
+        if paths.is_empty() { None } else { Some(DirectoryChanges::Paths(paths)) }
+    }
+}
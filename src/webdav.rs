@@ -0,0 +1,209 @@
+// --- WebDAV servers (Nextcloud, ownCloud, SharePoint...) ---
+//
+// The location URL is the root of the browsed tree, e.g.
+// "https://cloud.example.com/remote.php/dav/files/alice". Listings use PROPFIND with
+// depth 1, the other operations map to GET, PUT, MKCOL, MOVE and DELETE.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use base64::Engine;
+
+use crate::remote::{self, Progress, RemoteEntry, RemoteLocation, UploadReader, child_text, parse_xml};
+
+/// Properties requested for every listed entry.
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getcontentlength/><d:getlastmodified/></d:prop></d:propfind>"#;
+
+/// Connection to one WebDAV server.
+pub struct WebDavClient {
+    root: String,      // Location URL without trailing "/"
+    root_path: String, // Decoded path of the root on the server, to make listed hrefs relative
+    authorization: Option<String>,
+    insecure: bool, // Credentials are set but the URL is plain HTTP, so nothing is sent
+    agent: ureq::Agent,
+}
+
+impl WebDavClient {
+    pub fn new(location: &RemoteLocation) -> Self {
+        let root = location.url.trim().trim_end_matches('/').to_string();
+        let after_scheme = root.split_once("://").map_or(root.as_str(), |(_, rest)| rest);
+        let root_path = after_scheme.find('/').map_or("", |index| &after_scheme[index..]);
+        let authorization = (!location.user.trim().is_empty()).then(|| {
+            let credentials = format!("{}:{}", location.user.trim(), location.secret);
+            format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials))
+        });
+        Self {
+            root_path: percent_decode(root_path),
+            root,
+            authorization,
+            insecure: location.sends_password_unencrypted(),
+            agent: ureq::AgentBuilder::new().timeout_connect(Duration::from_secs(15)).build(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.root, percent_encode(path))
+    }
+
+    /// Builds a request with the credentials, refusing to send them over plain HTTP.
+    fn request(&self, method: &str, path: &str) -> io::Result<ureq::Request> {
+        if self.insecure {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the password would be sent unencrypted, use an https:// URL",
+            ));
+        }
+        let request = self.agent.request(method, &self.url(path));
+        Ok(match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        })
+    }
+
+    /// Lists the folders and files directly inside `folder`.
+    pub fn list(&self, folder: &str) -> io::Result<Vec<RemoteEntry>> {
+        let request = self.request("PROPFIND", folder)?.set("Depth", "1").set("Content-Type", "application/xml");
+        let body = send(request.send_string(PROPFIND_BODY))?.into_string()?;
+        let document = parse_xml(&body)?;
+        let mut entries = Vec::new();
+        for response in document.descendants().filter(|node| node.has_tag_name("response")) {
+            let Some(href) = child_text(response, "href") else {
+                continue;
+            };
+            let Some(path) = self.relative_path(href) else {
+                continue;
+            };
+            // The folder itself is part of its own listing
+            if path.trim_end_matches('/') == folder.trim_end_matches('/') {
+                continue;
+            }
+            let Some(properties) = response
+                .descendants()
+                .find(|node| node.has_tag_name("propstat") && !child_text(*node, "status").is_some_and(is_failure))
+                .and_then(|propstat| propstat.children().find(|node| node.has_tag_name("prop")))
+            else {
+                continue;
+            };
+            let is_dir = properties.descendants().any(|node| node.has_tag_name("collection"));
+            let path = if is_dir && !path.ends_with('/') { path + "/" } else { path };
+            entries.push(RemoteEntry::new(
+                path,
+                is_dir,
+                child_text(properties, "getcontentlength").and_then(|size| size.trim().parse().ok()),
+                child_text(properties, "getlastmodified")
+                    .and_then(|modified| chrono::DateTime::parse_from_rfc2822(modified.trim()).ok())
+                    .map(SystemTime::from),
+            ));
+        }
+        Ok(entries)
+    }
+
+    /// Turns an href of a listing (an absolute URL or path) into a path relative to the root.
+    fn relative_path(&self, href: &str) -> Option<String> {
+        let href = match href.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or("/", |index| &rest[index..]),
+            None => href,
+        };
+        let path = percent_decode(href);
+        let relative = path.strip_prefix(&self.root_path)?;
+        Some(relative.trim_start_matches('/').to_string())
+    }
+
+    /// Adds everything below the folders among `entries`, folders before their contents.
+    pub fn expand(&self, entries: &[RemoteEntry]) -> io::Result<Vec<RemoteEntry>> {
+        let mut expanded = Vec::new();
+        for entry in entries {
+            expanded.push(entry.clone());
+            if entry.is_dir {
+                expanded.extend(self.expand(&self.list(&entry.path)?)?);
+            }
+        }
+        Ok(expanded)
+    }
+
+    pub fn get(&self, path: &str) -> io::Result<Box<dyn Read + Send + Sync>> {
+        Ok(send(self.request("GET", path)?.call())?.into_reader())
+    }
+
+    /// Streams the file from disk, so that large uploads don't need to fit in memory.
+    pub fn put(&self, path: &str, source: &Path, size: u64, progress: &mut Progress) -> io::Result<()> {
+        let request = self.request("PUT", path)?.set("Content-Length", &size.to_string());
+        let result = request.send(UploadReader { file: File::open(source)?, progress: &mut *progress });
+        if progress.is_cancelled() {
+            return Err(remote::cancelled());
+        }
+        send(result)?;
+        Ok(())
+    }
+
+    pub fn create_folder(&self, path: &str) -> io::Result<()> {
+        match self.request("MKCOL", path)?.call() {
+            // The folder already exists
+            Err(ureq::Error::Status(405, _)) => Ok(()),
+            result => send(result).map(|_| ()),
+        }
+    }
+
+    /// Deletes `entry`, which for folders includes everything inside.
+    pub fn delete(&self, entry: &RemoteEntry) -> io::Result<usize> {
+        send(self.request("DELETE", &entry.path)?.call())?;
+        Ok(1)
+    }
+
+    pub fn rename(&self, entry: &RemoteEntry, new_path: &str) -> io::Result<()> {
+        let request = self.request("MOVE", &entry.path)?.set("Destination", &self.url(new_path)).set("Overwrite", "F");
+        send(request.call())?;
+        Ok(())
+    }
+}
+
+/// Turns failed requests into readable errors.
+fn send(result: Result<ureq::Response, ureq::Error>) -> io::Result<ureq::Response> {
+    result.map_err(|e| match e {
+        ureq::Error::Status(412, _) => io::Error::new(io::ErrorKind::AlreadyExists, "the target already exists"),
+        ureq::Error::Status(status, response) => {
+            io::Error::other(format!("the server answered with status {} {}", status, response.status_text()))
+        }
+        e => io::Error::other(e.to_string()),
+    })
+}
+
+/// Status lines like "HTTP/1.1 404 Not Found" of properties the server doesn't have.
+fn is_failure(status: &str) -> bool {
+    status.split_whitespace().nth(1).is_none_or(|code| !code.starts_with('2'))
+}
+
+/// Percent-encodes every path segment, keeping the slashes between them.
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%'
+            && let Some(byte) = value.get(index + 1..index + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}